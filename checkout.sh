@@ -4,6 +4,7 @@ set -eu
 dir=$1
 url=$2
 branch=$3
+commit=${4:-}
 
 git clone https://github.com/tezedge/tezedge-fuzzing.git "$dir"
 cd $dir
@@ -11,4 +12,7 @@ git config -f .gitmodules submodule.code/tezedge.url "$url"
 git config -f .gitmodules submodule.code/tezedge.branch "$branch"
 git submodule update --init --recursive --remote code/tezedge
 cd code/tezedge
+if [ -n "$commit" ]; then
+    git checkout "$commit"
+fi
 git status
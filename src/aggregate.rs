@@ -0,0 +1,110 @@
+//! Runs of other fuzzing servers, e.g. ones fuzzing different projects on other machines,
+//! pulled from their APIs, so a single server shows all fuzzing activity.
+
+use std::{
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use serde::Serialize;
+use slog::{debug, warn, Logger};
+
+use crate::{config, runs::Run, trigger::Trigger};
+
+/// Number of the most recent runs of each server shown.
+const RUNS_PER_SERVER: usize = 20;
+/// Time to wait for runs of a peer.
+const PULL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The most recent runs of a server, as last pulled.
+#[derive(Clone, Serialize)]
+pub struct ServerRuns {
+    pub name: String,
+    pub url: Option<String>,
+    /// Runs from the most recent one
+    pub runs: Vec<Run>,
+    /// Error of the last pull, if it failed
+    pub error: Option<String>,
+    /// Time of the last successful pull
+    pub updated: Option<String>,
+}
+
+impl ServerRuns {
+    /// Runs of this server, from the most recent one.
+    pub fn new(name: String, url: Option<String>, mut runs: Vec<Run>) -> Self {
+        runs.reverse();
+        runs.truncate(RUNS_PER_SERVER);
+        Self {
+            name,
+            url,
+            runs,
+            error: None,
+            updated: None,
+        }
+    }
+}
+
+/// Puller of runs of peer servers, keeping the last pulled ones.
+#[derive(Clone)]
+pub struct Aggregator {
+    peers: Vec<config::Peer>,
+    runs: Arc<RwLock<Vec<ServerRuns>>>,
+    log: Logger,
+}
+
+impl Aggregator {
+    pub fn new(config: &config::Aggregate, log: Logger) -> Self {
+        let runs = config
+            .peers
+            .iter()
+            .map(|peer| ServerRuns::new(peer.name.clone(), Some(peer.url.to_string()), vec![]))
+            .collect();
+        Self {
+            peers: config.peers.clone(),
+            runs: Arc::new(RwLock::new(runs)),
+            log,
+        }
+    }
+
+    /// Runs of peers, as last pulled, in the configured order.
+    pub fn runs(&self) -> Vec<ServerRuns> {
+        self.runs.read().unwrap().clone()
+    }
+
+    /// Pulls runs of all peers, keeping the previous ones of peers that cannot be reached.
+    pub async fn pull(&self) {
+        for (i, peer) in self.peers.iter().enumerate() {
+            let client = Trigger::new(peer.url.clone(), peer.token.clone());
+            let result = match tokio::time::timeout(PULL_TIMEOUT, client.runs()).await {
+                Ok(result) => result.map_err(|e| e.to_string()),
+                Err(_) => Err("timed out".to_string()),
+            };
+            let mut runs = self.runs.write().unwrap();
+            let server = &mut runs[i];
+            match result {
+                Ok(pulled) => {
+                    debug!(self.log, "Pulled runs"; "peer" => &peer.name, "runs" => pulled.len());
+                    *server = ServerRuns::new(server.name.clone(), server.url.clone(), pulled);
+                    server.updated = Some(chrono::Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string());
+                }
+                // unreachable peers are reported once, until they are reached again
+                Err(e) if server.error.is_none() => {
+                    warn!(self.log, "Cannot pull runs"; "peer" => &peer.name, "error" => &e);
+                    server.error = Some(e);
+                }
+                Err(e) => {
+                    debug!(self.log, "Cannot pull runs"; "peer" => &peer.name, "error" => &e);
+                    server.error = Some(e);
+                }
+            }
+        }
+    }
+
+    /// Periodically pulls runs of peers.
+    pub async fn run(self, interval: Duration) {
+        loop {
+            self.pull().await;
+            tokio::time::sleep(interval).await;
+        }
+    }
+}
@@ -0,0 +1,603 @@
+use std::{collections::HashSet, path::PathBuf, sync::Arc};
+
+use percent_encoding::percent_decode_str;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use slog::{error, info, Logger};
+use warp::{
+    hyper::{body::Bytes, StatusCode},
+    reply::{self, Json, Response, WithStatus},
+    Filter, Rejection, Reply,
+};
+
+use crate::{
+    cancel::ScopeError,
+    common,
+    config::{self, Config},
+    deliveries::DeliveryStatus,
+    report::{Baseline, Report},
+    runs::{Run, Trigger},
+    server::{RunOptions, Runner},
+};
+
+const API_PATH: &str = "api";
+/// Caller name of the main API token
+pub(crate) const OPERATOR: &str = "operator";
+/// Number of audit log entries returned by default
+const AUDIT_LIMIT: usize = 100;
+/// Number of webhook deliveries returned by default
+const DELIVERIES_LIMIT: usize = 100;
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+fn error_reply(status: StatusCode, error: impl Into<String>) -> WithStatus<Json> {
+    reply::with_status(reply::json(&ErrorResponse { error: error.into() }), status)
+}
+
+#[derive(Serialize)]
+struct SeedResponse {
+    target: String,
+    name: String,
+    added: bool,
+}
+
+/// Compares strings in time independent of the position of the first mismatch.
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+/// Name of the caller with the token: `operator` for the main API token, or the name of
+/// one of other tokens.
+pub(crate) fn caller(api: &config::Api, token: &str) -> Option<String> {
+    if !api.token.is_empty() && constant_time_eq(token.as_bytes(), api.token.as_bytes()) {
+        return Some(OPERATOR.to_string());
+    }
+    api.tokens
+        .iter()
+        .find(|(_, t)| !t.is_empty() && constant_time_eq(token.as_bytes(), t.as_bytes()))
+        .map(|(name, _)| name.clone())
+}
+
+/// Whether the caller is allowed admin requests.
+pub(crate) fn is_admin(api: &config::Api, caller: &str) -> bool {
+    caller == OPERATOR || api.admins.iter().any(|admin| admin == caller)
+}
+
+/// Rejects requests without an API bearer token, or all requests if no token is configured,
+/// extracting the name of the caller.
+fn authorized(api: Arc<config::Api>) -> impl Filter<Extract = (String,), Error = Rejection> + Clone {
+    warp::header::optional::<String>("authorization").and_then(move |auth: Option<String>| {
+        let api = api.clone();
+        async move {
+            if !api.is_enabled() {
+                return Err(warp::reject::not_found());
+            }
+            match auth.as_deref().and_then(|auth| auth.strip_prefix("Bearer ")) {
+                Some(token) => caller(&api, token).ok_or_else(|| warp::reject::custom(Unauthorized)),
+                None => Err(warp::reject::custom(Unauthorized)),
+            }
+        }
+    })
+}
+
+/// Rejects requests of callers other than admins, extracting the name of the caller.
+fn admin(api: Arc<config::Api>) -> impl Filter<Extract = (String,), Error = Rejection> + Clone {
+    authorized(api.clone()).and_then(move |caller: String| {
+        let api = api.clone();
+        async move {
+            if is_admin(&api, &caller) {
+                Ok(caller)
+            } else {
+                Err(warp::reject::custom(Forbidden))
+            }
+        }
+    })
+}
+
+#[derive(Debug)]
+struct Unauthorized;
+
+impl warp::reject::Reject for Unauthorized {}
+
+#[derive(Debug)]
+struct Forbidden;
+
+impl warp::reject::Reject for Forbidden {}
+
+/// Turns API rejections into JSON error responses.
+async fn handle_rejection(rejection: Rejection) -> Result<impl Reply, Rejection> {
+    if rejection.find::<Unauthorized>().is_some() {
+        Ok(error_reply(StatusCode::UNAUTHORIZED, "missing or invalid API token"))
+    } else if rejection.find::<Forbidden>().is_some() {
+        Ok(error_reply(StatusCode::FORBIDDEN, "the API token does not allow this request"))
+    } else if rejection.find::<warp::reject::PayloadTooLarge>().is_some() {
+        Ok(error_reply(StatusCode::PAYLOAD_TOO_LARGE, "seed input is too large"))
+    } else {
+        Err(rejection)
+    }
+}
+
+/// Stores the uploaded seed input into the target corpus, named after its content hash.
+async fn upload_seed(
+    target: String,
+    body: Bytes,
+    corpus: Option<PathBuf>,
+    targets: Arc<HashSet<String>>,
+    log: Logger,
+) -> Result<impl Reply, Rejection> {
+    let corpus = match corpus {
+        Some(corpus) => corpus,
+        None => return Ok(error_reply(StatusCode::SERVICE_UNAVAILABLE, "no corpus directory is configured")),
+    };
+    if !targets.contains(&target) {
+        return Ok(error_reply(StatusCode::NOT_FOUND, format!("unknown target `{}`", target)));
+    }
+    if body.is_empty() {
+        return Ok(error_reply(StatusCode::BAD_REQUEST, "seed input is empty"));
+    }
+
+    let name = format!("seed-{:x}", Sha256::digest(&body));
+    let dir = corpus.join(&target);
+    let path = dir.join(&name);
+    let added = !path.exists();
+    if added {
+        let write = async {
+            tokio::fs::create_dir_all(&dir).await?;
+            tokio::fs::write(&path, &body).await
+        };
+        if let Err(e) = write.await {
+            error!(log, "Cannot store seed input"; "target" => &target, "error" => e.to_string());
+            return Ok(error_reply(StatusCode::INTERNAL_SERVER_ERROR, "cannot store seed input"));
+        }
+        info!(log, "Seed input uploaded"; "target" => &target, "name" => &name, "size" => body.len());
+    }
+    Ok(reply::with_status(reply::json(&SeedResponse { target, name, added }), StatusCode::OK))
+}
+
+#[derive(Deserialize)]
+pub(crate) struct RunRequest {
+    pub branch: String,
+    pub commit: Option<String>,
+    pub repo: Option<String>,
+    /// Targets to fuzz, all if empty
+    #[serde(default)]
+    pub targets: Vec<String>,
+    /// Fuzzing time of each target, in seconds
+    pub duration: Option<u64>,
+}
+
+/// Starts a fuzzing run of the branch, like a push to it would, or returns the status
+/// and the error of the request.
+pub(crate) async fn start(request: RunRequest, runner: &Runner, log: &Logger) -> Result<Run, (StatusCode, String)> {
+    let config = runner.config();
+    if !config.branches.contains(&request.branch) {
+        return Err((StatusCode::BAD_REQUEST, format!("branch `{}` is not fuzzed", request.branch)));
+    }
+    let repo = match request.repo.as_ref().or(config.repository.as_ref()) {
+        Some(repo) => config.repository_url(repo),
+        None => return Err((StatusCode::BAD_REQUEST, "no repository is specified or configured".to_string())),
+    };
+    // targets of discovered projects are only known once the branch is checked out
+    if config.discovery.is_none() {
+        let known = config.targets.values().flat_map(|conf| &conf.targets).collect::<HashSet<_>>();
+        if let Some(target) = request.targets.iter().find(|target| !known.contains(target)) {
+            return Err((StatusCode::BAD_REQUEST, format!("unknown target `{}`", target)));
+        }
+    }
+    if request.duration == Some(0) {
+        return Err((StatusCode::BAD_REQUEST, "duration should be positive".to_string()));
+    }
+    let run_id = format!(
+        "manual run of {} at {}",
+        request.commit.as_deref().unwrap_or("HEAD"),
+        config.time.now()
+    );
+    let options = RunOptions {
+        targets: request.targets,
+        duration: request.duration,
+        ..RunOptions::default()
+    };
+    let (targets, duration) = (options.targets.join(", "), options.duration);
+    match runner.start_with(repo, request.branch, request.commit, run_id, Trigger::Api, options).await {
+        Ok(run) => {
+            info!(log, "Run started"; "id" => &run.id, "branch" => &run.branch, "targets" => targets, "duration" => duration);
+            Ok(run)
+        }
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, format!("cannot start run: {}", e))),
+    }
+}
+
+async fn start_run(request: RunRequest, runner: Runner, log: Logger) -> Result<impl Reply, Rejection> {
+    match start(request, &runner, &log).await {
+        Ok(run) => Ok(reply::with_status(reply::json(&run), StatusCode::ACCEPTED)),
+        Err((status, error)) => Ok(error_reply(status, error)),
+    }
+}
+
+async fn get_run(id: String, runner: Runner) -> Result<impl Reply, Rejection> {
+    match runner.run(&id).await {
+        Some(run) => Ok(reply::with_status(reply::json(&run), StatusCode::OK)),
+        None => Ok(error_reply(StatusCode::NOT_FOUND, format!("unknown run `{}`", id))),
+    }
+}
+
+/// Cancels the run in progress, or only its fuzzing project or target, or returns the
+/// status and the error of the request.
+pub(crate) async fn cancel(id: &str, scope: Option<&str>, runner: &Runner, log: &Logger) -> Result<Run, (StatusCode, String)> {
+    match runner.cancel(id, scope) {
+        None => Err((StatusCode::NOT_FOUND, format!("no run `{}` in progress", id))),
+        Some(Err(ScopeError::NotFound)) => Err((
+            StatusCode::NOT_FOUND,
+            format!("run `{}` has no project or target `{}`", id, scope.unwrap_or_default()),
+        )),
+        Some(Err(ScopeError::Ambiguous(paths))) => Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "`{}` is ambiguous in run `{}`, use one of `{}`",
+                scope.unwrap_or_default(),
+                id,
+                paths.join("`, `")
+            ),
+        )),
+        Some(Ok(())) => {
+            info!(log, "Run cancelled"; "id" => id, "scope" => scope);
+            runner.run(id).await.ok_or_else(|| (StatusCode::NOT_FOUND, format!("unknown run `{}`", id)))
+        }
+    }
+}
+
+async fn cancel_run(id: String, scope: Option<String>, runner: Runner, log: Logger) -> Result<impl Reply, Rejection> {
+    match cancel(&id, scope.as_deref(), &runner, &log).await {
+        Ok(run) => Ok(reply::with_status(reply::json(&run), StatusCode::ACCEPTED)),
+        Err((status, error)) => Ok(error_reply(status, error)),
+    }
+}
+
+/// Pauses the run in progress on behalf of the caller, stopping its processes, or resumes it.
+async fn pause_run(id: String, pause: bool, caller: &str, runner: Runner, log: Logger) -> Result<impl Reply, Rejection> {
+    match runner.pause(&id, if pause { Some(caller) } else { None }) {
+        None => Ok(error_reply(StatusCode::NOT_FOUND, format!("no run `{}` in progress", id))),
+        Some(false) if pause => Ok(error_reply(StatusCode::CONFLICT, format!("run `{}` is already paused", id))),
+        Some(false) => Ok(error_reply(StatusCode::CONFLICT, format!("run `{}` is not paused", id))),
+        Some(true) => {
+            info!(log, "Run {}", if pause { "paused" } else { "resumed" }; "id" => &id);
+            match runner.run(&id).await {
+                Some(run) => Ok(reply::with_status(reply::json(&run), StatusCode::ACCEPTED)),
+                None => Ok(error_reply(StatusCode::NOT_FOUND, format!("unknown run `{}`", id))),
+            }
+        }
+    }
+}
+
+/// Starts a new run of the repository, branch and commit of the run.
+async fn rerun(id: String, runner: Runner, log: Logger) -> Result<impl Reply, Rejection> {
+    match runner.rerun(&id).await {
+        None => Ok(error_reply(StatusCode::NOT_FOUND, format!("unknown run `{}`", id))),
+        Some(Ok(run)) => {
+            info!(log, "Run started again"; "id" => &run.id, "previous" => &id);
+            Ok(reply::with_status(reply::json(&run), StatusCode::ACCEPTED))
+        }
+        Some(Err(e)) => Ok(error_reply(StatusCode::INTERNAL_SERVER_ERROR, format!("cannot start run: {}", e))),
+    }
+}
+
+#[derive(Deserialize)]
+struct BaselineRequest {
+    branch: String,
+    repo: Option<String>,
+    /// Targets to change the baseline of, all targets if empty
+    #[serde(default)]
+    targets: Vec<String>,
+    baseline: Baseline,
+}
+
+#[derive(Serialize)]
+struct BaselineResponse {
+    report: PathBuf,
+    targets: Vec<String>,
+}
+
+/// Resets or re-baselines the initial status in the most recent report of the branch.
+async fn change_baseline(request: BaselineRequest, runner: Runner, log: Logger) -> Result<impl Reply, Rejection> {
+    let config = runner.config();
+    // reports of runs in multiple repositories are kept by repository name
+    let repo = match request.repo.as_ref().or(config.repository.as_ref()) {
+        _ if config.repositories.is_empty() => None,
+        Some(repo) => match config.repository_name(&config.repository_url(repo)) {
+            Some(name) => Some(name.to_string()),
+            None => return Ok(error_reply(StatusCode::NOT_FOUND, format!("repository `{}` is not fuzzed", repo))),
+        },
+        None => return Ok(error_reply(StatusCode::BAD_REQUEST, "no repository is specified or configured")),
+    };
+    let namespace = repo.iter().map(String::as_str).chain(std::iter::once(request.branch.as_str())).collect::<Vec<_>>();
+    let loc = match Report::latest(&config.reports_path, &common::new_local_path(&namespace), &log).await {
+        Ok(Some(loc)) => loc,
+        Ok(None) => return Ok(error_reply(StatusCode::NOT_FOUND, format!("no reports of branch `{}`", request.branch))),
+        Err(e) => return Ok(error_reply(StatusCode::INTERNAL_SERVER_ERROR, format!("cannot find reports: {}", e))),
+    };
+    let changed = async {
+        let report = Report::new(&config.reports_path, &config.url, &loc, None, &config.time, log.clone()).await?;
+        report.rebaseline(&request.targets, request.baseline).await
+    };
+    match changed.await {
+        Ok(targets) if targets.is_empty() => Ok(error_reply(StatusCode::NOT_FOUND, "no such targets in the report")),
+        Ok(targets) => {
+            info!(log, "Baseline changed"; "report" => loc.to_str(), "targets" => targets.join(", "));
+            Ok(reply::with_status(reply::json(&BaselineResponse { report: loc, targets }), StatusCode::OK))
+        }
+        Err(e) => Ok(error_reply(StatusCode::INTERNAL_SERVER_ERROR, format!("cannot change baseline: {}", e))),
+    }
+}
+
+/// Disables the target in runs started later on behalf of the caller, or enables it,
+/// replying with all disabled targets.
+async fn toggle_target(target: String, disable: bool, caller: &str, runner: Runner, log: Logger) -> Result<impl Reply, Rejection> {
+    let action = if disable { "disabled" } else { "enabled" };
+    let disabled_by = if disable { Some(caller) } else { None };
+    match runner.set_disabled(&target, disabled_by).await {
+        None => Ok(error_reply(StatusCode::NOT_FOUND, format!("unknown target `{}`", target))),
+        Some(false) => Ok(error_reply(StatusCode::CONFLICT, format!("target `{}` is already {}", target, action))),
+        Some(true) => {
+            info!(log, "Target {}", action; "target" => &target);
+            Ok(reply::with_status(reply::json(&runner.disabled_targets().await), StatusCode::OK))
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct AuditQuery {
+    limit: Option<usize>,
+}
+
+/// The most recent entries of the audit log.
+async fn get_audit(query: AuditQuery, runner: Runner) -> Result<impl Reply, Rejection> {
+    match runner.audit_log().entries(query.limit.unwrap_or(AUDIT_LIMIT)).await {
+        Ok(entries) => Ok(reply::with_status(reply::json(&entries), StatusCode::OK)),
+        Err(e) => Ok(error_reply(StatusCode::INTERNAL_SERVER_ERROR, format!("cannot read audit log: {}", e))),
+    }
+}
+
+#[derive(Deserialize)]
+struct DeliveriesQuery {
+    limit: Option<usize>,
+}
+
+/// The most recent webhook deliveries, without their payloads.
+async fn get_deliveries(query: DeliveriesQuery, runner: Runner) -> Result<impl Reply, Rejection> {
+    let deliveries = match runner.deliveries() {
+        Some(deliveries) => deliveries,
+        None => return Ok(error_reply(StatusCode::NOT_FOUND, "webhook deliveries are not stored")),
+    };
+    match deliveries.list(query.limit.unwrap_or(DELIVERIES_LIMIT)).await {
+        Ok(deliveries) => Ok(reply::with_status(reply::json(&deliveries), StatusCode::OK)),
+        Err(e) => Ok(error_reply(StatusCode::INTERNAL_SERVER_ERROR, format!("cannot read deliveries: {}", e))),
+    }
+}
+
+/// Processes the stored webhook delivery again.
+async fn replay_delivery(id: String, runner: Runner, log: Logger) -> Result<impl Reply, Rejection> {
+    match runner.replay_delivery(&id).await {
+        None => Ok(error_reply(StatusCode::NOT_FOUND, format!("unknown delivery `{}`", id))),
+        Some(Ok(delivery)) if delivery.status == DeliveryStatus::Processed => {
+            info!(log, "Webhook delivery replayed"; "delivery" => &id, "event" => &delivery.event);
+            Ok(reply::with_status(reply::json(&delivery), StatusCode::OK))
+        }
+        Some(Ok(_)) => Ok(error_reply(StatusCode::INTERNAL_SERVER_ERROR, format!("processing of delivery `{}` failed", id))),
+        Some(Err(e)) => Ok(error_reply(StatusCode::INTERNAL_SERVER_ERROR, format!("cannot read delivery: {}", e))),
+    }
+}
+
+/// Records the control action of the caller in the audit log, along with the status of the reply.
+async fn audited(runner: &Runner, caller: &str, action: &str, subject: &str, reply: impl Reply) -> Response {
+    let response = reply.into_response();
+    runner.audit(caller, action, subject, response.status().as_u16()).await;
+    response
+}
+
+/// API routes, available under the `/api` path.
+pub fn routes(config: &Config, runner: Runner, log: Logger) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    let api = Arc::new(config.api.clone());
+
+    let seeds = {
+        let runner = runner.clone();
+        let corpus = config.corpus.clone().map(PathBuf::from);
+        let targets = Arc::new(
+            config
+                .targets
+                .values()
+                .flat_map(|c| c.targets.iter().cloned())
+                .collect::<HashSet<_>>(),
+        );
+        let log = log.new(slog::o!("endpoint" => "seeds"));
+        warp::post()
+            .and(warp::path!("seeds" / String))
+            .and(authorized(api.clone()))
+            .and(warp::body::content_length_limit(api.max_seed_size))
+            .and(warp::body::bytes())
+            .and(warp::any().map(move || corpus.clone()))
+            .and(warp::any().map(move || targets.clone()))
+            .and(warp::any().map(move || runner.clone()))
+            .and(warp::any().map(move || log.clone()))
+            .and_then(|target: String, caller: String, body, corpus, targets, runner: Runner, log| async move {
+                let reply = upload_seed(target.clone(), body, corpus, targets, log).await?;
+                Ok::<_, Rejection>(audited(&runner, &caller, "upload_seed", &target, reply).await)
+            })
+    };
+
+    let start = {
+        let runner = runner.clone();
+        let log = log.new(slog::o!("endpoint" => "runs"));
+        warp::post()
+            .and(warp::path!("runs"))
+            .and(authorized(api.clone()))
+            .and(warp::body::json())
+            .and(warp::any().map(move || runner.clone()))
+            .and(warp::any().map(move || log.clone()))
+            .and_then(|caller: String, request: RunRequest, runner: Runner, log| async move {
+                let branch = request.branch.clone();
+                let reply = start_run(request, runner.clone(), log).await?;
+                Ok::<_, Rejection>(audited(&runner, &caller, "start", &branch, reply).await)
+            })
+    };
+
+    let runs = {
+        let runner = runner.clone();
+        warp::get()
+            .and(warp::path!("runs"))
+            .and(authorized(api.clone()))
+            .and_then(move |_| {
+                let runner = runner.clone();
+                async move { Ok::<_, Rejection>(reply::json(&runner.runs().await)) }
+            })
+    };
+
+    let run = {
+        let runner = runner.clone();
+        warp::get()
+            .and(warp::path!("runs" / String))
+            .and(authorized(api.clone()))
+            .and(warp::any().map(move || runner.clone()))
+            .and_then(|id, _, runner| get_run(id, runner))
+    };
+
+    let pause = {
+        let runner = runner.clone();
+        let log = log.new(slog::o!("endpoint" => "pause"));
+        let pause = warp::path!("runs" / String / "pause").map(|id| (id, true));
+        let resume = warp::path!("runs" / String / "resume").map(|id| (id, false));
+        warp::post()
+            .and(pause.or(resume).unify())
+            .and(authorized(api.clone()))
+            .and(warp::any().map(move || runner.clone()))
+            .and(warp::any().map(move || log.clone()))
+            .and_then(|(id, pause): (String, bool), caller: String, runner: Runner, log| async move {
+                let reply = pause_run(id.clone(), pause, &caller, runner.clone(), log).await?;
+                let action = if pause { "pause" } else { "resume" };
+                Ok::<_, Rejection>(audited(&runner, &caller, action, &id, reply).await)
+            })
+    };
+
+    let rerun = {
+        let runner = runner.clone();
+        let log = log.new(slog::o!("endpoint" => "rerun"));
+        warp::post()
+            .and(warp::path!("runs" / String / "rerun"))
+            .and(authorized(api.clone()))
+            .and(warp::any().map(move || runner.clone()))
+            .and(warp::any().map(move || log.clone()))
+            .and_then(|id: String, caller: String, runner: Runner, log| async move {
+                let reply = rerun(id.clone(), runner.clone(), log).await?;
+                Ok::<_, Rejection>(audited(&runner, &caller, "rerun", &id, reply).await)
+            })
+    };
+
+    let baseline = {
+        let runner = runner.clone();
+        let log = log.new(slog::o!("endpoint" => "baseline"));
+        warp::post()
+            .and(warp::path!("baseline"))
+            .and(authorized(api.clone()))
+            .and(warp::body::json())
+            .and(warp::any().map(move || runner.clone()))
+            .and(warp::any().map(move || log.clone()))
+            .and_then(|caller: String, request: BaselineRequest, runner: Runner, log| async move {
+                let branch = request.branch.clone();
+                let reply = change_baseline(request, runner.clone(), log).await?;
+                Ok::<_, Rejection>(audited(&runner, &caller, "baseline", &branch, reply).await)
+            })
+    };
+
+    let disabled = {
+        let runner = runner.clone();
+        warp::get()
+            .and(warp::path!("targets" / "disabled"))
+            .and(authorized(api.clone()))
+            .and_then(move |_| {
+                let runner = runner.clone();
+                async move { Ok::<_, Rejection>(reply::json(&runner.disabled_targets().await)) }
+            })
+    };
+
+    let toggle = {
+        let runner = runner.clone();
+        let log = log.new(slog::o!("endpoint" => "targets"));
+        let disable = warp::path!("targets" / String / "disable").map(|target| (target, true));
+        let enable = warp::path!("targets" / String / "enable").map(|target| (target, false));
+        warp::post()
+            .and(disable.or(enable).unify())
+            .and(admin(api.clone()))
+            .and(warp::any().map(move || runner.clone()))
+            .and(warp::any().map(move || log.clone()))
+            .and_then(|(target, disable): (String, bool), caller: String, runner: Runner, log| async move {
+                let target = percent_decode_str(&target).decode_utf8_lossy().into_owned();
+                let reply = toggle_target(target.clone(), disable, &caller, runner.clone(), log).await?;
+                let action = if disable { "disable_target" } else { "enable_target" };
+                Ok::<_, Rejection>(audited(&runner, &caller, action, &target, reply).await)
+            })
+    };
+
+    let audit = {
+        let runner = runner.clone();
+        warp::get()
+            .and(warp::path!("audit"))
+            .and(admin(api.clone()))
+            .and(warp::query::<AuditQuery>())
+            .and(warp::any().map(move || runner.clone()))
+            .and_then(|_, query, runner| get_audit(query, runner))
+    };
+
+    let deliveries = {
+        let runner = runner.clone();
+        warp::get()
+            .and(warp::path!("deliveries"))
+            .and(authorized(api.clone()))
+            .and(warp::query::<DeliveriesQuery>())
+            .and(warp::any().map(move || runner.clone()))
+            .and_then(|_, query, runner| get_deliveries(query, runner))
+    };
+
+    let replay = {
+        let runner = runner.clone();
+        let log = log.new(slog::o!("endpoint" => "deliveries"));
+        warp::post()
+            .and(warp::path!("deliveries" / String / "replay"))
+            .and(admin(api.clone()))
+            .and(warp::any().map(move || runner.clone()))
+            .and(warp::any().map(move || log.clone()))
+            .and_then(|id: String, caller: String, runner: Runner, log| async move {
+                let reply = replay_delivery(id.clone(), runner.clone(), log).await?;
+                Ok::<_, Rejection>(audited(&runner, &caller, "replay_delivery", &id, reply).await)
+            })
+    };
+
+    let cancel = {
+        let log = log.new(slog::o!("endpoint" => "cancel"));
+        let run = warp::path!("runs" / String / "cancel").map(|id| (id, None));
+        // with a trailing slash, the scope is a project even if a target has its name
+        let scope = warp::path!("runs" / String / "cancel" / String).and(warp::path::full()).map(
+            |id, scope: String, path: warp::path::FullPath| {
+                let scope = if path.as_str().ends_with('/') { scope + "/" } else { scope };
+                (id, Some(scope))
+            },
+        );
+        let target = warp::path!("runs" / String / "cancel" / String / String)
+            .map(|id, project, target| (id, Some(format!("{}/{}", project, target))));
+        warp::post()
+            .and(run.or(scope).unify().or(target).unify())
+            .and(authorized(api))
+            .and(warp::any().map(move || runner.clone()))
+            .and(warp::any().map(move || log.clone()))
+            .and_then(|(id, scope): (String, Option<String>), caller: String, runner: Runner, log| async move {
+                let subject = scope.as_ref().map_or_else(|| id.clone(), |scope| format!("{}/{}", id, scope));
+                let reply = cancel_run(id, scope, runner.clone(), log).await?;
+                Ok::<_, Rejection>(audited(&runner, &caller, "cancel", &subject, reply).await)
+            })
+    };
+
+    warp::path(API_PATH)
+        .and(seeds.or(start).or(runs).or(run).or(pause).or(rerun).or(baseline).or(disabled).or(toggle).or(audit).or(deliveries).or(replay).or(cancel))
+        .recover(handle_rejection)
+}
@@ -0,0 +1,184 @@
+//! Content-addressed storage of run artifacts, like crash inputs and corpus snapshots.
+//!
+//! Artifacts are stored once by the SHA-256 hash of their content, shared by all runs, and
+//! each run keeps a manifest mapping artifact names to hashes, so copies of the store, e.g.
+//! mirrored to object storage, can be verified.
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex;
+
+use crate::error::{Error, Result};
+
+/// Manifest of the run within its report directory.
+const MANIFEST_FILE: &str = "hfuzz-report/artifacts.toml";
+
+/// Artifacts stored by the SHA-256 hash of their content, as `sha256/<2 first digits>/<hash>`.
+#[derive(Clone, Debug)]
+pub struct Store {
+    dir: PathBuf,
+}
+
+impl Store {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Location of the artifact with the hash.
+    pub fn path(&self, hash: &str) -> PathBuf {
+        self.dir.join("sha256").join(&hash[..2.min(hash.len())]).join(hash)
+    }
+
+    /// Stores the content of the file, unless an artifact with the same content is already
+    /// stored, and returns its hash.
+    pub async fn put(&self, file: impl Into<PathBuf>) -> Result<String> {
+        let store = self.clone();
+        let file = file.into();
+        tokio::task::spawn_blocking(move || store.put_file(&file))
+            .await
+            .map_err(|e| Error::other(e.to_string()))?
+            .map_err(Into::into)
+    }
+
+    fn put_file(&self, file: &Path) -> io::Result<String> {
+        std::fs::create_dir_all(&self.dir)?;
+        // the content is hashed while copied, so the stored one matches the hash even if the file changes
+        let mut tmp = tempfile::NamedTempFile::new_in(&self.dir)?;
+        let hash = hash(File::open(file)?, &mut tmp)?;
+        let path = self.path(&hash);
+        if !path.exists() {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            tmp.persist(&path).map_err(|e| e.error)?;
+        }
+        Ok(hash)
+    }
+
+    /// Whether the artifact with the hash is stored and its content matches the hash.
+    pub async fn verify(&self, hash: &str) -> Result<bool> {
+        let path = self.path(hash);
+        let hash = hash.to_string();
+        let verified = tokio::task::spawn_blocking(move || match File::open(&path) {
+            Ok(file) => Ok(self::hash(file, io::sink())? == hash),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e),
+        });
+        verified.await.map_err(|e| Error::other(e.to_string()))?.map_err(Into::into)
+    }
+}
+
+/// Hex SHA-256 hash of the content read, which is also written to `out`.
+fn hash(mut read: impl Read, mut out: impl Write) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let n = read.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        out.write_all(&buf[..n])?;
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Artifacts of a run, as hashes by their names, e.g. `failures/<target>/<input>`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    #[serde(default)]
+    pub artifacts: BTreeMap<String, String>,
+}
+
+impl Manifest {
+    /// Manifest of the run with the report in `dir`, empty if the run has no artifacts.
+    pub async fn load(dir: impl AsRef<Path>) -> Result<Self> {
+        match tokio::fs::read(dir.as_ref().join(MANIFEST_FILE)).await {
+            Ok(bytes) => Ok(toml::from_slice(&bytes)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn save(&self, dir: &Path) -> Result<()> {
+        let file = dir.join(MANIFEST_FILE);
+        if let Some(parent) = file.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&file, toml::to_vec(self)?).await?;
+        Ok(())
+    }
+
+    /// Names of artifacts that are missing in the store or don't match their hashes.
+    pub async fn verify(&self, store: &Store) -> Result<Vec<String>> {
+        let mut invalid = vec![];
+        for (name, hash) in &self.artifacts {
+            if !store.verify(hash).await? {
+                invalid.push(name.clone());
+            }
+        }
+        Ok(invalid)
+    }
+}
+
+/// Artifacts of a run, put into the store and recorded in the manifest of the run.
+pub struct RunArtifacts {
+    store: Store,
+    /// Report directory of the run
+    dir: PathBuf,
+    /// Serializes manifest updates
+    lock: Mutex<()>,
+}
+
+impl RunArtifacts {
+    pub fn new(store: Store, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            store,
+            dir: dir.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Stores the file as the artifact with the name, returning its hash.
+    pub async fn add(&self, name: &str, file: impl Into<PathBuf>) -> Result<String> {
+        let hash = self.store.put(file).await?;
+        let _lock = self.lock.lock().await;
+        let mut manifest = Manifest::load(&self.dir).await?;
+        manifest.artifacts.insert(name.to_string(), hash.clone());
+        manifest.save(&self.dir).await?;
+        Ok(hash)
+    }
+
+    /// Stores files of the directory, e.g. a target corpus, as artifacts named by `prefix/<file name>`,
+    /// replacing ones previously stored with the prefix. Hidden files are skipped.
+    ///
+    /// Returns the number of stored files.
+    pub async fn add_dir(&self, prefix: &str, dir: impl AsRef<Path>) -> Result<usize> {
+        let mut hashes = BTreeMap::new();
+        let mut read_dir = tokio::fs::read_dir(dir).await?;
+        while let Some(entry) = read_dir.next_entry().await? {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if !name.starts_with('.') && entry.file_type().await?.is_file() {
+                hashes.insert(format!("{}/{}", prefix, name), self.store.put(entry.path()).await?);
+            }
+        }
+        let _lock = self.lock.lock().await;
+        let mut manifest = Manifest::load(&self.dir).await?;
+        let dir_prefix = format!("{}/", prefix);
+        manifest.artifacts = std::mem::take(&mut manifest.artifacts)
+            .into_iter()
+            .filter(|(name, _)| !name.starts_with(&dir_prefix))
+            .collect();
+        let stored = hashes.len();
+        manifest.artifacts.extend(hashes);
+        manifest.save(&self.dir).await?;
+        Ok(stored)
+    }
+}
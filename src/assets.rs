@@ -0,0 +1,174 @@
+//! Report assets like status files, CSV exports and output archives, served with
+//! their content types, caching headers and gzip compression.
+
+use std::{
+    io::Write,
+    path::{Component, Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use flate2::{write::GzEncoder, Compression};
+use percent_encoding::percent_decode_str;
+use warp::{
+    http::{header, Response, StatusCode},
+    hyper::Body,
+    path::Tail,
+    Filter, Rejection, Reply,
+};
+
+/// Format of HTTP dates, always in GMT.
+const HTTP_DATE: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+/// Smallest asset compressed if the client accepts gzip encoding.
+const MIN_COMPRESSED: usize = 1024;
+
+/// Asset kinds served with explicit headers.
+struct Asset {
+    content_type: &'static str,
+    compressible: bool,
+}
+
+impl Asset {
+    /// Kind of the asset by its file name, `None` for files left to the generic static
+    /// files route.
+    fn of(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?;
+        let (content_type, compressible) = match name.rsplit_once('.')?.1 {
+            "toml" => ("application/toml; charset=utf-8", true),
+            "json" => ("application/json", true),
+            "csv" => ("text/csv; charset=utf-8", true),
+            "log" | "txt" => ("text/plain; charset=utf-8", true),
+            "html" => ("text/html; charset=utf-8", true),
+            "gz" => ("application/gzip", false),
+            _ => return None,
+        };
+        Some(Self { content_type, compressible })
+    }
+}
+
+/// Relative path of the asset in the reports directory, if it stays inside it.
+fn asset_path(tail: &str) -> Option<PathBuf> {
+    let path = PathBuf::from(percent_decode_str(tail).decode_utf8().ok()?.as_ref());
+    if path.as_os_str().is_empty() || !path.components().all(|c| matches!(c, Component::Normal(_))) {
+        return None;
+    }
+    Some(path)
+}
+
+/// Whether the client accepts gzip content encoding, by its `Accept-Encoding` header.
+fn accepts_gzip(accept_encoding: &str) -> bool {
+    accept_encoding
+        .split(',')
+        .filter_map(|encoding| {
+            let mut params = encoding.split(';').map(str::trim);
+            let name = params.next()?;
+            let disabled = params.any(|param| matches!(param, "q=0" | "q=0.0" | "q=0.00" | "q=0.000"));
+            Some((name, disabled))
+        })
+        .any(|(name, disabled)| (name == "gzip" || name == "*") && !disabled)
+}
+
+fn gzip(content: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(content)?;
+    encoder.finish()
+}
+
+/// Responds with the asset, or `304 Not Modified` if it is not modified since the time
+/// the client has it from.
+async fn serve(
+    reports_path: PathBuf,
+    tail: Tail,
+    accept_encoding: Option<String>,
+    if_modified_since: Option<String>,
+) -> Result<Response<Body>, Rejection> {
+    let path = asset_path(tail.as_str()).ok_or_else(warp::reject::not_found)?;
+    let asset = Asset::of(&path).ok_or_else(warp::reject::not_found)?;
+    let file = reports_path.join(&path);
+    let metadata = tokio::fs::metadata(&file).await.map_err(|_| warp::reject::not_found())?;
+    if !metadata.is_file() {
+        return Err(warp::reject::not_found());
+    }
+    // compared as formatted, as HTTP dates have a precision of seconds
+    let modified = metadata
+        .modified()
+        .ok()
+        .map(|time| DateTime::<Utc>::from(time).format(HTTP_DATE).to_string());
+    let builder = Response::builder()
+        .header(header::CACHE_CONTROL, "no-cache")
+        .header(header::VARY, "Accept-Encoding");
+    let builder = match &modified {
+        Some(modified) => builder.header(header::LAST_MODIFIED, modified),
+        None => builder,
+    };
+    if let (Some(modified), Some(since)) = (&modified, &if_modified_since) {
+        let not_modified = match (DateTime::parse_from_rfc2822(modified), DateTime::parse_from_rfc2822(since)) {
+            (Ok(modified), Ok(since)) => modified <= since,
+            _ => false,
+        };
+        if not_modified {
+            return Ok(builder.status(StatusCode::NOT_MODIFIED).body(Body::empty()).unwrap());
+        }
+    }
+    let content = tokio::fs::read(&file).await.map_err(|_| warp::reject::not_found())?;
+    let builder = builder.header(header::CONTENT_TYPE, asset.content_type);
+    if asset.compressible && content.len() >= MIN_COMPRESSED && accepts_gzip(accept_encoding.as_deref().unwrap_or_default()) {
+        if let Ok(compressed) = gzip(&content) {
+            return Ok(builder.header(header::CONTENT_ENCODING, "gzip").body(Body::from(compressed)).unwrap());
+        }
+    }
+    Ok(builder.body(Body::from(content)).unwrap())
+}
+
+/// Route of report assets under the `/reports` path, rejecting other files so they are
+/// served by the generic static files route.
+pub(crate) fn routes(reports_path: PathBuf) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    warp::get()
+        .and(warp::path("reports"))
+        .and(warp::path::tail())
+        .and(warp::header::optional::<String>("accept-encoding"))
+        .and(warp::header::optional::<String>("if-modified-since"))
+        .and_then(move |tail, accept_encoding, if_modified_since| {
+            serve(reports_path.clone(), tail, accept_encoding, if_modified_since)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn asset_paths_stay_in_reports() {
+        assert_eq!(asset_path("master/run/status.toml"), Some(PathBuf::from("master/run/status.toml")));
+        assert_eq!(asset_path("master/_Run%20name_/coverage.csv"), Some(PathBuf::from("master/_Run name_/coverage.csv")));
+        assert_eq!(asset_path(""), None);
+        assert_eq!(asset_path("../secret.toml"), None);
+        assert_eq!(asset_path("master/../../secret.toml"), None);
+        assert_eq!(asset_path("%2E%2E/secret.toml"), None);
+        assert_eq!(asset_path("master/%2E%2E%2F%2E%2E%2Fsecret.toml"), None);
+        assert_eq!(asset_path("/etc/passwd"), None);
+        assert_eq!(asset_path("%2Fetc%2Fpasswd"), None);
+        assert_eq!(asset_path("./status.toml"), None);
+        assert_eq!(asset_path("%FF"), None);
+    }
+
+    #[test]
+    fn detects_gzip_acceptance() {
+        assert!(accepts_gzip("gzip"));
+        assert!(accepts_gzip("deflate, gzip;q=1.0, br"));
+        assert!(accepts_gzip("*"));
+        assert!(!accepts_gzip("deflate, br"));
+        assert!(!accepts_gzip("gzip;q=0"));
+        assert!(!accepts_gzip("gzip; q=0.000, deflate"));
+        assert!(!accepts_gzip(""));
+    }
+
+    #[test]
+    fn serves_known_asset_kinds() {
+        let toml = Asset::of(Path::new("run/status.toml")).unwrap();
+        assert_eq!((toml.content_type, toml.compressible), ("application/toml; charset=utf-8", true));
+        assert_eq!(Asset::of(Path::new("run/output.log.gz")).map(|a| a.compressible), Some(false));
+        assert!(Asset::of(Path::new("run/index")).is_none());
+        assert!(Asset::of(Path::new("run/image.png")).is_none());
+    }
+}
@@ -0,0 +1,74 @@
+//! Audit trail of control actions, like starting, cancelling or pausing runs, recording
+//! who performed each and when.
+
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::{fs::OpenOptions, io::AsyncWriteExt, sync::Mutex};
+
+use crate::error::{Result, ResultExt};
+
+/// Control action performed by a caller.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Entry {
+    pub time: DateTime<Utc>,
+    /// Name of the API token used, or the Slack user
+    pub caller: String,
+    /// Action, like `start` or `pause`
+    pub action: String,
+    /// Run, branch or target the action is performed on
+    pub subject: String,
+    /// HTTP status of the response to the action
+    pub status: u16,
+}
+
+/// Audit log kept as a file with an entry per line, in JSON.
+pub struct AuditLog {
+    file: PathBuf,
+    /// Serializes appends, so entries are not interleaved
+    lock: Mutex<()>,
+}
+
+impl AuditLog {
+    pub fn new(file: impl Into<PathBuf>) -> Self {
+        Self {
+            file: file.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Appends the action performed by the caller now.
+    pub async fn record(&self, caller: &str, action: &str, subject: &str, status: u16) -> Result<()> {
+        let entry = Entry {
+            time: Utc::now(),
+            caller: caller.to_string(),
+            action: action.to_string(),
+            subject: subject.to_string(),
+            status,
+        };
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+        let _lock = self.lock.lock().await;
+        if let Some(parent) = self.file.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.file).await?;
+        file.write_all(&line).await?;
+        Ok(())
+    }
+
+    /// The last `limit` entries, oldest first.
+    pub async fn entries(&self, limit: usize) -> Result<Vec<Entry>> {
+        let content = match tokio::fs::read_to_string(&self.file).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e).context(|| format!("cannot read {}", self.file.to_string_lossy())),
+        };
+        let lines = content.lines().filter(|line| !line.is_empty()).collect::<Vec<_>>();
+        lines[lines.len().saturating_sub(limit)..]
+            .iter()
+            .map(|line| Ok(serde_json::from_str(line)?))
+            .collect()
+    }
+}
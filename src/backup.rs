@@ -0,0 +1,118 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use slog::{debug, error, info, Logger};
+use tokio::{fs, process::Command};
+
+use crate::{
+    config, corpus,
+    error::{output_tail, Error, Result},
+};
+
+const PREFIX: &str = "corpus-";
+const SUFFIX: &str = ".tar.gz";
+
+/// Periodic tarball backups of the corpus directory.
+pub struct Backup {
+    corpus: PathBuf,
+    path: PathBuf,
+    keep: usize,
+    log: Logger,
+}
+
+impl Backup {
+    pub fn new(corpus: impl AsRef<Path>, config: &config::Backup, log: Logger) -> Self {
+        Self {
+            corpus: corpus.as_ref().to_path_buf(),
+            path: config.path.clone(),
+            keep: config.keep,
+            log,
+        }
+    }
+
+    /// Creates a new backup of the corpus and removes ones exceeding the number to keep.
+    ///
+    /// Hidden directories, like staging areas and branch overlays, are not backed up.
+    pub async fn create(&self) -> Result<PathBuf> {
+        fs::create_dir_all(&self.path).await?;
+        let name = format!("{}{}{}", PREFIX, chrono::Utc::now().format("%Y%m%d-%H%M%S"), SUFFIX);
+        let archive = self.path.join(&name);
+        // archive is written under a hidden name first, so incomplete backups are never restored
+        let tmp = self.path.join(format!(".{}", name));
+        let output = Command::new("tar")
+            .arg("-czf")
+            .arg(&tmp)
+            .arg("--exclude=./.*")
+            .arg("-C")
+            .arg(&self.corpus)
+            .arg(".")
+            .output()
+            .await?;
+        if !output.status.success() {
+            let _ = fs::remove_file(&tmp).await;
+            return Err(Error::other(format!("cannot create backup: {}", output_tail(&output.stderr))));
+        }
+        fs::rename(&tmp, &archive).await?;
+
+        let backups = list(&self.path).await?;
+        for old in backups.iter().take(backups.len().saturating_sub(self.keep)) {
+            debug!(self.log, "Removing old backup"; "path" => old.to_string_lossy().into_owned());
+            fs::remove_file(old).await?;
+        }
+        Ok(archive)
+    }
+
+    /// Periodically backs up the corpus.
+    pub async fn run(self, interval: Duration) {
+        loop {
+            tokio::time::sleep(interval).await;
+            match self.create().await {
+                Ok(archive) => info!(self.log, "Corpus backed up"; "path" => archive.to_string_lossy().into_owned()),
+                Err(e) => error!(self.log, "Error backing up corpus"; "error" => e.to_string()),
+            }
+        }
+    }
+}
+
+/// Backups found in the directory, from the oldest to the most recent one.
+pub async fn list(dir: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
+    let mut backups = vec![];
+    let mut read_dir = fs::read_dir(dir).await?;
+    while let Some(entry) = read_dir.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with(PREFIX) && name.ends_with(SUFFIX) && entry.file_type().await?.is_file() {
+            backups.push(entry.path());
+        }
+    }
+    backups.sort();
+    Ok(backups)
+}
+
+/// Restores the corpus directory from the backup archive.
+///
+/// The current corpus is kept next to it as a hidden `.bak` directory.
+pub async fn restore(corpus: impl AsRef<Path>, archive: impl AsRef<Path>) -> Result<()> {
+    let staging = corpus::staging_dir(&corpus);
+    if staging.exists() {
+        fs::remove_dir_all(&staging).await?;
+    }
+    fs::create_dir_all(&staging).await?;
+    let output = Command::new("tar")
+        .arg("-xzf")
+        .arg(archive.as_ref())
+        .arg("-C")
+        .arg(&staging)
+        .output()
+        .await?;
+    if !output.status.success() {
+        let _ = fs::remove_dir_all(&staging).await;
+        return Err(Error::other(format!("cannot unpack backup: {}", output_tail(&output.stderr))));
+    }
+    if corpus.as_ref().exists() {
+        Ok(corpus::replace(corpus, staging).await?)
+    } else {
+        Ok(fs::rename(staging, corpus.as_ref()).await?)
+    }
+}
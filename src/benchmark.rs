@@ -0,0 +1,176 @@
+//! Throughput benchmarks of fuzz targets, catching performance regressions in fuzzed
+//! code paths by comparing execs/sec and startup time of a commit with its base.
+
+use std::{
+    collections::HashMap,
+    fmt, io,
+    path::{Path, PathBuf},
+    process::Output,
+    time::{Duration, Instant},
+};
+
+use slog::{debug, Logger};
+use tokio::process::Command;
+
+use crate::{
+    cancel::Scope,
+    config::Variant,
+    error::{output_tail, Error, Result},
+    hfuzz::{self, OutputFormat},
+    process,
+};
+
+/// Time allowed for a target to start in addition to the benchmark duration, as
+/// `cargo hfuzz run` might need to check its build first.
+const START_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Performance of a fuzz target on a commit.
+#[derive(Clone, Copy, Debug)]
+pub struct Measurement {
+    pub execs_per_sec: f64,
+    /// Time of running the target over a single input
+    pub startup: Duration,
+}
+
+/// Performance of a fuzz target on the fuzzed commit compared with its base.
+#[derive(Clone, Debug)]
+pub struct Comparison {
+    pub target: String,
+    pub base: Measurement,
+    pub head: Measurement,
+}
+
+impl Comparison {
+    /// Change of the throughput, in percents, negative for slowdowns.
+    pub fn throughput_change(&self) -> f64 {
+        change(self.base.execs_per_sec, self.head.execs_per_sec)
+    }
+
+    /// Change of the startup time, in percents, positive for slowdowns.
+    pub fn startup_change(&self) -> f64 {
+        change(self.base.startup.as_secs_f64(), self.head.startup.as_secs_f64())
+    }
+
+    /// Whether the target is slower by at least `threshold` percents.
+    pub fn is_regression(&self, threshold: f64) -> bool {
+        -self.throughput_change() >= threshold || self.startup_change() >= threshold
+    }
+}
+
+fn change(base: f64, head: f64) -> f64 {
+    if base > 0.0 {
+        (head - base) * 100.0 / base
+    } else {
+        0.0
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "`{}`: {:.0} → {:.0} execs/sec ({:+.1}%), startup {:.2}s → {:.2}s ({:+.1}%)",
+            self.target,
+            self.base.execs_per_sec,
+            self.head.execs_per_sec,
+            self.throughput_change(),
+            self.base.startup.as_secs_f64(),
+            self.head.startup.as_secs_f64(),
+            self.startup_change()
+        )
+    }
+}
+
+/// Runs built fuzz targets for a fixed time with the run environment `env`, measuring their performance.
+pub struct Bench {
+    env: HashMap<String, String>,
+    duration: Duration,
+    output_format: OutputFormat,
+    cargo_hfuzz: Option<PathBuf>,
+    cancel: Scope,
+    log: Logger,
+}
+
+impl Bench {
+    pub fn new(env: HashMap<String, String>, duration: Duration, output_format: OutputFormat, cancel: Scope, log: Logger) -> Self {
+        Self {
+            env,
+            duration,
+            output_format,
+            cargo_hfuzz: None,
+            cancel,
+            log,
+        }
+    }
+
+    /// Runs targets with the `cargo-hfuzz` binary, instead of the one found by cargo.
+    pub fn with_cargo_hfuzz(mut self, cargo_hfuzz: Option<PathBuf>) -> Self {
+        self.cargo_hfuzz = cargo_hfuzz;
+        self
+    }
+
+    /// Measures performance of the target of the project in `dir`, built for the variant with
+    /// the project environment `env`, fuzzing it with a single thread from the `corpus`.
+    /// New inputs are not added to the corpus.
+    pub async fn measure(
+        &self,
+        dir: &Path,
+        env: &HashMap<String, String>,
+        variant: &Variant,
+        target: &str,
+        binary: &str,
+        corpus: Option<&Path>,
+    ) -> Result<Measurement> {
+        let mut project_env = self.env.clone();
+        project_env.extend(env.clone());
+        let mut command = hfuzz::cargo_hfuzz(self.cargo_hfuzz.as_deref());
+        command
+            .arg("run")
+            .arg(binary)
+            .args(variant.cargo_args())
+            .current_dir(dir)
+            .envs(&self.env)
+            .envs(env)
+            .envs(variant.env(&project_env));
+        let (_, startup) = self.hfuzz_run(&mut command, target, "-v -N 1 -n 1".to_string()).await?;
+
+        let output_dir = tempfile::tempdir()?;
+        let mut args = format!("-v -n 1 --run_time {}", self.duration.as_secs());
+        if let Some(corpus) = corpus {
+            args += &format!(" -i {} -o {}", corpus.to_string_lossy(), output_dir.path().to_string_lossy());
+        }
+        let (output, _) = self.hfuzz_run(&mut command, target, args).await?;
+        let execs_per_sec = self
+            .output_format
+            .execs_per_sec(&String::from_utf8_lossy(&output.stderr))
+            .map_err(|e| Error::fuzz(target, e))?;
+        debug!(self.log, "Benchmarked target"; "target" => target, "dir" => dir.to_str(),
+               "execs_per_sec" => execs_per_sec, "startup" => startup.as_secs_f64());
+        Ok(Measurement { execs_per_sec, startup })
+    }
+
+    /// Runs `cargo hfuzz run` command with honggfuzz arguments, returning its output and run time.
+    async fn hfuzz_run(&self, command: &mut Command, target: &str, args: String) -> Result<(Output, Duration)> {
+        command.env("HFUZZ_RUN_ARGS", args);
+        let timeout = self.duration + START_TIMEOUT;
+        let start = Instant::now();
+        let (output, _) = process::output_with_usage(command, timeout, &self.cancel)
+            .await
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::TimedOut => Error::Timeout {
+                    command: format!("benchmark of {}", target),
+                    timeout,
+                },
+                io::ErrorKind::Interrupted => Error::Cancelled(format!("benchmark of {}", target)),
+                _ => e.into(),
+            })?;
+        let elapsed = start.elapsed();
+        if !output.status.success() {
+            return Err(Error::fuzz(
+                target,
+                format!("error running target with {}: {}", output.status, output_tail(&output.stderr)),
+            ));
+        }
+        Ok((output, elapsed))
+    }
+}
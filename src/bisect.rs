@@ -0,0 +1,173 @@
+//! Bisection of coverage regressions, finding the commit after which replaying the
+//! corpus of a fuzz target covers considerably fewer edges.
+
+use std::{collections::HashMap, fmt, future::Future, path::{Path, PathBuf}, process::Stdio, time::Duration};
+
+use slog::{debug, Logger};
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+use crate::{
+    cancel::Scope,
+    config::Variant,
+    error::{Error, Result},
+    hfuzz::{self, OutputFormat},
+    process,
+};
+
+/// Commit found to lose coverage of a fuzz target.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct Bisection {
+    pub target: String,
+    /// The last commit without the regression, of the previous run
+    pub good: String,
+    /// The first commit with the regression
+    pub commit: String,
+    /// Edges covered by the corpus on the good commit and on the found one
+    pub before: u32,
+    pub after: u32,
+    /// Whether the search was cut short by the step limit, so the commit is only
+    /// the oldest one known to lose coverage
+    pub approximate: bool,
+}
+
+impl fmt::Display for Bisection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "coverage of `{}` dropped from {} to {} edges {} commit {}",
+            self.target,
+            self.before,
+            self.after,
+            if self.approximate { "at or before" } else { "at" },
+            self.commit
+        )
+    }
+}
+
+/// Whether `after` covered edges are fewer than `before` by at least `threshold` percents.
+pub fn is_regression(before: u32, after: u32, threshold: f64) -> bool {
+    before > 0 && f64::from(before.saturating_sub(after)) * 100.0 / f64::from(before) >= threshold
+}
+
+/// Finds the first of `commits` the `is_bad` predicate holds for, given it holds for
+/// the last one and the predicate holds for all commits after a bad one.
+///
+/// Returns the index of the found commit and whether the search was exact, as at most
+/// `max_steps` commits are tested.
+pub async fn search<F, Fut>(commits: &[String], max_steps: usize, mut is_bad: F) -> Result<(usize, bool)>
+where
+    F: FnMut(&str) -> Fut,
+    Fut: Future<Output = Result<bool>>,
+{
+    // commits before `good` are good, ones from `bad` on are bad
+    let (mut good, mut bad) = (0, commits.len().saturating_sub(1));
+    for _ in 0..max_steps {
+        if good >= bad {
+            break;
+        }
+        let mid = good + (bad - good) / 2;
+        if is_bad(&commits[mid]).await? {
+            bad = mid;
+        } else {
+            good = mid + 1;
+        }
+    }
+    Ok((bad, good >= bad))
+}
+
+/// Replays corpora of built fuzz targets with the run environment `env`, counting covered edges.
+pub struct Replay {
+    env: HashMap<String, String>,
+    timeout: Duration,
+    output_format: OutputFormat,
+    cargo_hfuzz: Option<PathBuf>,
+    cancel: Scope,
+    log: Logger,
+}
+
+impl Replay {
+    pub fn new(env: HashMap<String, String>, timeout: Duration, output_format: OutputFormat, cancel: Scope, log: Logger) -> Self {
+        Self {
+            env,
+            timeout,
+            output_format,
+            cargo_hfuzz: None,
+            cancel,
+            log,
+        }
+    }
+
+    /// Runs targets with the `cargo-hfuzz` binary, instead of the one found by cargo.
+    pub fn with_cargo_hfuzz(mut self, cargo_hfuzz: Option<PathBuf>) -> Self {
+        self.cargo_hfuzz = cargo_hfuzz;
+        self
+    }
+
+    /// Edges of the target of the project in `dir`, built for the variant with the project
+    /// environment `env`, covered by its `corpus`. The corpus is not modified.
+    pub async fn coverage(
+        &self,
+        dir: &Path,
+        env: &HashMap<String, String>,
+        variant: &Variant,
+        target: &str,
+        binary: &str,
+        corpus: &Path,
+    ) -> Result<u32> {
+        let output_dir = tempfile::tempdir()?;
+        let mut project_env = self.env.clone();
+        project_env.extend(env.clone());
+        let mut command = hfuzz::cargo_hfuzz(self.cargo_hfuzz.as_deref());
+        command
+            .arg("run")
+            .arg(binary)
+            .args(variant.cargo_args())
+            .current_dir(dir)
+            .envs(&self.env)
+            .envs(env)
+            .envs(variant.env(&project_env))
+            .env(
+                "HFUZZ_RUN_ARGS",
+                format!("-v -n 1 -i {} -o {}", corpus.to_string_lossy(), output_dir.path().to_string_lossy()),
+            )
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        let mut child = process::new_process_group(&mut command).spawn()?;
+        let stderr = child.stderr.take().ok_or_else(|| Error::fuzz(target, "cannot get stderr"))?;
+        let pid = child.id();
+
+        // edges found while the corpus is run through, before fuzzing starts
+        let replay = async {
+            let mut lines = BufReader::new(stderr).lines();
+            let mut edges = 0;
+            while let Some(line) = lines.next_line().await? {
+                if line.contains("Entering phase 2/3") {
+                    break;
+                }
+                match self.output_format.new_edges(&line) {
+                    Some(Ok(e)) => edges += e,
+                    Some(Err(e)) => return Err(Error::fuzz(target, e)),
+                    None => (),
+                }
+            }
+            Ok(edges)
+        };
+        let edges = tokio::select! {
+            edges = replay => edges,
+            _ = tokio::time::sleep(self.timeout) => Err(Error::Timeout {
+                command: format!("corpus replay of {}", target),
+                timeout: self.timeout,
+            }),
+            _ = self.cancel.cancelled() => Err(Error::Cancelled(format!("corpus replay of {}", target))),
+        };
+        if let Some(pid) = pid {
+            let _ = process::kill_group(pid);
+        }
+        let _ = child.wait().await;
+        let edges = edges?;
+        debug!(self.log, "Replayed corpus"; "target" => target, "dir" => dir.to_str(), "edges" => edges);
+        Ok(edges)
+    }
+}
@@ -1,36 +1,161 @@
 use std::{
+    collections::HashMap,
     ffi::OsStr,
     io,
     path::{Path, PathBuf},
     process::Output,
+    sync::Arc,
+    time::Duration,
 };
 
-use slog::{debug, trace, FnValue, Logger};
-use tokio::{fs::read_dir, process::Command};
+use slog::{debug, error, trace, FnValue, Logger};
+use tokio::{
+    fs::read_dir,
+    process::Command,
+    sync::{Mutex, OwnedMutexGuard, OwnedSemaphorePermit, Semaphore},
+};
+
+use crate::{
+    cancel::Scope,
+    common::u8_slice_to_string,
+    config::{self, KCov, Variant},
+    error::{output_tail, Error, Result},
+    hfuzz,
+    process,
+    resources::Usage,
+    workspace::Metadata,
+};
+
+/// Build slots shared by all runs of the server.
+///
+/// A fuzzing project is built by a single run at a time, and at most `parallelism`
+/// build steps (cleaning, building or generating coverage) run at once overall,
+/// so fuzzing of already built projects doesn't wait for others to compile.
+pub struct BuildSlots {
+    global: Arc<Semaphore>,
+    projects: Mutex<HashMap<String, Arc<Mutex<()>>>>,
+}
+
+/// Permission to run build steps of a fuzzing project, released when dropped.
+pub struct BuildSlot {
+    _global: OwnedSemaphorePermit,
+    _project: OwnedMutexGuard<()>,
+}
+
+impl BuildSlots {
+    pub fn new(parallelism: usize) -> Self {
+        Self {
+            global: Arc::new(Semaphore::new(parallelism.max(1))),
+            projects: Mutex::default(),
+        }
+    }
 
-use crate::{common::u8_slice_to_string, config::KCov};
+    /// Waits for the project to be free of other builds, and then for a global slot.
+    pub async fn acquire(&self, project: &str) -> BuildSlot {
+        let project = self
+            .projects
+            .lock()
+            .await
+            .entry(project.to_string())
+            .or_default()
+            .clone();
+        let project = project.lock_owned().await;
+        let global = self
+            .global
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("build semaphore is never closed");
+        BuildSlot {
+            _global: global,
+            _project: project,
+        }
+    }
+}
 
+/// Builds fuzz targets and coverage reports of fuzzing projects.
 #[derive(Clone)]
 pub struct Builder {
-    corpus: Option<String>,
     kcov: Option<KCov>,
+    cargo_env: HashMap<String, String>,
+    vendor_dir: Option<PathBuf>,
+    build_timeout: Duration,
+    clean_timeout: Duration,
+    kcov_timeout: Duration,
+    cargo_hfuzz: Option<PathBuf>,
+    cancel: Scope,
     log: Logger,
 }
 
 impl Builder {
-    pub fn new(corpus: Option<String>, kcov: Option<KCov>, log: Logger) -> Self {
-        Builder { corpus, kcov, log }
+    pub fn new(kcov: Option<KCov>, build: &config::Build, log: Logger) -> Self {
+        Builder {
+            kcov,
+            cargo_env: build.cargo_env(),
+            vendor_dir: build.vendor_dir.clone(),
+            build_timeout: Duration::from_secs(build.build_timeout),
+            clean_timeout: Duration::from_secs(build.clean_timeout),
+            kcov_timeout: Duration::from_secs(build.kcov_timeout),
+            cargo_hfuzz: None,
+            cancel: Scope::new(),
+            log,
+        }
+    }
+
+    /// Makes build steps stop when the scope is cancelled.
+    pub fn with_cancel(mut self, cancel: Scope) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// Builds fuzz targets with the `cargo-hfuzz` binary, instead of the one found by cargo.
+    pub fn with_cargo_hfuzz(mut self, cargo_hfuzz: Option<PathBuf>) -> Self {
+        self.cargo_hfuzz = cargo_hfuzz;
+        self
     }
 
-    fn error(msg: impl AsRef<str>) -> io::Error {
-        io::Error::new(io::ErrorKind::Other, msg.as_ref().to_owned())
+    /// Runs the command, killing it along with its child processes if it
+    /// doesn't complete within the `timeout`.
+    async fn output(&self, name: &str, command: &mut Command, timeout: Duration) -> Result<Output> {
+        self.output_with_usage(name, command, timeout).await.map(|(output, _)| output)
+    }
+
+    /// Same as [Self::output], also returning the peak resource usage of the command.
+    async fn output_with_usage(&self, name: &str, command: &mut Command, timeout: Duration) -> Result<(Output, Usage)> {
+        process::output_with_usage(command, timeout, &self.cancel).await.map_err(|e| match e.kind() {
+            io::ErrorKind::TimedOut => {
+                error!(self.log, "{} timed out", name; "timeout" => timeout.as_secs());
+                Error::Timeout {
+                    command: name.to_string(),
+                    timeout,
+                }
+            }
+            io::ErrorKind::Interrupted => Error::Cancelled(name.to_string()),
+            _ => e.into(),
+        })
+    }
+
+    /// Configures cargo in the checked out directory to use vendored crates, if specified.
+    pub async fn configure_sources(&self, root: impl AsRef<Path>) -> io::Result<()> {
+        let vendor_dir = match &self.vendor_dir {
+            Some(vendor_dir) => vendor_dir,
+            None => return Ok(()),
+        };
+        debug!(self.log, "Using vendored sources"; "dir" => vendor_dir.to_str());
+        let cargo_dir = root.as_ref().join(".cargo");
+        tokio::fs::create_dir_all(&cargo_dir).await?;
+        let config = format!(
+            "[source.crates-io]\nreplace-with = \"vendored-sources\"\n\n[source.vendored-sources]\ndirectory = {:?}\n",
+            vendor_dir.to_string_lossy()
+        );
+        tokio::fs::write(cargo_dir.join("config"), config).await
     }
 
     fn os_str_to_string<'a>(os_str: impl AsRef<OsStr>) -> String {
         os_str.as_ref().to_string_lossy().into_owned()
     }
 
-    fn check_output(&self, command: impl AsRef<str>, output: Output) -> io::Result<()> {
+    fn check_output(&self, command: impl AsRef<str>, dir: impl AsRef<Path>, output: Output) -> Result<()> {
         trace!(self.log, "checking output of {}", command.as_ref();
                "stdout" => u8_slice_to_string(&output.stdout),
                "stderr" => u8_slice_to_string(&output.stderr),
@@ -40,7 +165,12 @@ impl Builder {
             debug!(self.log, "{} returned error", command.as_ref();
                    "stderr" => FnValue(|_| u8_slice_to_string(&output.stderr)),
                    "code" => output.status.code());
-            return Err(Self::error(format!("error running {}", command.as_ref())));
+            return Err(Error::Build {
+                command: command.as_ref().to_string(),
+                dir: dir.as_ref().to_path_buf(),
+                status: output.status,
+                output: output_tail(&output.stderr),
+            });
         } else {
             debug!(self.log, "{} finished successfully", command.as_ref());
         }
@@ -52,15 +182,15 @@ impl Builder {
         &self,
         dir: impl AsRef<Path>,
         pattern: impl AsRef<OsStr>,
-    ) -> io::Result<PathBuf> {
+    ) -> Result<PathBuf> {
         debug!(
             self.log,
             "searching in {:?} for a file starting with {:?}",
-            dir.as_ref().to_path_buf().join("debug/target/deps"),
+            dir.as_ref(),
             pattern.as_ref()
         );
         let pattern = Self::os_str_to_string(pattern.as_ref());
-        let mut read_dir = read_dir(dir.as_ref().to_path_buf().join("target/debug/deps")).await?;
+        let mut read_dir = read_dir(dir.as_ref()).await?;
         while let Some(next) = read_dir.next_entry().await? {
             let file_name = Self::os_str_to_string(next.file_name());
             if next.file_type().await?.is_file()
@@ -70,26 +200,45 @@ impl Builder {
                 return Ok(next.path());
             }
         }
-        return Err(Self::error(format!("cannot find file {}", pattern)));
+        Err(Error::other(format!(
+            "cannot find file {} in {}",
+            pattern,
+            dir.as_ref().to_string_lossy()
+        )))
     }
 
-    pub async fn kcov(&self, root: impl AsRef<Path>, dir: impl AsRef<Path>) -> io::Result<()> {
+    /// Renders the coverage report of the project tests over the corpus, running them with the environment.
+    pub async fn kcov(&self, dir: impl AsRef<Path>, corpus: Option<&str>, env: &HashMap<String, String>) -> Result<()> {
         debug!(self.log, "Running cargo build"; "dir" => dir.as_ref().to_str());
 
-        let KCov { kcov_args } = self
+        let KCov { kcov_args, .. } = self
             .kcov
             .as_ref()
             .expect("builder::kcov() shouldn't be called");
 
-        let build_output = Command::new("cargo")
-            .args(&["build", "--tests"])
-            .current_dir(&dir)
-            .output()
+        let build_output = self
+            .output(
+                "cargo build",
+                Command::new("cargo")
+                    .args(&["build", "--tests"])
+                    .envs(&self.cargo_env)
+                    .current_dir(&dir),
+                self.kcov_timeout,
+            )
             .await?;
-        self.check_output("cargo build", build_output)?;
+        self.check_output("cargo build", &dir, build_output)?;
 
+        // the project might be a member of a workspace, so its artifacts are
+        // located in the workspace target directory
+        let metadata = Metadata::read(&dir, &self.log).await?;
+        let dir_name = Self::os_str_to_string(dir.as_ref().file_name().expect("no file name"));
+        let crate_name = metadata
+            .members()
+            .find(|p| p.dir() == dir.as_ref())
+            .map(|p| p.name.replace('-', "_"))
+            .unwrap_or(dir_name);
         let test_file = self
-            .find_file(&dir, dir.as_ref().file_name().expect("no file name"))
+            .find_file(metadata.profile_dir("debug").join("deps"), crate_name)
             .await?;
         let mut test_command = Command::new("kcov");
         test_command
@@ -97,63 +246,75 @@ impl Builder {
             .args(kcov_args)
             .arg(test_file)
             .current_dir(dir.as_ref())
-            .env(
-                "LD_LIBRARY_PATH",
-                PathBuf::from(root.as_ref()).join("tezos/sys/lib_tezos/artifacts/"),
-            );
-        if let Some(corpus) = &self.corpus {
+            .envs(env);
+        if let Some(corpus) = corpus {
             test_command.env("CORPUS", corpus);
         }
 
         debug!(self.log, "Running kcov"; "command" => FnValue(|_| format!("{:?}", test_command)));
-        self.check_output("kcov", test_command.output().await?)?;
+        let output = self.output("kcov", &mut test_command, self.kcov_timeout).await?;
+        self.check_output("kcov", &dir, output)?;
 
         Ok(())
     }
 
-    pub async fn clean(&self, dir: impl AsRef<Path>) -> io::Result<()> {
+    pub async fn clean(&self, dir: impl AsRef<Path>) -> Result<()> {
         debug!(self.log, "Running cargo clean"; "dir" => dir.as_ref().to_str());
-        let output = Command::new("cargo")
-            .arg("clean")
-            .current_dir(dir)
-            .output()
+        let output = self
+            .output(
+                "cargo clean",
+                Command::new("cargo")
+                    .arg("clean")
+                    .envs(&self.cargo_env)
+                    .current_dir(&dir),
+                self.clean_timeout,
+            )
             .await?;
-
-        if output.status.success() {
-            debug!(self.log, "cargo build finished successfully");
-        } else {
-            debug!(self.log, "cargo build returned error";
-                   "stderr" => FnValue(|_| std::str::from_utf8(&output.stderr).unwrap_or("<invalid utf8>")),
-                   "code" => output.status.code());
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                "error running cargo clean",
-            ));
-        }
-
-        Ok(())
+        self.check_output("cargo clean", &dir, output)
     }
 
-    pub async fn build(&self, dir: impl AsRef<Path>) -> io::Result<()> {
-        debug!(self.log, "Running cargo hfuzz build"; "dir" => dir.as_ref().to_str());
-        let output = Command::new("cargo")
-            .args(&["hfuzz", "build"])
-            .current_dir(dir)
-            .output()
+    /// Builds fuzz targets of the variant, returning the peak resource usage of the build.
+    pub async fn build(
+        &self,
+        dir: impl AsRef<Path>,
+        variant: &Variant,
+        env: &HashMap<String, String>,
+    ) -> Result<Usage> {
+        debug!(self.log, "Running cargo hfuzz build"; "dir" => dir.as_ref().to_str(), "variant" => &variant.name);
+        let (output, usage) = self
+            .output_with_usage(
+                "cargo hfuzz build",
+                hfuzz::cargo_hfuzz(self.cargo_hfuzz.as_deref())
+                    .arg("build")
+                    .args(variant.cargo_args())
+                    .envs(&self.cargo_env)
+                    .envs(env)
+                    .envs(variant.env(env))
+                    .current_dir(&dir),
+                self.build_timeout,
+            )
             .await?;
+        self.check_output("cargo hfuzz build", &dir, output)?;
+        debug!(self.log, "Resource usage of cargo hfuzz build"; "usage" => usage.to_string());
+        Ok(usage)
+    }
 
-        if output.status.success() {
-            debug!(self.log, "cargo build finished successfully");
-        } else {
-            debug!(self.log, "cargo build returned error";
-                   "stderr" => FnValue(|_| std::str::from_utf8(&output.stderr).unwrap_or("<invalid utf8>")),
-                   "code" => output.status.code());
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                "error running cargo hfuzz build",
-            ));
-        }
-
-        Ok(())
+    /// Runs the build command of a `command` engine project, returning the peak resource usage of the build.
+    pub async fn build_command(&self, dir: impl AsRef<Path>, command: &[String], env: &HashMap<String, String>) -> Result<Usage> {
+        let (program, args) = match command.split_first() {
+            Some(command) => command,
+            None => return Ok(Usage::default()),
+        };
+        let name = command.join(" ");
+        debug!(self.log, "Running build command"; "dir" => dir.as_ref().to_str(), "command" => &name);
+        let (output, usage) = self
+            .output_with_usage(
+                &name,
+                Command::new(program).args(args).envs(env).current_dir(&dir),
+                self.build_timeout,
+            )
+            .await?;
+        self.check_output(&name, &dir, output)?;
+        Ok(usage)
     }
 }
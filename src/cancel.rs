@@ -0,0 +1,160 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use tokio_util::sync::CancellationToken;
+
+/// Why a descendant scope cannot be cancelled.
+#[derive(Debug, PartialEq)]
+pub enum ScopeError {
+    NotFound,
+    /// Several scopes have the name, at these paths
+    Ambiguous(Vec<String>),
+}
+
+/// Hierarchical cancellation scope of a fuzzing run.
+///
+/// Cancelling a scope cancels all its descendants: a run scope has a child scope for
+/// each fuzzing project, and a project scope has one for each of its fuzz targets.
+#[derive(Clone, Default)]
+pub struct Scope {
+    token: CancellationToken,
+    children: Arc<RwLock<HashMap<String, Scope>>>,
+}
+
+impl Scope {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Child scope with the name, created on first use.
+    pub fn child(&self, name: &str) -> Scope {
+        self.children
+            .write()
+            .unwrap()
+            .entry(name.to_string())
+            .or_insert_with(|| Scope {
+                token: self.token.child_token(),
+                children: Arc::default(),
+            })
+            .clone()
+    }
+
+    /// Cancels the descendant scope at the path of child names joined by `/`, like
+    /// `project/target` or `project/` of a run, or with the name at any depth if only one
+    /// scope has it, like `project` or `target`.
+    pub fn cancel_child(&self, path: &str) -> Result<(), ScopeError> {
+        if path.contains('/') {
+            let mut scope = self.clone();
+            for name in path.split('/').filter(|name| !name.is_empty()) {
+                let child = scope.children.read().unwrap().get(name).cloned();
+                scope = child.ok_or(ScopeError::NotFound)?;
+            }
+            scope.cancel();
+            return Ok(());
+        }
+        let mut found = vec![];
+        self.find(path, "", &mut found);
+        match found.len() {
+            0 => Err(ScopeError::NotFound),
+            1 => {
+                found.remove(0).1.cancel();
+                Ok(())
+            }
+            _ => {
+                // children of this scope are listed as `name/`, so their paths aren't ambiguous
+                let mut paths = found
+                    .into_iter()
+                    .map(|(path, _)| if path.contains('/') { path } else { path + "/" })
+                    .collect::<Vec<_>>();
+                paths.sort();
+                Err(ScopeError::Ambiguous(paths))
+            }
+        }
+    }
+
+    /// Collects descendant scopes with the name, with their paths.
+    fn find(&self, name: &str, prefix: &str, found: &mut Vec<(String, Scope)>) {
+        for (child_name, child) in self.children.read().unwrap().iter() {
+            let path = format!("{}{}", prefix, child_name);
+            if child_name == name {
+                found.push((path.clone(), child.clone()));
+            }
+            child.find(name, &format!("{}/", path), found);
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// Completes when the scope or any of its ancestors is cancelled.
+    pub async fn cancelled(&self) {
+        self.token.cancelled().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Run scope with projects `p` (targets `a`, `b`) and `q` (targets `a`, `p`).
+    fn run() -> Scope {
+        let run = Scope::new();
+        let p = run.child("p");
+        p.child("a");
+        p.child("b");
+        let q = run.child("q");
+        q.child("a");
+        q.child("p");
+        run
+    }
+
+    #[test]
+    fn cancels_by_path() {
+        let run = run();
+        assert_eq!(run.cancel_child("q/a"), Ok(()));
+        assert!(run.child("q").child("a").is_cancelled());
+        assert!(!run.child("p").child("a").is_cancelled());
+        assert!(!run.child("q").is_cancelled());
+        assert_eq!(run.cancel_child("q"), Ok(()));
+        assert!(run.child("q").child("p").is_cancelled());
+        assert!(!run.child("p").is_cancelled());
+    }
+
+    #[test]
+    fn cancels_unique_name() {
+        let run = run();
+        assert_eq!(run.cancel_child("b"), Ok(()));
+        assert!(run.child("p").child("b").is_cancelled());
+        assert!(!run.child("p").child("a").is_cancelled());
+    }
+
+    #[test]
+    fn rejects_ambiguous_names() {
+        let run = run();
+        assert_eq!(run.cancel_child("a"), Err(ScopeError::Ambiguous(vec!["p/a".to_string(), "q/a".to_string()])));
+        // a project named like a target of another project
+        assert_eq!(run.cancel_child("p"), Err(ScopeError::Ambiguous(vec!["p/".to_string(), "q/p".to_string()])));
+        assert!(!run.child("p").is_cancelled());
+        assert_eq!(run.cancel_child("p/"), Ok(()));
+        assert!(run.child("p").is_cancelled());
+        assert!(!run.child("q").child("p").is_cancelled());
+        assert!(!run.child("q").child("a").is_cancelled());
+    }
+
+    #[test]
+    fn rejects_unknown_scopes() {
+        let run = run();
+        assert_eq!(run.cancel_child("c"), Err(ScopeError::NotFound));
+        assert_eq!(run.cancel_child("p/c"), Err(ScopeError::NotFound));
+        assert_eq!(run.cancel_child("r/a"), Err(ScopeError::NotFound));
+        assert_eq!(run.cancel_child("p/a/x"), Err(ScopeError::NotFound));
+        assert!(!run.is_cancelled());
+    }
+}
@@ -0,0 +1,298 @@
+use std::{collections::HashSet, ffi::OsStr, path::Path};
+
+use serde::Serialize;
+use slog::Logger;
+
+use crate::{
+    config::{Config, Engine},
+    github,
+    server::{discover_projects, project_dir},
+    slack,
+    workspace::Metadata,
+};
+
+/// Issues found in the configuration, printed as they are reported unless
+/// JSON output is requested.
+#[derive(Default, Serialize)]
+struct Issues {
+    errors: Vec<String>,
+    warnings: Vec<String>,
+    #[serde(skip)]
+    json: bool,
+}
+
+impl Issues {
+    fn error(&mut self, message: impl AsRef<str>) {
+        if !self.json {
+            println!("error: {}", message.as_ref());
+        }
+        self.errors.push(message.as_ref().to_string());
+    }
+
+    fn warning(&mut self, message: impl AsRef<str>) {
+        if !self.json {
+            println!("warning: {}", message.as_ref());
+        }
+        self.warnings.push(message.as_ref().to_string());
+    }
+
+    /// Prints the summary, or all issues as JSON.
+    fn print(&self) {
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(self).unwrap());
+        } else {
+            println!("{} errors, {} warnings", self.errors.len(), self.warnings.len());
+        }
+    }
+
+    fn dir(&mut self, path: &Path, what: &str, required: bool) {
+        if path.is_dir() {
+            return;
+        }
+        let message = if path.exists() {
+            format!("{} `{}` is not a directory", what, path.to_string_lossy())
+        } else {
+            format!("{} `{}` does not exist", what, path.to_string_lossy())
+        };
+        if required {
+            self.error(message)
+        } else {
+            self.warning(message)
+        }
+    }
+}
+
+/// Validates the configuration file, printing found issues.
+///
+/// If `checkout` directory is specified, fuzzing projects are looked up in it.
+/// Unless `offline`, external services like Slack are checked too. With `json`,
+/// issues are printed as a JSON object with `errors` and `warnings` lists.
+///
+/// Returns `false` if errors are found.
+pub async fn check(file: impl AsRef<OsStr>, checkout: Option<&Path>, offline: bool, json: bool, log: &Logger) -> bool {
+    let mut issues = Issues {
+        json,
+        ..Issues::default()
+    };
+    let config = match Config::read(file.as_ref()) {
+        Ok(config) => config,
+        Err(e) => {
+            issues.error(format!("cannot read configuration: {}", e));
+            if json {
+                issues.print();
+            }
+            return false;
+        }
+    };
+    match Config::unknown_keys(file.as_ref()) {
+        Ok(keys) => keys
+            .iter()
+            .for_each(|key| issues.warning(format!("unknown key `{}` is ignored", key))),
+        Err(e) => issues.error(format!("cannot read configuration: {}", e)),
+    }
+
+    check_paths(&config, &mut issues);
+    check_urls(&config, &mut issues);
+    check_targets(&config, &mut issues);
+    if let Some(checkout) = checkout {
+        check_checkout(&config, checkout, &mut issues, log).await;
+    }
+    if let Some(slack) = &config.slack {
+        if slack.max_length == 0 || slack.max_length > slack::MAX_MESSAGE_LENGTH {
+            issues.error(format!("`slack.max_length` should be between 1 and {}", slack::MAX_MESSAGE_LENGTH));
+        }
+    }
+    if let Some(factor) = config.feedback.exec_rate_drop {
+        if factor <= 1.0 {
+            issues.error("`feedback.exec_rate_drop` should be greater than 1");
+        } else if config.honggfuzz.stats_dir.is_none() {
+            issues.warning("`feedback.exec_rate_drop` is set, but execution rates are not sampled without `honggfuzz.stats_dir`");
+        }
+    }
+    if !offline {
+        if let Some(slack) = &config.slack {
+            if slack.token.is_empty() {
+                issues.error("no Slack token is configured, set `slack.token` or `SLACK_AUTH_TOKEN`");
+            } else if let Err(e) = slack::check_channel(&slack.channel, &slack.token).await {
+                issues.error(format!("cannot access Slack channel `{}`: {}", slack.channel, e));
+            }
+        }
+    }
+
+    issues.print();
+    issues.errors.is_empty()
+}
+
+fn check_paths(config: &Config, issues: &mut Issues) {
+    if let Some(corpus) = &config.corpus {
+        issues.dir(Path::new(corpus), "corpus directory", false);
+    } else {
+        if config.branch_corpus {
+            issues.warning("`branch_corpus` has no effect without `corpus`");
+        }
+        if config.backup.is_some() {
+            issues.warning("`[backup]` has no effect without `corpus`");
+        }
+        if config.storage.is_some() {
+            issues.warning("`[storage]` has no effect without `corpus`");
+        }
+    }
+    for (name, conf) in &config.targets {
+        if let Some(corpus) = &conf.corpus {
+            issues.dir(Path::new(corpus), &format!("corpus directory of `{}`", name), false);
+        }
+    }
+    issues.dir(&config.reports_path, "reports directory", false);
+    if let Some(cargo_home) = &config.build.cargo_home {
+        issues.dir(cargo_home, "cargo home", config.build.offline);
+    }
+    if let Some(vendor_dir) = &config.build.vendor_dir {
+        issues.dir(vendor_dir, "vendored crates directory", true);
+    }
+    if let Some(backup) = &config.backup {
+        issues.dir(&backup.path, "backup directory", false);
+        if backup.keep == 0 {
+            issues.error("`backup.keep` should be at least 1");
+        }
+    }
+}
+
+fn check_urls(config: &Config, issues: &mut Issues) {
+    if let Some(url) = &config.url {
+        if !matches!(url.scheme(), "http" | "https") {
+            issues.error(format!("`url` should be an HTTP(S) url, got `{}`", url));
+        }
+    }
+    if let Some(github) = &config.github {
+        if config.url.is_none() {
+            issues.warning("`[github]` deployments need `url` of the reports");
+        }
+        match &github.app {
+            Some(app) => {
+                if let Err(e) = github::app_jwt(app) {
+                    issues.error(format!("invalid `github.app.private_key_file`: {}", e));
+                }
+            }
+            None if github.token.is_empty() => {
+                issues.error("no GitHub token is configured, set `github.token` or `GITHUB_TOKEN`, or `[github.app]`");
+            }
+            None => (),
+        }
+        if let Some(repository) = &github.repository {
+            if repository.split('/').count() != 2 {
+                issues.error(format!("`github.repository` should be `<owner>/<name>`, got `{}`", repository));
+            }
+        }
+    }
+    if let Some(comparison) = &config.harness_comparison {
+        if comparison.comment && config.github.is_none() {
+            issues.warning("`harness_comparison.comment` has no effect without `[github]`");
+        }
+    }
+    if let Some(storage) = &config.storage {
+        if !matches!(storage.url.scheme(), "s3" | "gs") {
+            issues.error(format!("`storage.url` should be an `s3://` or `gs://` url, got `{}`", storage.url));
+        }
+    }
+    for (name, conf) in &config.targets {
+        for seed in &conf.seeds {
+            if seed.contains("://") {
+                if !(seed.starts_with("http://") || seed.starts_with("https://")) {
+                    issues.error(format!("seed source `{}` of `{}` should be an HTTP(S) url or a local path", seed, name));
+                }
+            } else if !seed.contains("{target}") && !Path::new(seed).exists() {
+                issues.warning(format!("seed source `{}` of `{}` does not exist", seed, name));
+            }
+        }
+    }
+}
+
+fn check_targets(config: &Config, issues: &mut Issues) {
+    if config.targets.is_empty() && config.discovery.is_none() {
+        issues.error("no fuzzing projects are configured in `[targets]`, and `[discovery]` is not enabled");
+    }
+    let mut all_targets = HashSet::new();
+    for (name, conf) in &config.targets {
+        if conf.targets.is_empty() {
+            issues.warning(format!("project `{}` has no targets and will not be fuzzed", name));
+        }
+        for target in &conf.targets {
+            if !all_targets.insert(target) {
+                issues.error(format!("target `{}` of `{}` is also specified in another project", target, name));
+            }
+        }
+        for branch in &conf.branches {
+            if !config.branches.is_empty() && !config.branches.contains(branch) {
+                issues.warning(format!("branch `{}` of `{}` is not fuzzed", branch, name));
+            }
+        }
+    }
+    for branch in &config.protected_branches {
+        if !config.branches.is_empty() && !config.branches.contains(branch) {
+            issues.warning(format!("protected branch `{}` is not fuzzed", branch));
+        }
+    }
+    if let Some(refuzz) = &config.scheduling.refuzz {
+        if refuzz.interval == 0 {
+            issues.error("`scheduling.refuzz.interval` should be positive");
+        }
+        for entry in &refuzz.branches {
+            let branch = match entry.split_once('/') {
+                Some((repo, branch)) if config.repositories.contains_key(repo) => branch,
+                _ if config.repository.is_none() => {
+                    issues.warning(format!("re-fuzzed branch `{}` needs `repository`", entry));
+                    continue;
+                }
+                _ => entry,
+            };
+            if !config.branches.is_empty() && !config.branches.iter().any(|b| b == branch) {
+                issues.warning(format!("re-fuzzed branch `{}` is not fuzzed", entry));
+            }
+        }
+    }
+    if let Some(cross_pollination) = &config.cross_pollination {
+        for group in &cross_pollination.groups {
+            if group.len() < 2 {
+                issues.warning(format!("cross-pollination group {:?} should have at least two targets", group));
+            }
+            // discovered targets are only known in the checkout
+            for target in group.iter().filter(|t| config.discovery.is_none() && !all_targets.contains(t)) {
+                issues.warning(format!("cross-pollinated target `{}` is not configured", target));
+            }
+        }
+    }
+}
+
+async fn check_checkout(config: &Config, checkout: &Path, issues: &mut Issues, log: &Logger) {
+    if config.code_path.is_some() {
+        issues.dir(&config.code_dir(checkout), "fuzzed project", true);
+    }
+    let metadata = match Metadata::read_opt(checkout, log).await {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            issues.warning(format!("cannot read checkout workspace metadata: {}", e));
+            None
+        }
+    };
+    let discovered = discover_projects(checkout, config, &metadata);
+    for (name, conf) in &discovered {
+        if !issues.json {
+            println!("discovered fuzzing project `{}` with targets {}", name, conf.targets.join(", "));
+        }
+    }
+    if config.targets.is_empty() && discovered.is_empty() && config.discovery.is_some() {
+        issues.error("no fuzzing projects are discovered in the checkout");
+    }
+    for (name, conf) in &config.targets {
+        let dir = project_dir(checkout, name, conf, &metadata);
+        if conf.engine == Engine::Command {
+            issues.dir(&dir, &format!("`{}` project", name), true);
+        } else if !dir.join("Cargo.toml").is_file() {
+            issues.error(format!(
+                "fuzzing project `{}` is not found in the checkout, expected at `{}`",
+                name,
+                dir.to_string_lossy()
+            ));
+        }
+    }
+}
@@ -1,24 +1,155 @@
-use std::{ffi::OsStr, io};
+use std::{
+    ffi::OsStr,
+    path::{Path, PathBuf},
+    process::Output,
+};
 
 use slog::{info, FnValue};
 use tokio::process::Command;
 
+use crate::error::{output_tail, Error, Result};
+
+/// Checks out the fuzzing project into `dir`, with the fuzzed project at the `branch`
+/// (or the `commit`) of the repository `url`.
+///
+/// Without the `script`, the repository is cloned with git along with its submodules,
+/// as a repository containing its fuzzing projects. The script is called with the
+/// directory, the url, the branch and the optional commit as arguments instead.
 pub async fn checkout(
     dir: impl AsRef<OsStr>,
     url: impl AsRef<str>,
     branch: impl AsRef<str>,
+    commit: Option<&str>,
+    script: Option<&Path>,
     log: slog::Logger,
-) -> io::Result<()> {
+) -> Result<()> {
     let dir = dir.as_ref();
-    info!(log, "Checking out"; "dir" => dir.to_str(), "url" => url.as_ref(), "branch" => branch.as_ref());
-    let output = Command::new("./checkout.sh")
-        .arg(dir)
-        .arg(url.as_ref())
-        .arg(branch.as_ref())
+    info!(log, "Checking out"; "dir" => dir.to_str(), "url" => url.as_ref(), "branch" => branch.as_ref(), "commit" => commit,
+          "script" => script.map(|s| s.to_string_lossy().into_owned()));
+    let error = |output: &Output| Error::Checkout {
+        url: url.as_ref().to_string(),
+        branch: commit.unwrap_or(branch.as_ref()).to_string(),
+        dir: PathBuf::from(dir),
+        output: output_tail(&output.stderr),
+    };
+    let output = match script {
+        Some(script) => {
+            Command::new(script)
+                .arg(dir)
+                .arg(url.as_ref())
+                .arg(branch.as_ref())
+                .args(commit)
+                .output()
+                .await?
+        }
+        None => {
+            let output = Command::new("git")
+                .args(&["clone", "--recurse-submodules", "--branch", branch.as_ref(), url.as_ref()])
+                .arg(dir)
+                .output()
+                .await?;
+            match commit {
+                Some(commit) if output.status.success() => {
+                    let output = Command::new("git").args(&["checkout", commit]).current_dir(dir).output().await?;
+                    if !output.status.success() {
+                        return Err(error(&output));
+                    }
+                    Command::new("git")
+                        .args(&["submodule", "update", "--init", "--recursive"])
+                        .current_dir(dir)
+                        .output()
+                        .await?
+                }
+                _ => output,
+            }
+        }
+    };
+
+    if !output.status.success() {
+        return Err(error(&output));
+    }
+
+    slog::debug!(log, "Checkout command completes successfully"; "output" => FnValue(|_| std::str::from_utf8(&output.stderr).unwrap_or("<invalid utf8>")));
+
+    Ok(())
+}
+
+/// Id of the parent commit of the `commit`, or of the head, in the git repository in `dir`.
+pub async fn parent(dir: impl AsRef<Path>, commit: Option<&str>) -> Result<String> {
+    let output = Command::new("git")
+        .arg("rev-parse")
+        .arg(format!("{}^", commit.unwrap_or("HEAD")))
+        .current_dir(dir)
         .output()
         .await?;
+    if !output.status.success() {
+        return Err(Error::other(format!("cannot get the parent commit: {}", output_tail(&output.stderr))));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
 
-    slog::debug!(log, "Checkout command completes successfully"; "output" => FnValue(|_| std::str::from_utf8(&output.stderr).unwrap_or("<invalid utf8>")));
+/// Id of the head commit of the git repository in `dir`.
+pub async fn head(dir: impl AsRef<Path>) -> Result<String> {
+    let output = Command::new("git").arg("rev-parse").arg("HEAD").current_dir(dir).output().await?;
+    if !output.status.success() {
+        return Err(Error::other(format!("cannot get the head commit: {}", output_tail(&output.stderr))));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
 
+/// Files changed between the `from` and `to` commits, relative to the git repository in `dir`.
+pub async fn changed_files(dir: impl AsRef<Path>, from: &str, to: &str) -> Result<Vec<PathBuf>> {
+    let output = Command::new("git")
+        .arg("diff")
+        .arg("--name-only")
+        .arg(from)
+        .arg(to)
+        .current_dir(dir)
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(Error::other(format!("cannot list changed files: {}", output_tail(&output.stderr))));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).lines().map(PathBuf::from).collect())
+}
+
+/// Restores files under `path` of the git repository in `dir` to their state at the
+/// `commit`, removing ones added since.
+pub async fn restore(dir: impl AsRef<Path>, commit: &str, path: &Path) -> Result<()> {
+    let output = Command::new("git")
+        .arg("restore")
+        .arg(format!("--source={}", commit))
+        .arg("--staged")
+        .arg("--worktree")
+        .arg("--")
+        .arg(path)
+        .current_dir(dir)
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(Error::other(format!(
+            "cannot restore {} at {}: {}",
+            path.to_string_lossy(),
+            commit,
+            output_tail(&output.stderr)
+        )));
+    }
     Ok(())
 }
+
+/// Ids of commits after the `from` commit up to the `to` one, oldest first, that are
+/// descendants of `from`, in the git repository in `dir`.
+pub async fn commits_between(dir: impl AsRef<Path>, from: &str, to: &str) -> Result<Vec<String>> {
+    let output = Command::new("git")
+        .arg("rev-list")
+        .arg("--reverse")
+        .arg("--ancestry-path")
+        .arg(format!("{}..{}", from, to))
+        .current_dir(dir)
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(Error::other(format!("cannot list commits: {}", output_tail(&output.stderr))));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).lines().map(String::from).collect())
+}
@@ -0,0 +1,710 @@
+//! Subcommands of the `fuzz-ci` binary, run with their parsed command line arguments.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use slog::{crit, error, info};
+use url::Url;
+
+use crate::{
+    artifacts, backup,
+    cancel::Scope,
+    checkout, common,
+    config::{self, HonggfuzzConfig, TargetConfig},
+    corpus, dashboard,
+    error::Error,
+    feedback::{Feedback, LoggerClient, RegressionsOnly},
+    hfuzz, policy, replay, report, runs, server, simulate, trigger,
+};
+
+/// Logger writing to the terminal in the format, at the level and above.
+pub fn logger(format: config::LogFormat, level: slog::Level) -> slog::Logger {
+    use slog::Drain;
+    let drain = match format {
+        config::LogFormat::Text => {
+            let decorator = slog_term::TermDecorator::new().build();
+            let drain = slog_term::FullFormat::new(decorator).build().fuse();
+            slog_async::Async::new(drain).build()
+        }
+        config::LogFormat::Json => {
+            let drain = slog_json::Json::new(std::io::stderr())
+                .add_default_keys()
+                .build()
+                .fuse();
+            slog_async::Async::new(drain).build()
+        }
+    };
+    slog::Logger::root(drain.filter_level(level).fuse(), slog::o!())
+}
+
+/// Feedback reporting to the log, for running fuzzing from the command line.
+async fn logger_feedback(config: &config::Config, log: &slog::Logger) -> Arc<Feedback> {
+    let client = LoggerClient::new("feedback", log.clone()).with_level(config.feedback.log_level);
+    let report = report::Report::new(
+        &config.reports_path,
+        &config.url,
+        Path::new("reports"),
+        None,
+        &config.time,
+        log.new(slog::o!("role" => "report")),
+    )
+    .await
+    .unwrap();
+    Arc::new(Feedback::new(
+        &config.feedback,
+        &config.time,
+        RegressionsOnly::with_policy(Box::new(client), config.feedback.log_policy),
+        report,
+        log.clone(),
+    ))
+}
+
+/// Writes the commented sample configuration to the file, or prints it if not specified.
+///
+/// An existing file is overwritten only with `force`.
+pub fn config_init_command(path: Option<&Path>, force: bool) -> Result<(), Error> {
+    match path {
+        Some(path) if path.exists() && !force => Err(Error::other(format!(
+            "File {} already exists, use --force to overwrite it",
+            path.to_string_lossy()
+        ))),
+        Some(path) => std::fs::write(path, config::SAMPLE)
+            .map_err(|e| Error::other(format!("Cannot write configuration to {}: {}", path.to_string_lossy(), e))),
+        None => {
+            print!("{}", config::SAMPLE);
+            Ok(())
+        }
+    }
+}
+
+/// Options of the `checkout` command.
+pub struct CheckoutOptions {
+    /// Directory to check out to
+    pub dir: PathBuf,
+    pub repo: String,
+    pub branch: String,
+    /// Commit to check out, the branch head if not specified
+    pub commit: Option<String>,
+}
+
+pub async fn checkout_command(options: CheckoutOptions, config: &config::Config, log: &slog::Logger) -> Result<(), Error> {
+    let CheckoutOptions { dir, repo, branch, commit } = options;
+    checkout::checkout(dir, repo, branch, commit.as_deref(), config.checkout_script.as_deref(), log.clone()).await
+}
+
+/// Options of the `hfuzz` command.
+pub struct HfuzzOptions {
+    /// Directory of the honggfuzz project
+    pub dir: PathBuf,
+    pub corpus: Option<String>,
+    pub targets: Vec<String>,
+    /// Honggfuzz run arguments
+    pub run_args: String,
+    /// Whether to show the dashboard of targets instead of the log
+    pub tui: bool,
+}
+
+/// Fuzzes targets of the built project, showing the dashboard with `tui`, failing if
+/// fuzzing fails.
+pub async fn hfuzz_command(options: HfuzzOptions, config: &config::Config, log: &slog::Logger) -> Result<(), Error> {
+    let HfuzzOptions { dir, corpus, targets, run_args, tui } = options;
+    let target_ids = targets.clone();
+    let targets = TargetConfig::new(None, targets);
+    let mut hfuzz_config = HonggfuzzConfig::new(run_args);
+
+    // the dashboard replaces the log, and shows speed from honggfuzz statistics files
+    let log = if tui { slog::Logger::root(slog::Discard, slog::o!()) } else { log.clone() };
+    let _stats_tempdir = if tui {
+        let tempdir = match config.honggfuzz.stats_dir.clone() {
+            Some(stats_dir) => {
+                hfuzz_config.stats_dir = Some(stats_dir);
+                None
+            }
+            None => {
+                let tempdir = tempfile::tempdir().expect("cannot create temporary directory");
+                hfuzz_config.stats_dir = Some(tempdir.path().to_path_buf());
+                Some(tempdir)
+            }
+        };
+        tempdir
+    } else {
+        None
+    };
+    let stats_dir = hfuzz_config.stats_dir.clone();
+
+    let output_format = match hfuzz::detect(config.honggfuzz.cargo_hfuzz.as_deref()).await {
+        Ok((_, format)) => format,
+        Err(e) => return Err(Error::other(format!("Cannot use installed cargo-hfuzz: {}", e))),
+    };
+    let feedback = logger_feedback(config, &log).await;
+    let cancel = Scope::new();
+    feedback.started().await;
+    let run = hfuzz::run(
+        dir,
+        config.env.clone(),
+        targets,
+        hfuzz_config,
+        output_format,
+        corpus,
+        feedback.clone(),
+        None,
+        cancel.clone(),
+        log.new(slog::o!()),
+    );
+    match stats_dir {
+        Some(stats_dir) if tui => {
+            tokio::pin!(run);
+            tokio::select! {
+                result = &mut run => result,
+                shown = dashboard::show(target_ids, stats_dir, feedback, cancel) => {
+                    if let Err(e) = shown {
+                        eprintln!("Cannot show dashboard: {}", e);
+                    }
+                    run.await
+                }
+            }
+        }
+        _ => run.await,
+    }
+}
+
+/// Options of the `replay` command.
+pub struct ReplayOptions {
+    /// Checkout of the fuzzing project
+    pub checkout: PathBuf,
+    pub project: String,
+    /// Target, as `target@variant` for build variants
+    pub target: String,
+    pub input: PathBuf,
+    /// Whether to build the target before running it
+    pub build: bool,
+}
+
+/// Runs the fuzz target with the input, failing if the target doesn't exit successfully.
+pub async fn replay_command(options: ReplayOptions, config: &config::Config, log: &slog::Logger) -> Result<(), Error> {
+    match replay::replay(config, &options.checkout, &options.project, &options.target, &options.input, options.build, log).await {
+        Ok(status) if status.success() => {
+            info!(log, "Target exited successfully");
+            Ok(())
+        }
+        Ok(status) => Err(Error::other(format!("Target exited with {}", status))),
+        Err(e) => Err(Error::other(format!("Cannot replay input: {}", e))),
+    }
+}
+
+pub async fn server_command(
+    overrides: server::Overrides,
+    config_file: PathBuf,
+    config: config::Config,
+    log: slog::Logger,
+) -> Result<(), Error> {
+    server::start(config_file, config, overrides, log).await
+}
+
+/// Server of commands using the API of a running server.
+pub struct ApiOptions {
+    pub server: Url,
+    /// API token, `FUZZ_CI_API_TOKEN` if not specified
+    pub token: Option<String>,
+}
+
+/// Client of the server API specified on the command line.
+fn api_client(api: &ApiOptions) -> Result<trigger::Trigger, Error> {
+    let token = match &api.token {
+        Some(token) => token.clone(),
+        None => std::env::var("FUZZ_CI_API_TOKEN").map_err(|_| Error::other("no API token, use --token or FUZZ_CI_API_TOKEN"))?,
+    };
+    Ok(trigger::Trigger::new(api.server.clone(), token))
+}
+
+/// Options of the `trigger` command.
+pub struct TriggerOptions {
+    pub api: ApiOptions,
+    pub branch: String,
+    pub commit: Option<String>,
+    pub repo: Option<String>,
+    /// Targets to fuzz, all if empty
+    pub targets: Vec<String>,
+    /// Fuzzing time of each target, in seconds
+    pub duration: Option<u64>,
+    /// Whether to wait for the run to complete
+    pub wait: bool,
+    /// Interval between run status checks, in seconds
+    pub interval: u64,
+    pub json: bool,
+}
+
+/// Starts a run on the server, returning an error if it cannot be started or fails.
+pub async fn trigger_command(options: TriggerOptions) -> Result<(), Error> {
+    let json = options.json;
+    let trigger = api_client(&options.api)?;
+    let mut run = trigger
+        .start(&options.branch, options.commit.as_deref(), options.repo.as_deref(), &options.targets, options.duration)
+        .await?;
+    if !json {
+        println!("Run {} started", run.id);
+        if let Some(report_url) = &run.report_url {
+            println!("Report: {}", report_url);
+        }
+    }
+    if options.wait {
+        run = trigger.wait(&run.id, std::time::Duration::from_secs(options.interval)).await?;
+        if !json {
+            match run.verdict {
+                Some(verdict) => println!("Run {} completed with {:?}, verdict: {}", run.id, run.status, verdict.name()),
+                None => println!("Run {} completed with {:?}", run.id, run.status),
+            }
+        }
+    }
+    if json {
+        println!("{}", serde_json::to_string_pretty(&run)?);
+    }
+    match run.verdict {
+        Some(policy::Verdict::Pass) => return Ok(()),
+        Some(policy::Verdict::Fail) => return Err(Error::other(format!("run {} failed the policy", run.id))),
+        _ => (),
+    }
+    match run.status {
+        runs::RunStatus::Failure => Err(Error::other(format!("run {} failed", run.id))),
+        runs::RunStatus::Cancelled => Err(Error::other(format!("run {} is cancelled", run.id))),
+        _ => Ok(()),
+    }
+}
+
+/// Cancels the run, or only its project or target of the `scope`.
+pub async fn cancel_command(api: &ApiOptions, id: &str, scope: Option<&str>) -> Result<(), Error> {
+    let client = api_client(api)?;
+    let run = client.cancel(id, scope).await?;
+    match scope {
+        Some(scope) => println!("Cancelled `{}` of run {}", scope, run.id),
+        None => println!("Cancelled run {}", run.id),
+    }
+    Ok(())
+}
+
+pub async fn pause_command(api: &ApiOptions, id: &str, pause: bool) -> Result<(), Error> {
+    let client = api_client(api)?;
+    if pause {
+        let run = client.pause(id).await?;
+        println!("Paused run {}", run.id);
+    } else {
+        let run = client.resume(id).await?;
+        println!("Resumed run {}", run.id);
+    }
+    Ok(())
+}
+
+/// Options of the `baseline` command.
+pub struct BaselineOptions {
+    pub api: ApiOptions,
+    pub branch: String,
+    pub repo: Option<String>,
+    /// Targets to change the baseline of, all if empty
+    pub targets: Vec<String>,
+    /// Whether to remove the baseline instead of using the current status
+    pub reset: bool,
+}
+
+pub async fn baseline_command(options: BaselineOptions) -> Result<(), Error> {
+    let client = api_client(&options.api)?;
+    let baseline = if options.reset { report::Baseline::Reset } else { report::Baseline::Current };
+    let change = client
+        .baseline(&options.branch, options.repo.as_deref(), &options.targets, baseline)
+        .await?;
+    println!("Baseline of {} changed in {}", change.targets.join(", "), change.report);
+    Ok(())
+}
+
+/// Prints targets disabled on the server.
+pub async fn targets_command(api: &ApiOptions, json: bool) -> Result<(), Error> {
+    let client = api_client(api)?;
+    let targets = client.disabled_targets().await?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&targets)?);
+        return Ok(());
+    }
+    if targets.is_empty() {
+        println!("No targets are disabled");
+        return Ok(());
+    }
+    println!("{:<30} {:<20} SINCE", "TARGET", "DISABLED BY");
+    for target in targets {
+        println!(
+            "{:<30} {:<20} {}",
+            target.target,
+            target.by,
+            target.since.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+        );
+    }
+    Ok(())
+}
+
+pub async fn toggle_command(api: &ApiOptions, target: &str, disable: bool) -> Result<(), Error> {
+    let client = api_client(api)?;
+    if disable {
+        client.disable(target).await?;
+        println!("Disabled target {}", target);
+    } else {
+        client.enable(target).await?;
+        println!("Enabled target {}", target);
+    }
+    Ok(())
+}
+
+/// Prints control actions recorded in the audit log of the server.
+pub async fn audit_command(api: &ApiOptions, limit: Option<usize>, json: bool) -> Result<(), Error> {
+    let client = api_client(api)?;
+    let entries = client.audit(limit).await?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+        return Ok(());
+    }
+    println!("{:<25} {:<20} {:<12} {:<6} SUBJECT", "TIME", "CALLER", "ACTION", "STATUS");
+    for entry in entries {
+        println!(
+            "{:<25} {:<20} {:<12} {:<6} {}",
+            entry.time.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            entry.caller,
+            entry.action,
+            entry.status,
+            entry.subject
+        );
+    }
+    Ok(())
+}
+
+pub async fn deliveries_command(api: &ApiOptions, limit: Option<usize>, json: bool) -> Result<(), Error> {
+    let client = api_client(api)?;
+    let deliveries = client.deliveries(limit).await?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&deliveries)?);
+        return Ok(());
+    }
+    println!("{:<25} {:<40} {:<10} {:<10} REPLAYS", "RECEIVED", "DELIVERY", "EVENT", "STATUS");
+    for delivery in deliveries {
+        println!(
+            "{:<25} {:<40} {:<10} {:<10} {}",
+            delivery.received.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            delivery.id,
+            delivery.event,
+            format!("{:?}", delivery.status).to_lowercase(),
+            delivery.replays
+        );
+    }
+    Ok(())
+}
+
+pub async fn redeliver_command(api: &ApiOptions, id: &str) -> Result<(), Error> {
+    let client = api_client(api)?;
+    let delivery = client.replay_delivery(id).await?;
+    println!("Processed {} delivery {} again", delivery.event, delivery.id);
+    Ok(())
+}
+
+/// Prints the run, or runs started on the server if not specified.
+pub async fn status_command(api: &ApiOptions, id: Option<&str>, json: bool) -> Result<(), Error> {
+    let client = api_client(api)?;
+    let runs = match id {
+        Some(id) => vec![client.run(id).await?],
+        None => client.runs().await?,
+    };
+    if json {
+        if id.is_some() {
+            println!("{}", serde_json::to_string_pretty(&runs[0])?);
+        } else {
+            println!("{}", serde_json::to_string_pretty(&runs)?);
+        }
+        return Ok(());
+    }
+    println!("{:<20} {:<20} {:<10} DESCRIPTION", "RUN", "BRANCH", "STATUS");
+    for run in runs {
+        println!("{:<20} {:<20} {:<10} {}", run.id, run.branch, format!("{:?}", run.status).to_lowercase(), run.description);
+    }
+    Ok(())
+}
+
+/// Re-renders reports of the branches, or of all branches if not specified.
+pub async fn report_command(branches: Option<Vec<String>>, config: &config::Config, log: &slog::Logger) {
+    let branches: Vec<String> = match branches {
+        Some(branches) => branches
+            .iter()
+            .map(|b| common::sanitize_path_segment(b).to_string_lossy().into_owned())
+            .collect(),
+        None => match corpus::target_dirs(&config.reports_path).await {
+            Ok(dirs) => dirs.into_iter().map(|(branch, _)| branch).collect(),
+            Err(e) => {
+                error!(log, "Cannot read reports directory"; "error" => e.to_string());
+                return;
+            }
+        },
+    };
+    let mut rendered = 0;
+    for branch in branches {
+        let runs = match corpus::target_dirs(config.reports_path.join(&branch)).await {
+            Ok(runs) => runs,
+            Err(e) => {
+                error!(log, "Cannot read reports of {}", branch; "error" => e.to_string());
+                continue;
+            }
+        };
+        for (run, _) in runs {
+            let path = PathBuf::from(&branch).join(&run);
+            let regenerated = match report::Report::new(&config.reports_path, &config.url, &path, None, &config.time, log.clone()).await {
+                Ok(report) => report.regenerate().await,
+                Err(e) => Err(e),
+            };
+            match regenerated {
+                Ok(true) => rendered += 1,
+                Ok(false) => (),
+                Err(e) => error!(log, "Cannot re-render report"; "branch" => &branch, "run" => &run, "error" => e.to_string()),
+            }
+        }
+    }
+    info!(log, "Re-rendered {} reports", rendered);
+}
+
+/// Verifies artifacts of runs of the branches, or of all branches if not specified, against
+/// their manifests, printing invalid ones. Artifacts are looked up in the `store` directory,
+/// the configured one by default.
+///
+/// Returns `false` if any artifact is missing or corrupted.
+pub async fn verify_artifacts(
+    store: Option<&Path>,
+    branches: Option<Vec<String>>,
+    config: &config::Config,
+    log: &slog::Logger,
+) -> bool {
+    let store = match (store, &config.artifacts) {
+        (Some(dir), _) => artifacts::Store::new(dir),
+        (None, Some(artifacts)) => artifacts::Store::new(&artifacts.path),
+        (None, None) => {
+            eprintln!("No artifacts directory is configured, use --store");
+            return false;
+        }
+    };
+    let branches: Vec<String> = match branches {
+        Some(branches) => branches
+            .iter()
+            .map(|b| common::sanitize_path_segment(b).to_string_lossy().into_owned())
+            .collect(),
+        None => match corpus::target_dirs(&config.reports_path).await {
+            Ok(dirs) => dirs.into_iter().map(|(branch, _)| branch).collect(),
+            Err(e) => {
+                error!(log, "Cannot read reports directory"; "error" => e.to_string());
+                return false;
+            }
+        },
+    };
+    let (mut verified, mut valid) = (0, true);
+    for branch in branches {
+        let runs = match corpus::target_dirs(config.reports_path.join(&branch)).await {
+            Ok(runs) => runs,
+            Err(e) => {
+                error!(log, "Cannot read reports of {}", branch; "error" => e.to_string());
+                valid = false;
+                continue;
+            }
+        };
+        for (run, dir) in runs {
+            let invalid = match artifacts::Manifest::load(&dir).await {
+                Ok(manifest) => {
+                    verified += manifest.artifacts.len();
+                    manifest.verify(&store).await
+                }
+                Err(e) => Err(e),
+            };
+            match invalid {
+                Ok(invalid) => {
+                    for name in &invalid {
+                        println!("{}/{}: {}", branch, run, name);
+                    }
+                    valid &= invalid.is_empty();
+                }
+                Err(e) => {
+                    error!(log, "Cannot verify artifacts"; "branch" => &branch, "run" => &run, "error" => e.to_string());
+                    valid = false;
+                }
+            }
+        }
+    }
+    info!(log, "Verified {} artifacts", verified);
+    valid
+}
+
+/// Options of the `simulate` command.
+pub struct SimulateOptions {
+    /// Webhook url, the `run` endpoint of the configured address if not specified
+    pub url: Option<Url>,
+    pub repo: Option<String>,
+    pub branch: String,
+    /// Pushed commit, a random one if not specified
+    pub commit: Option<String>,
+    pub message: Option<String>,
+    /// Whether the push rewrites the branch history
+    pub forced: bool,
+}
+
+/// Sends the push event to the server webhook, failing if the server doesn't accept it.
+pub async fn simulate_command(options: SimulateOptions, config: &config::Config) -> Result<(), Error> {
+    let url = match options.url {
+        Some(url) => url,
+        None => simulate::webhook_url(config)?,
+    };
+    let repo = options
+        .repo
+        .as_deref()
+        .or(config.repository.as_deref())
+        .ok_or_else(|| Error::other("no repository, use --repo or configure `repository`"))?;
+    let repo = &config.repository_url(repo);
+    let branch = &options.branch;
+    if !config.branches.iter().any(|b| b == branch) {
+        eprintln!("Branch {} is not fuzzed, the server will skip it", branch);
+    }
+    let push = simulate::Push {
+        repo,
+        branch,
+        commit: options.commit.as_deref(),
+        message: options.message.as_deref().unwrap_or("Simulated push"),
+        forced: options.forced,
+    };
+    simulate::send(url.clone(), &push, config.webhook_secret.as_deref()).await?;
+    println!("Push event for {} sent to {}", branch, url);
+    Ok(())
+}
+
+/// Subcommands of the `corpus` command.
+pub enum CorpusCommand {
+    /// Prints number of inputs and their size for each target, as JSON with `json`
+    Stats { json: bool },
+    /// Minimizes corpora of the targets using the built honggfuzz project in `dir`
+    Minimize { dir: PathBuf, run_args: String, targets: Vec<String> },
+    /// Merges inputs of the directory, containing a subdirectory for each target, or only
+    /// inputs of the `target` if specified
+    Merge { dir: PathBuf, target: Option<String> },
+    /// Removes inputs with duplicate content
+    Dedup,
+    /// Restores the corpus from the backup archive, the most recent one by default
+    Restore { backup: Option<PathBuf> },
+}
+
+pub async fn corpus_command(command: CorpusCommand, config: &config::Config, log: &slog::Logger) {
+    let corpus = match &config.corpus {
+        Some(corpus) => corpus,
+        None => {
+            crit!(log, "No corpus directory is configured");
+            return;
+        }
+    };
+    match command {
+        CorpusCommand::Stats { json } => {
+            let dirs = match corpus::target_dirs(corpus).await {
+                Ok(dirs) => dirs,
+                Err(e) => {
+                    error!(log, "Error occurred"; "error" => e.to_string());
+                    return;
+                }
+            };
+            let (mut total_files, mut total_bytes) = (0, 0);
+            let mut targets = vec![];
+            if !json {
+                println!("{:<40} {:>10} {:>14}", "TARGET", "FILES", "BYTES");
+            }
+            for (target, dir) in dirs {
+                match corpus::size(&dir).await {
+                    Ok((files, bytes)) => {
+                        if json {
+                            targets.push(serde_json::json!({ "target": target, "files": files, "bytes": bytes }));
+                        } else {
+                            println!("{:<40} {:>10} {:>14}", target, files, bytes);
+                        }
+                        total_files += files;
+                        total_bytes += bytes;
+                    }
+                    Err(e) => error!(log, "Cannot read corpus for {}", target; "error" => e.to_string()),
+                }
+            }
+            if json {
+                let stats = serde_json::json!({
+                    "targets": targets,
+                    "total": { "files": total_files, "bytes": total_bytes },
+                });
+                println!("{}", serde_json::to_string_pretty(&stats).unwrap());
+            } else {
+                println!("{:<40} {:>10} {:>14}", "TOTAL", total_files, total_bytes);
+            }
+        }
+        CorpusCommand::Minimize { dir, run_args, targets } => {
+            let feedback = logger_feedback(config, log).await;
+            hfuzz::minimize(
+                dir,
+                config.env.clone(),
+                targets,
+                HonggfuzzConfig::new(run_args),
+                corpus,
+                feedback,
+                log.new(slog::o!()),
+            )
+            .await;
+        }
+        CorpusCommand::Merge { dir, target } => {
+            let sources = match target {
+                Some(target) => Ok(vec![(target, dir)]),
+                None => corpus::target_dirs(&dir).await,
+            };
+            let sources = match sources {
+                Ok(sources) => sources,
+                Err(e) => {
+                    error!(log, "Error occurred"; "error" => e.to_string());
+                    return;
+                }
+            };
+            for (target, src) in sources {
+                match corpus::merge(&src, Path::new(corpus).join(&target)).await {
+                    Ok(added) => info!(log, "Merged inputs"; "target" => target, "added" => added),
+                    Err(e) => error!(log, "Cannot merge inputs for {}", target; "error" => e.to_string()),
+                }
+            }
+        }
+        CorpusCommand::Dedup => {
+            let dirs = match corpus::target_dirs(corpus).await {
+                Ok(dirs) => dirs,
+                Err(e) => {
+                    error!(log, "Error occurred"; "error" => e.to_string());
+                    return;
+                }
+            };
+            for (target, dir) in dirs {
+                match corpus::dedup(&dir).await {
+                    Ok(stats) => info!(log, "Removed duplicate inputs"; "target" => target, "files" => stats.files, "removed" => stats.removed, "bytes" => stats.reclaimed),
+                    Err(e) => error!(log, "Cannot deduplicate corpus for {}", target; "error" => e.to_string()),
+                }
+            }
+        }
+        CorpusCommand::Restore { backup } => {
+            let archive = match (backup, &config.backup) {
+                (Some(archive), _) => archive,
+                (None, Some(backup)) => match backup::list(&backup.path).await {
+                    Ok(backups) if !backups.is_empty() => backups[backups.len() - 1].clone(),
+                    Ok(_) => {
+                        crit!(log, "No backups found in {}", backup.path.to_string_lossy());
+                        return;
+                    }
+                    Err(e) => {
+                        crit!(log, "Cannot list backups in {}", backup.path.to_string_lossy(); "error" => e.to_string());
+                        return;
+                    }
+                },
+                (None, None) => {
+                    crit!(log, "No backup archive is specified and no backups are configured");
+                    return;
+                }
+            };
+            match backup::restore(corpus, &archive).await {
+                Ok(_) => info!(log, "Corpus restored from {}", archive.to_string_lossy()),
+                Err(e) => error!(log, "Error occurred"; "error" => e.to_string()),
+            }
+        }
+    }
+}
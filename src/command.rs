@@ -0,0 +1,237 @@
+//! Fuzzing engine running commands configured for the project, so targets that are
+//! not built with `cargo hfuzz`, e.g. of non-Rust components, can be fuzzed too.
+//!
+//! Progress is reported by matching lines of the command output against configured
+//! regexes, see [CommandConfig].
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::Arc,
+};
+
+use regex::Regex;
+use slog::{debug, error, info, o, trace, Logger};
+use tokio::{
+    io::{AsyncBufRead, AsyncBufReadExt, BufReader},
+    process::Command,
+};
+
+use crate::{
+    cancel::Scope,
+    config::{CommandConfig, TargetConfig},
+    error::{Error, Result},
+    feedback::Feedback,
+    orphans::Pids,
+    process, resources,
+};
+
+/// Regexes matching lines of the command output that report fuzzing progress.
+struct Patterns {
+    coverage: Option<Regex>,
+    total: Option<Regex>,
+    crash: Option<Regex>,
+}
+
+impl Patterns {
+    fn new(config: &CommandConfig) -> Result<Self> {
+        let compile = |regex: &Option<String>| {
+            regex
+                .as_deref()
+                .map(Regex::new)
+                .transpose()
+                .map_err(|e| Error::other(format!("invalid regex: {}", e)))
+        };
+        Ok(Self {
+            coverage: compile(&config.coverage)?,
+            total: compile(&config.total)?,
+            crash: compile(&config.crash)?,
+        })
+    }
+}
+
+/// Captured value of the first group of the regex matching the line.
+fn capture<'a>(regex: &Option<Regex>, line: &'a str) -> Option<&'a str> {
+    regex.as_ref()?.captures(line)?.get(1).map(|m| m.as_str())
+}
+
+/// Replaces `{target}`, `{corpus}` and `{dir}` placeholders in the command arguments.
+pub fn expand(args: &[String], target: &str, corpus: &Path, dir: &Path) -> Vec<String> {
+    args.iter()
+        .map(|arg| {
+            arg.replace("{target}", target)
+                .replace("{corpus}", &corpus.to_string_lossy())
+                .replace("{dir}", &dir.to_string_lossy())
+        })
+        .collect()
+}
+
+/// Corpus directory of the target, `corpus/<target>` in the project directory if no corpus is configured.
+pub fn corpus_dir(dir: &Path, corpus: Option<&str>, target: &str) -> PathBuf {
+    match corpus {
+        Some(corpus) => Path::new(corpus).join(target),
+        None => dir.join("corpus").join(target),
+    }
+}
+
+/// Runs all targets of the `command` engine project located in `dir`, until they exit
+/// or the `cancel` scope is cancelled.
+///
+/// Each target runs in a child scope of `cancel` named by its name, so it can be stopped separately.
+/// Fuzzing processes are recorded in `pids`, if given.
+pub async fn run(
+    dir: impl AsRef<Path>,
+    env: HashMap<String, String>,
+    config: TargetConfig,
+    corpus: Option<String>,
+    feedback: Arc<Feedback>,
+    pids: Option<Pids>,
+    cancel: Scope,
+    log: Logger,
+) -> Result<()> {
+    info!(log, "Starting fuzzing commands"; "dir" => dir.as_ref().to_str());
+    let command = config
+        .command
+        .as_ref()
+        .ok_or_else(|| Error::other("no `command` section for `command` engine"))?;
+    let patterns = Arc::new(Patterns::new(command)?);
+    let mut handles = vec![];
+
+    for name in &config.targets {
+        let dir = dir.as_ref().to_path_buf();
+        let corpus = corpus_dir(&dir, corpus.as_deref(), name);
+        let target = Target {
+            args: expand(&command.run, name, &corpus, &dir),
+            name: name.clone(),
+            dir,
+            env: env.clone(),
+            corpus,
+            patterns: patterns.clone(),
+            feedback: feedback.clone(),
+            pids: pids.clone(),
+            cancel: cancel.child(name),
+            log: log.new(o!("target" => name.clone())),
+        };
+        handles.push(tokio::spawn(async move { target.run().await }));
+    }
+
+    for handle in handles {
+        match handle.await {
+            Err(e) => error!(log, "Target panicked: {}", e),
+            Ok(Err(e)) => error!(log, "Target error: {}", e),
+            Ok(Ok(_)) => (),
+        }
+    }
+
+    Ok(())
+}
+
+struct Target {
+    name: String,
+    dir: PathBuf,
+    env: HashMap<String, String>,
+    /// Fuzzing command with placeholders replaced
+    args: Vec<String>,
+    corpus: PathBuf,
+    patterns: Arc<Patterns>,
+    feedback: Arc<Feedback>,
+    pids: Option<Pids>,
+    cancel: Scope,
+    log: Logger,
+}
+
+impl Target {
+    fn error(&self, message: impl Into<String>) -> Error {
+        Error::fuzz(&self.name, message)
+    }
+
+    async fn run(&self) -> Result<()> {
+        tokio::fs::create_dir_all(&self.corpus).await?;
+        let (program, args) = self.args.split_first().ok_or_else(|| self.error("empty command"))?;
+        let mut command = Command::new(program);
+        command
+            .args(args)
+            .current_dir(&self.dir)
+            .envs(&self.env)
+            .env("FUZZ_CI_TARGET", &self.name)
+            .env("FUZZ_CI_CORPUS", &self.corpus)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        trace!(self.log, "fuzzing command: {:?}", command);
+
+        let mut child = process::new_process_group(&mut command).spawn()?;
+        let stdout = child.stdout.take().ok_or_else(|| self.error("cannot get stdout"))?;
+        let stderr = child.stderr.take().ok_or_else(|| self.error("cannot get stderr"))?;
+        let pid = child.id();
+        let _pid_file = match (&self.pids, pid) {
+            (Some(pids), Some(pid)) => pids
+                .track(&self.name, pid)
+                .map_err(|e| error!(self.log, "Cannot record fuzzing process"; "error" => e.to_string()))
+                .ok(),
+            _ => None,
+        };
+        let sampling = pid.map(|pid| {
+            tokio::spawn(resources::sample_target(pid, self.name.clone(), Some(self.corpus.clone()), self.feedback.clone()))
+        });
+        tokio::select! {
+            _ = async { tokio::join!(self.filter_output(BufReader::new(stdout)), self.filter_output(BufReader::new(stderr))) } => (),
+            _ = self.cancel.cancelled() => {
+                debug!(self.log, "Terminating target {}", self.name);
+                // the command can be a script running the fuzzer in a child process
+                if let Some(pid) = pid {
+                    let _ = process::kill_group(pid);
+                }
+            }
+        }
+
+        let res = child.wait().await?;
+        if let Some(sampling) = sampling {
+            sampling.abort();
+        }
+        info!(self.log, "Finished target {}", self.name; "status" => res.code());
+        Ok(())
+    }
+
+    /// Processes the command output, reporting coverage and crashes.
+    async fn filter_output(&self, mut read: impl AsyncBufRead + Unpin) {
+        let mut edges = 0;
+        let mut line = String::new();
+        while {
+            line.clear();
+            match read.read_line(&mut line).await {
+                Ok(s) => s,
+                Err(e) => {
+                    error!(self.log, "error in command output filter"; "error" => e);
+                    0
+                }
+            }
+        } > 0
+        {
+            if let Some(total) = capture(&self.patterns.total, &line) {
+                match total.parse() {
+                    Ok(total) => self.feedback.set_total(&self.name, total),
+                    Err(_) => error!(self.log, "Cannot parse total edges"; "line" => &line),
+                }
+            }
+            if let Some(covered) = capture(&self.patterns.coverage, &line) {
+                match covered.parse() {
+                    Ok(0) => (),
+                    Ok(covered) => {
+                        self.feedback.add_covered(&self.name, covered);
+                        edges += covered;
+                        trace!(self.log, "coverage update"; "edges" => edges);
+                    }
+                    Err(_) => error!(self.log, "Cannot parse covered edges"; "line" => &line),
+                }
+            }
+            if let Some(file) = capture(&self.patterns.crash, &line) {
+                // relative paths are resolved against the project directory the command runs in
+                let file = self.dir.join(file.trim());
+                self.feedback.add_error(&self.name, &file.to_string_lossy());
+            }
+        }
+    }
+}
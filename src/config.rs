@@ -1,44 +1,1066 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap, HashSet},
+    convert::TryFrom,
     ffi::OsStr,
     fs::File,
     io::Read,
-    path::PathBuf,
+    path::{Component, Path, PathBuf},
 };
 
 use derive_new::new;
-use failure::{Error, ResultExt};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use url::Url;
 
+use crate::{
+    error::{Error, ResultExt},
+    feedback::{FeedbackLevel, NotificationPolicy},
+    policy::{Condition, Comparison, Metric, Rule},
+    slack::LongMessages,
+};
+
+/// Commented configuration describing all parameters with their default values.
+pub const SAMPLE: &str = include_str!("../samples/fuzz-ci.toml");
+
 #[derive(Clone, Deserialize, new)]
 pub struct Config {
     pub address: String,
     pub url: Option<Url>,
+    /// Fuzzed project repository url, used for runs started via the API
+    pub repository: Option<String>,
+    /// Fuzzed repositories by name, enabling multi-repo mode with runs namespaced by the name
+    #[serde(default)]
+    pub repositories: BTreeMap<String, String>,
     pub branches: Vec<String>,
+    /// Tags whose pushes start runs, a trailing `*` matching any suffix
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Long runs of tagged releases, started by tag creation and GitHub release events
+    pub releases: Option<Releases>,
+    /// Secret of the GitHub webhook, required to sign its requests if set
+    #[serde(default = "Config::get_webhook_secret")]
+    #[new(value = "Config::get_webhook_secret()")]
+    pub webhook_secret: Option<String>,
     pub corpus: Option<String>,
+    /// Fuzz each branch with its own corpus overlay seeded from the main corpus
+    #[serde(default)]
+    pub branch_corpus: bool,
+    /// Branches whose new corpus inputs are merged back into the main corpus
+    #[serde(default)]
+    pub protected_branches: Vec<String>,
+    /// Path to the fuzzed project within the checkout, the checkout itself if not specified
+    pub code_path: Option<String>,
+    /// Script checking out the fuzzing project, overriding the embedded git checkout
+    pub checkout_script: Option<PathBuf>,
     pub kcov: Option<KCov>,
+    #[serde(default)]
     pub targets: HashMap<String, TargetConfig>,
+    /// Discovery of fuzzing projects in the checkout, in addition to the configured ones
+    pub discovery: Option<Discovery>,
+    /// Throughput benchmarks of fuzz targets run before fuzzing
+    pub benchmark: Option<Benchmark>,
+    /// Bisection of coverage regressions against the previous run, run before fuzzing
+    pub bisect: Option<Bisect>,
+    /// Scheduled distillation of target corpora to inputs covering unique edges
+    pub distillation: Option<Distillation>,
+    /// Comparison of fuzzing harnesses changed by the fuzzed commit with their previous versions
+    pub harness_comparison: Option<HarnessComparison>,
     #[serde(default)]
     pub env: HashMap<String, String>,
     #[serde(default)]
     pub path_env: HashMap<String, String>,
-    pub honggfuzz: Option<HonggfuzzConfig>,
+    #[serde(default)]
+    pub honggfuzz: HonggfuzzConfig,
     #[serde(default)]
     pub feedback: Feedback,
     pub slack: Option<Slack>,
+    /// GitHub deployments pointing fuzzed commits at their reports
+    pub github: Option<Github>,
     pub reports_path: PathBuf,
+    #[serde(default)]
+    pub hooks: Hooks,
+    #[serde(default)]
+    pub build: Build,
+    #[serde(default)]
+    pub maintenance: Maintenance,
+    #[serde(default)]
+    pub workdir: Workdir,
+    #[serde(default)]
+    pub deleted_branches: DeletedBranches,
+    pub storage: Option<Storage>,
+    pub backup: Option<Backup>,
+    /// Copying of corpus inputs between related targets during runs
+    pub cross_pollination: Option<CrossPollination>,
+    /// Content-addressed storage of crash inputs and corpus snapshots of runs
+    pub artifacts: Option<Artifacts>,
+    /// Runs of other servers pulled from their APIs, shown on the combined `/peers` page
+    pub aggregate: Option<Aggregate>,
+    pub state: Option<State>,
+    /// Webhook deliveries stored before they are processed, so they can be replayed
+    pub deliveries: Option<Deliveries>,
+    #[serde(default)]
+    pub scheduling: Scheduling,
+    /// Rules deciding whether finished runs pass
+    #[serde(default)]
+    pub policy: Policy,
+    #[serde(default)]
+    pub api: Api,
+    #[serde(default)]
+    pub log_format: LogFormat,
+    #[serde(default)]
+    pub time: Time,
+}
+
+/// Format of the log output.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human-readable text
+    Text,
+    /// JSON object per line, keeping key-value pairs as separate fields
+    Json,
+}
+
+impl Default for LogFormat {
+    fn default() -> Self {
+        Self::Text
+    }
+}
+
+#[derive(Clone, Default, Deserialize, new)]
+pub struct HonggfuzzConfig {
+    #[serde(default)]
+    pub run_args: String,
+    /// Minimize the corpus of each target after the run
+    #[serde(default)]
+    #[new(default)]
+    pub minimize: bool,
+    /// Directory honggfuzz writes statistics of each target to
+    #[serde(default)]
+    #[new(default)]
+    pub stats_dir: Option<PathBuf>,
+    /// The `cargo-hfuzz` binary, instead of the one found by cargo
+    #[serde(default)]
+    #[new(default)]
+    pub cargo_hfuzz: Option<PathBuf>,
+    /// Verbosity of honggfuzz and of fuzz targets it runs
+    #[serde(default)]
+    #[new(default)]
+    pub verbosity: Verbosity,
+    /// Number of the last honggfuzz output lines of each target kept in memory, shown when
+    /// fuzzing fails
+    #[serde(default = "HonggfuzzConfig::default_output_lines")]
+    #[new(value = "HonggfuzzConfig::default_output_lines()")]
+    pub output_lines: usize,
+    /// Keep the whole honggfuzz output of each target gz-compressed in the run report
+    #[serde(default)]
+    #[new(default)]
+    pub archive_output: bool,
+    /// Number of times the total number of edges of each target is measured
+    #[serde(default = "HonggfuzzConfig::default_total_runs")]
+    #[new(value = "HonggfuzzConfig::default_total_runs()")]
+    pub total_runs: u32,
+    /// Spread of measured totals, in percents of the total, over which coverage of the
+    /// target isn't compared with previous runs
+    #[serde(default = "HonggfuzzConfig::default_unreliable_spread")]
+    #[new(value = "HonggfuzzConfig::default_unreliable_spread()")]
+    pub unreliable_spread: f64,
+}
+
+impl HonggfuzzConfig {
+    fn default_output_lines() -> usize {
+        100
+    }
+    fn default_total_runs() -> u32 {
+        3
+    }
+    fn default_unreliable_spread() -> f64 {
+        1.0
+    }
+
+    /// Removes `--run_time` options from run arguments, returning the fuzzing time, in
+    /// seconds, set by the last of them, so it is enforced by the caller instead.
+    pub fn take_run_time(&mut self) -> Option<u64> {
+        let mut run_time = None;
+        let mut args = vec![];
+        let mut words = self.run_args.split_whitespace().peekable();
+        while let Some(word) = words.next() {
+            let value = match word.strip_prefix("--run_time") {
+                Some("") => words.peek().copied(),
+                Some(value) => value.strip_prefix('='),
+                None => None,
+            };
+            match value.map(|value| value.parse::<u64>()) {
+                Some(Ok(seconds)) => {
+                    if word == "--run_time" {
+                        words.next();
+                    }
+                    run_time = Some(seconds);
+                }
+                // left for honggfuzz to reject
+                Some(Err(_)) | None => args.push(word),
+            }
+        }
+        if run_time.is_some() {
+            self.run_args = args.join(" ");
+        }
+        run_time
+    }
+}
+
+/// Verbosity of honggfuzz output.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Verbosity {
+    /// Honggfuzz messages and output of fuzz targets
+    Normal,
+    /// Honggfuzz messages only, output of fuzz targets is discarded
+    Quiet,
+    /// Honggfuzz debug messages and output of fuzz targets
+    Debug,
+}
+
+impl Default for Verbosity {
+    fn default() -> Self {
+        Self::Normal
+    }
+}
+
+impl Verbosity {
+    /// Honggfuzz arguments, in addition to `-v` its output is parsed in.
+    pub fn run_args(self) -> &'static str {
+        match self {
+            Self::Normal => "",
+            Self::Quiet => "-q",
+            Self::Debug => "-d",
+        }
+    }
+}
+
+/// Corpus maintenance tasks.
+#[derive(Clone, Default, Deserialize)]
+pub struct Maintenance {
+    /// Remove corpus files with duplicate content before each run
+    #[serde(default)]
+    pub dedup: bool,
+    /// Default corpus size limits for all targets
+    #[serde(flatten)]
+    pub limits: CorpusLimits,
+}
+
+/// Per-run working directories the fuzzing project is checked out to.
+#[derive(Clone, Deserialize)]
+pub struct Workdir {
+    /// Directory containing a directory for each branch with run working directories
+    #[serde(default = "Workdir::default_path")]
+    pub path: PathBuf,
+    /// When working directories are removed after a run
+    #[serde(default)]
+    pub cleanup: Cleanup,
+    /// Number of the most recent working directories kept for each branch, for `keep-last` policy
+    #[serde(default = "Workdir::default_keep")]
+    pub keep: usize,
+    /// Kill fuzzing processes left running by previous server instances, e.g. after a crash,
+    /// on startup and periodically
+    #[serde(default = "Workdir::default_kill_orphans")]
+    pub kill_orphans: bool,
+    /// Interval between checks for orphaned fuzzing processes, in seconds
+    #[serde(default = "Workdir::default_orphans_interval")]
+    pub orphans_interval: u64,
+}
+
+impl Workdir {
+    fn default_path() -> PathBuf {
+        PathBuf::from("work")
+    }
+
+    fn default_keep() -> usize {
+        1
+    }
+
+    fn default_kill_orphans() -> bool {
+        true
+    }
+
+    fn default_orphans_interval() -> u64 {
+        5 * 60
+    }
+
+    /// Directory with pid files of fuzzing processes of all runs.
+    pub fn pids_dir(&self) -> PathBuf {
+        self.path.join(".pids")
+    }
+
+    /// File whose modification time is the time the last corpus distillation was started.
+    pub fn distill_stamp(&self) -> PathBuf {
+        self.path.join(".distilled")
+    }
+}
+
+impl Default for Workdir {
+    fn default() -> Self {
+        Self {
+            path: Self::default_path(),
+            cleanup: Cleanup::default(),
+            keep: Self::default_keep(),
+            kill_orphans: Self::default_kill_orphans(),
+            orphans_interval: Self::default_orphans_interval(),
+        }
+    }
+}
+
+/// Working directory cleanup policy.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Cleanup {
+    /// Remove the working directory after each run
+    Always,
+    /// Remove the working directory after a successful run, keeping failed ones for investigation
+    OnSuccess,
+    /// Keep a number of the most recent working directories
+    KeepLast,
+}
+
+impl Default for Cleanup {
+    fn default() -> Self {
+        Self::KeepLast
+    }
+}
+
+/// Handling of fuzzed branches deleted upstream. Fuzzing of a deleted branch is always stopped.
+#[derive(Clone, Default, Deserialize)]
+pub struct DeletedBranches {
+    /// What is done with reports of the branch
+    #[serde(default)]
+    pub reports: Retention,
+    /// Remove the corpus overlay of the branch, when branches are fuzzed with their own overlays
+    #[serde(default)]
+    pub prune_overlay: bool,
+}
+
+/// What is done with data of a deleted branch.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Retention {
+    /// Keep the data in place
+    Keep,
+    /// Move the data into the `archived` directory
+    Archive,
+    /// Remove the data
+    Prune,
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Self::Keep
+    }
+}
+
+/// Target corpus size limits, enforced after each run.
+#[derive(Clone, Copy, Default, Deserialize)]
+pub struct CorpusLimits {
+    /// Maximum number of files in a target corpus
+    pub max_files: Option<usize>,
+    /// Maximum total size of a target corpus, in bytes
+    pub max_bytes: Option<u64>,
+    /// Which inputs are evicted first when limits are exceeded
+    #[serde(default)]
+    pub eviction: Eviction,
+}
+
+impl CorpusLimits {
+    pub fn is_set(&self) -> bool {
+        self.max_files.is_some() || self.max_bytes.is_some()
+    }
+}
+
+/// Corpus eviction policy.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Eviction {
+    /// Evict the oldest inputs first
+    Oldest,
+    /// Minimize the corpus to drop inputs not contributing to coverage, then
+    /// evict the oldest inputs if still needed
+    Coverage,
+}
+
+impl Default for Eviction {
+    fn default() -> Self {
+        Self::Oldest
+    }
+}
+
+/// Object storage the corpus is synchronized with.
+#[derive(Clone, Deserialize)]
+pub struct Storage {
+    /// Bucket url, `s3://bucket/path` or `gs://bucket/path`
+    pub url: Url,
+    /// Additional arguments for the `aws s3 sync` or `gsutil rsync` command
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Interval between synchronizations, in seconds
+    #[serde(default = "Storage::default_sync_interval")]
+    pub sync_interval: u64,
+}
+
+impl Storage {
+    fn default_sync_interval() -> u64 {
+        60 * 60
+    }
+}
+
+/// Scheduler state kept in an SQLite database, which can be shared by several servers.
+#[derive(Clone, Deserialize)]
+pub struct State {
+    /// Database file
+    pub path: PathBuf,
+    /// Restart runs interrupted by a server restart or crash
+    #[serde(default = "State::default_resume")]
+    pub resume: bool,
+    /// Run as a warm standby of the primary server sharing the database
+    pub standby: Option<Standby>,
+}
+
+impl State {
+    fn default_resume() -> bool {
+        true
+    }
+}
+
+/// Warm standby following a primary server, taking over webhook processing and its
+/// runs when the primary stops responding.
+#[derive(Clone, Deserialize)]
+pub struct Standby {
+    /// URL of the primary server, whose `/health` endpoint is checked
+    pub primary: Url,
+    /// Interval between health checks, in seconds, also used as their timeout
+    #[serde(default = "Standby::default_interval")]
+    pub interval: u64,
+    /// Failed health checks in a row after which the standby takes over
+    #[serde(default = "Standby::default_failures")]
+    pub failures: u32,
 }
 
-#[derive(Clone, Deserialize, new)]
-pub struct HonggfuzzConfig {
-    #[serde(default)]
-    pub run_args: String,
+impl Standby {
+    fn default_interval() -> u64 {
+        10
+    }
+
+    fn default_failures() -> u32 {
+        3
+    }
+}
+
+/// Webhook deliveries stored on disk before they are processed.
+#[derive(Clone, Deserialize)]
+pub struct Deliveries {
+    /// Directory deliveries are stored in
+    pub path: PathBuf,
+    /// Number of the most recent deliveries kept
+    #[serde(default = "Deliveries::default_keep")]
+    pub keep: usize,
+}
+
+impl Deliveries {
+    fn default_keep() -> usize {
+        1000
+    }
+}
+
+/// Sharing of the machine between runs of different branches.
+#[derive(Clone, Deserialize)]
+pub struct Scheduling {
+    /// Maximum number of runs fuzzing at once, unlimited if 0
+    #[serde(default)]
+    pub max_runs: usize,
+    /// Pause a run of a lower priority branch when a higher priority one has no free slot
+    #[serde(default = "Scheduling::default_preempt")]
+    pub preempt: bool,
+    /// Priorities of branches, or `repository/branch` in multi-repo mode, 0 for others
+    #[serde(default)]
+    pub priorities: HashMap<String, i32>,
+    /// Re-fuzzing of branches not fuzzed for a while, even without pushes
+    pub refuzz: Option<Refuzz>,
+}
+
+impl Default for Scheduling {
+    fn default() -> Self {
+        Self {
+            max_runs: 0,
+            preempt: Self::default_preempt(),
+            priorities: HashMap::new(),
+            refuzz: None,
+        }
+    }
+}
+
+/// Rules deciding the verdict of finished runs, used by exit codes of waiting clients,
+/// GitHub statuses and check runs, and notifications.
+#[derive(Clone, Deserialize)]
+pub struct Policy {
+    /// Rules failing runs meeting their conditions, failing runs with crashes by default
+    #[serde(default = "Policy::default_rules")]
+    pub rules: Vec<Rule>,
+}
+
+impl Policy {
+    fn default_rules() -> Vec<Rule> {
+        vec![Rule {
+            when: Condition {
+                metric: Metric::Crashes,
+                comparison: Comparison::Greater,
+                value: 0.0,
+            },
+            message: Some("Crashes found by fuzzing: {value}".to_string()),
+        }]
+    }
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self {
+            rules: Self::default_rules(),
+        }
+    }
+}
+
+/// Runs of branches started when they are not fuzzed for a number of days.
+#[derive(Clone, Deserialize)]
+pub struct Refuzz {
+    /// Re-fuzzed branches, or `repository/branch` in multi-repo mode
+    pub branches: Vec<String>,
+    /// Days without a run after which a branch is fuzzed again
+    #[serde(default = "Refuzz::default_after_days")]
+    pub after_days: u64,
+    /// Interval of checking when the branches were fuzzed, in seconds
+    #[serde(default = "Refuzz::default_interval")]
+    pub interval: u64,
+}
+
+impl Refuzz {
+    fn default_after_days() -> u64 {
+        7
+    }
+
+    pub fn default_interval() -> u64 {
+        3600
+    }
+}
+
+impl Scheduling {
+    fn default_preempt() -> bool {
+        true
+    }
+
+    /// Priority of runs of the branch, in the repository in multi-repo mode.
+    pub fn priority(&self, repo: Option<&str>, branch: &str) -> i32 {
+        repo.and_then(|repo| self.priorities.get(&format!("{}/{}", repo, branch)))
+            .or_else(|| self.priorities.get(branch))
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+/// HTTP API for developers, available under `/api` path.
+#[derive(Clone, Deserialize)]
+pub struct Api {
+    /// Bearer token required for API requests, the API is disabled if empty
+    #[serde(default = "Api::get_token")]
+    pub token: String,
+    #[serde(flatten)]
+    pub token_source: SecretSource,
+    /// Maximum size of an uploaded seed input, in bytes
+    #[serde(default = "Api::default_max_seed_size")]
+    pub max_seed_size: u64,
+    /// Tokens of other callers by their names, recorded in the audit log
+    #[serde(default)]
+    pub tokens: BTreeMap<String, String>,
+    /// Names of callers allowed to read the audit log and disable targets, in addition
+    /// to the `token` one
+    #[serde(default)]
+    pub admins: Vec<String>,
+    /// File the audit log of control actions is appended to
+    #[serde(default = "Api::default_audit_log")]
+    pub audit_log: PathBuf,
+    /// Serve the gRPC control service alongside the HTTP API
+    #[serde(default)]
+    pub grpc: bool,
+}
+
+impl Default for Api {
+    fn default() -> Self {
+        Self {
+            token: Self::get_token(),
+            token_source: SecretSource::default(),
+            max_seed_size: Self::default_max_seed_size(),
+            tokens: BTreeMap::new(),
+            admins: Vec::new(),
+            audit_log: Self::default_audit_log(),
+            grpc: false,
+        }
+    }
+}
+
+impl Api {
+    fn get_token() -> String {
+        std::env::var("FUZZ_CI_API_TOKEN").unwrap_or_default()
+    }
+
+    fn default_max_seed_size() -> u64 {
+        1024 * 1024
+    }
+
+    fn default_audit_log() -> PathBuf {
+        PathBuf::from("audit.jsonl")
+    }
+
+    /// Whether the API is enabled, i.e. any token is configured.
+    pub fn is_enabled(&self) -> bool {
+        !self.token.is_empty() || !self.tokens.is_empty()
+    }
+}
+
+/// Groups of related fuzz targets, e.g. an encoder and a decoder of the same message,
+/// whose corpora are periodically copied into each other during a run.
+#[derive(Clone, Deserialize)]
+pub struct CrossPollination {
+    /// Interval between copying, in seconds
+    #[serde(default = "CrossPollination::default_interval")]
+    pub interval: u64,
+    /// Target names of each group
+    pub groups: Vec<Vec<String>>,
+}
+
+impl CrossPollination {
+    fn default_interval() -> u64 {
+        10 * 60
+    }
+}
+
+/// Periodic corpus backups.
+#[derive(Clone, Deserialize)]
+pub struct Backup {
+    /// Directory to put corpus tarballs to
+    pub path: PathBuf,
+    /// Interval between backups, in seconds
+    #[serde(default = "Backup::default_interval")]
+    pub interval: u64,
+    /// Number of the most recent backups to keep
+    #[serde(default = "Backup::default_keep")]
+    pub keep: usize,
+}
+
+impl Backup {
+    fn default_interval() -> u64 {
+        24 * 60 * 60
+    }
+
+    fn default_keep() -> usize {
+        7
+    }
+}
+
+/// Fuzzing of tagged releases, with reports kept under `releases/<tag>` apart from
+/// ones of branches.
+#[derive(Clone, Deserialize)]
+pub struct Releases {
+    /// Fuzzed release tags, a trailing `*` matching any suffix, all tags if empty
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Engine run arguments of release runs, overriding ones of `[honggfuzz]` section and
+    /// of projects, e.g. with a longer `--run_time`
+    pub run_args: Option<String>,
+}
+
+/// Pulling of runs of other fuzzing servers.
+#[derive(Clone, Deserialize)]
+pub struct Aggregate {
+    pub peers: Vec<Peer>,
+    /// Interval between pulls, in seconds
+    #[serde(default = "Aggregate::default_interval")]
+    pub interval: u64,
+}
+
+impl Aggregate {
+    fn default_interval() -> u64 {
+        60
+    }
+}
+
+/// Fuzzing server runs are pulled from.
+#[derive(Clone, Deserialize)]
+pub struct Peer {
+    /// Name of the server on the combined page
+    pub name: String,
+    /// URL of the server
+    pub url: Url,
+    /// API token of the server
+    #[serde(default)]
+    pub token: String,
+    #[serde(flatten)]
+    pub token_source: SecretSource,
+}
+
+/// Content-addressed storage of run artifacts, shared by all runs.
+#[derive(Clone, Deserialize)]
+pub struct Artifacts {
+    /// Directory to store artifacts in
+    pub path: PathBuf,
+    /// Store the corpus of each target after fuzzing, as a snapshot of the run
+    #[serde(default)]
+    pub snapshot_corpus: bool,
+}
+
+/// Cargo settings applied to building and running fuzzing projects.
+#[derive(Clone, Deserialize)]
+pub struct Build {
+    /// Run cargo without accessing the network
+    #[serde(default)]
+    pub offline: bool,
+    /// Shared cargo home directory with pre-populated registry
+    pub cargo_home: Option<PathBuf>,
+    /// Directory with vendored crates to be used instead of crates.io
+    pub vendor_dir: Option<PathBuf>,
+    /// Timeout for building a fuzzing project, in seconds
+    #[serde(default = "Build::default_build_timeout")]
+    pub build_timeout: u64,
+    /// Timeout for cleaning a fuzzing project, in seconds
+    #[serde(default = "Build::default_clean_timeout")]
+    pub clean_timeout: u64,
+    /// Timeout for generating coverage report for a fuzzing project, in seconds
+    #[serde(default = "Build::default_kcov_timeout")]
+    pub kcov_timeout: u64,
+    /// Maximum number of build steps running at once, across all runs
+    #[serde(default = "Build::default_parallelism")]
+    pub parallelism: usize,
+    /// Default build profile for fuzzing projects
+    #[serde(default)]
+    pub profile: BuildProfile,
+    /// Default optimization level for fuzzing projects
+    pub opt_level: Option<String>,
+    /// Additional `RUSTFLAGS` for fuzzing projects
+    pub rustflags: Option<String>,
+    /// Directory for build artifacts of fuzzing projects (`CARGO_TARGET_DIR`), relative to
+    /// each project, or with a subdirectory per project checkout if absolute
+    pub target_dir: Option<PathBuf>,
+    /// Directory for honggfuzz workspaces of fuzzing projects (`HFUZZ_WORKSPACE`), the same way
+    pub hfuzz_workspace: Option<PathBuf>,
+}
+
+/// Build profile of fuzz targets.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum BuildProfile {
+    /// Optimized build, as done by `cargo hfuzz` by default
+    Release,
+    /// Optimized build with full debug information
+    ReleaseDebuginfo,
+    /// Non-optimized build with full debug information
+    Debug,
+}
+
+impl Default for BuildProfile {
+    fn default() -> Self {
+        Self::Release
+    }
+}
+
+impl BuildProfile {
+    fn rustflags(&self) -> &'static str {
+        match self {
+            Self::Release => "",
+            Self::ReleaseDebuginfo => "-C debuginfo=2",
+            Self::Debug => "-C opt-level=0 -C debuginfo=2",
+        }
+    }
+}
+
+impl Default for Build {
+    fn default() -> Self {
+        Self {
+            offline: false,
+            cargo_home: None,
+            vendor_dir: None,
+            build_timeout: Self::default_build_timeout(),
+            clean_timeout: Self::default_clean_timeout(),
+            kcov_timeout: Self::default_kcov_timeout(),
+            parallelism: Self::default_parallelism(),
+            profile: BuildProfile::default(),
+            opt_level: None,
+            rustflags: None,
+            target_dir: None,
+            hfuzz_workspace: None,
+        }
+    }
+}
+
+impl Build {
+    fn default_build_timeout() -> u64 {
+        60 * 60
+    }
+    fn default_clean_timeout() -> u64 {
+        10 * 60
+    }
+    fn default_kcov_timeout() -> u64 {
+        2 * 60 * 60
+    }
+    fn default_parallelism() -> usize {
+        2
+    }
+
+    /// Environment variables for cargo invocations.
+    pub fn cargo_env(&self) -> HashMap<String, String> {
+        let mut env = HashMap::new();
+        if self.offline {
+            env.insert("CARGO_NET_OFFLINE".to_string(), "true".to_string());
+        }
+        if let Some(cargo_home) = &self.cargo_home {
+            env.insert(
+                "CARGO_HOME".to_string(),
+                cargo_home.to_string_lossy().into_owned(),
+            );
+        }
+        env
+    }
+
+    /// Environment variables placing build artifacts and the honggfuzz workspace of the
+    /// project in `dir` into configured directories. Absolute directories get a subdirectory
+    /// named after the project path, so projects of concurrent runs don't overwrite each other's.
+    pub fn dirs_env(&self, dir: &Path) -> HashMap<String, String> {
+        let project_dir = |base: &Path| {
+            let path = if base.is_absolute() {
+                let relative = dir.components().filter(|c| matches!(c, Component::Normal(_)));
+                base.join(relative.collect::<PathBuf>())
+            } else {
+                base.to_path_buf()
+            };
+            path.to_string_lossy().into_owned()
+        };
+        let mut env = HashMap::new();
+        if let Some(target_dir) = &self.target_dir {
+            env.insert("CARGO_TARGET_DIR".to_string(), project_dir(target_dir));
+        }
+        if let Some(hfuzz_workspace) = &self.hfuzz_workspace {
+            env.insert("HFUZZ_WORKSPACE".to_string(), project_dir(hfuzz_workspace));
+        }
+        env
+    }
+}
+
+/// Shell commands executed at different stages of a fuzzing run.
+#[derive(Clone, Default, Deserialize)]
+pub struct Hooks {
+    #[serde(default)]
+    pub after_checkout: Vec<String>,
+    #[serde(default)]
+    pub before_fuzzing: Vec<String>,
+    #[serde(default)]
+    pub after_run: Vec<String>,
+}
+
+/// Discovery of fuzzing projects among checkout workspace members, i.e. packages
+/// depending on `honggfuzz` or having binaries in a `fuzz_targets` directory.
+#[derive(Clone, Default, Deserialize)]
+pub struct Discovery {
+    /// Packages not fuzzed even if they look like fuzzing projects
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+/// Throughput benchmarks comparing execs/sec and startup time of fuzz targets on the
+/// fuzzed commit with its parent commit or the base branch head.
+#[derive(Clone, Deserialize)]
+pub struct Benchmark {
+    /// Time each target is run for on each commit, in seconds
+    #[serde(default = "Benchmark::default_duration")]
+    pub duration: u64,
+    /// Slowdown of throughput or startup time reported as a regression, in percents
+    #[serde(default = "Benchmark::default_threshold")]
+    pub threshold: f64,
+    /// Branch the head of which is compared with, the parent commit if not specified
+    pub base_branch: Option<String>,
+    /// Branches benchmarked, all fuzzed branches if empty
+    #[serde(default)]
+    pub branches: Vec<String>,
+}
+
+impl Benchmark {
+    fn default_duration() -> u64 {
+        30
+    }
+
+    fn default_threshold() -> f64 {
+        10.0
+    }
+
+    /// Whether fuzz targets should be benchmarked on the branch.
+    pub fn is_scheduled(&self, branch: &str) -> bool {
+        self.base_branch.as_deref() != Some(branch) && (self.branches.is_empty() || self.branches.iter().any(|b| b == branch))
+    }
+}
+
+/// Bisection of commits between the previous run and the fuzzed one, when covered edges
+/// of a target drop sharply, finding the commit that lost coverage by replaying the corpus.
+#[derive(Clone, Deserialize)]
+pub struct Bisect {
+    /// Drop of covered edges compared with the previous run bisected, in percents
+    #[serde(default = "Bisect::default_threshold")]
+    pub threshold: f64,
+    /// Maximal number of commits built and tested for each target
+    #[serde(default = "Bisect::default_max_steps")]
+    pub max_steps: usize,
+    /// Time allowed for a corpus replay, in seconds
+    #[serde(default = "Bisect::default_timeout")]
+    pub timeout: u64,
+    /// Branches bisected, all fuzzed branches if empty
+    #[serde(default)]
+    pub branches: Vec<String>,
+}
+
+impl Bisect {
+    fn default_threshold() -> f64 {
+        10.0
+    }
+
+    fn default_max_steps() -> usize {
+        8
+    }
+
+    fn default_timeout() -> u64 {
+        10 * 60
+    }
+
+    /// Whether coverage regressions should be bisected on the branch.
+    pub fn is_scheduled(&self, branch: &str) -> bool {
+        self.branches.is_empty() || self.branches.iter().any(|b| b == branch)
+    }
+}
+
+/// Distillation of target corpora run on a schedule, replaying each corpus on the
+/// instrumented build of its target and keeping only inputs covering unique edges.
+///
+/// Its replay settings also apply to merging corpus overlays back into the main corpus.
+#[derive(Clone, Deserialize)]
+pub struct Distillation {
+    /// Branch, or `repository/branch` in multi-repo mode, whose builds replay corpora
+    pub branch: String,
+    /// Interval between distillations, in seconds
+    #[serde(default = "Distillation::default_interval")]
+    pub interval: u64,
+    /// Time allowed for a corpus replay, in seconds
+    #[serde(default = "Distillation::default_timeout")]
+    pub timeout: u64,
+    /// Number of inputs replayed together
+    #[serde(default = "Distillation::default_batch")]
+    pub batch: usize,
+}
+
+impl Distillation {
+    fn default_interval() -> u64 {
+        7 * 24 * 60 * 60
+    }
+
+    pub fn default_timeout() -> u64 {
+        10 * 60
+    }
+
+    pub fn default_batch() -> usize {
+        64
+    }
+}
+
+/// Comparison of coverage of fuzzing projects changed by the fuzzed commit with their
+/// previous versions, replaying the corpus of each target on both builds of the same code.
+#[derive(Clone, Deserialize)]
+pub struct HarnessComparison {
+    /// Drop of covered edges reported as a regression, in percents
+    #[serde(default = "HarnessComparison::default_threshold")]
+    pub threshold: f64,
+    /// Time allowed for a corpus replay, in seconds
+    #[serde(default = "HarnessComparison::default_timeout")]
+    pub timeout: u64,
+    /// Comment the comparison on pull requests of the commit using the `[github]` token
+    #[serde(default = "HarnessComparison::default_comment")]
+    pub comment: bool,
+    /// Branches compared, all fuzzed branches if empty
+    #[serde(default)]
+    pub branches: Vec<String>,
+}
+
+impl HarnessComparison {
+    fn default_threshold() -> f64 {
+        1.0
+    }
+
+    fn default_timeout() -> u64 {
+        10 * 60
+    }
+
+    fn default_comment() -> bool {
+        true
+    }
+
+    /// Whether changed harnesses should be compared on the branch.
+    pub fn is_scheduled(&self, branch: &str) -> bool {
+        self.branches.is_empty() || self.branches.iter().any(|b| b == branch)
+    }
+}
+
+#[derive(Clone, Deserialize, new)]
+pub struct KCov {
+    pub kcov_args: Vec<String>,
+    /// Environment variables for test executions run by kcov, added to ones for fuzzing
+    #[serde(default)]
+    #[new(default)]
+    pub env: HashMap<String, String>,
+}
+
+/// Timezone and format of timestamps in run names, reports and notifications.
+#[derive(Clone, Deserialize)]
+pub struct Time {
+    /// Timezone name, e.g. `Europe/Prague`
+    #[serde(default = "Time::default_timezone")]
+    pub timezone: chrono_tz::Tz,
+    /// `strftime`-like format string
+    #[serde(default = "Time::default_format", deserialize_with = "Time::deserialize_format")]
+    pub format: String,
+}
+
+impl Time {
+    fn default_timezone() -> chrono_tz::Tz {
+        chrono_tz::UTC
+    }
+
+    fn default_format() -> String {
+        "%Y-%m-%d %H:%M:%S".to_string()
+    }
+
+    fn deserialize_format<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+        use chrono::format::{Item, StrftimeItems};
+        let format = String::deserialize(deserializer)?;
+        if StrftimeItems::new(&format).any(|item| item == Item::Error) {
+            return Err(serde::de::Error::custom(format!("invalid time format `{}`", format)));
+        }
+        Ok(format)
+    }
+
+    /// Formats the time in the configured timezone.
+    pub fn format(&self, time: &chrono::DateTime<chrono::Utc>) -> String {
+        time.with_timezone(&self.timezone).format(&self.format).to_string()
+    }
+
+    pub fn now(&self) -> String {
+        self.format(&chrono::Utc::now())
+    }
 }
 
-#[derive(Clone, Deserialize, new)]
-pub struct KCov {
-    pub kcov_args: Vec<String>,
+impl Default for Time {
+    fn default() -> Self {
+        Self {
+            timezone: Self::default_timezone(),
+            format: Self::default_format(),
+        }
+    }
 }
 
 #[derive(Clone, Deserialize, new)]
@@ -49,6 +1071,41 @@ pub struct Feedback {
     pub update_timeout: u64,
     #[serde(default = "Feedback::default_no_update_timeout")]
     pub no_update_timeout: u64,
+    /// Time without coverage updates after which a run with no processes left is failed,
+    /// in seconds, 0 to disable
+    #[serde(default = "Feedback::default_stall_timeout")]
+    #[new(value = "Feedback::default_stall_timeout()")]
+    pub stall_timeout: u64,
+    /// Number of retries of important messages, like fuzzing start and crashes, that failed to be delivered
+    #[serde(default = "Feedback::default_retries")]
+    #[new(value = "Feedback::default_retries()")]
+    pub retries: u32,
+    /// Delay before the first retry, in seconds, doubled for each next one
+    #[serde(default = "Feedback::default_retry_delay")]
+    #[new(value = "Feedback::default_retry_delay()")]
+    pub retry_delay: u64,
+    /// Lowest level of messages written to the log
+    #[serde(default = "Feedback::default_log_level")]
+    #[new(value = "Feedback::default_log_level()")]
+    pub log_level: FeedbackLevel,
+    /// Messages written to the log
+    #[serde(default)]
+    #[new(default)]
+    pub log_policy: NotificationPolicy,
+    /// Drop of covered edges of a target since the previous run reported as a regression
+    /// when the run stops, in percents
+    #[serde(default = "Feedback::default_coverage_drop")]
+    #[new(value = "Feedback::default_coverage_drop()")]
+    pub coverage_drop: f64,
+    /// Interval of exporting covered edges of targets to the run report, in seconds
+    #[serde(default)]
+    #[new(default)]
+    pub coverage_export: Option<u64>,
+    /// Factor by which the execution rate of a target dropping below its highest rate in
+    /// the run is reported as a warning, e.g. 2 for a drop to a half, requires `stats_dir`
+    #[serde(default)]
+    #[new(default)]
+    pub exec_rate_drop: Option<f64>,
 }
 
 impl Feedback {
@@ -61,6 +1118,21 @@ impl Feedback {
     fn default_no_update_timeout() -> u64 {
         24 * 60 * 60
     }
+    fn default_stall_timeout() -> u64 {
+        60 * 60
+    }
+    fn default_retries() -> u32 {
+        3
+    }
+    fn default_retry_delay() -> u64 {
+        5
+    }
+    fn default_log_level() -> FeedbackLevel {
+        FeedbackLevel::Debug
+    }
+    fn default_coverage_drop() -> f64 {
+        10.0
+    }
 }
 
 impl Default for Feedback {
@@ -69,15 +1141,390 @@ impl Default for Feedback {
             start_timeout: Self::default_start_timeout(),
             update_timeout: Self::default_update_timeout(),
             no_update_timeout: Self::default_no_update_timeout(),
+            stall_timeout: Self::default_stall_timeout(),
+            retries: Self::default_retries(),
+            retry_delay: Self::default_retry_delay(),
+            log_level: Self::default_log_level(),
+            log_policy: NotificationPolicy::default(),
+            coverage_drop: Self::default_coverage_drop(),
+            coverage_export: None,
+            exec_rate_drop: None,
         }
     }
 }
 
+/// Fuzzing project, defined in its `[targets.<project>]` section.
 #[derive(Clone, Deserialize, new)]
+#[serde(try_from = "RawTargetConfig")]
 pub struct TargetConfig {
     pub path: Option<String>,
     pub targets: Vec<String>,
-    pub honggfuzz: Option<HonggfuzzConfig>,
+    /// Fuzzing engine used for the project targets
+    #[new(default)]
+    pub engine: Engine,
+    /// Engine run arguments, overriding ones from `[honggfuzz]` section
+    #[new(default)]
+    pub run_args: Option<String>,
+    /// Corpus minimization, overriding one from `[honggfuzz]` section
+    #[new(default)]
+    pub minimize: Option<bool>,
+    /// Environment variables for building and fuzzing, added to ones from `[env]` section
+    #[new(default)]
+    pub env: HashMap<String, String>,
+    /// Corpus directory, overriding the global one
+    #[new(default)]
+    pub corpus: Option<String>,
+    /// Branches the project is fuzzed on, all configured branches if empty
+    #[new(default)]
+    pub branches: Vec<String>,
+    #[new(default)]
+    pub variants: Vec<Variant>,
+    /// Build profile, overriding the one from `[build]` section
+    #[new(default)]
+    pub profile: Option<BuildProfile>,
+    /// Optimization level, overriding the one from `[build]` section
+    #[new(default)]
+    pub opt_level: Option<String>,
+    /// Additional `RUSTFLAGS`, appended to ones from `[build]` section
+    #[new(default)]
+    pub rustflags: Option<String>,
+    /// Corpus size limits, overriding ones from `[maintenance]` section
+    #[new(default)]
+    pub corpus_limits: Option<CorpusLimits>,
+    /// Sources of seed inputs imported into a newly created target corpus
+    #[new(default)]
+    pub seeds: Vec<String>,
+    /// Environment variables for coverage runs of the project, added to ones from `[kcov]` section
+    #[new(default)]
+    pub kcov_env: HashMap<String, String>,
+    /// Name of target binaries, with `{target}` replaced by the target name, the target name if not specified
+    #[new(default)]
+    pub binary: Option<String>,
+    /// Commands building and fuzzing targets of a `command` engine project
+    #[new(default)]
+    pub command: Option<CommandConfig>,
+    /// Owners, descriptions and harness sources of targets, by target name
+    #[new(default)]
+    pub metadata: HashMap<String, TargetMetadata>,
+}
+
+/// Description of a target shown in reports and crash messages, defined in the
+/// `[targets.<project>.metadata.<target>]` section.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct TargetMetadata {
+    /// Person or team to ask about the target
+    pub owner: Option<String>,
+    /// What the target exercises
+    pub description: Option<String>,
+    /// URL of the harness source
+    pub source: Option<Url>,
+}
+
+impl TargetMetadata {
+    /// Metadata of the target among `metadata`, variants sharing one of their target.
+    pub fn of<'a>(metadata: &'a HashMap<String, Self>, target: &str) -> Option<&'a Self> {
+        metadata
+            .get(target)
+            .or_else(|| metadata.get(target.split('@').next().unwrap_or(target)))
+    }
+}
+
+/// Fuzzing engine.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Engine {
+    Honggfuzz,
+    /// Arbitrary commands configured in the `command` section of the project
+    Command,
+}
+
+/// Commands of a `command` engine project, defined in its `[targets.<project>.command]` section.
+///
+/// Arguments of the `run` command may contain `{target}`, `{corpus}` and `{dir}` placeholders,
+/// replaced with the target name, its corpus directory and the project directory.
+#[derive(Clone, Debug, Deserialize)]
+pub struct CommandConfig {
+    /// Command building the targets in the project directory, nothing is built if empty
+    #[serde(default)]
+    pub build: Vec<String>,
+    /// Command fuzzing a target in the project directory
+    pub run: Vec<String>,
+    /// Regex matching output lines that report newly covered edges, capturing their number
+    pub coverage: Option<String>,
+    /// Regex matching the output line that reports the total number of edges, capturing it
+    pub total: Option<String>,
+    /// Regex matching output lines that report crashes, capturing the crashing input file
+    pub crash: Option<String>,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::Honggfuzz
+    }
+}
+
+/// Fuzzing project section as written in the configuration file, including
+/// deprecated keys.
+#[derive(Deserialize)]
+struct RawTargetConfig {
+    path: Option<String>,
+    targets: Vec<String>,
+    #[serde(default)]
+    engine: Engine,
+    run_args: Option<String>,
+    minimize: Option<bool>,
+    #[serde(default)]
+    env: HashMap<String, String>,
+    corpus: Option<String>,
+    #[serde(default)]
+    branches: Vec<String>,
+    /// Deprecated `honggfuzz = { run_args = "..." }` table, replaced with top-level keys
+    honggfuzz: Option<LegacyHonggfuzzConfig>,
+    #[serde(default)]
+    variants: Vec<Variant>,
+    profile: Option<BuildProfile>,
+    opt_level: Option<String>,
+    rustflags: Option<String>,
+    corpus_limits: Option<CorpusLimits>,
+    #[serde(default)]
+    seeds: Vec<String>,
+    #[serde(default)]
+    kcov_env: HashMap<String, String>,
+    binary: Option<String>,
+    command: Option<CommandConfig>,
+    #[serde(default)]
+    metadata: HashMap<String, TargetMetadata>,
+}
+
+#[derive(Deserialize)]
+struct LegacyHonggfuzzConfig {
+    run_args: Option<String>,
+    minimize: Option<bool>,
+}
+
+impl TryFrom<RawTargetConfig> for TargetConfig {
+    type Error = String;
+
+    fn try_from(mut raw: RawTargetConfig) -> Result<Self, Self::Error> {
+        if let Some(legacy) = raw.honggfuzz.take() {
+            fn migrate<T>(key: &str, legacy: Option<T>, value: &mut Option<T>) -> Result<(), String> {
+                match (legacy, value.is_some()) {
+                    (Some(_), true) => Err(format!("both `honggfuzz.{0}` and `{0}` are specified, use `{0}` only", key)),
+                    (Some(legacy), false) => {
+                        *value = Some(legacy);
+                        Ok(())
+                    }
+                    (None, _) => Ok(()),
+                }
+            }
+            migrate("run_args", legacy.run_args, &mut raw.run_args)?;
+            migrate("minimize", legacy.minimize, &mut raw.minimize)?;
+        }
+
+        for target in &raw.targets {
+            if target.is_empty() || target.contains('/') {
+                return Err(format!("invalid target name `{}`", target));
+            }
+        }
+        if let Some(target) = raw.metadata.keys().find(|target| !raw.targets.contains(target)) {
+            return Err(format!("metadata of unknown target `{}`", target));
+        }
+        if let Some(binary) = &raw.binary {
+            if binary.is_empty() || binary.contains('/') {
+                return Err(format!("invalid binary name `{}`", binary));
+            }
+            if raw.targets.len() > 1 && !binary.contains("{target}") {
+                return Err(format!("binary name `{}` should contain `{{target}}` for several targets", binary));
+            }
+        }
+        match (&raw.engine, &raw.command) {
+            (Engine::Command, Some(command)) => {
+                if command.run.is_empty() {
+                    return Err("`command.run` should not be empty".to_string());
+                }
+                if !raw.variants.is_empty() {
+                    return Err("variants are not supported by `command` engine".to_string());
+                }
+                for (key, regex) in &[("coverage", &command.coverage), ("total", &command.total), ("crash", &command.crash)] {
+                    if let Some(regex) = regex {
+                        match regex::Regex::new(regex) {
+                            Ok(regex) if regex.captures_len() > 1 => (),
+                            Ok(_) => return Err(format!("`command.{}` regex should have a capture group", key)),
+                            Err(e) => return Err(format!("invalid `command.{}` regex: {}", key, e)),
+                        }
+                    }
+                }
+            }
+            (Engine::Command, None) => return Err("`command` section is required by `command` engine".to_string()),
+            (Engine::Honggfuzz, Some(_)) => return Err("`command` section is only used by `command` engine".to_string()),
+            (Engine::Honggfuzz, None) => (),
+        }
+        let mut variants = HashSet::new();
+        for variant in &raw.variants {
+            if variant.name.is_empty() {
+                return Err("variant name should not be empty".to_string());
+            }
+            if !variants.insert(&variant.name) {
+                return Err(format!("variant `{}` is specified more than once", variant.name));
+            }
+        }
+
+        Ok(Self {
+            path: raw.path,
+            targets: raw.targets,
+            engine: raw.engine,
+            run_args: raw.run_args,
+            minimize: raw.minimize,
+            env: raw.env,
+            corpus: raw.corpus,
+            branches: raw.branches,
+            variants: raw.variants,
+            profile: raw.profile,
+            opt_level: raw.opt_level,
+            rustflags: raw.rustflags,
+            corpus_limits: raw.corpus_limits,
+            seeds: raw.seeds,
+            kcov_env: raw.kcov_env,
+            binary: raw.binary,
+            command: raw.command,
+            metadata: raw.metadata,
+        })
+    }
+}
+
+impl TargetConfig {
+    /// Honggfuzz configuration for the project, i.e. the global one with
+    /// project-specific overrides applied.
+    pub fn hfuzz_config(&self, global: &HonggfuzzConfig) -> HonggfuzzConfig {
+        HonggfuzzConfig {
+            run_args: self.run_args.clone().unwrap_or_else(|| global.run_args.clone()),
+            minimize: self.minimize.unwrap_or(global.minimize),
+            stats_dir: global.stats_dir.clone(),
+            cargo_hfuzz: global.cargo_hfuzz.clone(),
+            verbosity: global.verbosity,
+            output_lines: global.output_lines,
+            archive_output: global.archive_output,
+            total_runs: global.total_runs,
+            unreliable_spread: global.unreliable_spread,
+        }
+    }
+
+    /// Corpus directory of the project, either its own or the global one.
+    pub fn corpus_dir<'a>(&'a self, global: Option<&'a str>) -> Option<&'a str> {
+        self.corpus.as_deref().or(global)
+    }
+
+    /// Whether the project should be fuzzed on the branch.
+    pub fn is_scheduled(&self, branch: &str) -> bool {
+        self.branches.is_empty() || self.branches.iter().any(|b| b == branch)
+    }
+
+    /// Environment for building and running fuzz targets of the project in `dir`, i.e.
+    /// `RUSTFLAGS` reflecting configured profile and optimization level, and its build
+    /// artifacts and honggfuzz workspace directories.
+    pub fn build_env(&self, build: &Build, dir: &Path) -> HashMap<String, String> {
+        let profile = self.profile.unwrap_or(build.profile);
+        let mut rustflags = vec![profile.rustflags().to_string()];
+        if let Some(opt_level) = self.opt_level.as_ref().or(build.opt_level.as_ref()) {
+            rustflags.push(format!("-C opt-level={}", opt_level));
+        }
+        rustflags.extend(build.rustflags.iter().cloned());
+        rustflags.extend(self.rustflags.iter().cloned());
+        let rustflags = rustflags
+            .iter()
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let mut env = build.dirs_env(dir);
+        env.extend(self.env.clone());
+        if !rustflags.is_empty() {
+            env.insert("RUSTFLAGS".to_string(), rustflags);
+        }
+        env
+    }
+
+    /// Name of the binary of the fuzz target, as passed to `cargo hfuzz run`.
+    pub fn binary_name(&self, target: &str) -> String {
+        match &self.binary {
+            Some(binary) => binary.replace("{target}", target),
+            None => target.to_string(),
+        }
+    }
+
+    /// Environment for running tests of the project under kcov, added to the fuzzing one.
+    pub fn kcov_env(&self, kcov: &KCov) -> HashMap<String, String> {
+        let mut env = self.env.clone();
+        env.extend(kcov.env.clone());
+        env.extend(self.kcov_env.clone());
+        env
+    }
+
+    /// Build variants of the project, or the single default one if none is configured.
+    pub fn variants(&self) -> Vec<Variant> {
+        if self.variants.is_empty() {
+            vec![Variant::default()]
+        } else {
+            self.variants.clone()
+        }
+    }
+}
+
+/// Build variant of a fuzzing project, i.e. a set of cargo features it should be built with.
+#[derive(Clone, Default, Deserialize)]
+pub struct Variant {
+    pub name: String,
+    #[serde(default)]
+    pub features: Vec<String>,
+    #[serde(default)]
+    pub no_default_features: bool,
+}
+
+impl Variant {
+    fn is_default(&self) -> bool {
+        self.name.is_empty()
+    }
+
+    /// Name of the fuzz target built with this variant, as used in reports.
+    pub fn target_id(&self, target: &str) -> String {
+        if self.is_default() {
+            target.to_string()
+        } else {
+            format!("{}@{}", target, self.name)
+        }
+    }
+
+    /// Additional arguments for `cargo hfuzz build/run`.
+    pub fn cargo_args(&self) -> Vec<String> {
+        let mut args = vec![];
+        if self.no_default_features {
+            args.push("--no-default-features".to_string());
+        }
+        if !self.features.is_empty() {
+            args.push("--features".to_string());
+            args.push(self.features.join(","));
+        }
+        args
+    }
+
+    /// Environment making build artifacts and honggfuzz workspace of this variant
+    /// separate from other ones, within directories set in the project environment `env`.
+    pub fn env(&self, env: &HashMap<String, String>) -> HashMap<String, String> {
+        if self.is_default() {
+            return HashMap::new();
+        }
+        let dir = |key: &str, default: &str| {
+            let base = env.get(key).map_or(default, String::as_str);
+            Path::new(base).join(&self.name).to_string_lossy().into_owned()
+        };
+        vec![
+            ("CARGO_TARGET_DIR".to_string(), dir("CARGO_TARGET_DIR", "hfuzz_target")),
+            ("HFUZZ_WORKSPACE".to_string(), dir("HFUZZ_WORKSPACE", "hfuzz_workspace")),
+        ]
+        .into_iter()
+        .collect()
+    }
 }
 
 #[derive(Clone, Deserialize, new)]
@@ -85,31 +1532,239 @@ pub struct Slack {
     pub channel: String,
     #[serde(default = "Slack::get_token")]
     pub token: String,
+    #[serde(flatten)]
+    #[new(default)]
+    pub token_source: SecretSource,
     #[serde(default)]
     pub verbose: bool,
+    /// Lowest level of messages posted to the channel, overriding `verbose`
+    #[serde(default)]
+    #[new(default)]
+    pub level: Option<FeedbackLevel>,
+    /// Messages posted to the channel
+    #[serde(default)]
+    #[new(default)]
+    pub policy: NotificationPolicy,
+    /// Signing secret of the Slack app, enabling buttons pausing and resuming runs
+    #[serde(default = "Slack::get_signing_secret")]
+    #[new(value = "Slack::get_signing_secret()")]
+    pub signing_secret: Option<String>,
+    /// Maximum length of a message, in characters
+    #[serde(default = "Slack::default_max_length")]
+    #[new(value = "Slack::default_max_length()")]
+    pub max_length: usize,
+    /// How messages longer than `max_length` are posted
+    #[serde(default)]
+    #[new(default)]
+    pub long_messages: LongMessages,
+}
+
+/// GitHub deployments of fuzzed commits with run reports as their environment URLs.
+#[derive(Clone, Deserialize)]
+pub struct Github {
+    /// Token with the `repo_deployment` scope, and `repo:status` for commit statuses,
+    /// or of a GitHub App with the `checks:write` permission for check runs
+    #[serde(default = "Github::get_token")]
+    pub token: String,
+    #[serde(flatten)]
+    pub token_source: SecretSource,
+    /// GitHub App authenticating requests with installation tokens instead of the token
+    pub app: Option<GithubApp>,
+    /// Deployment environment
+    #[serde(default = "Github::default_environment")]
+    pub environment: String,
+    /// Repository as `<owner>/<name>`, taken from the fuzzed repository URL if not set
+    pub repository: Option<String>,
+    #[serde(default = "Github::default_api_url")]
+    pub api_url: Url,
+    /// Attach archives of reports of runs of fuzzed tags to their releases
+    #[serde(default)]
+    pub release_assets: bool,
+    /// Set statuses of fuzzed commits, pending while they are fuzzed
+    #[serde(default)]
+    pub statuses: bool,
+    /// Context distinguishing commit statuses of fuzzing from other ones
+    #[serde(default = "Github::default_status_context")]
+    pub status_context: String,
+    /// Create check runs of fuzzed commits summarizing coverage of targets as it changes
+    #[serde(default)]
+    pub checks: bool,
+    /// Name of check runs of fuzzing
+    #[serde(default = "Github::default_check_name")]
+    pub check_name: String,
+    /// Comment coverage changes of targets on open pull requests of fuzzed commits when
+    /// runs finish, updating the earlier comment
+    #[serde(default)]
+    pub pr_comments: bool,
+    /// Open an issue for each new crash signature of a target
+    #[serde(default)]
+    pub crash_issues: bool,
+    /// Labels of issues opened for crashes
+    #[serde(default)]
+    pub issue_labels: Vec<String>,
+}
+
+impl Github {
+    fn get_token() -> String {
+        std::env::var("GITHUB_TOKEN").unwrap_or_default()
+    }
+    fn default_environment() -> String {
+        "fuzzing-reports".to_string()
+    }
+    fn default_status_context() -> String {
+        "fuzzing-ci".to_string()
+    }
+    fn default_check_name() -> String {
+        "Fuzzing".to_string()
+    }
+    fn default_api_url() -> Url {
+        Url::parse("https://api.github.com/").unwrap()
+    }
+}
+
+/// GitHub App the server authenticates as, minting short-lived installation tokens.
+#[derive(Clone, Deserialize)]
+pub struct GithubApp {
+    pub app_id: u64,
+    /// PEM file with the private key of the app
+    pub private_key_file: PathBuf,
+    /// Private key read from the file
+    #[serde(skip)]
+    pub private_key: String,
+    /// Installation of the app, looked up by the repository if not set
+    pub installation_id: Option<u64>,
+}
+
+/// Alternative source of a secret token, taking precedence over the token value.
+#[derive(Clone, Default, Deserialize)]
+pub struct SecretSource {
+    /// File containing the token, e.g. a Docker or Kubernetes secret
+    pub token_file: Option<PathBuf>,
+    /// Name of the systemd credential containing the token (see `LoadCredential=`)
+    pub token_credential: Option<String>,
+    /// Vault secret containing the token, as `<path>#<field>`
+    pub token_vault: Option<String>,
+}
+
+impl SecretSource {
+    /// Reads the token from the configured source, if any.
+    fn read(&self) -> Result<Option<String>, Error> {
+        let token = if let Some(file) = &self.token_file {
+            std::fs::read_to_string(file)
+                .context(|| format!("cannot read token from {}", file.to_string_lossy()))?
+        } else if let Some(name) = &self.token_credential {
+            let dir = std::env::var_os("CREDENTIALS_DIRECTORY")
+                .ok_or_else(|| Error::other(format!("no systemd credentials are available for `{}`", name)))?;
+            std::fs::read_to_string(Path::new(&dir).join(name))
+                .context(|| format!("cannot read systemd credential `{}`", name))?
+        } else if let Some(secret) = &self.token_vault {
+            let (path, field) = secret
+                .split_once('#')
+                .ok_or_else(|| Error::other(format!("Vault secret `{}` should be `<path>#<field>`", secret)))?;
+            let output = std::process::Command::new("vault")
+                .args(&["kv", "get", &format!("-field={}", field), path])
+                .output()
+                .context(|| "cannot run vault")?;
+            if !output.status.success() {
+                return Err(Error::other(format!(
+                    "cannot read Vault secret `{}`: {}",
+                    secret,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                )));
+            }
+            String::from_utf8(output.stdout)?
+        } else {
+            return Ok(None);
+        };
+        Ok(Some(token.trim_end_matches(&['\r', '\n'][..]).to_string()))
+    }
 }
 
 impl Config {
+    fn get_webhook_secret() -> Option<String> {
+        std::env::var("GITHUB_WEBHOOK_SECRET").ok().filter(|s| !s.is_empty())
+    }
+
+    /// Directory of the fuzzed project within the checkout.
+    pub fn code_dir(&self, checkout: &Path) -> PathBuf {
+        match &self.code_path {
+            Some(path) => checkout.join(path),
+            None => checkout.to_path_buf(),
+        }
+    }
+
+    /// Name of the repository with the url, in multi-repo mode.
+    pub fn repository_name(&self, url: &str) -> Option<&str> {
+        let normalize = |url: &str| url.trim_end_matches('/').trim_end_matches(".git").to_string();
+        self.repositories
+            .iter()
+            .find(|(_, u)| normalize(u) == normalize(url))
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Whether pushes of the tag start runs.
+    pub fn is_fuzzed_tag(&self, tag: &str) -> bool {
+        matches_tag(&self.tags, tag)
+    }
+
+    /// Whether the release of the tag starts a release run.
+    pub fn is_fuzzed_release(&self, tag: &str) -> bool {
+        match &self.releases {
+            Some(releases) => releases.tags.is_empty() || matches_tag(&releases.tags, tag),
+            None => false,
+        }
+    }
+
+    /// Applies settings of release runs to the configuration of a run.
+    pub fn for_release(&mut self) {
+        if let Some(run_args) = self.releases.as_ref().and_then(|releases| releases.run_args.clone()) {
+            for target in self.targets.values_mut() {
+                target.run_args = Some(run_args.clone());
+            }
+            self.honggfuzz.run_args = run_args;
+        }
+    }
+
+    /// Url of the repository given by its name or url.
+    pub fn repository_url(&self, repo: &str) -> String {
+        self.repositories.get(repo).cloned().unwrap_or_else(|| repo.to_string())
+    }
+
+    /// Configuration file from standard locations: `fuzz-ci.toml` in the current
+    /// directory, `$XDG_CONFIG_HOME/fuzzing-ci/config.toml` (`~/.config` if not set)
+    /// and `/etc/fuzzing-ci/config.toml`, whichever exists first.
+    ///
+    /// Falls back to `fuzz-ci.toml` if none of them exists.
+    pub fn find_file() -> PathBuf {
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")));
+        let mut candidates = vec![PathBuf::from("fuzz-ci.toml")];
+        candidates.extend(config_home.map(|dir| dir.join("fuzzing-ci").join("config.toml")));
+        candidates.push(PathBuf::from("/etc/fuzzing-ci/config.toml"));
+        candidates
+            .into_iter()
+            .find(|file| file.is_file())
+            .unwrap_or_else(|| PathBuf::from("fuzz-ci.toml"))
+    }
+
     pub fn read(file: impl AsRef<OsStr>) -> Result<Self, Error> {
-        let mut config = String::new();
-        File::open(file.as_ref()).and_then(|mut f| f.read_to_string(&mut config))?;
-        let mut config: Config = toml::from_str(&config)?;
+        let mut config: Config = Self::read_value(file.as_ref())?.try_into()?;
 
         if let Some(ref mut corpus) = config.corpus {
             let path = PathBuf::from(&corpus);
             if path.is_relative() {
-                *corpus = PathBuf::from(file.as_ref())
-                    .canonicalize()
-                    .with_context(|e| {
-                        format!(
-                            "cannot canonicalize path {}: {}",
-                            file.as_ref().to_string_lossy(),
-                            e
-                        )
-                    })?
-                    .parent()
-                    .unwrap()
-                    .join(path)
+                *corpus = Self::resolve_path(file.as_ref(), &path)?
+                    .to_string_lossy()
+                    .into_owned();
+            }
+        }
+
+        for corpus in config.targets.values_mut().filter_map(|c| c.corpus.as_mut()) {
+            let path = PathBuf::from(&corpus);
+            if path.is_relative() {
+                *corpus = Self::resolve_path(file.as_ref(), &path)?
                     .to_string_lossy()
                     .into_owned();
             }
@@ -117,26 +1772,543 @@ impl Config {
 
         let path = PathBuf::from(&config.reports_path);
         if path.is_relative() {
-            config.reports_path = PathBuf::from(file.as_ref())
-                .canonicalize()
-                .with_context(|e| {
-                    format!(
-                        "cannot canonicalize path {}: {}",
-                        file.as_ref().to_string_lossy(),
-                        e
-                    )
-                })?
-                .parent()
-                .unwrap()
-                .join(path);
+            config.reports_path = Self::resolve_path(file.as_ref(), &path)?;
+        }
+
+        let backup_path = config.backup.as_mut().map(|b| &mut b.path);
+        let artifacts_path = config.artifacts.as_mut().map(|a| &mut a.path);
+        let slack_token_file = config.slack.as_mut().and_then(|s| s.token_source.token_file.as_mut());
+        let (github_token_file, github_app_key) = match config.github.as_mut() {
+            Some(g) => (g.token_source.token_file.as_mut(), g.app.as_mut().map(|a| &mut a.private_key_file)),
+            None => (None, None),
+        };
+        for path in [
+            config.build.cargo_home.as_mut(),
+            config.build.vendor_dir.as_mut(),
+            config.honggfuzz.cargo_hfuzz.as_mut(),
+            backup_path,
+            artifacts_path,
+            slack_token_file,
+            github_token_file,
+            github_app_key,
+            config.api.token_source.token_file.as_mut(),
+            Some(&mut config.api.audit_log),
+            config.checkout_script.as_mut(),
+        ]
+        .iter_mut()
+        .flatten()
+        {
+            if path.is_relative() {
+                **path = Self::resolve_path(file.as_ref(), path)?;
+            }
+        }
+
+        if let Some(script) = &config.checkout_script {
+            if !script.is_file() {
+                return Err(Error::other(format!("checkout script {} is not found", script.to_string_lossy())));
+            }
+        }
+
+        if let Some(slack) = &mut config.slack {
+            if let Some(token) = slack.token_source.read()? {
+                slack.token = token;
+            }
+        }
+        if let Some(github) = &mut config.github {
+            if let Some(token) = github.token_source.read()? {
+                github.token = token;
+            }
+            if let Some(app) = &mut github.app {
+                app.private_key = std::fs::read_to_string(&app.private_key_file).context(|| {
+                    format!("cannot read GitHub App private key from {}", app.private_key_file.to_string_lossy())
+                })?;
+            }
+        }
+        if let Some(token) = config.api.token_source.read()? {
+            config.api.token = token;
+        }
+        for peer in config.aggregate.iter_mut().flat_map(|a| a.peers.iter_mut()) {
+            if let Some(path) = peer.token_source.token_file.as_mut().filter(|path| path.is_relative()) {
+                *path = Self::resolve_path(file.as_ref(), path)?;
+            }
+            if let Some(token) = peer.token_source.read()? {
+                peer.token = token;
+            }
         }
 
         Ok(config)
     }
+
+    /// Keys in the configuration file that don't correspond to any configuration
+    /// parameter, e.g. misspelled ones.
+    pub fn unknown_keys(file: impl AsRef<OsStr>) -> Result<Vec<String>, Error> {
+        let mut unknown = vec![];
+        let _: Config = serde_ignored::deserialize(Self::read_value(file.as_ref())?, |path| {
+            unknown.push(path.to_string())
+        })?;
+        Ok(unknown)
+    }
+
+    /// Reads the configuration file as TOML value, with environment variables
+    /// interpolated into all strings and included files merged in.
+    fn read_value(file: &OsStr) -> Result<toml::Value, Error> {
+        Self::read_value_included(Path::new(file), &mut vec![])
+    }
+
+    fn read_value_included(file: &Path, including: &mut Vec<PathBuf>) -> Result<toml::Value, Error> {
+        let mut config = String::new();
+        File::open(file)
+            .and_then(|mut f| f.read_to_string(&mut config))
+            .context(|| format!("cannot read {}", file.to_string_lossy()))?;
+        let mut value: toml::Value = toml::from_str(&config)
+            .context(|| format!("cannot parse {}", file.to_string_lossy()))?;
+        interpolate_value(&mut value)?;
+
+        let includes = match value.as_table_mut().and_then(|t| t.remove("include")) {
+            Some(includes) => includes,
+            None => return Ok(value),
+        };
+        let includes: Vec<PathBuf> = includes.try_into()?;
+        let canonical = file.canonicalize()?;
+        if including.contains(&canonical) {
+            return Err(Error::other(format!("{} includes itself", file.to_string_lossy())));
+        }
+        including.push(canonical);
+        let dir = file.parent().unwrap_or_else(|| Path::new(""));
+        for include in includes {
+            for file in Self::included_files(&dir.join(include))? {
+                let included = Self::read_value_included(&file, including)?;
+                merge_value(&mut value, included, "")?;
+            }
+        }
+        including.pop();
+        Ok(value)
+    }
+
+    /// The included file, or all `.toml` files in the included directory.
+    fn included_files(path: &Path) -> Result<Vec<PathBuf>, Error> {
+        if !path.is_dir() {
+            return Ok(vec![path.to_path_buf()]);
+        }
+        let mut files = std::fs::read_dir(path)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        files.retain(|f| f.extension() == Some(OsStr::new("toml")));
+        files.sort();
+        Ok(files)
+    }
+
+    /// Resolves the path relative to the configuration file location.
+    fn resolve_path(file: &OsStr, path: &Path) -> Result<PathBuf, Error> {
+        Ok(PathBuf::from(file)
+            .canonicalize()
+            .context(|| format!("cannot canonicalize path {}", file.to_string_lossy()))?
+            .parent()
+            .unwrap()
+            .join(path))
+    }
 }
 
 impl Slack {
     fn get_token() -> String {
         std::env::var("SLACK_AUTH_TOKEN").unwrap_or(String::new())
     }
+
+    fn get_signing_secret() -> Option<String> {
+        std::env::var("SLACK_SIGNING_SECRET").ok().filter(|s| !s.is_empty())
+    }
+
+    fn default_max_length() -> usize {
+        4000
+    }
+
+    /// Lowest level of messages posted to the channel, errors by default, or all
+    /// progress messages if `verbose` is set.
+    pub fn level(&self) -> FeedbackLevel {
+        self.level.unwrap_or(if self.verbose { FeedbackLevel::Info } else { FeedbackLevel::Error })
+    }
+}
+
+/// Whether the tag matches any of the patterns, a trailing `*` matching any suffix.
+fn matches_tag(patterns: &[String], tag: &str) -> bool {
+    patterns.iter().any(|pattern| match pattern.strip_suffix('*') {
+        Some(prefix) => tag.starts_with(prefix),
+        None => tag == pattern,
+    })
+}
+
+/// Merges tables from the included configuration into the including one.
+///
+/// Other values can't be specified in both configurations.
+fn merge_value(value: &mut toml::Value, included: toml::Value, key: &str) -> Result<(), Error> {
+    match (value, included) {
+        (toml::Value::Table(table), toml::Value::Table(included)) => {
+            for (k, v) in included {
+                let path = if key.is_empty() { k.clone() } else { format!("{}.{}", key, k) };
+                match table.get_mut(&k) {
+                    Some(existing) => merge_value(existing, v, &path)?,
+                    None => {
+                        table.insert(k, v);
+                    }
+                }
+            }
+            Ok(())
+        }
+        _ => Err(Error::other(format!("`{}` is specified in more than one configuration file", key))),
+    }
+}
+
+/// Interpolates environment variables into all strings of the TOML value.
+fn interpolate_value(value: &mut toml::Value) -> Result<(), Error> {
+    match value {
+        toml::Value::String(s) => *s = interpolate(s)?,
+        toml::Value::Array(array) => {
+            for value in array {
+                interpolate_value(value)?;
+            }
+        }
+        toml::Value::Table(table) => {
+            for (_, value) in table.iter_mut() {
+                interpolate_value(value)?;
+            }
+        }
+        _ => (),
+    }
+    Ok(())
+}
+
+/// Replaces `${VAR}` with the value of the `VAR` environment variable.
+///
+/// `${VAR:-default}` is replaced with `default` if the variable is not set,
+/// and `$$` is replaced with a single `$`.
+fn interpolate(s: &str) -> Result<String, Error> {
+    let mut result = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(pos) = rest.find('$') {
+        result.push_str(&rest[..pos]);
+        rest = &rest[pos..];
+        if let Some(after) = rest.strip_prefix("$$") {
+            result.push('$');
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("${") {
+            let end = after
+                .find('}')
+                .ok_or_else(|| Error::other(format!("unterminated `${{` in `{}`", s)))?;
+            let (name, default) = match after[..end].split_once(":-") {
+                Some((name, default)) => (name, Some(default)),
+                None => (&after[..end], None),
+            };
+            match (std::env::var(name), default) {
+                (Ok(value), _) => result.push_str(&value),
+                (Err(_), Some(default)) => result.push_str(default),
+                (Err(e), None) => return Err(Error::other(format!("cannot interpolate `${{{}}}`: {}", name, e))),
+            }
+            rest = &after[end + 1..];
+        } else {
+            result.push('$');
+            rest = &rest[1..];
+        }
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Debug;
+
+    use serde::{de, forward_to_deserialize_any};
+
+    use super::*;
+
+    /// The sample with all commented parameters and sections enabled.
+    fn uncommented() -> String {
+        SAMPLE
+            .lines()
+            .map(|line| match line.strip_prefix('#') {
+                Some(rest) if rest.starts_with("    ") || !(rest.is_empty() || rest.starts_with(' ')) => rest,
+                _ => line,
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// The configuration without the `key` parameter of `section`, or of the root table if empty.
+    fn without(config: &str, section: &str, key: &str) -> String {
+        let mut current = String::new();
+        let mut removed = false;
+        let lines = config
+            .lines()
+            .filter(|line| {
+                if line.starts_with('[') {
+                    current = line.trim_matches(|c| c == '[' || c == ']').to_string();
+                } else if !removed && current == section && line.split('=').next().map(str::trim) == Some(key) {
+                    removed = true;
+                    return false;
+                }
+                true
+            })
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        assert!(removed, "`{}` of `[{}]` is not in the sample", key, section);
+        lines.join("\n")
+    }
+
+    /// The uncommented sample without settings conflicting with the other ones.
+    fn enabled() -> String {
+        // variants of the project are not supported by the `command` engine
+        without(&uncommented(), "targets.tezos_messages_fuzzing", "command")
+    }
+
+    fn parse(config: &str) -> Config {
+        let mut value: toml::Value = toml::from_str(config).unwrap();
+        value.as_table_mut().unwrap().remove("include");
+        let mut unknown = vec![];
+        let config = serde_ignored::deserialize(value, |path| unknown.push(path.to_string())).unwrap();
+        assert!(unknown.is_empty(), "unknown parameters in the sample: {:?}", unknown);
+        config
+    }
+
+    /// Deserializer recording fields of the deserialized struct.
+    struct Fields<'a>(&'a mut Vec<&'static str>);
+
+    impl<'de, 'a> de::Deserializer<'de> for Fields<'a> {
+        type Error = de::value::Error;
+
+        fn deserialize_any<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+            Err(de::Error::custom("not a struct"))
+        }
+
+        fn deserialize_struct<V: de::Visitor<'de>>(
+            self,
+            _name: &'static str,
+            fields: &'static [&'static str],
+            _visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            self.0.extend(fields);
+            Err(de::Error::custom("fields recorded"))
+        }
+
+        forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+            option unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier
+            ignored_any
+        }
+    }
+
+    /// Fields of `T`, not including ones of flattened structs.
+    fn fields<T: for<'de> Deserialize<'de>>() -> Vec<&'static str> {
+        let mut fields = vec![];
+        let _ = T::deserialize(Fields(&mut fields));
+        fields
+    }
+
+    #[test]
+    fn run_time_is_taken_from_run_args() {
+        let take = |run_args: &str| {
+            let mut config = HonggfuzzConfig::new(run_args.to_string());
+            let run_time = config.take_run_time();
+            (config.run_args, run_time)
+        };
+        assert_eq!(take("-t 1 --run_time 600 -n 1"), ("-t 1 -n 1".to_string(), Some(600)));
+        assert_eq!(take("--run_time=60 --run_time 30"), ("".to_string(), Some(30)));
+        assert_eq!(take("-t 1  -n 1"), ("-t 1  -n 1".to_string(), None));
+        assert_eq!(take("--run_time x"), ("--run_time x".to_string(), None));
+    }
+
+    #[test]
+    fn sample_parses() {
+        parse(SAMPLE);
+        parse(&enabled());
+    }
+
+    #[test]
+    fn sample_documents_all_parameters() {
+        let sample: toml::Value = toml::from_str(&uncommented()).unwrap();
+        // serde doesn't list fields of structs with flattened ones
+        let api = vec!["token", "max_seed_size", "tokens", "admins", "audit_log", "grpc"];
+        let slack = vec![
+            "channel",
+            "token",
+            "verbose",
+            "level",
+            "policy",
+            "signing_secret",
+            "max_length",
+            "long_messages",
+        ];
+        let github = vec![
+            "token",
+            "app",
+            "environment",
+            "repository",
+            "api_url",
+            "release_assets",
+            "statuses",
+            "status_context",
+            "checks",
+            "check_name",
+            "pr_comments",
+            "crash_issues",
+            "issue_labels",
+        ];
+        let peer = vec!["name", "url", "token"];
+        let tables = vec![
+            ("", fields::<Config>()),
+            ("time", fields::<Time>()),
+            ("discovery", fields::<Discovery>()),
+            ("benchmark", fields::<Benchmark>()),
+            ("bisect", fields::<Bisect>()),
+            ("distillation", fields::<Distillation>()),
+            ("harness_comparison", fields::<HarnessComparison>()),
+            ("kcov", fields::<KCov>()),
+            ("honggfuzz", fields::<HonggfuzzConfig>()),
+            // the deprecated `honggfuzz` table is not documented
+            (
+                "targets.tezos_messages_fuzzing",
+                fields::<RawTargetConfig>().into_iter().filter(|f| *f != "honggfuzz").collect(),
+            ),
+            ("targets.tezos_messages_fuzzing.command", fields::<CommandConfig>()),
+            ("targets.tezos_messages_fuzzing.corpus_limits", fields::<CorpusLimits>()),
+            ("targets.tezos_messages_fuzzing.variants", fields::<Variant>()),
+            (
+                "targets.tezos_messages_fuzzing.metadata.AckMessage_from_bytes",
+                fields::<TargetMetadata>(),
+            ),
+            ("maintenance", [vec!["dedup"], fields::<CorpusLimits>()].concat()),
+            ("workdir", fields::<Workdir>()),
+            ("deleted_branches", fields::<DeletedBranches>()),
+            ("releases", fields::<Releases>()),
+            ("storage", fields::<Storage>()),
+            ("backup", fields::<Backup>()),
+            ("cross_pollination", fields::<CrossPollination>()),
+            ("artifacts", fields::<Artifacts>()),
+            ("aggregate", fields::<Aggregate>()),
+            ("aggregate.peers", peer),
+            ("state", fields::<State>()),
+            ("state.standby", fields::<Standby>()),
+            ("deliveries", fields::<Deliveries>()),
+            ("scheduling", fields::<Scheduling>()),
+            ("scheduling.refuzz", fields::<Refuzz>()),
+            // token sources of other sections are described along with the `[slack]` ones
+            ("api", api),
+            ("build", fields::<Build>()),
+            ("hooks", fields::<Hooks>()),
+            ("feedback", fields::<Feedback>()),
+            ("slack", [slack, fields::<SecretSource>()].concat()),
+            ("github", github),
+            ("github.app", fields::<GithubApp>()),
+            ("policy", fields::<Policy>()),
+            ("policy.rules", fields::<Rule>()),
+        ];
+        for (path, fields) in tables {
+            assert!(!fields.is_empty(), "no fields of `[{}]`", path);
+            let table = path
+                .split('.')
+                .filter(|key| !key.is_empty())
+                .fold(&sample, |value, key| match &value[key] {
+                    toml::Value::Array(tables) => &tables[0],
+                    value => value,
+                });
+            for field in fields {
+                assert!(table.get(field).is_some(), "`{}` of `[{}]` is not in the sample", field, path);
+            }
+        }
+    }
+
+    /// Asserts the value of `key` in `section` of the sample is the default one.
+    fn assert_default<T: PartialEq + Debug>(section: &str, key: &str, value: impl Fn(&Config) -> T) {
+        assert_eq!(
+            value(&parse(&enabled())),
+            value(&parse(&without(&enabled(), section, key))),
+            "`{}` of `[{}]` is not the default in the sample",
+            key,
+            section
+        );
+    }
+
+    #[test]
+    fn sample_shows_defaults() {
+        assert_default("", "branch_corpus", |c| c.branch_corpus);
+        assert_default("", "log_format", |c| c.log_format);
+        assert_default("time", "timezone", |c| c.time.timezone);
+        assert_default("time", "format", |c| c.time.format.clone());
+        assert_default("benchmark", "duration", |c| c.benchmark.as_ref().map(|b| b.duration));
+        assert_default("benchmark", "threshold", |c| c.benchmark.as_ref().map(|b| b.threshold));
+        assert_default("bisect", "threshold", |c| c.bisect.as_ref().map(|b| b.threshold));
+        assert_default("bisect", "max_steps", |c| c.bisect.as_ref().map(|b| b.max_steps));
+        assert_default("bisect", "timeout", |c| c.bisect.as_ref().map(|b| b.timeout));
+        assert_default("distillation", "interval", |c| c.distillation.as_ref().map(|d| d.interval));
+        assert_default("distillation", "timeout", |c| c.distillation.as_ref().map(|d| d.timeout));
+        assert_default("distillation", "batch", |c| c.distillation.as_ref().map(|d| d.batch));
+        assert_default("harness_comparison", "threshold", |c| c.harness_comparison.as_ref().map(|h| h.threshold));
+        assert_default("harness_comparison", "timeout", |c| c.harness_comparison.as_ref().map(|h| h.timeout));
+        assert_default("harness_comparison", "comment", |c| c.harness_comparison.as_ref().map(|h| h.comment));
+        assert_default("honggfuzz", "minimize", |c| c.honggfuzz.minimize);
+        assert_default("honggfuzz", "verbosity", |c| c.honggfuzz.verbosity);
+        assert_default("honggfuzz", "output_lines", |c| c.honggfuzz.output_lines);
+        assert_default("honggfuzz", "archive_output", |c| c.honggfuzz.archive_output);
+        assert_default("honggfuzz", "total_runs", |c| c.honggfuzz.total_runs);
+        assert_default("honggfuzz", "unreliable_spread", |c| c.honggfuzz.unreliable_spread);
+        assert_default("maintenance", "dedup", |c| c.maintenance.dedup);
+        assert_default("maintenance", "eviction", |c| c.maintenance.limits.eviction);
+        assert_default("workdir", "path", |c| c.workdir.path.clone());
+        assert_default("workdir", "cleanup", |c| c.workdir.cleanup);
+        assert_default("workdir", "keep", |c| c.workdir.keep);
+        assert_default("workdir", "kill_orphans", |c| c.workdir.kill_orphans);
+        assert_default("workdir", "orphans_interval", |c| c.workdir.orphans_interval);
+        assert_default("deleted_branches", "reports", |c| c.deleted_branches.reports);
+        assert_default("deleted_branches", "prune_overlay", |c| c.deleted_branches.prune_overlay);
+        assert_default("storage", "sync_interval", |c| c.storage.as_ref().map(|s| s.sync_interval));
+        assert_default("backup", "interval", |c| c.backup.as_ref().map(|b| b.interval));
+        assert_default("backup", "keep", |c| c.backup.as_ref().map(|b| b.keep));
+        assert_default("cross_pollination", "interval", |c| c.cross_pollination.as_ref().map(|p| p.interval));
+        assert_default("artifacts", "snapshot_corpus", |c| c.artifacts.as_ref().map(|a| a.snapshot_corpus));
+        assert_default("aggregate", "interval", |c| c.aggregate.as_ref().map(|a| a.interval));
+        assert_default("state", "resume", |c| c.state.as_ref().map(|s| s.resume));
+        assert_default("state.standby", "interval", |c| {
+            c.state.as_ref().and_then(|s| s.standby.as_ref()).map(|s| s.interval)
+        });
+        assert_default("state.standby", "failures", |c| {
+            c.state.as_ref().and_then(|s| s.standby.as_ref()).map(|s| s.failures)
+        });
+        assert_default("deliveries", "keep", |c| c.deliveries.as_ref().map(|d| d.keep));
+        assert_default("scheduling", "max_runs", |c| c.scheduling.max_runs);
+        assert_default("scheduling", "preempt", |c| c.scheduling.preempt);
+        assert_default("scheduling.refuzz", "after_days", |c| {
+            c.scheduling.refuzz.as_ref().map(|r| r.after_days)
+        });
+        assert_default("scheduling.refuzz", "interval", |c| c.scheduling.refuzz.as_ref().map(|r| r.interval));
+        assert_default("api", "max_seed_size", |c| c.api.max_seed_size);
+        assert_default("api", "audit_log", |c| c.api.audit_log.clone());
+        assert_default("api", "grpc", |c| c.api.grpc);
+        assert_default("build", "offline", |c| c.build.offline);
+        assert_default("build", "build_timeout", |c| c.build.build_timeout);
+        assert_default("build", "clean_timeout", |c| c.build.clean_timeout);
+        assert_default("build", "kcov_timeout", |c| c.build.kcov_timeout);
+        assert_default("build", "parallelism", |c| c.build.parallelism);
+        assert_default("build", "profile", |c| c.build.profile);
+        assert_default("feedback", "start_timeout", |c| c.feedback.start_timeout);
+        assert_default("feedback", "update_timeout", |c| c.feedback.update_timeout);
+        assert_default("feedback", "no_update_timeout", |c| c.feedback.no_update_timeout);
+        assert_default("feedback", "stall_timeout", |c| c.feedback.stall_timeout);
+        assert_default("feedback", "retries", |c| c.feedback.retries);
+        assert_default("feedback", "retry_delay", |c| c.feedback.retry_delay);
+        assert_default("feedback", "log_level", |c| c.feedback.log_level);
+        assert_default("feedback", "log_policy", |c| c.feedback.log_policy);
+        assert_default("feedback", "coverage_drop", |c| c.feedback.coverage_drop);
+        assert_default("slack", "verbose", |c| c.slack.as_ref().map(|s| s.verbose));
+        assert_default("slack", "policy", |c| c.slack.as_ref().map(|s| s.policy));
+        assert_default("slack", "max_length", |c| c.slack.as_ref().map(|s| s.max_length));
+        assert_default("slack", "long_messages", |c| c.slack.as_ref().map(|s| s.long_messages));
+        assert_default("github", "environment", |c| c.github.as_ref().map(|g| g.environment.clone()));
+        assert_default("github", "api_url", |c| c.github.as_ref().map(|g| g.api_url.clone()));
+        assert_default("github", "release_assets", |c| c.github.as_ref().map(|g| g.release_assets));
+        assert_default("github", "statuses", |c| c.github.as_ref().map(|g| g.statuses));
+        assert_default("github", "status_context", |c| c.github.as_ref().map(|g| g.status_context.clone()));
+        assert_default("github", "checks", |c| c.github.as_ref().map(|g| g.checks));
+        assert_default("github", "check_name", |c| c.github.as_ref().map(|g| g.check_name.clone()));
+        assert_default("github", "pr_comments", |c| c.github.as_ref().map(|g| g.pr_comments));
+        assert_default("github", "crash_issues", |c| c.github.as_ref().map(|g| g.crash_issues));
+    }
 }
@@ -0,0 +1,295 @@
+use std::{
+    collections::HashSet,
+    io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use sha2::{Digest, Sha256};
+use tokio::fs;
+
+use crate::common::sanitize_path_segment;
+
+/// Directory a new version of the target corpus is prepared in before
+/// replacing the current one.
+pub fn staging_dir(corpus: impl AsRef<Path>) -> PathBuf {
+    sibling_dir(corpus.as_ref(), "new")
+}
+
+/// Directory keeping the previous version of the target corpus.
+pub fn backup_dir(corpus: impl AsRef<Path>) -> PathBuf {
+    sibling_dir(corpus.as_ref(), "bak")
+}
+
+/// Directory crashing inputs are moved to from the target corpus.
+pub fn quarantine_dir(corpus: impl AsRef<Path>) -> PathBuf {
+    sibling_dir(corpus.as_ref(), "quarantine")
+}
+
+/// File listing inputs of the corpus root known to be in the object storage after its last
+/// synchronization.
+pub fn synced_list(corpus: impl AsRef<Path>) -> PathBuf {
+    sibling_dir(corpus.as_ref(), "synced")
+}
+
+/// Hidden entry next to the corpus directory, so it isn't taken for a target corpus.
+fn sibling_dir(corpus: &Path, suffix: &str) -> PathBuf {
+    let name = corpus
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    corpus.with_file_name(format!(".{}.{}", name, suffix))
+}
+
+/// Root directory of the branch corpus overlay, containing a corpus directory for each target.
+pub fn overlay_dir(corpus: impl AsRef<Path>, branch: &str) -> PathBuf {
+    corpus
+        .as_ref()
+        .join(".branches")
+        .join(sanitize_path_segment(branch))
+}
+
+/// Target corpus directories within the corpus root, sorted by target name.
+///
+/// Hidden directories, like staging areas and branch overlays, are skipped.
+pub async fn target_dirs(corpus: impl AsRef<Path>) -> io::Result<Vec<(String, PathBuf)>> {
+    let mut dirs = vec![];
+    let mut read_dir = fs::read_dir(corpus).await?;
+    while let Some(entry) = read_dir.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !name.starts_with('.') && entry.file_type().await?.is_dir() {
+            dirs.push((name, entry.path()));
+        }
+    }
+    dirs.sort();
+    Ok(dirs)
+}
+
+/// Adds files from the `src` directory that are missing in the `dst` one,
+/// using hard links where possible.
+///
+/// Returns number of added files.
+pub async fn merge(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> io::Result<usize> {
+    fs::create_dir_all(dst.as_ref()).await?;
+    let mut added = 0;
+    let mut read_dir = fs::read_dir(src).await?;
+    while let Some(entry) = read_dir.next_entry().await? {
+        if !entry.file_type().await?.is_file() {
+            continue;
+        }
+        let dst = dst.as_ref().join(entry.file_name());
+        if dst.exists() {
+            continue;
+        }
+        if fs::hard_link(entry.path(), &dst).await.is_err() {
+            fs::copy(entry.path(), &dst).await?;
+        }
+        added += 1;
+    }
+    Ok(added)
+}
+
+/// Adds the input files missing in the `dst` directory, using hard links where possible.
+///
+/// Returns number of added files.
+pub async fn add(inputs: &[PathBuf], dst: impl AsRef<Path>) -> io::Result<usize> {
+    fs::create_dir_all(dst.as_ref()).await?;
+    let mut added = 0;
+    for input in inputs {
+        let dst = match input.file_name() {
+            Some(name) => dst.as_ref().join(name),
+            None => continue,
+        };
+        if dst.exists() {
+            continue;
+        }
+        if fs::hard_link(input, &dst).await.is_err() {
+            fs::copy(input, &dst).await?;
+        }
+        added += 1;
+    }
+    Ok(added)
+}
+
+/// Copies inputs of each of the target corpus directories that are missing in the others,
+/// skipping directories that don't exist yet.
+///
+/// Returns number of added files.
+pub async fn cross_pollinate(dirs: &[PathBuf]) -> io::Result<usize> {
+    let mut added = 0;
+    for src in dirs.iter().filter(|dir| dir.exists()) {
+        for dst in dirs.iter().filter(|dst| *dst != src) {
+            added += merge(src, dst).await?;
+        }
+    }
+    Ok(added)
+}
+
+/// Number of files in the corpus directory.
+pub async fn count_files(dir: impl AsRef<Path>) -> io::Result<usize> {
+    let mut count = 0;
+    let mut read_dir = fs::read_dir(dir).await?;
+    while let Some(entry) = read_dir.next_entry().await? {
+        if entry.file_type().await?.is_file() {
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// Replaces the corpus directory with the `new` one, keeping the previous
+/// version as a backup.
+pub async fn replace(corpus: impl AsRef<Path>, new: impl AsRef<Path>) -> io::Result<()> {
+    let backup = backup_dir(&corpus);
+    if backup.exists() {
+        fs::remove_dir_all(&backup).await?;
+    }
+    fs::rename(corpus.as_ref(), &backup).await?;
+    fs::rename(new.as_ref(), corpus.as_ref()).await
+}
+
+/// Result of the corpus deduplication.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DedupStats {
+    /// Number of files in the corpus before deduplication
+    pub files: usize,
+    /// Number of removed duplicates
+    pub removed: usize,
+    /// Size of removed duplicates, in bytes
+    pub reclaimed: u64,
+}
+
+/// Removes files with duplicate content from the corpus directory.
+///
+/// Of files with the same content the one with the lexicographically
+/// smallest name is kept.
+pub async fn dedup(dir: impl AsRef<Path>) -> io::Result<DedupStats> {
+    let mut files = vec![];
+    let mut read_dir = fs::read_dir(dir).await?;
+    while let Some(entry) = read_dir.next_entry().await? {
+        if entry.file_type().await?.is_file() {
+            files.push(entry.path());
+        }
+    }
+    files.sort();
+
+    let mut stats = DedupStats {
+        files: files.len(),
+        ..Default::default()
+    };
+    let mut hashes = HashSet::new();
+    for file in files {
+        let content = fs::read(&file).await?;
+        if !hashes.insert(Sha256::digest(&content)) {
+            fs::remove_file(&file).await?;
+            stats.removed += 1;
+            stats.reclaimed += content.len() as u64;
+        }
+    }
+    Ok(stats)
+}
+
+/// Result of the corpus eviction.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EvictionStats {
+    /// Number of evicted files
+    pub evicted: usize,
+    /// Size of evicted files, in bytes
+    pub bytes: u64,
+}
+
+/// Total number of files and their size, in bytes, for the corpus directory.
+pub async fn size(dir: impl AsRef<Path>) -> io::Result<(usize, u64)> {
+    let files = files_with_metadata(dir).await?;
+    Ok((files.len(), files.iter().map(|(_, _, size)| size).sum()))
+}
+
+async fn files_with_metadata(dir: impl AsRef<Path>) -> io::Result<Vec<(PathBuf, SystemTime, u64)>> {
+    let mut files = vec![];
+    let mut read_dir = fs::read_dir(dir).await?;
+    while let Some(entry) = read_dir.next_entry().await? {
+        let metadata = entry.metadata().await?;
+        if metadata.is_file() {
+            files.push((entry.path(), metadata.modified()?, metadata.len()));
+        }
+    }
+    Ok(files)
+}
+
+/// Whether the corpus of the given number of files and total size exceeds
+/// any of the limits.
+pub fn exceeds_limits((files, bytes): (usize, u64), max_files: Option<usize>, max_bytes: Option<u64>) -> bool {
+    matches!(max_files, Some(max) if files > max) || matches!(max_bytes, Some(max) if bytes > max)
+}
+
+/// Removes the oldest files from the corpus directory until it fits into
+/// the specified number of files and total size.
+pub async fn evict_oldest(
+    dir: impl AsRef<Path>,
+    max_files: Option<usize>,
+    max_bytes: Option<u64>,
+) -> io::Result<EvictionStats> {
+    let mut files = files_with_metadata(dir).await?;
+    files.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+
+    let mut count = files.len();
+    let mut bytes: u64 = files.iter().map(|(_, _, size)| size).sum();
+    let mut stats = EvictionStats::default();
+    for (file, _, size) in files {
+        if !exceeds_limits((count, bytes), max_files, max_bytes) {
+            break;
+        }
+        fs::remove_file(&file).await?;
+        count -= 1;
+        bytes -= size;
+        stats.evicted += 1;
+        stats.bytes += size;
+    }
+    Ok(stats)
+}
+
+/// Moves files with the given content from the corpus directory to its quarantine directory.
+///
+/// Returns names of quarantined files.
+pub async fn quarantine(corpus: impl AsRef<Path>, content: &[u8]) -> io::Result<Vec<String>> {
+    let quarantine = quarantine_dir(&corpus);
+    let mut quarantined = vec![];
+    let mut read_dir = fs::read_dir(corpus).await?;
+    while let Some(entry) = read_dir.next_entry().await? {
+        let metadata = entry.metadata().await?;
+        if !metadata.is_file() || metadata.len() != content.len() as u64 {
+            continue;
+        }
+        if fs::read(entry.path()).await? == content {
+            fs::create_dir_all(&quarantine).await?;
+            fs::rename(entry.path(), quarantine.join(entry.file_name())).await?;
+            quarantined.push(entry.file_name().to_string_lossy().into_owned());
+        }
+    }
+    Ok(quarantined)
+}
+
+/// Removes files with content of inputs in the quarantine directory from the corpus directory,
+/// e.g. copies of crashing inputs downloaded from other hosts.
+///
+/// Returns names of removed files.
+pub async fn remove_quarantined(corpus: impl AsRef<Path>) -> io::Result<Vec<String>> {
+    let quarantine = quarantine_dir(&corpus);
+    if !quarantine.exists() {
+        return Ok(vec![]);
+    }
+    let mut hashes = HashSet::new();
+    let mut sizes = HashSet::new();
+    for (file, _, size) in files_with_metadata(&quarantine).await? {
+        hashes.insert(Sha256::digest(&fs::read(&file).await?));
+        sizes.insert(size);
+    }
+    let mut removed = vec![];
+    for (file, _, size) in files_with_metadata(&corpus).await? {
+        if sizes.contains(&size) && hashes.contains(&Sha256::digest(&fs::read(&file).await?)) {
+            fs::remove_file(&file).await?;
+            removed.extend(file.file_name().map(|name| name.to_string_lossy().into_owned()));
+        }
+    }
+    Ok(removed)
+}
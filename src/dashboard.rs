@@ -0,0 +1,128 @@
+use std::{
+    io::{self, Stdout, Write},
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crossterm::{
+    event::{self, Event, KeyCode, KeyModifiers},
+    execute,
+    terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use tui::{
+    backend::CrosstermBackend,
+    layout::Constraint,
+    style::{Modifier, Style},
+    widgets::{Block, Borders, Row, Table},
+    Terminal,
+};
+
+use crate::{
+    cancel::Scope,
+    feedback::Feedback,
+    hfuzz::{self, Stats},
+};
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Terminal switched to the alternate screen, restored when dropped.
+struct Screen(Terminal<CrosstermBackend<Stdout>>);
+
+impl Screen {
+    fn new() -> io::Result<Self> {
+        terminal::enable_raw_mode().map_err(error)?;
+        let mut stdout = io::stdout();
+        execute!(stdout, EnterAlternateScreen).map_err(error)?;
+        let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+        terminal.hide_cursor()?;
+        Ok(Self(terminal))
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = terminal::disable_raw_mode();
+        let _ = execute!(self.0.backend_mut(), LeaveAlternateScreen);
+        let _ = self.0.show_cursor();
+    }
+}
+
+fn error(e: crossterm::ErrorKind) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, e.to_string())
+}
+
+pub(crate) fn format_duration(secs: u64) -> String {
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// Shows a live table of the targets coverage, speed and crashes, until fuzzing is
+/// cancelled or `q` is pressed, which cancels it.
+///
+/// Speed is read from statistics files honggfuzz writes to the `stats_dir`.
+pub async fn show(targets: Vec<String>, stats_dir: PathBuf, feedback: Arc<Feedback>, cancel: Scope) -> io::Result<()> {
+    let mut screen = Screen::new()?;
+    let started = Instant::now();
+    loop {
+        let status = feedback.status();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let rows = targets
+            .iter()
+            .map(|target| {
+                let status = status.get(target).copied().unwrap_or_default();
+                let stats = Stats::read(&hfuzz::stats_file(&stats_dir, target)).ok().flatten();
+                let percent = if status.total > 0 {
+                    format!("{:.1}%", f64::from(status.covered) * 100.0 / f64::from(status.total))
+                } else {
+                    "-".to_string()
+                };
+                Row::new(vec![
+                    target.clone(),
+                    format!("{}/{}", status.covered, status.total),
+                    percent,
+                    stats.map_or_else(|| "-".to_string(), |s| s.exec_per_sec.to_string()),
+                    stats.map_or_else(|| "-".to_string(), |s| s.total_exec.to_string()),
+                    status.errors.to_string(),
+                    stats
+                        .filter(|s| s.last_cov_update > 0)
+                        .map_or_else(|| "-".to_string(), |s| format_duration(now.saturating_sub(s.last_cov_update))),
+                ])
+            })
+            .collect::<Vec<_>>();
+        let title = format!(
+            " Fuzzing for {}, press q to stop ",
+            format_duration(started.elapsed().as_secs())
+        );
+        let table = Table::new(rows)
+            .header(
+                Row::new(vec!["Target", "Edges", "Coverage", "Execs/s", "Execs", "Crashes", "Since new edge"])
+                    .style(Style::default().add_modifier(Modifier::BOLD)),
+            )
+            .block(Block::default().title(title).borders(Borders::ALL))
+            .widths(&[
+                Constraint::Percentage(34),
+                Constraint::Percentage(12),
+                Constraint::Percentage(9),
+                Constraint::Percentage(9),
+                Constraint::Percentage(12),
+                Constraint::Percentage(9),
+                Constraint::Percentage(15),
+            ]);
+        screen.0.draw(|f| f.render_widget(table, f.size()))?;
+
+        if event::poll(Duration::from_millis(0)).map_err(error)? {
+            if let Event::Key(key) = event::read().map_err(error)? {
+                // the terminal is in raw mode, so Ctrl-C doesn't interrupt the process
+                let interrupt = key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+                if interrupt || matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) {
+                    cancel.cancel();
+                    return Ok(());
+                }
+            }
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(REFRESH_INTERVAL) => (),
+            _ = cancel.cancelled() => return Ok(()),
+        }
+    }
+}
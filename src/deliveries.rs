@@ -0,0 +1,223 @@
+//! Webhook deliveries stored on disk before they are processed, so an event received
+//! while the server restarts is processed once it is up again, and a stored delivery can
+//! be replayed, e.g. one whose run failed to start during a deploy.
+
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::{
+    common,
+    config,
+    error::{Result, ResultExt},
+};
+
+/// Outcome of processing a delivery.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    /// Received, but not processed yet
+    Pending,
+    Processed,
+    /// Processing failed, e.g. its run could not be started
+    Failed,
+}
+
+/// Webhook request with its event and payload.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Delivery {
+    /// Id of the delivery given by the sender, like `X-GitHub-Delivery`, or generated
+    pub id: String,
+    /// Event, like `push`, or `repo:push` of Bitbucket
+    pub event: String,
+    pub received: DateTime<Utc>,
+    pub status: DeliveryStatus,
+    /// Number of times the delivery is replayed
+    #[serde(default)]
+    pub replays: u32,
+    /// Payload of the request, omitted in listings
+    #[serde(default, skip_serializing_if = "serde_json::Value::is_null")]
+    pub payload: serde_json::Value,
+}
+
+/// Deliveries kept as a JSON file each, named by the time they are received and their id,
+/// so the most recent `keep` ones are found without reading them.
+pub struct Deliveries {
+    dir: PathBuf,
+    keep: usize,
+    /// Serializes writes and pruning
+    lock: Mutex<()>,
+}
+
+impl Deliveries {
+    pub fn new(config: &config::Deliveries) -> Self {
+        Self {
+            dir: config.path.clone(),
+            keep: config.keep,
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn file_name(delivery: &Delivery) -> String {
+        format!("{}-{}.json", delivery.received.format("%Y%m%d%H%M%S%6f"), delivery.id)
+    }
+
+    /// Names of stored delivery files, oldest first.
+    async fn file_names(&self) -> Result<Vec<String>> {
+        let mut names = vec![];
+        let mut entries = match tokio::fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(names),
+            Err(e) => return Err(e).context(|| format!("cannot read {}", self.dir.to_string_lossy())),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            if let Some(name) = entry.file_name().to_str().filter(|name| name.ends_with(".json")) {
+                names.push(name.to_string());
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    async fn read(&self, name: &str) -> Result<Delivery> {
+        let file = self.dir.join(name);
+        let content = tokio::fs::read(&file)
+            .await
+            .context(|| format!("cannot read {}", file.to_string_lossy()))?;
+        Ok(serde_json::from_slice(&content)?)
+    }
+
+    /// Stores the delivery of the event received now as pending, with the id given by the
+    /// sender or a generated one, removing the oldest deliveries beyond the kept number.
+    pub async fn store(&self, id: Option<&str>, event: &str, payload: serde_json::Value) -> Result<Delivery> {
+        let received = Utc::now();
+        let id = match id.map(common::sanitize_path_segment) {
+            Some(id) if !id.is_empty() => id.to_string_lossy().into_owned(),
+            _ => received.format("%Y%m%d%H%M%S%9f").to_string(),
+        };
+        let delivery = Delivery {
+            id,
+            event: event.to_string(),
+            received,
+            status: DeliveryStatus::Pending,
+            replays: 0,
+            payload,
+        };
+        self.save(&delivery).await?;
+        self.prune().await?;
+        Ok(delivery)
+    }
+
+    /// Writes the delivery, replacing its previous state.
+    pub async fn save(&self, delivery: &Delivery) -> Result<()> {
+        let _lock = self.lock.lock().await;
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let file = self.dir.join(Self::file_name(delivery));
+        // written aside and renamed, so a crash doesn't leave a truncated delivery
+        let tmp = file.with_extension("tmp");
+        tokio::fs::write(&tmp, serde_json::to_vec(delivery)?)
+            .await
+            .context(|| format!("cannot write {}", tmp.to_string_lossy()))?;
+        tokio::fs::rename(&tmp, &file).await?;
+        Ok(())
+    }
+
+    async fn prune(&self) -> Result<()> {
+        let _lock = self.lock.lock().await;
+        let names = self.file_names().await?;
+        for name in &names[..names.len().saturating_sub(self.keep)] {
+            tokio::fs::remove_file(self.dir.join(name)).await?;
+        }
+        Ok(())
+    }
+
+    /// The delivery with the id, with its payload.
+    pub async fn get(&self, id: &str) -> Result<Option<Delivery>> {
+        let suffix = format!("-{}.json", id);
+        match self.file_names().await?.into_iter().rev().find(|name| name.ends_with(&suffix)) {
+            Some(name) => Ok(Some(self.read(&name).await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// The last `limit` deliveries, oldest first, without their payloads.
+    pub async fn list(&self, limit: usize) -> Result<Vec<Delivery>> {
+        let names = self.file_names().await?;
+        let mut deliveries = vec![];
+        for name in &names[names.len().saturating_sub(limit)..] {
+            let mut delivery = self.read(name).await?;
+            delivery.payload = serde_json::Value::Null;
+            deliveries.push(delivery);
+        }
+        Ok(deliveries)
+    }
+
+    /// Deliveries not processed yet, e.g. received right before a restart, oldest first.
+    pub async fn pending(&self) -> Result<Vec<Delivery>> {
+        let mut pending = vec![];
+        for name in self.file_names().await? {
+            let delivery = self.read(&name).await?;
+            if delivery.status == DeliveryStatus::Pending {
+                pending.push(delivery);
+            }
+        }
+        Ok(pending)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn deliveries(dir: &tempfile::TempDir, keep: usize) -> Deliveries {
+        Deliveries::new(&config::Deliveries { path: dir.path().join("deliveries"), keep })
+    }
+
+    fn ids(deliveries: &[Delivery]) -> Vec<&str> {
+        deliveries.iter().map(|d| d.id.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn keeps_most_recent_deliveries() {
+        let dir = tempfile::tempdir().unwrap();
+        let deliveries = deliveries(&dir, 3);
+        assert!(deliveries.list(10).await.unwrap().is_empty());
+        for i in 0..5 {
+            let id = format!("d{}", i);
+            deliveries.store(Some(&id), "push", json!({ "n": i })).await.unwrap();
+        }
+        assert_eq!(ids(&deliveries.list(10).await.unwrap()), ["d2", "d3", "d4"]);
+        assert_eq!(ids(&deliveries.list(2).await.unwrap()), ["d3", "d4"]);
+        assert!(deliveries.list(10).await.unwrap().iter().all(|d| d.payload.is_null()));
+        assert!(deliveries.get("d1").await.unwrap().is_none());
+        assert_eq!(deliveries.get("d2").await.unwrap().unwrap().payload, json!({ "n": 2 }));
+        assert_eq!(std::fs::read_dir(dir.path().join("deliveries")).unwrap().count(), 3);
+    }
+
+    #[tokio::test]
+    async fn lists_pending_deliveries() {
+        let dir = tempfile::tempdir().unwrap();
+        let deliveries = deliveries(&dir, 10);
+        let mut first = deliveries.store(Some("first"), "push", json!({})).await.unwrap();
+        deliveries.store(Some("second"), "push", json!({})).await.unwrap();
+        first.status = DeliveryStatus::Processed;
+        deliveries.save(&first).await.unwrap();
+        assert_eq!(ids(&deliveries.pending().await.unwrap()), ["second"]);
+        assert_eq!(deliveries.get("first").await.unwrap().unwrap().status, DeliveryStatus::Processed);
+    }
+
+    #[tokio::test]
+    async fn sanitizes_ids() {
+        let dir = tempfile::tempdir().unwrap();
+        let deliveries = deliveries(&dir, 10);
+        let delivery = deliveries.store(Some("../escape"), "push", json!({})).await.unwrap();
+        assert!(!delivery.id.contains('/'), "{}", delivery.id);
+        let generated = deliveries.store(None, "push", json!({})).await.unwrap();
+        assert!(!generated.id.is_empty());
+        assert_eq!(deliveries.list(10).await.unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,213 @@
+//! Distillation of target corpora, replaying their inputs through the instrumented build of
+//! the target and keeping only inputs covering edges no other kept input covers, like `afl-cmin`.
+
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    io,
+    path::{Path, PathBuf},
+};
+
+use tokio::fs;
+
+use crate::{
+    bisect::Replay,
+    config::Variant,
+    corpus,
+    error::{Error, Result},
+};
+
+/// Result of the corpus distillation.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DistillStats {
+    /// Number of inputs before distillation
+    pub before: usize,
+    /// Number of kept inputs
+    pub after: usize,
+    /// Edges covered by kept inputs
+    pub edges: u32,
+}
+
+/// Selects inputs of `candidates`, in their order, each covering edges not covered by `base`
+/// inputs and candidates selected before it, as measured by `coverage` of a set of inputs.
+///
+/// Candidates are replayed in groups of `batch` inputs, and a group is split in halves only if
+/// it adds coverage, so groups of redundant inputs are dropped with a single replay.
+///
+/// Returns selected inputs and edges covered by them together with `base` ones.
+pub async fn select<F, Fut>(base: &[PathBuf], candidates: &[PathBuf], batch: usize, mut coverage: F) -> Result<(Vec<PathBuf>, u32)>
+where
+    F: FnMut(Vec<PathBuf>) -> Fut,
+    Fut: Future<Output = Result<u32>>,
+{
+    let mut covered = if base.is_empty() { 0 } else { coverage(base.to_vec()).await? };
+    let mut selected = vec![];
+    // groups left to replay, the next one last
+    let mut groups = candidates.chunks(batch.max(1)).rev().collect::<Vec<_>>();
+    while let Some(group) = groups.pop() {
+        let inputs = base.iter().chain(&selected).chain(group).cloned().collect();
+        let edges = coverage(inputs).await?;
+        if edges <= covered {
+            continue;
+        }
+        if let [input] = group {
+            selected.push(input.clone());
+            covered = edges;
+        } else {
+            let (first, second) = group.split_at(group.len() / 2);
+            groups.push(second);
+            groups.push(first);
+        }
+    }
+    Ok((selected, covered))
+}
+
+/// Fuzz target of a built project, replaying inputs to distill its corpus.
+pub struct Distiller<'a> {
+    pub replay: &'a Replay,
+    pub project: &'a Path,
+    /// Build environment of the project
+    pub env: &'a HashMap<String, String>,
+    pub variant: &'a Variant,
+    pub target: &'a str,
+    /// Name of the target binary
+    pub binary: &'a str,
+    /// Number of inputs replayed together
+    pub batch: usize,
+}
+
+impl Distiller<'_> {
+    /// Removes inputs not covering unique edges from the target corpus, replaying smaller
+    /// inputs first, so of inputs covering the same edges the smallest one is kept.
+    ///
+    /// The previous version of the corpus is kept as a backup. Inputs added to the corpus
+    /// while it is distilled, e.g. by a run in progress, are kept.
+    pub async fn distill(&self, corpus: &Path) -> Result<DistillStats> {
+        let inputs = inputs_by_size(corpus).await?;
+        if inputs.is_empty() {
+            return Ok(DistillStats::default());
+        }
+        let (kept, edges) = select(&[], &inputs, self.batch, |inputs| self.coverage(corpus, inputs)).await?;
+        if kept.is_empty() {
+            return Err(Error::fuzz(self.target, "replaying the corpus covers no edges"));
+        }
+
+        let backup = corpus::backup_dir(corpus);
+        if backup.exists() {
+            fs::remove_dir_all(&backup).await?;
+        }
+        corpus::merge(corpus, &backup).await?;
+        let kept = kept.into_iter().collect::<HashSet<_>>();
+        for input in inputs.iter().filter(|input| !kept.contains(*input)) {
+            match fs::remove_file(input).await {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+                _ => (),
+            }
+        }
+        Ok(DistillStats {
+            before: inputs.len(),
+            after: kept.len(),
+            edges,
+        })
+    }
+
+    /// Inputs of the corpus missing in the `base` directory that add coverage to its inputs,
+    /// smallest first.
+    pub async fn new_inputs(&self, base: &Path, corpus: &Path) -> Result<Vec<PathBuf>> {
+        let base_inputs = match fs::metadata(base).await {
+            Ok(_) => inputs_by_size(base).await?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => vec![],
+            Err(e) => return Err(e.into()),
+        };
+        // inputs are named by their content, so ones of the same name are the same
+        let names = base_inputs.iter().filter_map(|input| input.file_name()).collect::<HashSet<_>>();
+        let mut inputs = inputs_by_size(corpus).await?;
+        inputs.retain(|input| input.file_name().map_or(false, |name| !names.contains(name)));
+        if inputs.is_empty() {
+            return Ok(vec![]);
+        }
+        let (new, _) = select(&base_inputs, &inputs, self.batch, |inputs| self.coverage(corpus, inputs)).await?;
+        Ok(new)
+    }
+
+    /// Edges covered by the inputs, replayed from a temporary directory of links to them
+    /// created next to the corpus, so they are linked within the same file system.
+    async fn coverage(&self, corpus: &Path, inputs: Vec<PathBuf>) -> Result<u32> {
+        let parent = corpus.parent().unwrap_or(corpus);
+        let dir = tempfile::Builder::new().prefix(".distill").tempdir_in(parent)?;
+        for (i, input) in inputs.iter().enumerate() {
+            // inputs of different directories may have the same names
+            let name = input.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+            let link = dir.path().join(format!("{}-{}", i, name));
+            if fs::hard_link(input, &link).await.is_err() {
+                fs::copy(input, &link).await?;
+            }
+        }
+        self.replay
+            .coverage(self.project, self.env, self.variant, self.target, self.binary, dir.path())
+            .await
+    }
+}
+
+/// Files of the corpus directory, smallest first.
+async fn inputs_by_size(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut inputs = vec![];
+    let mut read_dir = fs::read_dir(dir).await?;
+    while let Some(entry) = read_dir.next_entry().await? {
+        let metadata = entry.metadata().await?;
+        if metadata.is_file() {
+            inputs.push((metadata.len(), entry.path()));
+        }
+    }
+    inputs.sort();
+    Ok(inputs.into_iter().map(|(_, input)| input).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Coverage of inputs named after edges they cover, like `1,2`.
+    async fn edges(inputs: Vec<PathBuf>) -> Result<u32> {
+        let edges = inputs
+            .iter()
+            .flat_map(|input| input.to_string_lossy().split(',').map(String::from).collect::<Vec<_>>())
+            .collect::<HashSet<_>>();
+        Ok(edges.len() as u32)
+    }
+
+    fn inputs(names: &[&str]) -> Vec<PathBuf> {
+        names.iter().map(PathBuf::from).collect()
+    }
+
+    #[tokio::test]
+    async fn select_keeps_inputs_with_new_edges() {
+        let candidates = inputs(&["1", "1,2", "2", "3", "1,3"]);
+        for batch in 1..=5 {
+            let (selected, covered) = select(&[], &candidates, batch, edges).await.unwrap();
+            assert_eq!(selected, inputs(&["1", "1,2", "3"]), "batch {}", batch);
+            assert_eq!(covered, 3);
+        }
+    }
+
+    #[tokio::test]
+    async fn select_skips_edges_of_base() {
+        let (selected, covered) = select(&inputs(&["1,2"]), &inputs(&["2", "3", "1,3,4"]), 2, edges).await.unwrap();
+        assert_eq!(selected, inputs(&["3", "1,3,4"]));
+        assert_eq!(covered, 4);
+    }
+
+    #[tokio::test]
+    async fn select_drops_redundant_groups_in_one_replay() {
+        let candidates = inputs(&["1", "1", "1", "1", "1", "1", "1", "1"]);
+        let mut replays = 0;
+        let (selected, _) = select(&inputs(&["1"]), &candidates, 8, |inputs| {
+            replays += 1;
+            edges(inputs)
+        })
+        .await
+        .unwrap();
+        assert!(selected.is_empty());
+        assert_eq!(replays, 2);
+    }
+}
@@ -0,0 +1,162 @@
+use std::{
+    ffi::OsStr,
+    path::Path,
+    process::{Output, Stdio},
+};
+
+use tokio::{io::AsyncWriteExt, process::Command};
+
+use crate::{config::Config, hfuzz};
+
+/// Outcome of checking a single prerequisite.
+enum Outcome {
+    /// The prerequisite is usable, with a short description like its version
+    Ok(String),
+    /// The prerequisite is missing, but it is not needed with this configuration
+    Warning(String),
+    Error(String),
+}
+
+/// Runs the program, returning the first line of its output if it succeeds.
+async fn probe(program: &str, args: &[&str]) -> Result<String, String> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .await
+        .map_err(|e| format!("cannot run `{}`: {}", program, e))?;
+    first_line(program, output)
+}
+
+fn first_line(program: &str, output: Output) -> Result<String, String> {
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if output.status.success() {
+        Ok(stdout.lines().chain(stderr.lines()).next().unwrap_or_default().trim().to_string())
+    } else {
+        Err(format!(
+            "`{}` fails with {}: {}",
+            program,
+            output.status,
+            stderr.lines().chain(stdout.lines()).next().unwrap_or_default().trim()
+        ))
+    }
+}
+
+/// Checks that the C header can be found by the C compiler used to build honggfuzz.
+async fn header(name: &str) -> Result<String, String> {
+    let mut child = Command::new("cc")
+        .args(&["-E", "-x", "c", "-o", "/dev/null", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("cannot run `cc`: {}", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(format!("#include <{}>\n", name).as_bytes())
+            .await
+            .map_err(|e| format!("cannot run `cc`: {}", e))?;
+    }
+    let output = child
+        .wait_with_output()
+        .await
+        .map_err(|e| format!("cannot run `cc`: {}", e))?;
+    first_line("cc", output).map(|_| format!("{} found", name))
+}
+
+fn checkout_script(script: &Path) -> Result<String, String> {
+    use std::os::unix::fs::PermissionsExt;
+    let metadata = script
+        .metadata()
+        .map_err(|e| format!("cannot access `{}`: {}", script.to_string_lossy(), e))?;
+    if metadata.permissions().mode() & 0o111 == 0 {
+        return Err(format!("`{}` is not executable", script.to_string_lossy()));
+    }
+    Ok(format!("{} found", script.to_string_lossy()))
+}
+
+fn outcome(result: Result<String, String>, required: bool) -> Outcome {
+    match result {
+        Ok(message) => Outcome::Ok(message),
+        Err(message) if required => Outcome::Error(message),
+        Err(message) => Outcome::Warning(message),
+    }
+}
+
+/// Checks that tools needed for fuzzing are present and usable, printing hints
+/// for missing ones.
+///
+/// The configuration, if it could be read, tells which optional tools are needed.
+/// Returns `false` if a required tool is missing.
+pub async fn doctor(config_file: impl AsRef<OsStr>) -> bool {
+    let config = Config::read(config_file.as_ref());
+    if let Err(e) = &config {
+        println!("warning: cannot read configuration, assuming defaults: {}", e);
+    }
+    let kcov_required = config.as_ref().map_or(false, |c| c.kcov.is_some());
+
+    let checks = vec![
+        (
+            "git",
+            outcome(probe("git", &["--version"]).await, true),
+            "install Git, e.g. `apt install git`",
+        ),
+        (
+            "cargo",
+            outcome(probe("cargo", &["--version"]).await, true),
+            "install the Rust toolchain, see https://rustup.rs",
+        ),
+        (
+            "cargo-hfuzz",
+            outcome(
+                hfuzz::detect(config.as_ref().ok().and_then(|c| c.honggfuzz.cargo_hfuzz.as_deref()))
+                    .await
+                    .map(|(version, format)| format!("cargo-hfuzz {}, {}", version, format))
+                    .map_err(|e| e.to_string()),
+                true,
+            ),
+            "install it with `cargo install honggfuzz`",
+        ),
+        (
+            "C compiler",
+            outcome(probe("cc", &["--version"]).await, true),
+            "install a C compiler and make, e.g. `apt install build-essential`",
+        ),
+        (
+            "libunwind",
+            outcome(header("libunwind-ptrace.h").await, true),
+            "install libunwind development files, e.g. `apt install libunwind-dev`",
+        ),
+        (
+            "binutils",
+            outcome(header("bfd.h").await, true),
+            "install binutils development files, e.g. `apt install binutils-dev`",
+        ),
+        (
+            "kcov",
+            outcome(probe("kcov", &["--version"]).await, kcov_required),
+            "install kcov, see https://github.com/SimonKagstrom/kcov, or remove the `kcov` section from the configuration",
+        ),
+        (
+            "checkout script",
+            match config.as_ref().ok().and_then(|c| c.checkout_script.as_ref()) {
+                Some(script) => outcome(checkout_script(script), true),
+                None => outcome(Ok("not configured, git checkout is used".to_string()), false),
+            },
+            "make the `checkout_script` executable, or remove it to use the embedded git checkout",
+        ),
+    ];
+
+    let mut ok = true;
+    for (name, outcome, hint) in checks {
+        match outcome {
+            Outcome::Ok(message) => println!("ok: {}: {}", name, message),
+            Outcome::Warning(message) => println!("warning: {}: {}\n  hint: {}", name, message, hint),
+            Outcome::Error(message) => {
+                println!("error: {}: {}\n  hint: {}", name, message, hint);
+                ok = false;
+            }
+        }
+    }
+    ok
+}
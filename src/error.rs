@@ -1,61 +1,128 @@
-use failure::Fail;
+use std::{
+    path::PathBuf,
+    process::ExitStatus,
+    time::Duration,
+};
 
-#[derive(Fail, Debug)]
+/// Number of the last output lines kept in errors of external commands.
+const OUTPUT_LINES: usize = 20;
+
+#[derive(Debug, thiserror::Error)]
 pub enum Error {
-    #[fail(display = "i/o error: {}", _0)]
-    IOError(std::io::Error),
-    #[fail(display = "format error: {}", _0)]
-    FmtError(std::fmt::Error),
-    #[fail(display = "url parse error: {}", _0)]
-    UrlParseError(url::ParseError),
-    #[fail(display = "toml deserialization error: {}", _0)]
-    TomlDeError(toml::de::Error),
-    #[fail(display = "toml serialization error: {}", _0)]
-    TomlSerError(toml::ser::Error),
-    #[fail(display = "JSON serialization error: {}", _0)]
-    JsonError(serde_json::Error),
-    #[fail(display = "Template substitution error: {}", _0)]
-    HandlebarsRenderError(handlebars::RenderError),
+    #[error("i/o error: {0}")]
+    IOError(#[from] std::io::Error),
+    #[error("UTF-8 decoding error: {0}")]
+    Utf8Error(#[from] std::string::FromUtf8Error),
+    #[error("format error: {0}")]
+    FmtError(#[from] std::fmt::Error),
+    #[error("url parse error: {0}")]
+    UrlParseError(#[from] url::ParseError),
+    #[error("toml deserialization error: {0}")]
+    TomlDeError(#[from] toml::de::Error),
+    #[error("toml serialization error: {0}")]
+    TomlSerError(#[from] toml::ser::Error),
+    #[error("JSON serialization error: {0}")]
+    JsonError(#[from] serde_json::Error),
+    #[error("Template substitution error: {0}")]
+    HandlebarsRenderError(#[from] handlebars::RenderError),
+    #[error("HTTP error: {0}")]
+    HttpError(#[from] reqwest::Error),
+    #[error("state database error: {0}")]
+    SqliteError(#[from] rusqlite::Error),
+    /// Checking out the fuzzing project failed
+    #[error("cannot check out `{branch}` of {url} into {}: {output}", dir.to_string_lossy())]
+    Checkout {
+        url: String,
+        branch: String,
+        dir: PathBuf,
+        output: String,
+    },
+    /// A build step, like building fuzz targets or generating coverage, failed
+    #[error("{command} failed in {} with {status}: {output}", dir.to_string_lossy())]
+    Build {
+        command: String,
+        dir: PathBuf,
+        status: ExitStatus,
+        output: String,
+    },
+    /// An external command didn't complete in time
+    #[error("{command} timed out after {}s", timeout.as_secs())]
+    Timeout { command: String, timeout: Duration },
+    /// An operation was cancelled, e.g. by a newer run of the branch
+    #[error("{0} cancelled")]
+    Cancelled(String),
+    /// Running a fuzz target failed
+    #[error("error fuzzing `{target}`: {message}")]
+    Fuzz { target: String, message: String },
+    /// Reading or writing a report file failed
+    #[error("report file {}: {source}", path.to_string_lossy())]
+    Report {
+        path: PathBuf,
+        #[source]
+        source: Box<Error>,
+    },
+    /// Sending feedback failed
+    #[error("feedback error: {0}")]
+    Feedback(String),
+    /// Error of an operation described by the message
+    #[error("{message}: {source}")]
+    Context {
+        message: String,
+        #[source]
+        source: Box<Error>,
+    },
+    #[error("{0}")]
+    Other(String),
 }
 
-impl From<std::io::Error> for Error {
-    fn from(error: std::io::Error) -> Self {
-        Self::IOError(error)
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+impl Error {
+    pub fn other(message: impl Into<String>) -> Self {
+        Self::Other(message.into())
     }
-}
 
-impl From<std::fmt::Error> for Error {
-    fn from(error: std::fmt::Error) -> Self {
-        Self::FmtError(error)
+    pub fn fuzz(target: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::Fuzz {
+            target: target.into(),
+            message: message.into(),
+        }
     }
-}
 
-impl From<url::ParseError> for Error {
-    fn from(error: url::ParseError) -> Self {
-        Self::UrlParseError(error)
+    /// Whether the error is caused by an external command timeout.
+    pub fn is_timeout(&self) -> bool {
+        match self {
+            Self::Timeout { .. } => true,
+            Self::IOError(e) => e.kind() == std::io::ErrorKind::TimedOut,
+            Self::Context { source, .. } | Self::Report { source, .. } => source.is_timeout(),
+            _ => false,
+        }
     }
 }
 
-impl From<toml::de::Error> for Error {
-    fn from(error: toml::de::Error) -> Self {
-        Self::TomlDeError(error)
+impl slog::Value for Error {
+    fn serialize(&self, _record: &slog::Record, key: slog::Key, serializer: &mut dyn slog::Serializer) -> slog::Result {
+        serializer.emit_arguments(key, &format_args!("{}", self))
     }
 }
 
-impl From<toml::ser::Error> for Error {
-    fn from(error: toml::ser::Error) -> Self {
-        Self::TomlSerError(error)
-    }
+/// Last lines of an external command output, to be included into errors.
+pub fn output_tail(output: &[u8]) -> String {
+    let output = String::from_utf8_lossy(output);
+    let lines = output.lines().collect::<Vec<_>>();
+    lines[lines.len().saturating_sub(OUTPUT_LINES)..].join("\n")
 }
 
-impl From<serde_json::Error> for Error {
-    fn from(error: serde_json::Error) -> Self {
-        Self::JsonError(error)
-    }
+/// Adds a description of the failed operation to errors.
+pub trait ResultExt<T> {
+    fn context<M: Into<String>>(self, message: impl FnOnce() -> M) -> Result<T>;
 }
 
-impl From<handlebars::RenderError> for Error {
-    fn from(error: handlebars::RenderError) -> Self {
-        Self::HandlebarsRenderError(error)
+impl<T, E: Into<Error>> ResultExt<T> for std::result::Result<T, E> {
+    fn context<M: Into<String>>(self, message: impl FnOnce() -> M) -> Result<T> {
+        self.map_err(|e| Error::Context {
+            message: message().into(),
+            source: Box::new(e.into()),
+        })
     }
 }
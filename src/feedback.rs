@@ -1,40 +1,195 @@
 use std::{
+    collections::HashMap,
     path::Path,
     sync::{Arc, RwLock},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
+use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use reqwest::Url;
-use slog::{error, info, o, trace, Logger};
+use slog::{crit, debug, error, info, o, trace, warn, Logger};
 use tokio::sync::Notify;
 
 use crate::{
+    artifacts::RunArtifacts,
+    bisect::{self, Bisection},
     config,
     error::Error,
-    report::{FuzzingStatus, Report, TargetStatus},
+    github::{CheckConclusion, CheckRun, CrashIssues},
+    hfuzz,
+    policy::{RunStats, Verdict},
+    provenance::Provenance,
+    report::{FuzzingStatus, Report, Summary, TargetStatus},
+    resources::{Resources, Usage},
+    runs::Trigger,
 };
 
-#[derive(Debug, PartialEq, PartialOrd)]
+/// Severity of a feedback message, in ascending order, so clients can skip messages below
+/// their threshold.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum FeedbackLevel {
+    /// Routine details, like corpus maintenance
+    Debug,
+    /// Progress of fuzzing
     Info,
+    /// Problems not stopping fuzzing, like performance or coverage regressions
+    Warning,
+    /// Crashes of fuzz targets and failed builds
     Error,
+    /// Failures of the whole run
+    Critical,
 }
 
+/// Which messages a client delivers.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationPolicy {
+    /// All messages of levels passing the client threshold
+    All,
+    /// Only regressions, like new crashes, coverage drops or failed builds, and nothing
+    /// while runs are healthy
+    Regressions,
+}
+
+impl Default for NotificationPolicy {
+    fn default() -> Self {
+        Self::All
+    }
+}
+
+/// Destination of fuzzing progress messages, e.g. a Slack channel.
+#[async_trait]
 pub trait FeedbackClient {
-    fn message(&self, level: FeedbackLevel, message: &str);
+    /// Delivers the message, or skips it if its level is filtered out.
+    async fn message(&self, level: FeedbackLevel, message: &str) -> Result<(), Error>;
+
+    /// Delivers the message reporting a regression, like a new crash, a coverage drop
+    /// or a failed build.
+    async fn regression(&self, level: FeedbackLevel, message: &str) -> Result<(), Error> {
+        self.message(level, message).await
+    }
+
+    async fn info(&self, message: &str) -> Result<(), Error> {
+        self.message(FeedbackLevel::Info, message).await
+    }
+
+    async fn warning(&self, message: &str) -> Result<(), Error> {
+        self.message(FeedbackLevel::Warning, message).await
+    }
+
+    async fn error(&self, message: &str) -> Result<(), Error> {
+        self.message(FeedbackLevel::Error, message).await
+    }
+
+    /// Delivers the message along with controls of the run, like buttons pausing and
+    /// resuming it, if the client supports them.
+    async fn message_with_controls(&self, level: FeedbackLevel, message: &str) -> Result<(), Error> {
+        self.message(level, message).await
+    }
+}
+
+type Client = Arc<Box<dyn FeedbackClient + Send + Sync>>;
+
+/// How a message is delivered to the client.
+#[derive(Clone, Copy)]
+enum Delivery {
+    /// Along with controls of the run
+    Controls,
+    /// As a regression
+    Regression,
+}
+
+/// How failed deliveries of important messages are retried.
+#[derive(Clone, Copy)]
+struct RetryPolicy {
+    retries: u32,
+    delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Delivers the message, retrying with exponential backoff if it fails.
+    async fn deliver(self, client: &Client, level: FeedbackLevel, message: &str, delivery: Delivery, log: &Logger) -> Result<(), Error> {
+        let send = || async {
+            match delivery {
+                Delivery::Controls => client.message_with_controls(level, message).await,
+                Delivery::Regression => client.regression(level, message).await,
+            }
+        };
+        let mut delay = self.delay;
+        for _ in 0..self.retries {
+            match send().await {
+                Ok(()) => return Ok(()),
+                Err(e) => warn!(log, "Cannot deliver message, retrying"; "error" => e, "delay" => delay.as_secs()),
+            }
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+        }
+        send().await
+    }
+}
+
+/// Sends the message in background, logging failures.
+fn send(client: &Client, level: FeedbackLevel, message: impl Into<String>, log: &Logger) {
+    let client = client.clone();
+    let message = message.into();
+    let log = log.clone();
+    tokio::spawn(async move {
+        if let Err(e) = client.message(level, &message).await {
+            error!(log, "Cannot deliver message"; "error" => e);
+        }
+    });
+}
+
+/// Sends the regression message in background, logging failures.
+fn send_regression(client: &Client, level: FeedbackLevel, message: impl Into<String>, log: &Logger) {
+    let client = client.clone();
+    let message = message.into();
+    let log = log.clone();
+    tokio::spawn(async move {
+        if let Err(e) = client.regression(level, &message).await {
+            error!(log, "Cannot deliver message"; "error" => e);
+        }
+    });
+}
+
+/// Client delivering only regressions to the wrapped client.
+pub struct RegressionsOnly {
+    client: Box<dyn FeedbackClient + Send + Sync>,
+}
 
-    fn info(&self, message: &str) {
-        self.message(FeedbackLevel::Info, message)
+impl RegressionsOnly {
+    /// The client itself if its policy is to deliver all messages.
+    pub fn with_policy(
+        client: Box<dyn FeedbackClient + Send + Sync>,
+        policy: NotificationPolicy,
+    ) -> Box<dyn FeedbackClient + Send + Sync> {
+        match policy {
+            NotificationPolicy::All => client,
+            NotificationPolicy::Regressions => Box::new(Self { client }),
+        }
+    }
+}
+
+#[async_trait]
+impl FeedbackClient for RegressionsOnly {
+    async fn message(&self, _level: FeedbackLevel, _message: &str) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn regression(&self, level: FeedbackLevel, message: &str) -> Result<(), Error> {
+        self.client.message(level, message).await
     }
 
-    fn error(&self, message: &str) {
-        self.message(FeedbackLevel::Error, message)
+    async fn message_with_controls(&self, _level: FeedbackLevel, _message: &str) -> Result<(), Error> {
+        Ok(())
     }
 }
 
 pub struct LoggerClient {
     id: String,
+    level: FeedbackLevel,
     log: Logger,
 }
 
@@ -42,37 +197,118 @@ impl LoggerClient {
     pub fn new(id: &str, log: Logger) -> Self {
         Self {
             id: id.to_string(),
+            level: FeedbackLevel::Debug,
             log,
         }
     }
+
+    /// Skips messages below the level.
+    pub fn with_level(mut self, level: FeedbackLevel) -> Self {
+        self.level = level;
+        self
+    }
 }
 
+#[async_trait]
 impl FeedbackClient for LoggerClient {
-    fn message(&self, level: FeedbackLevel, message: &str) {
+    async fn message(&self, level: FeedbackLevel, message: &str) -> Result<(), Error> {
+        if level < self.level {
+            return Ok(());
+        }
         match level {
+            FeedbackLevel::Critical => crit!(self.log, "{}", message; "client" => &self.id),
             FeedbackLevel::Error => error!(self.log, "{}", message; "client" => &self.id),
+            FeedbackLevel::Warning => warn!(self.log, "{}", message; "client" => &self.id),
             FeedbackLevel::Info => info!(self.log, "{}", message; "client" => &self.id),
+            FeedbackLevel::Debug => debug!(self.log, "{}", message; "client" => &self.id),
         }
+        Ok(())
     }
 }
 
+/// Client delivering messages to each of its clients, which filter them by their own levels.
+pub struct MultiClient {
+    clients: Vec<Box<dyn FeedbackClient + Send + Sync>>,
+}
+
+impl MultiClient {
+    pub fn new(clients: Vec<Box<dyn FeedbackClient + Send + Sync>>) -> Self {
+        Self { clients }
+    }
+}
+
+#[async_trait]
+impl FeedbackClient for MultiClient {
+    /// Delivers the message to all clients, returning the first error.
+    async fn message(&self, level: FeedbackLevel, message: &str) -> Result<(), Error> {
+        let mut result = Ok(());
+        for client in &self.clients {
+            let delivered = client.message(level, message).await;
+            result = result.and(delivered);
+        }
+        result
+    }
+
+    async fn regression(&self, level: FeedbackLevel, message: &str) -> Result<(), Error> {
+        let mut result = Ok(());
+        for client in &self.clients {
+            let delivered = client.regression(level, message).await;
+            result = result.and(delivered);
+        }
+        result
+    }
+
+    async fn message_with_controls(&self, level: FeedbackLevel, message: &str) -> Result<(), Error> {
+        let mut result = Ok(());
+        for client in &self.clients {
+            let delivered = client.message_with_controls(level, message).await;
+            result = result.and(delivered);
+        }
+        result
+    }
+}
+
+/// Collects fuzzing progress of targets, periodically reporting it to the client
+/// and updating the coverage report.
 pub struct Feedback {
     map: Arc<SharedFeedbackMap>,
-    client: Arc<Box<dyn FeedbackClient + Send + Sync>>,
+    resources: Arc<RwLock<Resources>>,
+    /// Fuzzing projects that failed to build
+    build_failures: RwLock<Vec<String>>,
+    /// Owners, descriptions and harness sources of fuzzed targets, added to crash messages
+    targets: RwLock<HashMap<String, config::TargetMetadata>>,
+    client: Client,
+    retry: RetryPolicy,
     updater: Arc<ScheduledUpdater>,
+    /// Time of the last coverage update, or of the start of fuzzing
+    updated: RwLock<Instant>,
     report: Arc<Report>,
+    artifacts: Option<Arc<RunArtifacts>>,
+    /// Client opening issues for new crash signatures, if they are opened
+    crash_issues: Option<Arc<CrashIssues>>,
+    /// Check run of the fuzzed commit summarizing report updates, if it is created
+    check_run: Arc<RwLock<Option<Arc<CheckRun>>>>,
+    /// Drop of covered edges since the previous run reported as a regression, in percents
+    coverage_drop: f64,
+    /// Interval of exporting covered edges to the report, if they are exported
+    coverage_export: Option<Duration>,
+    /// Notified when fuzzing stops, to export covered edges for the last time
+    export_stopped: Arc<Notify>,
+    /// Factor of the drop of execution rates of targets reported as a warning, if reported
+    exec_rate_drop: Option<f64>,
+    time: config::Time,
     log: Logger,
 }
 
 impl Feedback {
-    pub async fn new<'a>(
-        config: &'a config::Feedback,
+    /// Feedback of the run reported by the client, updating its report.
+    pub fn new(
+        config: &config::Feedback,
+        time: &config::Time,
         client: Box<dyn FeedbackClient + Send + Sync>,
-        reports_dir: impl AsRef<Path>,
-        reports_url: &'a Option<Url>,
-        reports_loc: impl AsRef<Path>,
+        report: Report,
         log: Logger,
-    ) -> Result<Self, Error> {
+    ) -> Self {
         let client = Arc::new(client);
         let updater = ScheduledUpdater::new(
             Duration::from_secs(config.start_timeout),
@@ -80,99 +316,476 @@ impl Feedback {
             Duration::from_secs(config.no_update_timeout),
             log.new(o!("role" => "updater")),
         );
-        let report = Report::new(
-            reports_dir.as_ref(),
-            reports_url,
-            reports_loc.as_ref(),
-            log.new(o!("role" => "report")),
-        )
-        .await?;
-        Ok(Self {
+        Self {
             map: Arc::new(SharedFeedbackMap::new()),
+            resources: Arc::default(),
+            build_failures: RwLock::default(),
+            targets: RwLock::default(),
             client,
+            retry: RetryPolicy {
+                retries: config.retries,
+                delay: Duration::from_secs(config.retry_delay),
+            },
             updater: Arc::new(updater),
+            updated: RwLock::new(Instant::now()),
             report: Arc::new(report),
+            artifacts: None,
+            crash_issues: None,
+            check_run: Arc::default(),
+            coverage_drop: config.coverage_drop,
+            coverage_export: config.coverage_export.map(Duration::from_secs),
+            export_stopped: Arc::new(Notify::new()),
+            exec_rate_drop: config.exec_rate_drop,
+            time: time.clone(),
             log,
-        })
+        }
+    }
+
+    /// Stores crash inputs and corpus snapshots of the run as content-addressed artifacts.
+    pub fn with_artifacts(mut self, artifacts: RunArtifacts) -> Self {
+        self.artifacts = Some(Arc::new(artifacts));
+        self
+    }
+
+    /// Opens issues for crashes with signatures not seen before.
+    pub(crate) fn with_crash_issues(mut self, crash_issues: CrashIssues) -> Self {
+        self.crash_issues = Some(Arc::new(crash_issues));
+        self
     }
 
     pub fn set_total(&self, target: &str, total: u32) {
         self.map.set_total(target, total);
-        self.updater.update();
+        self.updated();
+    }
+
+    /// Records the spread of measured totals of the target, and whether it makes its coverage
+    /// unreliable to compare with other builds.
+    pub fn set_total_spread(&self, target: &str, spread: u32, unreliable: bool) {
+        self.map.set_total_spread(target, spread, unreliable);
     }
 
     pub fn add_covered(&self, target: &str, covered: u32) {
         self.map.add_covered(target, covered);
+        self.updated();
+    }
+
+    fn updated(&self) {
+        *self.updated.write().unwrap() = Instant::now();
         self.updater.update();
     }
 
+    /// Time elapsed since the last coverage update, or since the start of fuzzing if none.
+    pub fn since_update(&self) -> Duration {
+        self.updated.read().unwrap().elapsed()
+    }
+
+    /// Current coverage and errors of all targets.
+    pub fn status(&self) -> FuzzingStatus {
+        self.map.snapshot()
+    }
+
+    /// Records the peak resource usage of building the project.
+    pub fn set_build_usage(&self, project: &str, usage: Usage) {
+        self.resources.write().unwrap().builds.insert(project.to_string(), usage);
+    }
+
+    /// Reports the failed build of the project as a regression, counting it in the final
+    /// statistics of the run.
+    pub fn build_failed(&self, project: &str, error: impl std::fmt::Display) {
+        self.build_failures.write().unwrap().push(project.to_string());
+        self.regression(FeedbackLevel::Error, format!("Building `{}` failed: {}", project, error));
+    }
+
+    /// Final statistics of the run lasting `duration`, for its verdict: crashes, the
+    /// largest loss of covered edges of a target since the previous run, not counting
+    /// targets with unreliable totals, and failed builds.
+    pub fn run_stats(&self, duration: Duration) -> RunStats {
+        let status = self.map.snapshot();
+        let coverage_delta = self
+            .report
+            .previous_run()
+            .map(|(previous, _)| {
+                status
+                    .iter()
+                    .filter_map(|(target, status)| Some((previous.get(target)?, status)))
+                    .filter(|(previous, status)| previous.covered > 0 && !previous.unreliable && !status.unreliable)
+                    .map(|(previous, status)| {
+                        (f64::from(status.covered) - f64::from(previous.covered)) * 100.0 / f64::from(previous.covered)
+                    })
+                    .fold(0.0, f64::min)
+            })
+            .unwrap_or_default();
+        RunStats {
+            crashes: status.values().map(|status| status.errors).sum(),
+            coverage_delta,
+            build_failures: self.build_failures.read().unwrap().len() as u32,
+            duration,
+        }
+    }
+
+    /// Reports the verdict of the finished run, as a regression if it fails.
+    pub fn verdict(&self, verdict: Verdict, description: &str) {
+        let message = format!("Run verdict: {}, {}", verdict.name(), description);
+        match verdict {
+            Verdict::Pass | Verdict::Cancelled => self.message(message),
+            Verdict::Fail | Verdict::Error => self.regression(FeedbackLevel::Error, message),
+        }
+    }
+
+    /// Records the recent resource usage of the target.
+    pub fn set_target_usage(&self, target: &str, usage: Usage) {
+        self.resources.write().unwrap().targets.insert(target.to_string(), usage);
+    }
+
+    /// Resource usage recorded so far.
+    pub fn resources(&self) -> Resources {
+        self.resources.read().unwrap().clone()
+    }
+
+    /// Records the fuzzed commit in the report.
+    pub async fn set_commit(&self, commit: &str) {
+        if let Err(e) = self.report.set_commit(commit).await {
+            error!(self.log, "Error recording fuzzed commit: {}", e);
+        }
+    }
+
+    /// Records what started the run in the report.
+    pub async fn set_trigger(&self, trigger: Trigger) {
+        if let Err(e) = self.report.set_trigger(trigger).await {
+            error!(self.log, "Error recording run trigger: {}", e);
+        }
+    }
+
+    /// Records the provenance of the run in the report.
+    pub async fn set_provenance(&self, provenance: Provenance) {
+        if let Err(e) = self.report.set_provenance(provenance).await {
+            error!(self.log, "Error recording run provenance: {}", e);
+        }
+    }
+
+    /// Records owners, descriptions and harness sources of fuzzed targets, shown in the
+    /// report and in crash messages.
+    pub async fn set_targets(&self, targets: HashMap<String, config::TargetMetadata>) {
+        *self.targets.write().unwrap() = targets.clone();
+        if let Err(e) = self.report.set_targets(targets).await {
+            error!(self.log, "Error recording target metadata: {}", e);
+        }
+    }
+
+    /// Fuzzed commit of the run and the URL of its report, if both are known.
+    pub async fn deployment(&self) -> Option<(String, Url)> {
+        let commit = self.commit().await?;
+        Some((commit, self.report_url()?))
+    }
+
+    /// Fuzzed commit of the run, if it is checked out.
+    pub async fn commit(&self) -> Option<String> {
+        match self.report.commit().await {
+            Ok(commit) => commit,
+            Err(e) => {
+                error!(self.log, "Error reading fuzzed commit: {}", e);
+                None
+            }
+        }
+    }
+
+    /// URL of the run report, if the reports URL is configured.
+    pub fn report_url(&self) -> Option<Url> {
+        self.report.url().ok().flatten()
+    }
+
+    /// Factor of the drop of execution rates of targets reported as a warning, if reported.
+    pub fn exec_rate_drop(&self) -> Option<f64> {
+        self.exec_rate_drop
+    }
+
+    /// Directory of the run report.
+    pub fn report_dir(&self) -> &Path {
+        self.report.dir()
+    }
+
+    /// Updates the run report with the current status, returning its summary.
+    pub async fn update_report(&self) -> Result<Summary, Error> {
+        self.report.update(&self.map.snapshot()).await
+    }
+
+    /// Updates the run report with the final status and packs it with crashes found into
+    /// the gzipped tarball `archive`.
+    pub async fn archive_report(&self, archive: &Path) -> Result<(), Error> {
+        let status = self.map.snapshot();
+        self.report.update(&status).await?;
+        self.report.archive(&status, archive).await
+    }
+
+    /// Publishes coverage tables of further report updates as summaries of the check run.
+    pub(crate) fn set_check_run(&self, check_run: CheckRun) {
+        *self.check_run.write().unwrap() = Some(Arc::new(check_run));
+    }
+
+    /// Completes the check run, if it is created, with the conclusion and the coverage
+    /// table of the final status as its summary.
+    pub(crate) async fn complete_check_run(&self, conclusion: CheckConclusion, title: &str) {
+        let check_run = match self.check_run.write().unwrap().take() {
+            Some(check_run) => check_run,
+            None => return,
+        };
+        let summary = match self.update_report().await {
+            Ok(summary) => summary.table,
+            Err(e) => {
+                error!(self.log, "Error updating final report: {}", e);
+                String::new()
+            }
+        };
+        if let Err(e) = check_run.complete(conclusion, title, &summary).await {
+            error!(self.log, "Cannot complete check run"; "error" => e.to_string());
+        }
+    }
+
+    /// Final status and fuzzed commit of the previous run of the branch, if known.
+    pub fn previous_run(&self) -> Option<(FuzzingStatus, Option<String>)> {
+        self.report
+            .previous_run()
+            .map(|(status, commit)| (status.clone(), commit.map(String::from)))
+    }
+
+    /// Reports the commit found to lose coverage, adding it to the report.
+    pub async fn add_bisection(&self, bisection: Bisection) {
+        self.regression(FeedbackLevel::Warning, format!("Coverage regression: {}", bisection));
+        if let Err(e) = self.report.add_bisection(bisection).await {
+            error!(self.log, "Error adding bisection to the report: {}", e);
+        }
+    }
+
+    /// Reports the crash found in the target, retrying its delivery in background.
     pub fn add_error(&self, target: &str, error_input: &str) {
         self.map.add_errors(target, 1);
         let client = self.client.clone();
-        let message = match self.report.add_error(target, error_input) {
+        let retry = self.retry;
+        let log = self.log.clone();
+        let mut message = match self.report.add_error(target, error_input) {
             Ok(message) => message,
             Err(err) => {
                 error!(self.log, "Error reporting error input file: {}", err);
                 format!("Error detected in `{}`: `{}`", target, error_input)
             }
         };
+        if let Some(metadata) = config::TargetMetadata::of(&self.targets.read().unwrap(), target) {
+            if let Some(description) = &metadata.description {
+                message += &format!("\nTarget: {}", description);
+            }
+            if let Some(owner) = &metadata.owner {
+                message += &format!("\nOwner: {}", owner);
+            }
+            if let Some(source) = &metadata.source {
+                message += &format!("\nHarness source: {}", source);
+            }
+        }
+        if let Some(crash_issues) = self.crash_issues.clone() {
+            self.open_crash_issue(crash_issues, target, error_input);
+        }
+        if let Some(artifacts) = self.artifacts.clone() {
+            let input = Path::new(error_input);
+            let name = format!("failures/{}/{}", target, input.file_name().unwrap_or_default().to_string_lossy());
+            let input = input.to_path_buf();
+            let log = self.log.clone();
+            tokio::spawn(async move {
+                if let Err(e) = artifacts.add(&name, input).await {
+                    error!(log, "Cannot store error input"; "name" => name, "error" => e.to_string());
+                }
+            });
+        }
+        tokio::spawn(async move {
+            if let Err(e) = retry.deliver(&client, FeedbackLevel::Error, &message, Delivery::Regression, &log).await {
+                error!(log, "Cannot deliver crash report"; "error" => e);
+            }
+        });
+    }
+
+    /// Opens an issue for the crash of the target in background, unless its signature
+    /// already has one.
+    fn open_crash_issue(&self, crash_issues: Arc<CrashIssues>, target: &str, error_input: &str) {
+        let name = Path::new(error_input).file_name().unwrap_or_default().to_string_lossy().into_owned();
+        let signature = hfuzz::crash_signature(&name).to_string();
+        let input = match self.report.error_location(target, &name) {
+            Ok(location) => location,
+            Err(_) => format!("`{}`", error_input),
+        };
+        let report = self.report.clone();
+        let target = target.to_string();
+        let log = self.log.clone();
         tokio::spawn(async move {
-            client.error(&message);
+            let commit = report.commit().await.ok().flatten();
+            let report_url = report.url().ok().flatten();
+            match crash_issues.report(&target, &signature, &input, commit.as_deref(), report_url.as_ref()).await {
+                Ok(Some(issue)) => info!(log, "Opened crash issue"; "target" => &target, "issue" => issue),
+                Ok(None) => (),
+                Err(e) => error!(log, "Cannot open crash issue"; "target" => &target, "error" => e.to_string()),
+            }
         });
     }
 
-    fn update_text(time: &DateTime<Utc>) -> String {
+    /// Stores the corpus of the target as a snapshot of the run, if artifacts are stored.
+    pub async fn add_corpus_snapshot(&self, target: &str, corpus: &Path) {
+        if let Some(artifacts) = &self.artifacts {
+            match artifacts.add_dir(&format!("corpus/{}", target), corpus).await {
+                Ok(stored) => debug!(self.log, "Stored corpus snapshot"; "target" => target, "inputs" => stored),
+                Err(e) => error!(self.log, "Cannot store corpus snapshot"; "target" => target, "error" => e.to_string()),
+            }
+        }
+    }
+
+    fn update_text(time: &DateTime<Utc>, format: &config::Time) -> String {
         let dur = Utc::now().signed_duration_since(time.clone());
         format!(
             "Last coverage update at {}, {}s ago",
-            time.format("%Y-%m-%d %H:%M:%S").to_string(),
+            format.format(time),
             dur.num_seconds(),
         )
     }
 
-    pub fn started(&self) {
-        self.client.info("Fuzzing is started");
+    /// Reports the start of fuzzing, waiting for the message to be delivered, and starts
+    /// periodic progress messages.
+    pub async fn started(&self) {
+        *self.updated.write().unwrap() = Instant::now();
+        if let Err(e) = self
+            .retry
+            .deliver(&self.client, FeedbackLevel::Info, "Fuzzing is started", Delivery::Controls, &self.log)
+            .await
+        {
+            error!(self.log, "Cannot deliver fuzzing start message"; "error" => e);
+        }
+        if let Some(interval) = self.coverage_export {
+            self.export_coverage(interval);
+        }
         let client = self.client.clone();
         let report = self.report.clone();
         let map = self.map.clone();
+        let resources = self.resources.clone();
+        let check_run = self.check_run.clone();
+        let format = self.time.clone();
         let log = self.log.clone();
         self.updater.start(move |time, update| {
             if !update {
-                client.info(&format!(
-                    "No coverage updates since {}",
-                    time.format("%Y-%m-%d %H:%M:%S").to_string(),
-                ));
+                let message = format!("No coverage updates since {}", format.format(time));
+                send(&client, FeedbackLevel::Warning, message, &log);
                 return;
             }
-            let mut message = Self::update_text(time);
+            let mut message = Self::update_text(time, &format);
             let snap = map.snapshot();
+            let resources = resources.read().unwrap().clone();
             let report = report.clone();
             let client = client.clone();
+            let check_run = check_run.read().unwrap().clone();
             let log = log.clone();
             tokio::spawn(async move {
                 match report.update(&snap).await {
                     Ok(summary) => {
-                        message = format!("{}\n{}", message, summary);
+                        message = format!("{}\n{}", message, summary.text);
+                        if let Some(check_run) = check_run {
+                            if let Err(e) = check_run.update(&summary.table).await {
+                                error!(log, "Cannot update check run"; "error" => e.to_string());
+                            }
+                        }
                     }
                     Err(e) => {
                         error!(log, "Error updating progress report: {}", e)
                     }
                 }
-                client.info(&message);
+                if let Err(e) = report.save_resources(&resources).await {
+                    error!(log, "Error saving resource usage: {}", e)
+                }
+                if !resources.targets.is_empty() {
+                    message += "Resource usage:\n";
+                    for (target, usage) in &resources.targets {
+                        message += &format!("{}: {}\n", target, usage);
+                    }
+                }
+                if let Err(e) = client.info(&message).await {
+                    error!(log, "Cannot deliver progress message"; "error" => e);
+                }
             });
         });
     }
 
+    /// Periodically exports covered edges of targets to the report, until fuzzing stops.
+    fn export_coverage(&self, interval: Duration) {
+        let report = self.report.clone();
+        let map = self.map.clone();
+        let stopped = self.export_stopped.clone();
+        let log = self.log.clone();
+        tokio::spawn(async move {
+            loop {
+                let stop = tokio::select! {
+                    _ = tokio::time::sleep(interval) => false,
+                    _ = stopped.notified() => true,
+                };
+                if let Err(e) = report.export_coverage(&map.snapshot(), Utc::now()).await {
+                    error!(log, "Error exporting coverage: {}", e);
+                }
+                if stop {
+                    return;
+                }
+            }
+        });
+    }
+
     pub fn stopped(&self) {
-        self.client.info("Fuzzing is stopped");
+        send(&self.client, FeedbackLevel::Info, "Fuzzing is stopped", &self.log);
         self.updater.stop();
+        self.export_stopped.notify_one();
+        if let Some((previous, _)) = self.report.previous_run() {
+            let mut dropped = self
+                .map
+                .snapshot()
+                .into_iter()
+                .filter_map(|(target, status)| {
+                    let previous = previous.get(&target)?;
+                    // coverage of targets with varying totals isn't comparable across builds
+                    if previous.unreliable || status.unreliable {
+                        None
+                    } else {
+                        Some((previous.covered, status.covered, target))
+                    }
+                })
+                .filter(|(before, after, _)| bisect::is_regression(*before, *after, self.coverage_drop))
+                .collect::<Vec<_>>();
+            dropped.sort_by(|(_, _, a), (_, _, b)| a.cmp(b));
+            for (before, after, target) in dropped {
+                self.regression(
+                    FeedbackLevel::Warning,
+                    format!("Coverage of `{}` dropped from {} to {} edges since the previous run", target, before, after),
+                );
+            }
+        }
+    }
+
+    pub fn debug(&self, msg: impl AsRef<str>) {
+        send(&self.client, FeedbackLevel::Debug, msg.as_ref(), &self.log);
     }
 
     pub fn message(&self, msg: impl AsRef<str>) {
-        self.client.info(msg.as_ref());
+        send(&self.client, FeedbackLevel::Info, msg.as_ref(), &self.log);
+    }
+
+    /// Reports a regression, like a failed build, also to clients delivering only regressions.
+    pub fn regression(&self, level: FeedbackLevel, msg: impl AsRef<str>) {
+        send_regression(&self.client, level, msg.as_ref(), &self.log);
+    }
+
+    pub fn warning(&self, msg: impl AsRef<str>) {
+        send(&self.client, FeedbackLevel::Warning, msg.as_ref(), &self.log);
+    }
+
+    pub fn error(&self, msg: impl AsRef<str>) {
+        send(&self.client, FeedbackLevel::Error, msg.as_ref(), &self.log);
+    }
+
+    pub fn critical(&self, msg: impl AsRef<str>) {
+        send(&self.client, FeedbackLevel::Critical, msg.as_ref(), &self.log);
     }
 }
 
+#[derive(Default)]
 pub struct SharedFeedbackMap {
     map: RwLock<FuzzingStatus>,
 }
@@ -195,6 +808,13 @@ impl SharedFeedbackMap {
             .insert(target.as_ref().into(), TargetStatus::new(total, 0, 0));
     }
 
+    pub fn set_total_spread(&self, target: impl AsRef<str>, spread: u32, unreliable: bool) {
+        if let Some(s) = self.map.write().unwrap().get_mut(target.as_ref()) {
+            s.spread = spread;
+            s.unreliable = unreliable;
+        }
+    }
+
     pub fn add_covered(&self, target: impl AsRef<str>, covered: u32) {
         self.map
             .write()
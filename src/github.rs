@@ -0,0 +1,659 @@
+//! GitHub deployments pointing at run reports, so the "View deployment" button of a
+//! fuzzed commit leads to its coverage report, commit statuses and check runs of runs,
+//! comments on pull requests, issues of new crashes, release assets with reports of fuzzed
+//! tags, and signatures of webhook requests, authenticated with a token or as a GitHub App.
+
+use std::{collections::HashMap, path::Path, sync::Mutex};
+
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac, NewMac};
+use reqwest::{header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE, USER_AGENT}, RequestBuilder, StatusCode, Url};
+use openssl::{hash::MessageDigest, pkey::PKey, sign::Signer};
+use serde_json::json;
+use sha2::Sha256;
+use slog::{debug, Logger};
+use static_init::dynamic;
+
+use crate::{
+    api::constant_time_eq,
+    config,
+    error::{Error, Result},
+    runs::Runs,
+};
+
+#[derive(serde::Deserialize)]
+struct Deployment {
+    id: u64,
+}
+
+/// Client creating deployments of fuzzed commits in the configured environment.
+pub struct Deployments {
+    api_url: Url,
+    credentials: Credentials,
+    environment: String,
+    log: Logger,
+}
+
+impl Deployments {
+    pub fn new(config: &config::Github, log: Logger) -> Self {
+        Self {
+            api_url: config.api_url.clone(),
+            credentials: Credentials::new(config),
+            environment: config.environment.clone(),
+            log,
+        }
+    }
+
+    /// Creates a deployment of the commit of the `<owner>/<name>` repository with the report
+    /// URL as its environment URL, and marks it successful, or failed if `success` is not set.
+    pub async fn publish(&self, repository: &str, commit: &str, report: &Url, success: bool) -> Result<u64> {
+        let deployments = self.api_url.join(&format!("repos/{}/deployments", repository))?;
+        let deployment = self
+            .post(
+                repository,
+                deployments.clone(),
+                json!({
+                    "ref": commit,
+                    "environment": self.environment,
+                    "description": "Fuzzing coverage report",
+                    "auto_merge": false,
+                    "required_contexts": [],
+                }),
+            )
+            .await?
+            .json::<Deployment>()
+            .await?;
+        let statuses = self.api_url.join(&format!("repos/{}/deployments/{}/statuses", repository, deployment.id))?;
+        self.post(
+            repository,
+            statuses,
+            json!({
+                "state": if success { "success" } else { "failure" },
+                "environment_url": report,
+                "log_url": report,
+            }),
+        )
+        .await?;
+        debug!(self.log, "Created deployment"; "repository" => repository, "commit" => commit, "id" => deployment.id);
+        Ok(deployment.id)
+    }
+
+    async fn post(&self, repository: &str, url: Url, json: serde_json::Value) -> Result<reqwest::Response> {
+        let request = self.credentials.authorize(reqwest::Client::new().post(url), repository).await?;
+        check(request.json(&json).send().await?).await
+    }
+}
+
+/// State of a commit status.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CommitState {
+    Pending,
+    Success,
+    Failure,
+    Error,
+}
+
+impl CommitState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CommitState::Pending => "pending",
+            CommitState::Success => "success",
+            CommitState::Failure => "failure",
+            CommitState::Error => "error",
+        }
+    }
+}
+
+/// Client setting statuses of fuzzed commits in the configured context, linking run reports.
+pub struct GithubStatusClient {
+    api_url: Url,
+    credentials: Credentials,
+    context: String,
+    log: Logger,
+}
+
+impl GithubStatusClient {
+    /// Longest description of a status accepted by GitHub.
+    const MAX_DESCRIPTION: usize = 140;
+
+    pub fn new(config: &config::Github, log: Logger) -> Self {
+        Self {
+            api_url: config.api_url.clone(),
+            credentials: Credentials::new(config),
+            context: config.status_context.clone(),
+            log,
+        }
+    }
+
+    /// Sets the status of the commit of the `<owner>/<name>` repository, with the report
+    /// URL as its target URL if known.
+    pub async fn set_status(
+        &self,
+        repository: &str,
+        commit: &str,
+        state: CommitState,
+        description: &str,
+        report: Option<&Url>,
+    ) -> Result<()> {
+        let url = self.api_url.join(&format!("repos/{}/statuses/{}", repository, commit))?;
+        let description = description.chars().take(Self::MAX_DESCRIPTION).collect::<String>();
+        let request = self.credentials.authorize(reqwest::Client::new().post(url), repository).await?.json(&json!({
+            "state": state.name(),
+            "target_url": report,
+            "description": description,
+            "context": self.context,
+        }));
+        check(request.send().await?).await?;
+        debug!(self.log, "Set commit status"; "repository" => repository, "commit" => commit, "state" => state.name());
+        Ok(())
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct CheckRunId {
+    id: u64,
+}
+
+/// Conclusion of a completed check run.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CheckConclusion {
+    Success,
+    Failure,
+    Cancelled,
+}
+
+impl CheckConclusion {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CheckConclusion::Success => "success",
+            CheckConclusion::Failure => "failure",
+            CheckConclusion::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// Check run of a fuzzed commit, with the coverage table of the run report as its summary.
+pub struct CheckRun {
+    api_url: Url,
+    credentials: Credentials,
+    repository: String,
+    id: u64,
+    log: Logger,
+}
+
+impl CheckRun {
+    /// Longest summary of a check run accepted by GitHub.
+    const MAX_SUMMARY: usize = 65535;
+
+    /// Creates an in-progress check run of the commit of the `<owner>/<name>` repository,
+    /// with the report URL as its details URL if known.
+    pub async fn create(
+        config: &config::Github,
+        repository: &str,
+        commit: &str,
+        report: Option<&Url>,
+        log: Logger,
+    ) -> Result<Self> {
+        let credentials = Credentials::new(config);
+        let url = config.api_url.join(&format!("repos/{}/check-runs", repository))?;
+        let request = credentials.authorize(reqwest::Client::new().post(url), repository).await?.json(&json!({
+            "name": config.check_name,
+            "head_sha": commit,
+            "status": "in_progress",
+            "details_url": report,
+            "output": {
+                "title": "Fuzzing is in progress",
+                "summary": "Waiting for the first coverage update",
+            },
+        }));
+        let id = check(request.send().await?).await?.json::<CheckRunId>().await?.id;
+        debug!(log, "Created check run"; "repository" => repository, "commit" => commit, "id" => id);
+        Ok(Self {
+            api_url: config.api_url.clone(),
+            credentials,
+            repository: repository.to_string(),
+            id,
+            log,
+        })
+    }
+
+    /// Replaces the summary of the check run while fuzzing is in progress.
+    pub async fn update(&self, summary: &str) -> Result<()> {
+        self.patch(json!({
+            "output": {
+                "title": "Fuzzing is in progress",
+                "summary": Self::truncated(summary),
+            },
+        }))
+        .await
+    }
+
+    /// Completes the check run with the conclusion, the title and the final summary.
+    pub async fn complete(&self, conclusion: CheckConclusion, title: &str, summary: &str) -> Result<()> {
+        self.patch(json!({
+            "status": "completed",
+            "conclusion": conclusion.name(),
+            "output": {
+                "title": title,
+                "summary": Self::truncated(summary),
+            },
+        }))
+        .await?;
+        debug!(self.log, "Completed check run"; "id" => self.id, "conclusion" => conclusion.name());
+        Ok(())
+    }
+
+    async fn patch(&self, json: serde_json::Value) -> Result<()> {
+        let url = self.api_url.join(&format!("repos/{}/check-runs/{}", self.repository, self.id))?;
+        let request = self.credentials.authorize(reqwest::Client::new().patch(url), &self.repository).await?;
+        check(request.json(&json).send().await?).await?;
+        Ok(())
+    }
+
+    fn truncated(summary: &str) -> &str {
+        match summary.char_indices().nth(Self::MAX_SUMMARY) {
+            Some((end, _)) => &summary[..end],
+            None => summary,
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct PullRequest {
+    number: u64,
+    state: String,
+}
+
+#[derive(serde::Deserialize)]
+struct IssueComment {
+    id: u64,
+    body: String,
+}
+
+/// Client commenting on pull requests of fuzzed commits.
+pub struct Comments {
+    api_url: Url,
+    credentials: Credentials,
+    log: Logger,
+}
+
+impl Comments {
+    pub fn new(config: &config::Github, log: Logger) -> Self {
+        Self {
+            api_url: config.api_url.clone(),
+            credentials: Credentials::new(config),
+            log,
+        }
+    }
+
+    /// Comments on open pull requests with the commit of the `<owner>/<name>` repository,
+    /// or on the commit itself if there are none, returning the number of comments.
+    pub async fn comment(&self, repository: &str, commit: &str, body: &str) -> Result<usize> {
+        let client = reqwest::Client::new();
+        let urls = self
+            .open_pulls(repository, commit)
+            .await?
+            .into_iter()
+            .map(|number| self.api_url.join(&format!("repos/{}/issues/{}/comments", repository, number)))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let urls = if urls.is_empty() {
+            vec![self.api_url.join(&format!("repos/{}/commits/{}/comments", repository, commit))?]
+        } else {
+            urls
+        };
+        for url in &urls {
+            let request = self.credentials.authorize(client.post(url.clone()), repository).await?;
+            check(request.json(&json!({ "body": body })).send().await?).await?;
+        }
+        debug!(self.log, "Commented"; "repository" => repository, "commit" => commit, "comments" => urls.len());
+        Ok(urls.len())
+    }
+
+    /// Comments on open pull requests with the commit of the `<owner>/<name>` repository,
+    /// replacing their earlier comment starting with the `marker`, so each of them keeps a
+    /// single one. Returns the number of pull requests commented on.
+    pub async fn replace_comment(&self, repository: &str, commit: &str, marker: &str, body: &str) -> Result<usize> {
+        let client = reqwest::Client::new();
+        let body = format!("{}\n{}", marker, body);
+        let pulls = self.open_pulls(repository, commit).await?;
+        for number in &pulls {
+            let url = self.api_url.join(&format!("repos/{}/issues/{}/comments", repository, number))?;
+            let mut list = url.clone();
+            list.query_pairs_mut().append_pair("per_page", "100");
+            let comments = check(self.credentials.authorize(client.get(list), repository).await?.send().await?)
+                .await?
+                .json::<Vec<IssueComment>>()
+                .await?;
+            let request = match comments.iter().find(|comment| comment.body.starts_with(marker)) {
+                Some(comment) => {
+                    let url = self.api_url.join(&format!("repos/{}/issues/comments/{}", repository, comment.id))?;
+                    client.patch(url)
+                }
+                None => client.post(url),
+            };
+            let request = self.credentials.authorize(request, repository).await?;
+            check(request.json(&json!({ "body": body })).send().await?).await?;
+        }
+        debug!(self.log, "Replaced comments"; "repository" => repository, "commit" => commit, "pulls" => pulls.len());
+        Ok(pulls.len())
+    }
+
+    /// Numbers of open pull requests with the commit.
+    async fn open_pulls(&self, repository: &str, commit: &str) -> Result<Vec<u64>> {
+        let url = self.api_url.join(&format!("repos/{}/commits/{}/pulls", repository, commit))?;
+        let pulls = check(self.credentials.authorize(reqwest::Client::new().get(url), repository).await?.send().await?)
+            .await?
+            .json::<Vec<PullRequest>>()
+            .await?;
+        Ok(pulls.into_iter().filter(|pull| pull.state == "open").map(|pull| pull.number).collect())
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct Issue {
+    number: u64,
+}
+
+/// Client opening issues for crashes of fuzz targets of a branch, a single one for each
+/// crash signature of a target.
+pub struct CrashIssues {
+    api_url: Url,
+    credentials: Credentials,
+    labels: Vec<String>,
+    repository: String,
+    branch: String,
+    runs: Runs,
+    log: Logger,
+}
+
+impl CrashIssues {
+    /// Client opening issues in the `<owner>/<name>` repository for crashes found on the
+    /// branch, with signatures claimed in `runs`.
+    pub fn new(config: &config::Github, repository: String, branch: String, runs: Runs, log: Logger) -> Self {
+        Self {
+            api_url: config.api_url.clone(),
+            credentials: Credentials::new(config),
+            labels: config.issue_labels.clone(),
+            repository,
+            branch,
+            runs,
+            log,
+        }
+    }
+
+    /// Opens an issue for the crash of the target with the signature, found on the commit
+    /// with the `input`, unless one is already opened for the signature. Returns the number
+    /// of the opened issue.
+    pub async fn report(
+        &self,
+        target: &str,
+        signature: &str,
+        input: &str,
+        commit: Option<&str>,
+        report: Option<&Url>,
+    ) -> Result<Option<u64>> {
+        if !self.runs.claim_crash(target, signature).await {
+            debug!(self.log, "Issue of the crash is already opened"; "target" => target, "signature" => signature);
+            return Ok(None);
+        }
+        let mut body = format!(
+            "Fuzzing found a new crash.\n\n\
+             - Target: `{}`\n\
+             - Branch: `{}`\n\
+             - Commit: {}\n\
+             - Signature: `{}`\n\
+             - Input: {}\n",
+            target,
+            self.branch,
+            commit.unwrap_or("unknown"),
+            signature,
+            input
+        );
+        if let Some(report) = report {
+            body += &format!("- Report: {}\n", report);
+        }
+        let url = self.api_url.join(&format!("repos/{}/issues", self.repository))?;
+        let issue = async {
+            let request = self.credentials.authorize(reqwest::Client::new().post(url), &self.repository).await?;
+            let request = request.json(&json!({
+                "title": format!("Crash in `{}`: {}", target, signature),
+                "body": body,
+                "labels": self.labels,
+            }));
+            check(request.send().await?).await?.json::<Issue>().await.map_err(Error::from)
+        };
+        match issue.await {
+            Ok(issue) => {
+                self.runs.set_crash_issue(target, signature, issue.number).await;
+                debug!(self.log, "Opened crash issue"; "target" => target, "issue" => issue.number);
+                Ok(Some(issue.number))
+            }
+            Err(e) => {
+                self.runs.release_crash(target, signature).await;
+                Err(e)
+            }
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct Release {
+    id: u64,
+    /// Hypermedia URL of the form `.../assets{?name,label}`
+    upload_url: String,
+}
+
+/// Client attaching assets to releases.
+pub struct Releases {
+    api_url: Url,
+    credentials: Credentials,
+    log: Logger,
+}
+
+impl Releases {
+    pub fn new(config: &config::Github, log: Logger) -> Self {
+        Self {
+            api_url: config.api_url.clone(),
+            credentials: Credentials::new(config),
+            log,
+        }
+    }
+
+    /// Uploads the gzipped `file` as the asset `name` of the release of the tag of the
+    /// `<owner>/<name>` repository, returning the release id, or `None` if the tag has
+    /// no release.
+    pub async fn upload(&self, repository: &str, tag: &str, name: &str, file: &Path) -> Result<Option<u64>> {
+        let client = reqwest::Client::new();
+        let url = self.api_url.join(&format!("repos/{}/releases/tags/{}", repository, tag))?;
+        let response = self.credentials.authorize(client.get(url), repository).await?.send().await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let release = check(response).await?.json::<Release>().await?;
+        let mut url = Url::parse(release.upload_url.split('{').next().unwrap_or_default())?;
+        url.query_pairs_mut().append_pair("name", name);
+        let body = tokio::fs::read(file).await?;
+        check(
+            self.credentials
+                .authorize(client.post(url), repository)
+                .await?
+                .header(CONTENT_TYPE, "application/gzip")
+                .body(body)
+                .send()
+                .await?,
+        )
+        .await?;
+        debug!(self.log, "Uploaded release asset"; "repository" => repository, "tag" => tag, "name" => name, "id" => release.id);
+        Ok(Some(release.id))
+    }
+}
+
+/// Installation tokens of GitHub Apps with their expiry, by API URL, app and installation.
+type InstallationTokens = HashMap<(Url, u64, u64), (String, DateTime<Utc>)>;
+
+#[dynamic]
+static INSTALLATION_TOKENS: Mutex<InstallationTokens> = Mutex::new(HashMap::new());
+
+/// Installations of GitHub Apps, by API URL, app and `<owner>/<name>` repository.
+#[dynamic]
+static INSTALLATIONS: Mutex<HashMap<(Url, u64, String), u64>> = Mutex::new(HashMap::new());
+
+#[derive(serde::Deserialize)]
+struct Installation {
+    id: u64,
+}
+
+#[derive(serde::Deserialize)]
+struct InstallationToken {
+    token: String,
+    expires_at: DateTime<Utc>,
+}
+
+/// Credentials of GitHub API requests: the configured token, or installation tokens of
+/// the configured GitHub App, minted on demand and shared by all clients until they are
+/// about to expire.
+#[derive(Clone)]
+pub struct Credentials {
+    api_url: Url,
+    token: String,
+    app: Option<config::GithubApp>,
+}
+
+impl Credentials {
+    /// Installation tokens expiring sooner are minted again.
+    const RENEW_BEFORE: i64 = 5 * 60;
+
+    pub fn new(config: &config::Github) -> Self {
+        Self {
+            api_url: config.api_url.clone(),
+            token: config.token.clone(),
+            app: config.app.clone(),
+        }
+    }
+
+    /// The request authorized for the `<owner>/<name>` repository.
+    pub async fn authorize(&self, request: RequestBuilder, repository: &str) -> Result<RequestBuilder> {
+        let token = match &self.app {
+            Some(app) => self.installation_token(app, repository).await?,
+            None => self.token.clone(),
+        };
+        Ok(authorized(request, &token))
+    }
+
+    /// Token of the installation of the app on the repository, minted if there is no
+    /// fresh one.
+    async fn installation_token(&self, app: &config::GithubApp, repository: &str) -> Result<String> {
+        let installation = match app.installation_id {
+            Some(id) => id,
+            None => self.installation(app, repository).await?,
+        };
+        let key = (self.api_url.clone(), app.app_id, installation);
+        let renew_after = Utc::now() + Duration::seconds(Self::RENEW_BEFORE);
+        if let Some((token, _)) = INSTALLATION_TOKENS.lock().unwrap().get(&key).filter(|(_, expires)| *expires > renew_after) {
+            return Ok(token.clone());
+        }
+        let url = self.api_url.join(&format!("app/installations/{}/access_tokens", installation))?;
+        let token = check(app_authorized(reqwest::Client::new().post(url), app)?.send().await?)
+            .await?
+            .json::<InstallationToken>()
+            .await?;
+        INSTALLATION_TOKENS.lock().unwrap().insert(key, (token.token.clone(), token.expires_at));
+        Ok(token.token)
+    }
+
+    /// Installation of the app on the repository.
+    async fn installation(&self, app: &config::GithubApp, repository: &str) -> Result<u64> {
+        let key = (self.api_url.clone(), app.app_id, repository.to_string());
+        if let Some(id) = INSTALLATIONS.lock().unwrap().get(&key) {
+            return Ok(*id);
+        }
+        let url = self.api_url.join(&format!("repos/{}/installation", repository))?;
+        let installation = check(app_authorized(reqwest::Client::new().get(url), app)?.send().await?)
+            .await?
+            .json::<Installation>()
+            .await?;
+        INSTALLATIONS.lock().unwrap().insert(key, installation.id);
+        Ok(installation.id)
+    }
+}
+
+/// JSON web token authenticating as the GitHub App, valid for 10 minutes, with the issue
+/// time a minute in the past to allow for clock drift.
+pub fn app_jwt(app: &config::GithubApp) -> Result<String> {
+    let now = Utc::now().timestamp();
+    let encode = |json: serde_json::Value| base64::encode_config(json.to_string(), base64::URL_SAFE_NO_PAD);
+    let message = format!(
+        "{}.{}",
+        encode(json!({ "alg": "RS256", "typ": "JWT" })),
+        encode(json!({ "iat": now - 60, "exp": now + 9 * 60, "iss": app.app_id }))
+    );
+    let signature = PKey::private_key_from_pem(app.private_key.as_bytes())
+        .and_then(|key| {
+            let mut signer = Signer::new(MessageDigest::sha256(), &key)?;
+            signer.update(message.as_bytes())?;
+            signer.sign_to_vec()
+        })
+        .map_err(|e| Error::other(format!("cannot sign with the GitHub App private key: {}", e)))?;
+    Ok(format!("{}.{}", message, base64::encode_config(signature, base64::URL_SAFE_NO_PAD)))
+}
+
+/// The request with headers of the GitHub API authorized as the app.
+fn app_authorized(request: RequestBuilder, app: &config::GithubApp) -> Result<RequestBuilder> {
+    Ok(with_headers(request, format!("Bearer {}", app_jwt(app)?)))
+}
+
+/// The request with headers of the GitHub API authorized with the token.
+fn authorized(request: RequestBuilder, token: &str) -> RequestBuilder {
+    with_headers(request, format!("token {}", token))
+}
+
+/// The request with headers of the GitHub API and the authorization.
+fn with_headers(request: RequestBuilder, authorization: String) -> RequestBuilder {
+    request
+        .header(AUTHORIZATION, authorization)
+        .header(ACCEPT, "application/vnd.github.v3+json")
+        .header(USER_AGENT, "fuzz-ci")
+}
+
+/// The response, or an error with its body if it is not successful.
+async fn check(response: reqwest::Response) -> Result<reqwest::Response> {
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        return Err(Error::other(format!("GitHub API returned {}: {}", status, body)));
+    }
+    Ok(response)
+}
+
+/// Repository name as `<owner>/<name>` from its SSH or HTTPS URL on GitHub.
+pub fn repository_name(url: &str) -> Option<String> {
+    let path = url
+        .strip_prefix("git@github.com:")
+        .or_else(|| url.split_once("github.com/").map(|(_, path)| path))?;
+    let path = path.trim_end_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    match path.split('/').collect::<Vec<_>>().as_slice() {
+        [owner, name] if !owner.is_empty() && !name.is_empty() => Some(path.to_string()),
+        _ => None,
+    }
+}
+
+/// Header with the HMAC signature of webhook request bodies.
+pub const SIGNATURE_HEADER: &str = "X-Hub-Signature-256";
+
+/// Signature of the webhook request body with the secret, as the value of the
+/// `X-Hub-Signature-256` header, see
+/// https://docs.github.com/en/webhooks/using-webhooks/validating-webhook-deliveries
+pub fn webhook_signature(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_varkey(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .fold(String::from("sha256="), |s, b| s + &format!("{:02x}", b))
+}
+
+/// Checks the signature of a webhook request sent by GitHub.
+pub fn verify_webhook(secret: &str, body: &[u8], signature: &str) -> bool {
+    constant_time_eq(webhook_signature(secret, body).as_bytes(), signature.as_bytes())
+}
@@ -0,0 +1,238 @@
+//! gRPC control service of `proto/fuzz_ci.proto`, starting, inspecting and cancelling
+//! runs like the HTTP API, served on the server address along with its HTTP routes.
+
+use slog::Logger;
+use tonic::{Code, Request, Response, Status};
+use warp::hyper::{header::CONTENT_TYPE, StatusCode};
+
+use crate::{
+    api::{self, RunRequest},
+    config::{self, Config},
+    policy::Verdict,
+    runs::{Run, RunStatus},
+    server::Runner,
+};
+
+/// Messages and the service generated from `proto/fuzz_ci.proto`.
+pub mod proto {
+    tonic::include_proto!("fuzz_ci.v1");
+}
+
+pub use proto::fuzz_ci_server::FuzzCiServer;
+
+/// The control service of the server runs, authorizing calls with API tokens.
+pub struct Service {
+    api: config::Api,
+    runner: Runner,
+    log: Logger,
+}
+
+impl Service {
+    /// Caller of the request, by the API token of its `authorization: Bearer` metadata.
+    fn caller<T>(&self, request: &Request<T>) -> Option<String> {
+        request
+            .metadata()
+            .get("authorization")
+            .and_then(|auth| auth.to_str().ok())
+            .and_then(|auth| auth.strip_prefix("Bearer "))
+            .and_then(|token| api::caller(&self.api, token))
+    }
+
+    /// Records the call in the audit log with the HTTP status of the equivalent API request,
+    /// returning its result.
+    async fn audited(
+        &self,
+        caller: &str,
+        action: &str,
+        subject: &str,
+        result: Result<Run, (StatusCode, String)>,
+    ) -> Result<Response<proto::Run>, Status> {
+        let status = result.as_ref().map_or_else(|(status, _)| *status, |_| StatusCode::ACCEPTED);
+        self.runner.audit(caller, action, subject, status.as_u16()).await;
+        result.map(|run| Response::new(run.into())).map_err(api_status)
+    }
+}
+
+#[tonic::async_trait]
+impl proto::fuzz_ci_server::FuzzCi for Service {
+    async fn start_run(&self, request: Request<proto::StartRunRequest>) -> Result<Response<proto::Run>, Status> {
+        let caller = self.caller(&request).ok_or_else(unauthenticated)?;
+        let request = RunRequest::from(request.into_inner());
+        let branch = request.branch.clone();
+        let result = api::start(request, &self.runner, &self.log).await;
+        self.audited(&caller, "start", &branch, result).await
+    }
+
+    async fn get_run(&self, request: Request<proto::GetRunRequest>) -> Result<Response<proto::Run>, Status> {
+        self.caller(&request).ok_or_else(unauthenticated)?;
+        let id = request.into_inner().id;
+        match self.runner.run(&id).await {
+            Some(run) => Ok(Response::new(run.into())),
+            None => Err(Status::not_found(format!("unknown run `{}`", id))),
+        }
+    }
+
+    async fn cancel_run(&self, request: Request<proto::CancelRunRequest>) -> Result<Response<proto::Run>, Status> {
+        let caller = self.caller(&request).ok_or_else(unauthenticated)?;
+        let proto::CancelRunRequest { id, scope } = request.into_inner();
+        let scope = Some(scope).filter(|scope| !scope.is_empty());
+        let result = api::cancel(&id, scope.as_deref(), &self.runner, &self.log).await;
+        let subject = scope.map_or_else(|| id.clone(), |scope| format!("{}/{}", id, scope));
+        self.audited(&caller, "cancel", &subject, result).await
+    }
+}
+
+fn unauthenticated() -> Status {
+    Status::unauthenticated("missing or invalid API token")
+}
+
+/// Status of the call failed like the equivalent API request.
+fn api_status((status, message): (StatusCode, String)) -> Status {
+    let code = match status {
+        StatusCode::BAD_REQUEST => Code::InvalidArgument,
+        StatusCode::NOT_FOUND => Code::NotFound,
+        _ => Code::Internal,
+    };
+    Status::new(code, message)
+}
+
+impl From<proto::StartRunRequest> for RunRequest {
+    fn from(request: proto::StartRunRequest) -> Self {
+        let non_empty = |value: String| Some(value).filter(|value| !value.is_empty());
+        RunRequest {
+            branch: request.branch,
+            commit: non_empty(request.commit),
+            repo: non_empty(request.repo),
+            targets: request.targets,
+            duration: Some(request.duration).filter(|duration| *duration != 0),
+        }
+    }
+}
+
+impl From<Run> for proto::Run {
+    fn from(run: Run) -> Self {
+        let status = match run.status {
+            RunStatus::Running => proto::RunStatus::Running,
+            RunStatus::Success => proto::RunStatus::Success,
+            RunStatus::Failure => proto::RunStatus::Failure,
+            RunStatus::Cancelled => proto::RunStatus::Cancelled,
+        };
+        let verdict = match run.verdict {
+            None => proto::Verdict::Unspecified,
+            Some(Verdict::Pass) => proto::Verdict::Pass,
+            Some(Verdict::Fail) => proto::Verdict::Fail,
+            Some(Verdict::Error) => proto::Verdict::Error,
+            Some(Verdict::Cancelled) => proto::Verdict::Cancelled,
+        };
+        proto::Run {
+            id: run.id,
+            branch: run.branch,
+            commit: run.commit.unwrap_or_default(),
+            description: run.description,
+            status: status as i32,
+            report_url: run.report_url.unwrap_or_default(),
+            verdict: verdict as i32,
+        }
+    }
+}
+
+/// The gRPC service, if enabled along with the API.
+pub fn service(config: &Config, runner: Runner, log: Logger) -> Option<FuzzCiServer<Service>> {
+    if !config.api.grpc || !config.api.is_enabled() {
+        return None;
+    }
+    let api = config.api.clone();
+    Some(FuzzCiServer::new(Service { api, runner, log }))
+}
+
+/// Whether the request is a gRPC call, rather than one of HTTP routes.
+pub fn is_call<B>(request: &warp::http::Request<B>) -> bool {
+    request
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .map_or(false, |content_type| content_type.starts_with("application/grpc"))
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message;
+
+    use super::*;
+
+    #[test]
+    fn run_is_converted() {
+        let run = Run {
+            id: "20210601120000-1".to_string(),
+            branch: "master".to_string(),
+            commit: Some("0123abcd".to_string()),
+            description: "push to master".to_string(),
+            status: RunStatus::Failure,
+            report_url: Some("http://fuzz-ci.example.com/master/".to_string()),
+            verdict: Some(Verdict::Cancelled),
+        };
+        let mut buf = vec![];
+        proto::Run::from(run).encode(&mut buf).unwrap();
+        let message = proto::Run::decode(&buf[..]).unwrap();
+        assert_eq!(message.id, "20210601120000-1");
+        assert_eq!(message.branch, "master");
+        assert_eq!(message.commit, "0123abcd");
+        assert_eq!(message.description, "push to master");
+        assert_eq!(message.status(), proto::RunStatus::Failure);
+        assert_eq!(message.report_url, "http://fuzz-ci.example.com/master/");
+        assert_eq!(message.verdict(), proto::Verdict::Cancelled);
+    }
+
+    #[test]
+    fn unfinished_run_has_no_verdict() {
+        let run = Run {
+            id: "1".to_string(),
+            branch: "master".to_string(),
+            commit: None,
+            description: String::new(),
+            status: RunStatus::Running,
+            report_url: None,
+            verdict: None,
+        };
+        let message = proto::Run::from(run);
+        assert_eq!(message.status(), proto::RunStatus::Running);
+        assert_eq!(message.verdict(), proto::Verdict::Unspecified);
+        assert!(message.commit.is_empty());
+        assert!(message.report_url.is_empty());
+    }
+
+    #[test]
+    fn empty_fields_are_defaults() {
+        // `StartRunRequest { branch: "master" }` as encoded by protoc
+        let request = RunRequest::from(proto::StartRunRequest::decode(&b"\x0a\x06master"[..]).unwrap());
+        assert_eq!(request.branch, "master");
+        assert_eq!(request.commit, None);
+        assert_eq!(request.repo, None);
+        assert!(request.targets.is_empty());
+        assert_eq!(request.duration, None);
+    }
+
+    #[test]
+    fn start_run_request_is_converted() {
+        let request = RunRequest::from(proto::StartRunRequest {
+            branch: "master".to_string(),
+            commit: "0123abcd".to_string(),
+            repo: "tezedge/tezedge".to_string(),
+            targets: vec!["ack".to_string(), "connection".to_string()],
+            duration: 3600,
+        });
+        assert_eq!(request.branch, "master");
+        assert_eq!(request.commit.as_deref(), Some("0123abcd"));
+        assert_eq!(request.repo.as_deref(), Some("tezedge/tezedge"));
+        assert_eq!(request.targets, vec!["ack", "connection"]);
+        assert_eq!(request.duration, Some(3600));
+    }
+
+    #[test]
+    fn api_errors_are_statuses() {
+        let code = |status| api_status((status, String::new())).code();
+        assert_eq!(code(StatusCode::BAD_REQUEST), Code::InvalidArgument);
+        assert_eq!(code(StatusCode::NOT_FOUND), Code::NotFound);
+        assert_eq!(code(StatusCode::CONFLICT), Code::Internal);
+    }
+}
@@ -0,0 +1,67 @@
+//! Comparison of fuzzing harnesses changed by a commit with their previous versions,
+//! verifying that harness refactors don't lose coverage of the same fuzzed code.
+
+use std::fmt;
+
+/// Edges of the fuzzed code covered by the corpus of a target, replayed on the previous
+/// and on the changed harness.
+#[derive(Clone, Debug)]
+pub struct HarnessCoverage {
+    pub target: String,
+    pub previous: u32,
+    pub changed: u32,
+}
+
+impl HarnessCoverage {
+    /// Change of covered edges, in percents, negative for losses.
+    pub fn change(&self) -> f64 {
+        if self.previous > 0 {
+            (f64::from(self.changed) - f64::from(self.previous)) * 100.0 / f64::from(self.previous)
+        } else {
+            0.0
+        }
+    }
+
+    /// Whether the changed harness covers at least `threshold` percents fewer edges.
+    pub fn is_regression(&self, threshold: f64) -> bool {
+        -self.change() >= threshold
+    }
+}
+
+impl fmt::Display for HarnessCoverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "`{}`: {} → {} edges ({:+.1}%)",
+            self.target,
+            self.previous,
+            self.changed,
+            self.change()
+        )
+    }
+}
+
+/// Markdown comment with coverages of targets of harnesses changed by the `commit`
+/// compared with ones of the `previous` commit, marking losses over the `threshold`.
+pub fn comment(previous: &str, commit: &str, coverages: &[HarnessCoverage], threshold: f64) -> String {
+    let mut comment = format!(
+        "### Fuzzing harness comparison\n\n\
+         Edges covered by the corpus of each target, replayed on the harness of {} and of {} \
+         built with the same fuzzed code:\n\n\
+         | Target | Previous | Changed | Change |\n\
+         |---|---:|---:|---:|\n",
+        previous, commit
+    );
+    for coverage in coverages {
+        let mark = if coverage.is_regression(threshold) { " :warning:" } else { "" };
+        comment.push_str(&format!(
+            "| `{}` | {} | {} | {:+.1}%{} |\n",
+            coverage.target,
+            coverage.previous,
+            coverage.changed,
+            coverage.change(),
+            mark
+        ));
+    }
+    comment
+}
@@ -1,11 +1,120 @@
-use std::{collections::{HashMap, VecDeque}, io, path::{Path, PathBuf}, sync::Arc};
+use std::{collections::{HashMap, VecDeque}, io, path::{Path, PathBuf}, sync::Arc, time::Duration};
 
 use slog::{error, info, o, trace, Logger};
-use tokio::sync::broadcast::Sender;
+use tokio::process::Command;
 
-use crate::{config::{HonggfuzzConfig, TargetConfig}, feedback::Feedback};
+use crate::{cancel::Scope, config::{HonggfuzzConfig, TargetConfig, Variant}, error::{Error, Result}, feedback::Feedback, orphans::Pids};
 
 mod target;
+mod version;
+
+pub use version::{detect, OutputFormat, Version};
+
+/// Interval between samples of execution rates of targets.
+const EXEC_RATE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// `cargo hfuzz` command, running the `cargo-hfuzz` binary directly if one is configured.
+pub fn cargo_hfuzz(binary: Option<&Path>) -> Command {
+    let mut command = match binary {
+        Some(binary) => Command::new(binary),
+        None => Command::new("cargo"),
+    };
+    command.arg("hfuzz");
+    command
+}
+
+/// File honggfuzz writes statistics of the target to, within the statistics directory.
+pub fn stats_file(stats_dir: &Path, target_id: &str) -> PathBuf {
+    stats_dir.join(format!("{}.csv", target_id))
+}
+
+/// Gz-compressed archive of honggfuzz output of the target, within the run report directory.
+pub fn output_archive(report_dir: &Path, target_id: &str) -> PathBuf {
+    let mut file = crate::common::sanitize_path_segment(target_id);
+    file.push(".log.gz");
+    report_dir.join("hfuzz-report/output").join(file)
+}
+
+/// Signature of the crash saved by honggfuzz into the file, identifying crashes at the same
+/// place with the same stack: the signal, the program counter and the stack hash of its
+/// name, like `SIGSEGV.PC.55555555f00d.STACK.1a2b3c4d5e` of
+/// `SIGSEGV.PC.55555555f00d.STACK.1a2b3c4d5e.CODE.1.ADDR.0.INSTR.mov....fuzz`, or the whole
+/// name if it is formatted differently.
+pub fn crash_signature(file: &str) -> &str {
+    let name = file.rsplit('/').next().unwrap_or(file);
+    match name.find(".CODE.") {
+        Some(end) if name.contains(".STACK.") => &name[..end],
+        _ => name.strip_suffix(".fuzz").unwrap_or(name),
+    }
+}
+
+/// Target statistics, as written by honggfuzz to its statistics file.
+#[derive(Clone, Copy, Default)]
+pub struct Stats {
+    pub unix_time: u64,
+    pub total_exec: u64,
+    pub exec_per_sec: u64,
+    pub last_cov_update: u64,
+}
+
+impl Stats {
+    /// Reads the most recent statistics from the file.
+    pub fn read(file: &Path) -> io::Result<Option<Self>> {
+        let content = std::fs::read_to_string(file)?;
+        // unix_time, last_cov_update, total_exec, exec_per_sec, crashes, unique_crashes, hangs, edge_cov, block_cov
+        let line = match content.lines().rev().find(|l| !l.starts_with('#') && !l.trim().is_empty()) {
+            Some(line) => line,
+            None => return Ok(None),
+        };
+        let fields = line.split(',').map(|f| f.trim().parse::<u64>()).collect::<Result<Vec<_>, _>>();
+        match fields.as_deref() {
+            Ok([unix_time, last_cov_update, total_exec, exec_per_sec, ..]) => Ok(Some(Self {
+                unix_time: *unix_time,
+                total_exec: *total_exec,
+                exec_per_sec: *exec_per_sec,
+                last_cov_update: *last_cov_update,
+            })),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, format!("invalid statistics line `{}`", line))),
+        }
+    }
+}
+
+/// Periodically samples the execution rate of the target from its statistics file, warning
+/// when it drops below the highest rate in the run by the factor, often due to a hang-prone
+/// input or a memory leak in the target, and reporting when it recovers.
+pub async fn watch_exec_rate(stats_file: PathBuf, id: String, factor: f64, feedback: Arc<Feedback>) {
+    let mut last: Option<Stats> = None;
+    let (mut peak, mut dropped) = (0.0, false);
+    loop {
+        tokio::time::sleep(EXEC_RATE_INTERVAL).await;
+        let stats = match Stats::read(&stats_file) {
+            Ok(Some(stats)) => stats,
+            _ => continue,
+        };
+        let rate = match last.replace(stats) {
+            // statistics of a previous run are left in the file until honggfuzz writes new ones
+            Some(last) if stats.unix_time > last.unix_time && stats.total_exec >= last.total_exec => {
+                (stats.total_exec - last.total_exec) as f64 / (stats.unix_time - last.unix_time) as f64
+            }
+            _ => continue,
+        };
+        if rate * factor < peak {
+            if !dropped {
+                dropped = true;
+                feedback.warning(format!(
+                    "Execution rate of `{}` dropped from {:.0} to {:.0} per second, possibly due to a hang-prone input or a memory leak",
+                    id, peak, rate
+                ));
+            }
+        } else {
+            if dropped {
+                dropped = false;
+                feedback.message(format!("Execution rate of `{}` recovered to {:.0} per second", id, rate));
+            }
+            peak = rate.max(peak);
+        }
+    }
+}
 
 async fn _find_reports(path: &impl AsRef<Path>, log: &Logger) -> io::Result<Vec<PathBuf>> {
     let mut result = vec![];
@@ -38,43 +147,91 @@ async fn _find_reports(path: &impl AsRef<Path>, log: &Logger) -> io::Result<Vec<
     Ok(result)
 }
 
+/// Runs all targets of the fuzzing project located in `dir` with honggfuzz, until
+/// they exit or the `cancel` scope is cancelled, failing if any target fails.
+///
+/// Each target runs in a child scope of `cancel` named by its id, so it can be stopped separately.
+/// Honggfuzz output is parsed according to `output_format`, see [detect]. Fuzzing processes
+/// are recorded in `pids`, if given.
 pub async fn run(
     dir: impl AsRef<Path>,
     env: HashMap<String, String>,
     config: TargetConfig,
     hfuzz_config: HonggfuzzConfig,
+    output_format: OutputFormat,
     corpus: Option<String>,
     feedback: Arc<Feedback>,
-    stop_bc: Sender<()>,
+    pids: Option<Pids>,
+    cancel: Scope,
     log: Logger,
-) -> io::Result<()> {
+) -> Result<()> {
     info!(log, "Starting hfuzz"; "dir" => dir.as_ref().to_str());
 
-    let hfuzz_config = config.honggfuzz.unwrap_or(hfuzz_config);
+    let hfuzz_config = config.hfuzz_config(&hfuzz_config);
     let mut handles = vec![];
 
-    for target in config.targets {
-        let dir = dir.as_ref().to_path_buf();
-        let env = env.clone();
-        let log = log.new(o!("target" => target.clone()));
-        let feedback = feedback.clone();
-        let corpus = corpus.as_ref().map(|c| PathBuf::from(c).join(&target));
-        let stop_bc = stop_bc.clone();
-        let hfuzz_config = hfuzz_config.clone();
-        handles.push(tokio::spawn(async move {
-            target::Target::new(target, &dir, env, &hfuzz_config, corpus, feedback, stop_bc, log)
-                .run()
-                .await
-        }));
+    for (i, variant) in config.variants().into_iter().enumerate() {
+        // targets of all variants share the same corpus, so only one of them should minimize it
+        let mut hfuzz_config = hfuzz_config.clone();
+        hfuzz_config.minimize &= i == 0;
+        let corpus_limits = config.corpus_limits.filter(|_| i == 0);
+        for target in &config.targets {
+            let target = target.clone();
+            let binary = config.binary_name(&target);
+            let dir = dir.as_ref().to_path_buf();
+            let env = env.clone();
+            let log = log.new(o!("target" => variant.target_id(&target)));
+            let feedback = feedback.clone();
+            let corpus = corpus.as_ref().map(|c| PathBuf::from(c).join(&target));
+            let cancel = cancel.child(&variant.target_id(&target));
+            let hfuzz_config = hfuzz_config.clone();
+            let variant = variant.clone();
+            let pids = pids.clone();
+            handles.push(tokio::spawn(async move {
+                target::Target::new(target, &dir, env, &variant, &hfuzz_config, corpus, feedback, cancel, log)
+                    .with_corpus_limits(corpus_limits)
+                    .with_binary(binary)
+                    .with_output_format(output_format)
+                    .with_pids(pids)
+                    .run()
+                    .await
+            }));
+        }
     }
 
+    let (total, mut failed) = (handles.len(), 0);
     for handle in handles {
         match handle.await {
             Err(e) => error!(log, "Target panicked: {}", e),
             Ok(Err(e)) => error!(log, "Target error: {}", e),
-            Ok(Ok(_)) => (),
+            Ok(Ok(_)) => continue,
         }
+        failed += 1;
     }
 
+    if failed > 0 {
+        return Err(Error::other(format!("{} of {} targets failed", failed, total)));
+    }
     Ok(())
 }
+
+/// Minimizes corpora of the targets, using their binaries built within the project directory.
+pub async fn minimize(
+    dir: impl AsRef<Path>,
+    env: HashMap<String, String>,
+    targets: Vec<String>,
+    hfuzz_config: HonggfuzzConfig,
+    corpus: impl AsRef<Path>,
+    feedback: Arc<Feedback>,
+    log: Logger,
+) {
+    let variant = Variant::default();
+    for target in targets {
+        let corpus = corpus.as_ref().join(&target);
+        let log = log.new(o!("target" => target.clone()));
+        let target = target::Target::new(target, dir.as_ref(), env.clone(), &variant, &hfuzz_config, Some(corpus), feedback.clone(), Scope::new(), log.clone());
+        if let Err(e) = target.minimize_corpus().await {
+            error!(log, "Error minimizing corpus"; "error" => e.to_string());
+        }
+    }
+}
@@ -1,21 +1,42 @@
-use std::{borrow::Cow, collections::HashMap, io, path::{Path, PathBuf}, process::Stdio, sync::Arc};
+use std::{borrow::Cow, collections::{HashMap, VecDeque}, fs::File, io::{self, BufWriter, Write}, path::{Path, PathBuf}, process::Stdio, sync::Arc};
 
-use slog::{FnValue, Logger, debug, error, info, trace};
+use flate2::{write::GzEncoder, Compression};
+use slog::{FnValue, Logger, debug, error, info, trace, warn};
 use tokio::{
     io::{AsyncBufRead, AsyncBufReadExt},
     process::Command,
-    sync::broadcast::Sender,
 };
 
-use crate::{config::HonggfuzzConfig, feedback::Feedback};
+use super::OutputFormat;
+use crate::{cancel::Scope, common::u8_slice_to_string, config::{CorpusLimits, Eviction, HonggfuzzConfig, Variant, Verbosity}, corpus, error::{output_tail, Error, Result}, feedback::Feedback, orphans::Pids, process, resources};
 
 pub struct Target {
     name: String,
+    id: String,
+    cargo_args: Vec<String>,
     dir: PathBuf,
     env: HashMap<String, String>,
     hfuzz_run_args: String,
+    corpus: Option<PathBuf>,
+    /// Name of the target binary
+    binary: String,
+    minimize: bool,
+    corpus_limits: Option<CorpusLimits>,
+    stats_file: Option<PathBuf>,
+    cargo_hfuzz: Option<PathBuf>,
+    output_format: OutputFormat,
+    verbosity: Verbosity,
+    /// Number of the last output lines kept in memory
+    output_lines: usize,
+    /// Archive of the whole output, if it is kept
+    output_archive: Option<PathBuf>,
+    /// Number of times the total number of edges is measured
+    total_runs: u32,
+    /// Spread of measured totals making coverage unreliable, in percents of the total
+    unreliable_spread: f64,
+    pids: Option<Pids>,
     feedback: Arc<Feedback>,
-    stop_bc: Sender<()>,
+    cancel: Scope,
     log: Logger,
 }
 
@@ -23,36 +44,88 @@ impl Target {
     pub fn new<'a>(
         name: impl Into<Cow<'a, str>>,
         dir: impl Into<Cow<'a, Path>>,
-        env: HashMap<String, String>,
+        mut env: HashMap<String, String>,
+        variant: &Variant,
         hfuzz_config: &HonggfuzzConfig,
         corpus: Option<PathBuf>,
         feedback: Arc<Feedback>,
-        stop_bc: Sender<()>,
+        cancel: Scope,
         log: Logger,
     ) -> Self {
         let name = name.into().into_owned();
+        let id = variant.target_id(&name);
+        let variant_env = variant.env(&env);
+        env.extend(variant_env);
         let mut hfuzz_run_args = hfuzz_config.run_args.clone();
-        if let Some(corpus) = corpus {
+        if let Some(corpus) = &corpus {
             hfuzz_run_args += &format!(" -i {}", corpus.to_string_lossy());
         }
+        let stats_file = hfuzz_config.stats_dir.as_ref().map(|dir| super::stats_file(dir, &id));
+        let output_archive = hfuzz_config
+            .archive_output
+            .then(|| super::output_archive(feedback.report_dir(), &id));
         Self {
+            binary: name.clone(),
             name,
+            id,
+            cargo_args: variant.cargo_args(),
             dir: dir.into().into_owned(),
             env,
             hfuzz_run_args,
+            corpus,
+            minimize: hfuzz_config.minimize,
+            corpus_limits: None,
+            stats_file,
+            cargo_hfuzz: hfuzz_config.cargo_hfuzz.clone(),
+            output_format: OutputFormat::default(),
+            verbosity: hfuzz_config.verbosity,
+            output_lines: hfuzz_config.output_lines,
+            output_archive,
+            total_runs: hfuzz_config.total_runs.max(1),
+            unreliable_spread: hfuzz_config.unreliable_spread,
+            pids: None,
             feedback,
-            stop_bc,
+            cancel,
             log,
         }
     }
 
+    /// Sets limits for the target corpus to be enforced after the run.
+    pub fn with_corpus_limits(mut self, corpus_limits: Option<CorpusLimits>) -> Self {
+        self.corpus_limits = corpus_limits.filter(CorpusLimits::is_set);
+        self
+    }
+
+    /// Sets the name of the target binary, if it differs from the target name.
+    pub fn with_binary(mut self, binary: impl Into<String>) -> Self {
+        self.binary = binary.into();
+        self
+    }
+
+    /// Sets the format of honggfuzz output, as detected for the installed `cargo hfuzz`.
+    pub fn with_output_format(mut self, output_format: OutputFormat) -> Self {
+        self.output_format = output_format;
+        self
+    }
+
+    /// Records the fuzzing process in the pid files, so it is killed if the server crashes.
+    pub fn with_pids(mut self, pids: Option<Pids>) -> Self {
+        self.pids = pids;
+        self
+    }
+
+    fn error(&self, message: impl Into<String>) -> Error {
+        Error::fuzz(&self.id, message)
+    }
+
     #[inline]
     fn hfuzz_run_base(&self, hfuzz_run_args: impl AsRef<str>) -> Command {
         let hfuzz_run_args = format!("{} {}", hfuzz_run_args.as_ref(), self.hfuzz_run_args);
-        let mut command = Command::new("cargo");
+        let mut command = super::cargo_hfuzz(self.cargo_hfuzz.as_deref());
         command
-            .args(&["hfuzz", "run"])
-            .arg(&self.name)
+            .arg("run")
+            .arg(&self.binary)
+            .args(&self.cargo_args)
             .current_dir(&self.dir)
             .kill_on_drop(true)
             .env("HFUZZ_RUN_ARGS", &hfuzz_run_args)
@@ -67,7 +140,11 @@ impl Target {
 
     #[inline]
     fn hfuzz_run(&self) -> Command {
-        self.hfuzz_run_base("-v")
+        let verbose = format!("-v {}", self.verbosity.run_args());
+        match &self.stats_file {
+            Some(stats_file) => self.hfuzz_run_base(format!("{} --statsfile {}", verbose, stats_file.to_string_lossy())),
+            None => self.hfuzz_run_base(verbose),
+        }
     }
 
     #[inline]
@@ -75,14 +152,21 @@ impl Target {
         self.hfuzz_run_base("-v -N 1 -n 1")
     }
 
+    /// Processes honggfuzz output, reporting coverage and crashes, and keeping it in `output`.
+    ///
+    /// Returns crash files saved during the dry run over the corpus.
     async fn filter_output(
         name: String,
         dir: PathBuf,
+        format: OutputFormat,
         feedback: Arc<Feedback>,
         mut read: (impl AsyncBufRead + Unpin + Send),
+        output: &mut Output,
         log: Logger,
-    ) {
+    ) -> Vec<PathBuf> {
         let mut edges = 0;
+        let mut dry_run = true;
+        let mut dry_run_crashes = vec![];
         let mut line = String::new();
         while {
             line.clear();
@@ -95,41 +179,40 @@ impl Target {
             }
         } > 0
         {
-            if line.starts_with("Sz:") {
-                let e = match line.split("/").skip(8).next() {
-                    Some(e) => e,
-                    None => {
-                        error!(log, "error in hfuzz output filter");
-                        break;
-                    }
-                };
-
-                if e == "0" {
-                    continue;
-                }
-                let e: u32 = match e.parse() {
+            output.push(&line, &log);
+            if dry_run && line.contains("Entering phase 2/3") {
+                dry_run = false;
+            }
+            if let Some(e) = format.new_edges(&line) {
+                let e = match e {
                     Ok(e) => e,
                     Err(e) => {
-                        error!(log, "error in hfuzz output filter"; "error" => e.to_string());
+                        error!(log, "error in hfuzz output filter"; "error" => e);
                         break;
                     }
                 };
+                if e == 0 {
+                    continue;
+                }
                 feedback.add_covered(&name, e);
                 edges += e;
                 trace!(log, "coverage update"; "edges" => edges);
             } else if line.starts_with("Crash: saved as '") {
                 if let Some(file) = line["Crash: saved as '".len()..].split_terminator("'").next() {
                     let file = dir.join(file);
-                    let file = file.to_string_lossy();
-                    feedback.add_error(&name, &file)
+                    feedback.add_error(&name, &file.to_string_lossy());
+                    if dry_run {
+                        dry_run_crashes.push(file);
+                    }
                 } else {
                     error!(log, "Cannot parse error line"; "line" => &line)
                 }
             }
         }
+        dry_run_crashes
     }
 
-    async fn get_total_coverage(&self) -> io::Result<u32> {
+    async fn get_total_coverage(&self) -> Result<u32> {
         trace!(self.log, "Run the target shortly to get target coverage"; "target" => &self.name);
         let output = self
             .hfuzz_run_min()
@@ -140,62 +223,286 @@ impl Target {
         if !output.status.success() {
             error!(self.log, "Error running target"; "code" => output.status.code());
             debug!(self.log, "Error running target"; "output" => std::str::from_utf8(&output.stderr).unwrap_or("<invalid utf8>"));
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!("error running target {}", self.name),
-            ));
+            return Err(self.error(format!(
+                "error running target with {}: {}",
+                output.status,
+                output_tail(&output.stderr)
+            )));
         }
-        let last = output
-            .stderr
-            .split(|ch| *ch == 0x0a)
-            .rev()
-            .find(|s| !s.is_empty())
-            .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "no output"))?;
-        let last = std::str::from_utf8(last)
-            .map_err(|_| io::Error::new(io::ErrorKind::Other, "invalid utf8"))?;
-        trace!(self.log, "last line found"; "line" => last);
-
-        let edge_nr = last
-            .split_once("guard_nb:")
-            .map(|(_, s)| s.split_once(" "))
-            .flatten()
-            .map(|(s, _)| s)
-            .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "cannot get edge nr"))?;
-        let edge_nr: u32 = edge_nr
-            .parse()
-            .map_err(|_| io::Error::new(io::ErrorKind::Other, "cannot parse"))?;
+        let stderr = std::str::from_utf8(&output.stderr)
+            .map_err(|_| self.error("invalid utf8 in the output"))?;
+        let edge_nr = self
+            .output_format
+            .total_edges(stderr)
+            .map_err(|e| self.error(format!("{} (expecting {})", e, self.output_format)))?;
         trace!(self.log, "edge nr"; "_" => edge_nr);
 
         Ok(edge_nr)
     }
 
-    pub async fn run(&self) -> io::Result<()> {
-        let total = self.get_total_coverage().await?;
-        self.feedback.set_total(&self.name, total);
+    /// Measures the total number of edges several times, as it may vary, returning the
+    /// largest total and the spread of measured ones.
+    async fn measure_total_coverage(&self) -> Result<(u32, u32)> {
+        let mut totals = vec![];
+        for _ in 0..self.total_runs {
+            totals.push(self.get_total_coverage().await?);
+        }
+        let (min, max) = (totals.iter().min().copied().unwrap_or_default(), totals.iter().max().copied().unwrap_or_default());
+        if min != max {
+            debug!(self.log, "Total number of edges varies"; "totals" => format!("{:?}", totals));
+        }
+        Ok((max, max - min))
+    }
+
+    pub async fn run(&self) -> Result<()> {
+        let (total, spread) = self.measure_total_coverage().await?;
+        self.feedback.set_total(&self.id, total);
+        let unreliable = f64::from(spread) * 100.0 > f64::from(total) * self.unreliable_spread;
+        if unreliable {
+            warn!(self.log, "Total number of edges varies too much to compare coverage with other builds"; "total" => total, "spread" => spread);
+            self.feedback.warning(format!(
+                "Total number of edges of `{}` varies by {} of {} between measurements, its coverage isn't compared with the previous run",
+                self.id, spread, total
+            ));
+        }
+        self.feedback.set_total_spread(&self.id, spread, unreliable);
 
         trace!(self.log, "Run the target");
-        let mut child = self
-            .hfuzz_run()
-            .stdout(Stdio::null())
-            .stderr(Stdio::piped())
-            .spawn()?;
+        let mut command = self.hfuzz_run();
+        command.stdout(Stdio::null()).stderr(Stdio::piped());
+        // honggfuzz is run by cargo, so they are killed together
+        let mut child = process::new_process_group(&mut command).spawn()?;
+        let _pid_file = match (&self.pids, child.id()) {
+            (Some(pids), Some(pid)) => pids
+                .track(&self.id, pid)
+                .map_err(|e| error!(self.log, "Cannot record fuzzing process"; "error" => e.to_string()))
+                .ok(),
+            _ => None,
+        };
         let stderr = child
             .stderr
             .take()
-            .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "cannot get stderr"))?;
+            .ok_or_else(|| self.error("cannot get stderr"))?;
         let stderr = tokio::io::BufReader::new(stderr);
-        let mut stop = self.stop_bc.subscribe();
-        tokio::select! {
-            _ = Self::filter_output(self.name.clone(), self.dir.clone(), self.feedback.clone(), stderr, self.log.clone()) => (),
-            _ = stop.recv() => {
-                debug!(self.log, "Terminating target {}", self.name);
+        let sampling = child
+            .id()
+            .map(|pid| tokio::spawn(resources::sample_target(pid, self.id.clone(), self.corpus.clone(), self.feedback.clone())));
+        let exec_rate = match (&self.stats_file, self.feedback.exec_rate_drop()) {
+            (Some(stats_file), Some(factor)) => Some(tokio::spawn(super::watch_exec_rate(
+                stats_file.clone(),
+                self.id.clone(),
+                factor,
+                self.feedback.clone(),
+            ))),
+            _ => None,
+        };
+        let mut output = Output::new(self.output_lines, self.output_archive.as_deref(), &self.log);
+        let mut cancelled = false;
+        let dry_run_crashes = tokio::select! {
+            crashes = Self::filter_output(self.id.clone(), self.dir.clone(), self.output_format, self.feedback.clone(), stderr, &mut output, self.log.clone()) => crashes,
+            _ = self.cancel.cancelled() => {
+                debug!(self.log, "Terminating target {}", self.id);
+                if let Some(pid) = child.id() {
+                    let _ = process::kill_group(pid);
+                }
                 child.kill().await?;
+                cancelled = true;
+                vec![]
             }
         };
 
         let res = child.wait().await?;
-        info!(self.log, "Finished target {}", self.name; "status" => res.code());
+        if let Some(sampling) = sampling {
+            sampling.abort();
+        }
+        if let Some(exec_rate) = exec_rate {
+            exec_rate.abort();
+        }
+        if let Err(e) = output.finish() {
+            error!(self.log, "Error archiving honggfuzz output"; "error" => e.to_string());
+        }
+        info!(self.log, "Finished target {}", self.id; "status" => res.code());
+        let failed = !res.success() && !cancelled;
+        if failed {
+            error!(self.log, "Honggfuzz failed"; "status" => res.code(), "output" => output.tail());
+            self.feedback.warning(format!("Fuzzing `{}` failed with {}: {}", self.id, res, output.tail()));
+        }
+
+        if let Err(e) = self.quarantine_crashes(&dry_run_crashes).await {
+            error!(self.log, "Error quarantining crashing inputs"; "error" => e.to_string());
+            self.feedback.warning(format!("Cannot quarantine crashing inputs for `{}`: {}", self.id, e));
+        }
+
+        let mut minimized = false;
+        if self.minimize {
+            if let Err(e) = self.minimize_corpus().await {
+                error!(self.log, "Error minimizing corpus"; "error" => e.to_string());
+                self.feedback.warning(format!("Cannot minimize corpus for `{}`: {}", self.id, e));
+            } else {
+                minimized = true;
+            }
+        }
+
+        if let Err(e) = self.enforce_corpus_limits(minimized).await {
+            error!(self.log, "Error enforcing corpus limits"; "error" => e.to_string());
+            self.feedback.warning(format!("Cannot enforce corpus limits for `{}`: {}", self.id, e));
+        }
 
+        if failed {
+            return Err(self.error(format!("honggfuzz failed with {}", res)));
+        }
         Ok(())
     }
+
+    /// Moves corpus inputs that crashed the target during the dry run into the quarantine
+    /// directory, so the same crashes aren't found again from the corpus on each run.
+    async fn quarantine_crashes(&self, crashes: &[PathBuf]) -> Result<()> {
+        let corpus = match &self.corpus {
+            Some(corpus) => corpus,
+            None => return Ok(()),
+        };
+        for crash in crashes {
+            let content = tokio::fs::read(crash).await?;
+            for input in corpus::quarantine(corpus, &content).await? {
+                info!(self.log, "Quarantined crashing input"; "input" => &input, "crash" => crash.to_str());
+                self.feedback.warning(format!(
+                    "Input `{}` from `{}` corpus crashes the target and is moved to {}",
+                    input,
+                    self.id,
+                    corpus::quarantine_dir(corpus).to_string_lossy()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Evicts inputs from the target corpus if it exceeds configured limits.
+    async fn enforce_corpus_limits(&self, minimized: bool) -> Result<()> {
+        let (corpus, limits) = match (&self.corpus, &self.corpus_limits) {
+            (Some(corpus), Some(limits)) => (corpus, limits),
+            _ => return Ok(()),
+        };
+        let exceeds = |size| corpus::exceeds_limits(size, limits.max_files, limits.max_bytes);
+        if !exceeds(corpus::size(corpus).await?) {
+            return Ok(());
+        }
+        if limits.eviction == Eviction::Coverage && !minimized {
+            self.minimize_corpus().await?;
+            if !exceeds(corpus::size(corpus).await?) {
+                return Ok(());
+            }
+        }
+        let stats = corpus::evict_oldest(corpus, limits.max_files, limits.max_bytes).await?;
+        info!(self.log, "Evicted corpus inputs"; "files" => stats.evicted, "bytes" => stats.bytes);
+        self.feedback.message(format!(
+            "Corpus for `{}` exceeds limits, {} oldest inputs ({} bytes) are evicted",
+            self.id, stats.evicted, stats.bytes
+        ));
+        Ok(())
+    }
+
+    /// Minimizes the target corpus using honggfuzz, keeping the original corpus as a backup.
+    pub async fn minimize_corpus(&self) -> Result<()> {
+        let corpus = match &self.corpus {
+            Some(corpus) => corpus,
+            None => return Ok(()),
+        };
+        debug!(self.log, "Minimizing corpus"; "corpus" => corpus.to_str());
+        let staging = corpus::staging_dir(corpus);
+        if staging.exists() {
+            tokio::fs::remove_dir_all(&staging).await?;
+        }
+        tokio::fs::create_dir_all(&staging).await?;
+
+        let before = corpus::count_files(corpus).await?;
+        let output = self
+            .hfuzz_run_base(format!("-M --output {}", staging.to_string_lossy()))
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .output()
+            .await?;
+        if !output.status.success() {
+            debug!(self.log, "Error minimizing corpus"; "output" => FnValue(|_| u8_slice_to_string(&output.stderr)));
+            return Err(self.error(format!(
+                "minimizing corpus {}: honggfuzz exited with {}: {}",
+                corpus.to_string_lossy(),
+                output.status,
+                output_tail(&output.stderr)
+            )));
+        }
+        let after = corpus::count_files(&staging).await?;
+        if after == 0 && before > 0 {
+            return Err(self.error(format!("minimized corpus {} is empty", staging.to_string_lossy())));
+        }
+
+        corpus::replace(corpus, &staging).await?;
+        info!(self.log, "Corpus minimized"; "before" => before, "after" => after);
+        self.feedback.debug(format!(
+            "Corpus for `{}` is minimized from {} to {} files",
+            self.id, before, after
+        ));
+        Ok(())
+    }
+}
+
+/// Honggfuzz output of a target, its last lines kept in memory, and optionally all of it
+/// in a gz-compressed archive.
+struct Output {
+    lines: VecDeque<String>,
+    max_lines: usize,
+    archive: Option<GzEncoder<BufWriter<File>>>,
+}
+
+impl Output {
+    /// Keeps `max_lines` last lines, and archives the output to the `archive` file, if
+    /// given and it can be created.
+    fn new(max_lines: usize, archive: Option<&Path>, log: &Logger) -> Self {
+        let archive = archive.and_then(|path| {
+            let file = path
+                .parent()
+                .map_or(Ok(()), std::fs::create_dir_all)
+                .and_then(|_| File::create(path));
+            match file {
+                Ok(file) => Some(GzEncoder::new(BufWriter::new(file), Compression::default())),
+                Err(e) => {
+                    error!(log, "Cannot create honggfuzz output archive"; "file" => path.to_str(), "error" => e.to_string());
+                    None
+                }
+            }
+        });
+        Self {
+            lines: VecDeque::with_capacity(max_lines),
+            max_lines,
+            archive,
+        }
+    }
+
+    fn push(&mut self, line: &str, log: &Logger) {
+        if let Some(archive) = &mut self.archive {
+            if let Err(e) = archive.write_all(line.as_bytes()) {
+                warn!(log, "Cannot archive honggfuzz output, stopping archiving"; "error" => e.to_string());
+                self.archive = None;
+            }
+        }
+        if self.max_lines == 0 {
+            return;
+        }
+        if self.lines.len() == self.max_lines {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line.trim_end().to_string());
+    }
+
+    /// The last lines of the output.
+    fn tail(&self) -> String {
+        self.lines.iter().map(String::as_str).collect::<Vec<_>>().join("\n")
+    }
+
+    /// Completes the archive.
+    fn finish(&mut self) -> io::Result<()> {
+        match self.archive.take() {
+            Some(archive) => archive.finish()?.flush(),
+            None => Ok(()),
+        }
+    }
 }
@@ -0,0 +1,223 @@
+use std::{fmt, path::Path};
+
+
+use crate::error::{output_tail, Error, Result};
+
+/// Version of `cargo hfuzz`, along with the honggfuzz it bundles.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl Version {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self { major, minor, patch }
+    }
+
+    /// Parses the first `x.y.z` version found in the text, like `cargo-hfuzz 0.5.52`.
+    pub fn parse(text: &str) -> Option<Self> {
+        text.split_whitespace().find_map(|word| {
+            let mut parts = word.trim_start_matches('v').splitn(3, '.');
+            let major = parts.next()?.parse().ok()?;
+            let minor = parts.next()?.parse().ok()?;
+            // pre-release and build suffixes are ignored
+            let patch = parts.next()?;
+            let patch = patch[..patch.find(|c: char| !c.is_ascii_digit()).unwrap_or(patch.len())]
+                .parse()
+                .ok()?;
+            Some(Self::new(major, minor, patch))
+        })
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Format of honggfuzz verbose output, that changes between its releases.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    /// Honggfuzz 2, bundled with `cargo hfuzz` 0.5: progress lines like
+    /// `Sz:... (i/b/h/e/p/c) New:0/0/0/5/0/0, ...`, and the number of edges
+    /// as `guard_nb:` of the final summary line.
+    V2,
+}
+
+/// Supported `cargo hfuzz` versions, as ranges with the lower bound included and the upper
+/// one excluded, and their output formats.
+const SUPPORTED: &[(Version, Version, OutputFormat)] = &[(Version::new(0, 5, 0), Version::new(0, 6, 0), OutputFormat::V2)];
+
+impl Default for OutputFormat {
+    fn default() -> Self {
+        Self::V2
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::V2 => write!(f, "honggfuzz 2 output"),
+        }
+    }
+}
+
+impl OutputFormat {
+    /// Output format of the `cargo hfuzz` version, or an error if the version is not supported.
+    pub fn for_version(version: Version) -> Result<Self> {
+        SUPPORTED
+            .iter()
+            .find(|(min, max, _)| *min <= version && version < *max)
+            .map(|(_, _, format)| *format)
+            .ok_or_else(|| {
+                let supported = SUPPORTED
+                    .iter()
+                    .map(|(min, max, _)| format!("{} to {} (exclusive)", min, max))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                Error::other(format!(
+                    "cargo-hfuzz {} is not supported, supported versions are {}",
+                    version, supported
+                ))
+            })
+    }
+
+    /// Number of edges newly covered as reported by a progress line, or `None` if it is
+    /// not a progress line.
+    pub fn new_edges(self, line: &str) -> Option<Result<u32, String>> {
+        match self {
+            Self::V2 => {
+                if !line.starts_with("Sz:") {
+                    return None;
+                }
+                Some(
+                    line.split('/')
+                        .nth(8)
+                        .ok_or_else(|| format!("cannot find new edges in `{}`", line.trim_end()))
+                        .and_then(|e| e.parse().map_err(|_| format!("cannot parse new edges `{}`", e))),
+                )
+            }
+        }
+    }
+
+    /// Total number of edges of the target, from the output of its short run.
+    pub fn total_edges(self, output: &str) -> Result<u32, String> {
+        match self {
+            Self::V2 => {
+                let last = output
+                    .lines()
+                    .rev()
+                    .find(|s| !s.is_empty())
+                    .ok_or_else(|| "no output".to_string())?;
+                let edge_nr = last
+                    .split_once("guard_nb:")
+                    .and_then(|(_, s)| s.split_once(' '))
+                    .map(|(s, _)| s)
+                    .ok_or_else(|| format!("cannot get number of edges from `{}`", last))?;
+                edge_nr
+                    .parse()
+                    .map_err(|_| format!("cannot parse number of edges `{}`", edge_nr))
+            }
+        }
+    }
+
+    /// Average number of target executions per second, from the output of its timed run.
+    pub fn execs_per_sec(self, output: &str) -> Result<f64, String> {
+        match self {
+            Self::V2 => {
+                let summary = output
+                    .lines()
+                    .rev()
+                    .find(|s| s.starts_with("Summary "))
+                    .ok_or_else(|| "no summary in the output".to_string())?;
+                let field = |name: &str| {
+                    summary
+                        .split_whitespace()
+                        .find_map(|f| f.strip_prefix(name)?.strip_prefix(':'))
+                        .and_then(|v| v.parse::<u64>().ok())
+                        .ok_or_else(|| format!("cannot get `{}` from `{}`", name, summary))
+                };
+                // the speed is rounded down to whole executions, so it is computed more precisely if possible
+                match field("time")? {
+                    0 => field("speed").map(|speed| speed as f64),
+                    time => Ok(field("iterations")? as f64 / time as f64),
+                }
+            }
+        }
+    }
+}
+
+/// Detects the installed `cargo hfuzz` version and the output format of its honggfuzz,
+/// failing if it cannot be run or its version is not supported.
+pub async fn detect(cargo_hfuzz: Option<&Path>) -> Result<(Version, OutputFormat)> {
+    let output = super::cargo_hfuzz(cargo_hfuzz).arg("version").output().await?;
+    if !output.status.success() {
+        return Err(Error::other(format!(
+            "`cargo hfuzz version` failed with {}: {}",
+            output.status,
+            output_tail(&output.stderr)
+        )));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let version = Version::parse(&stdout)
+        .ok_or_else(|| Error::other(format!("cannot get cargo-hfuzz version from `{}`", stdout.trim())))?;
+    Ok((version, OutputFormat::for_version(version)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_versions() {
+        assert_eq!(Version::parse("cargo-hfuzz 0.5.52\n"), Some(Version::new(0, 5, 52)));
+        assert_eq!(Version::parse("honggfuzz v2.4.0"), Some(Version::new(2, 4, 0)));
+        assert_eq!(Version::parse("cargo-hfuzz 0.5.55-alpha.1+build"), Some(Version::new(0, 5, 55)));
+        assert_eq!(Version::parse("version 1.2 of cargo-hfuzz 0.6.0"), Some(Version::new(0, 6, 0)));
+        assert_eq!(Version::parse("cargo-hfuzz 0.5"), None);
+        assert_eq!(Version::parse(""), None);
+    }
+
+    #[test]
+    fn supports_cargo_hfuzz_0_5() {
+        assert_eq!(OutputFormat::for_version(Version::new(0, 5, 0)).unwrap(), OutputFormat::V2);
+        assert_eq!(OutputFormat::for_version(Version::new(0, 5, 52)).unwrap(), OutputFormat::V2);
+        assert!(OutputFormat::for_version(Version::new(0, 4, 9)).is_err());
+        assert!(OutputFormat::for_version(Version::new(0, 6, 0)).is_err());
+    }
+
+    #[test]
+    fn parses_new_edges() {
+        let format = OutputFormat::V2;
+        assert_eq!(
+            format.new_edges("Sz:1 Tm:10us (i/b/h/e/p/c) New:0/0/0/12/0/0, Cur:0/0/0/40/0/0"),
+            Some(Ok(12))
+        );
+        assert_eq!(format.new_edges("Entering phase 2/3: Dynamic Main (Feedback Driven Mode)"), None);
+        assert!(matches!(format.new_edges("Sz:1 Tm:10us (i/b/h/e/p/c) New:0/0"), Some(Err(_))));
+        assert!(matches!(format.new_edges("Sz:1 Tm:10us (i/b/h/e/p/c) New:0/0/0/x/0/0, Cur:"), Some(Err(_))));
+    }
+
+    #[test]
+    fn parses_total_edges() {
+        let format = OutputFormat::V2;
+        let output = "Entering phase 1/3: Dry Run\n\
+                      Summary iterations:1 time:0 speed:0 guard_nb:4213 branch_coverage_percent:0\n\n";
+        assert_eq!(format.total_edges(output), Ok(4213));
+        assert!(format.total_edges("").is_err());
+        assert!(format.total_edges("Summary iterations:1 time:0").is_err());
+        assert!(format.total_edges("Summary guard_nb:many edges").is_err());
+    }
+
+    #[test]
+    fn parses_execs_per_sec() {
+        let format = OutputFormat::V2;
+        assert_eq!(format.execs_per_sec("Summary iterations:1000 time:3 speed:333 crashes_count:0"), Ok(1000.0 / 3.0));
+        assert_eq!(format.execs_per_sec("Summary iterations:10 time:0 speed:7 crashes_count:0"), Ok(7.0));
+        assert!(format.execs_per_sec("Sz:1 Tm:10us").is_err());
+        assert!(format.execs_per_sec("Summary iterations:10 speed:7").is_err());
+    }
+}
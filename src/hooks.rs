@@ -0,0 +1,72 @@
+use std::{collections::HashMap, path::Path};
+
+use slog::{debug, error, FnValue, Logger};
+use tokio::process::Command;
+
+use crate::{
+    common::u8_slice_to_string,
+    config::Hooks,
+    error::{output_tail, Error, Result},
+};
+
+/// Run stage a hook is executed at.
+#[derive(Clone, Copy, Debug)]
+pub enum Stage {
+    AfterCheckout,
+    BeforeFuzzing,
+    AfterRun,
+}
+
+impl Stage {
+    fn name(&self) -> &'static str {
+        match self {
+            Stage::AfterCheckout => "after_checkout",
+            Stage::BeforeFuzzing => "before_fuzzing",
+            Stage::AfterRun => "after_run",
+        }
+    }
+
+    fn commands<'a>(&self, hooks: &'a Hooks) -> &'a [String] {
+        match self {
+            Stage::AfterCheckout => &hooks.after_checkout,
+            Stage::BeforeFuzzing => &hooks.before_fuzzing,
+            Stage::AfterRun => &hooks.after_run,
+        }
+    }
+}
+
+/// Runs hook commands configured for the `stage` using `sh -c`.
+///
+/// Commands are executed in `dir` sequentially, with `env` describing the run
+/// and the `FUZZ_CI_STAGE` variable set to the stage name. The first failing
+/// command stops execution of the rest.
+pub async fn run(
+    hooks: &Hooks,
+    stage: Stage,
+    dir: impl AsRef<Path>,
+    env: &HashMap<String, String>,
+    log: &Logger,
+) -> Result<()> {
+    for command in stage.commands(hooks) {
+        debug!(log, "Running {} hook", stage.name(); "command" => command);
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .current_dir(dir.as_ref())
+            .envs(env)
+            .env("FUZZ_CI_STAGE", stage.name())
+            .output()
+            .await?;
+        if !output.status.success() {
+            error!(log, "Hook command failed"; "command" => command, "code" => output.status.code(),
+                   "stderr" => FnValue(|_| u8_slice_to_string(&output.stderr)));
+            return Err(Error::Build {
+                command: format!("{} hook `{}`", stage.name(), command),
+                dir: dir.as_ref().to_path_buf(),
+                status: output.status,
+                output: output_tail(&output.stderr),
+            });
+        }
+    }
+    Ok(())
+}
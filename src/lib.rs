@@ -0,0 +1,80 @@
+#![feature(str_split_once)]
+
+//! Continuous fuzzing of Rust projects with honggfuzz.
+//!
+//! The library contains the orchestration used by the `fuzz-ci` binary, so it can be
+//! embedded into other tools, e.g. CI binaries of fuzzed projects:
+//!
+//! - [config] reads and validates the configuration file;
+//! - [checkout] checks out the fuzzing project, and [build] builds its fuzz targets;
+//! - [hfuzz] runs fuzz targets with honggfuzz, and [command] with configured commands,
+//!   reporting progress to [feedback];
+//! - [benchmark] compares throughput of fuzz targets with the base commit, [bisect]
+//!   finds commits losing their coverage, and [harness] compares changed fuzzing
+//!   harnesses with their previous versions;
+//! - [cancel] stops a run, a fuzzing project or a single target, and [orphans] kills
+//!   fuzzing processes left behind by a crashed server;
+//! - [report] renders coverage reports of fuzzing runs, with the [provenance] of their
+//!   builds and scripts [repro]ducing found crashes, and [policy] decides whether
+//!   finished runs pass;
+//! - [corpus], [distill] and [backup] maintain the fuzzing corpus, and [artifacts] stores crash
+//!   inputs and corpus snapshots of runs by their content;
+//! - [server] runs the webhook server starting fuzzing on pushes, with [runs] it started
+//!   optionally kept in the [state] store shared with a [standby] server, and [schedule]d
+//!   by branch priorities, and [aggregate]s runs of other servers, with webhook
+//!   [deliveries] stored for replaying;
+//! - [trigger] and [simulate] are clients of a running server, also controllable over
+//!   gRPC, with control actions recorded in the [audit] log;
+//! - [testing] helps exercising the server pipeline without real fuzzers;
+//! - [cli] implements subcommands of the `fuzz-ci` binary.
+
+pub mod aggregate;
+pub mod artifacts;
+pub mod audit;
+pub mod backup;
+pub mod benchmark;
+pub mod bisect;
+pub mod build;
+pub mod cancel;
+pub mod check;
+pub mod checkout;
+pub mod cli;
+pub mod command;
+pub mod common;
+pub mod config;
+pub mod corpus;
+pub mod dashboard;
+pub mod deliveries;
+pub mod distill;
+pub mod doctor;
+pub mod error;
+pub mod feedback;
+pub mod harness;
+pub mod hfuzz;
+pub mod orphans;
+pub mod policy;
+pub mod provenance;
+pub mod replay;
+pub mod report;
+pub mod repro;
+pub mod resources;
+pub mod runs;
+pub mod schedule;
+pub mod server;
+pub mod simulate;
+pub mod standby;
+pub mod state;
+pub mod testing;
+pub mod trigger;
+
+mod api;
+mod assets;
+mod github;
+mod grpc;
+mod hooks;
+mod process;
+mod reload;
+mod seeds;
+mod slack;
+mod storage;
+mod workspace;
@@ -1,25 +1,8 @@
-#![feature(str_split_once)]
+use std::{fmt::Display, path::PathBuf, process::exit, str::FromStr};
 
-use std::sync::Arc;
-
-use config::TargetConfig;
-
-use feedback::{Feedback, LoggerClient};
-use slog::{crit, debug, error};
-use tokio::sync::broadcast::channel;
-
-use crate::config::HonggfuzzConfig;
-
-mod build;
-mod checkout;
-mod config;
-mod error;
-mod feedback;
-mod hfuzz;
-mod report;
-mod server;
-mod slack;
-mod common;
+use clap::ArgMatches;
+use fuzzing_ci::{check, cli, config, doctor, server};
+use slog::{crit, debug, info};
 
 #[macro_use]
 extern crate clap;
@@ -29,13 +12,14 @@ async fn main() {
     let matches = clap_app!(ci_fuzz =>
         (version: "1.0")
         (about: "Runs fuzzing in CI")
-        (@arg CONFIG: -c --config +takes_value "Sets a custom config file")
+        (@arg CONFIG: -c --config +takes_value "Sets a custom config file (looked up in standard locations by default)")
         (@arg debug: -d ... "Sets the level of debugging information")
         (@subcommand checkout =>
             (about: "checkout fuzzing repo and target project")
             (@arg DIR: +required "Directory checkout to")
             (@arg REPO: +required "Target project repository")
             (@arg BRANCH: +required "Target project branch")
+            (@arg COMMIT: "Target project commit (the branch head by default)")
         )
         (@subcommand hfuzz =>
             (about: "runs hfuzz")
@@ -44,12 +28,170 @@ async fn main() {
             (@arg HFUZZ_RUN_ARGS: --hfuzz-run-args "Honggfuzz run arguments")
             (@arg CORPUS: -c --corpus "Directory containing honggfuzz corpus")
             (@arg TARGET: ... "Targets to fuzz")
+            (@arg tui: --tui "Shows a live dashboard of targets instead of the log")
         )
         (@subcommand slack =>
             (about: "runs slack messaging")
             (@arg CHANNEL: +required "Slack channel to post to")
             (@arg TOKEN: +required "Slack authorization token")
         )
+        (@subcommand corpus =>
+            (about: "manages the corpus")
+            (@subcommand stats =>
+                (about: "prints number of inputs and their size for each target")
+                (@arg json: --json "Prints statistics as JSON")
+            )
+            (@subcommand minimize =>
+                (about: "minimizes target corpora using honggfuzz")
+                (@arg DIR: +required "Directory containing built honggfuzz project")
+                (@arg HFUZZ_RUN_ARGS: --hfuzz-run-args +takes_value "Honggfuzz run arguments")
+                (@arg TARGET: +required ... "Targets to minimize corpora for")
+            )
+            (@subcommand merge =>
+                (about: "merges inputs from the directory into the corpus")
+                (@arg DIR: +required "Directory containing a subdirectory of inputs for each target")
+                (@arg TARGET: -t --target +takes_value "Merge inputs from the directory into this target corpus")
+            )
+            (@subcommand dedup =>
+                (about: "removes inputs with duplicate content")
+            )
+            (@subcommand restore =>
+                (about: "restores the corpus from a backup")
+                (@arg BACKUP: "Backup archive to restore (the most recent one by default)")
+            )
+        )
+        (@subcommand replay =>
+            (about: "runs a fuzz target with an input, e.g. a reported crash")
+            (@arg PROJECT: +required "Fuzzing project")
+            (@arg TARGET: +required "Fuzz target (`target@variant` for build variants)")
+            (@arg INPUT: +required "Input file")
+            (@arg CHECKOUT: --checkout +takes_value "Fuzzing project checkout (the current directory by default)")
+            (@arg no_build: --("no-build") "Runs the already built target")
+        )
+        (@subcommand report =>
+            (about: "re-renders fuzzing reports from their stored status")
+            (@arg BRANCH: ... "Branches to re-render reports for (all by default)")
+        )
+        (@subcommand artifacts =>
+            (about: "manages content-addressed artifacts of fuzzing runs")
+            (@subcommand verify =>
+                (about: "checks that artifacts recorded in run manifests are stored with matching content")
+                (@arg STORE: --store +takes_value "Artifacts directory to verify, e.g. a mirrored copy (the configured one by default)")
+                (@arg BRANCH: ... "Branches to verify artifacts of runs of (all by default)")
+            )
+        )
+        (@subcommand config =>
+            (about: "manages the configuration")
+            (@subcommand init =>
+                (about: "writes a commented example configuration")
+                (@arg PATH: "File to write the configuration to (standard output by default)")
+                (@arg force: -f --force "Overwrite the existing file")
+            )
+            (@subcommand check =>
+                (about: "validates the configuration file")
+                (@arg CHECKOUT: --checkout +takes_value "Fuzzing project checkout to look up fuzzing projects in")
+                (@arg offline: --offline "Do not check external services")
+                (@arg json: --json "Prints found issues as JSON")
+            )
+        )
+        (@subcommand doctor =>
+            (about: "checks that tools needed for fuzzing are installed")
+        )
+        (@subcommand trigger =>
+            (about: "starts a fuzzing run on a running server")
+            (@arg SERVER: -s --server +takes_value +required "Server url, e.g. http://fuzz-ci.example.com:3030/")
+            (@arg BRANCH: -b --branch +takes_value +required "Branch to fuzz")
+            (@arg COMMIT: --commit +takes_value "Commit to fuzz (the branch head by default)")
+            (@arg REPO: --repo +takes_value "Target project repository (the configured one by default)")
+            (@arg TARGETS: -t --target ... +takes_value "Targets to fuzz (all by default)")
+            (@arg DURATION: --duration +takes_value "Fuzzing time of each target, in seconds (the configured one by default)")
+            (@arg TOKEN: --token +takes_value "API token (FUZZ_CI_API_TOKEN by default)")
+            (@arg wait: -w --wait "Waits for the run to complete, failing if the run fails")
+            (@arg INTERVAL: --interval +takes_value "Interval between run status checks, in seconds (10 by default)")
+            (@arg json: --json "Prints the run as JSON")
+        )
+        (@subcommand status =>
+            (about: "shows fuzzing runs of a running server")
+            (@arg SERVER: -s --server +takes_value +required "Server url, e.g. http://fuzz-ci.example.com:3030/")
+            (@arg TOKEN: --token +takes_value "API token (FUZZ_CI_API_TOKEN by default)")
+            (@arg RUN: "Run to show (all runs started since the server start by default)")
+            (@arg json: --json "Prints runs as JSON")
+        )
+        (@subcommand cancel =>
+            (about: "cancels a fuzzing run of a running server")
+            (@arg SERVER: -s --server +takes_value +required "Server url, e.g. http://fuzz-ci.example.com:3030/")
+            (@arg TOKEN: --token +takes_value "API token (FUZZ_CI_API_TOKEN by default)")
+            (@arg RUN: +required "Run to cancel")
+            (@arg SCOPE: "Fuzzing project or target to stop, as `project/target` or a unique name (the whole run by default)")
+        )
+        (@subcommand pause =>
+            (about: "pauses a fuzzing run of a running server, stopping its processes")
+            (@arg SERVER: -s --server +takes_value +required "Server url, e.g. http://fuzz-ci.example.com:3030/")
+            (@arg TOKEN: --token +takes_value "API token (FUZZ_CI_API_TOKEN by default)")
+            (@arg RUN: +required "Run to pause")
+        )
+        (@subcommand resume =>
+            (about: "resumes a paused fuzzing run of a running server")
+            (@arg SERVER: -s --server +takes_value +required "Server url, e.g. http://fuzz-ci.example.com:3030/")
+            (@arg TOKEN: --token +takes_value "API token (FUZZ_CI_API_TOKEN by default)")
+            (@arg RUN: +required "Run to resume")
+        )
+        (@subcommand baseline =>
+            (about: "changes the baseline coverage is compared with in the most recent report of a branch")
+            (@arg SERVER: -s --server +takes_value +required "Server url, e.g. http://fuzz-ci.example.com:3030/")
+            (@arg TOKEN: --token +takes_value "API token (FUZZ_CI_API_TOKEN by default)")
+            (@arg BRANCH: -b --branch +takes_value +required "Branch of the report")
+            (@arg REPO: --repo +takes_value "Target project repository (the configured one by default)")
+            (@arg reset: --reset "Removes the baseline, so the next status becomes one, instead of using the current status")
+            (@arg TARGETS: ... "Targets to change the baseline of (all by default)")
+        )
+        (@subcommand targets =>
+            (about: "shows fuzz targets disabled on a running server")
+            (@arg SERVER: -s --server +takes_value +required "Server url, e.g. http://fuzz-ci.example.com:3030/")
+            (@arg TOKEN: --token +takes_value "API token (FUZZ_CI_API_TOKEN by default)")
+            (@arg json: --json "Prints disabled targets as JSON")
+        )
+        (@subcommand disable =>
+            (about: "disables a fuzz target in runs started later on a running server")
+            (@arg SERVER: -s --server +takes_value +required "Server url, e.g. http://fuzz-ci.example.com:3030/")
+            (@arg TOKEN: --token +takes_value "API token of an admin (FUZZ_CI_API_TOKEN by default)")
+            (@arg TARGET: +required "Target to disable")
+        )
+        (@subcommand enable =>
+            (about: "enables a disabled fuzz target again on a running server")
+            (@arg SERVER: -s --server +takes_value +required "Server url, e.g. http://fuzz-ci.example.com:3030/")
+            (@arg TOKEN: --token +takes_value "API token of an admin (FUZZ_CI_API_TOKEN by default)")
+            (@arg TARGET: +required "Target to enable")
+        )
+        (@subcommand audit =>
+            (about: "shows control actions recorded in the audit log of a running server")
+            (@arg SERVER: -s --server +takes_value +required "Server url, e.g. http://fuzz-ci.example.com:3030/")
+            (@arg TOKEN: --token +takes_value "API token of an admin (FUZZ_CI_API_TOKEN by default)")
+            (@arg LIMIT: -n --limit +takes_value "Number of the most recent actions to show (100 by default)")
+            (@arg json: --json "Prints actions as JSON")
+        )
+        (@subcommand deliveries =>
+            (about: "shows webhook deliveries stored by a running server")
+            (@arg SERVER: -s --server +takes_value +required "Server url, e.g. http://fuzz-ci.example.com:3030/")
+            (@arg TOKEN: --token +takes_value "API token (FUZZ_CI_API_TOKEN by default)")
+            (@arg LIMIT: -n --limit +takes_value "Number of the most recent deliveries to show (100 by default)")
+            (@arg json: --json "Prints deliveries as JSON")
+        )
+        (@subcommand redeliver =>
+            (about: "processes a stored webhook delivery again on a running server")
+            (@arg SERVER: -s --server +takes_value +required "Server url, e.g. http://fuzz-ci.example.com:3030/")
+            (@arg TOKEN: --token +takes_value "API token of an admin (FUZZ_CI_API_TOKEN by default)")
+            (@arg DELIVERY: +required "Delivery to process again")
+        )
+        (@subcommand simulate =>
+            (about: "sends a synthetic push event to the server webhook")
+            (@arg BRANCH: -b --branch +takes_value +required "Pushed branch")
+            (@arg REPO: --repo +takes_value "Target project repository (the configured one by default)")
+            (@arg COMMIT: --commit +takes_value "Pushed commit (a random id by default)")
+            (@arg MESSAGE: -m --message +takes_value "Commit message")
+            (@arg FORCED: --forced "Simulates a force-push rewriting the branch history")
+            (@arg URL: -u --url +takes_value "Webhook url (the `run` endpoint of the configured address by default)")
+        )
         (@subcommand server =>
             (about: "runs CI server")
             (@arg ADDR: -l --listen +takes_value "Address listen to (0.0.0.0:3030 by default)")
@@ -59,107 +201,224 @@ async fn main() {
     )
     .get_matches();
 
-    let log = {
-        use slog::{Drain, Level::*};
-        let decorator = slog_term::TermDecorator::new().build();
-        let drain = slog_term::FullFormat::new(decorator).build().fuse();
-        let drain = slog_async::Async::new(drain)
-            .build()
-            .filter_level(match matches.occurrences_of("debug") {
-                0 => Info,
-                1 => Debug,
-                _ => Trace,
-            })
-            .fuse();
-
-        slog::Logger::root(drain, slog::o!())
+    let level = match matches.occurrences_of("debug") {
+        0 => slog::Level::Info,
+        1 => slog::Level::Debug,
+        _ => slog::Level::Trace,
     };
+    let mut log = cli::logger(config::LogFormat::Text, level);
 
     debug!(log, "Starting application");
 
-    let config = matches.value_of("CONFIG").unwrap_or("fuzz-ci.toml");
-    let mut config = match config::Config::read(config) {
-        Ok(c) => c,
-        Err(e) => {
-            crit!(log, "Failed to read configuration file {}", config; "error" => e.to_string());
-            return;
-        }
+    let config_file = match matches.value_of_os("CONFIG") {
+        Some(file) => PathBuf::from(file),
+        None => config::Config::find_file(),
     };
 
-    if let Some(matches) = matches.subcommand_matches("checkout") {
-        let dir = matches.value_of_os("DIR").unwrap();
-        let repo = matches.value_of("REPO").unwrap();
-        let branch = matches.value_of("BRANCH").unwrap();
-        match checkout::checkout(dir, repo, branch, log.clone()).await {
-            Ok(_) => (),
-            Err(e) => error!(log, "Error occurred"; "error" => e),
-        }
-    } else if let Some(matches) = matches.subcommand_matches("hfuzz") {
-        let dir = matches.value_of_os("DIR").unwrap();
-        let corpus = matches.value_of_lossy("CORPUS");
-        let targets = matches.values_of_lossy("TARGET").unwrap_or(vec![]);
-        let feedback = &config.feedback;
-        let targets = TargetConfig::new(None, targets, None);
-        let hfuzz_run_args = matches.value_of_lossy("HFUZZ_RUN_ARGS").unwrap_or_default().into_owned();
-        let hfuzz_config = HonggfuzzConfig::new(hfuzz_run_args);
-        let client = LoggerClient::new("feedback", log.clone());
-        let feedback = Arc::new(
-            Feedback::new(
-                feedback,
-                Box::new(client),
-                &config.reports_path,
-                &config.url,
-                "reports",
-                log.clone(),
-            )
-            .await
-            .unwrap(),
-        );
+    if let Some(matches) = matches.subcommand_matches("config") {
+        if let Some(matches) = matches.subcommand_matches("init") {
+            let path = matches.value_of_os("PATH").map(std::path::Path::new);
+            if let Err(e) = cli::config_init_command(path, matches.is_present("force")) {
+                fail(e);
+            }
+        } else if let Some(matches) = matches.subcommand_matches("check") {
+            let checkout = matches.value_of_os("CHECKOUT").map(std::path::Path::new);
+            if !check::check(&config_file, checkout, matches.is_present("offline"), matches.is_present("json"), &log).await {
+                exit(1);
+            }
+        } else {
+            println!("{}", matches.usage());
+        }
+        return;
+    }
+
+    if matches.subcommand_matches("doctor").is_some() {
+        if !doctor::doctor(&config_file).await {
+            exit(1);
+        }
+        return;
+    }
 
-        feedback.started();
-        match hfuzz::run(
-            dir,
-            config.env,
-            targets,
-            hfuzz_config,
-            corpus.map(|s| s.into_owned()),
-            feedback,
-            channel(1).0,
-            log.new(slog::o!()),
-        )
-        .await
-        {
-            Ok(_) => (),
-            Err(e) => error!(log, "Error occurred"; "error" => e),
-        }
-    } else if let Some(matches) = matches.subcommand_matches("server") {
-        if let Some(listen) = matches.value_of("ADDR") {
-            config.address = listen.to_string();
-        } else if config.address.is_empty() {
-            config.address = "0.0.0.0:3030".to_string();
+    // commands using the API of a running server, without the configuration
+    let result = match matches.subcommand() {
+        ("trigger", Some(matches)) => {
+            let options = cli::TriggerOptions {
+                api: api_options(matches),
+                branch: matches.value_of("BRANCH").unwrap().to_string(),
+                commit: matches.value_of("COMMIT").map(String::from),
+                repo: matches.value_of("REPO").map(String::from),
+                targets: matches.values_of_lossy("TARGETS").unwrap_or_default(),
+                duration: parsed(matches, "DURATION"),
+                wait: matches.is_present("wait"),
+                interval: parsed(matches, "INTERVAL").unwrap_or(10),
+                json: matches.is_present("json"),
+            };
+            Some(cli::trigger_command(options).await)
+        }
+        ("status", Some(matches)) => {
+            Some(cli::status_command(&api_options(matches), matches.value_of("RUN"), matches.is_present("json")).await)
+        }
+        ("cancel", Some(matches)) => {
+            let (id, scope) = (matches.value_of("RUN").unwrap(), matches.value_of("SCOPE"));
+            Some(cli::cancel_command(&api_options(matches), id, scope).await)
+        }
+        ("baseline", Some(matches)) => {
+            let options = cli::BaselineOptions {
+                api: api_options(matches),
+                branch: matches.value_of("BRANCH").unwrap().to_string(),
+                repo: matches.value_of("REPO").map(String::from),
+                targets: matches.values_of_lossy("TARGETS").unwrap_or_default(),
+                reset: matches.is_present("reset"),
+            };
+            Some(cli::baseline_command(options).await)
+        }
+        ("audit", Some(matches)) => {
+            Some(cli::audit_command(&api_options(matches), parsed(matches, "LIMIT"), matches.is_present("json")).await)
+        }
+        ("deliveries", Some(matches)) => {
+            Some(cli::deliveries_command(&api_options(matches), parsed(matches, "LIMIT"), matches.is_present("json")).await)
+        }
+        ("redeliver", Some(matches)) => Some(cli::redeliver_command(&api_options(matches), matches.value_of("DELIVERY").unwrap()).await),
+        ("targets", Some(matches)) => Some(cli::targets_command(&api_options(matches), matches.is_present("json")).await),
+        ("disable", Some(matches)) => Some(cli::toggle_command(&api_options(matches), matches.value_of("TARGET").unwrap(), true).await),
+        ("enable", Some(matches)) => Some(cli::toggle_command(&api_options(matches), matches.value_of("TARGET").unwrap(), false).await),
+        ("pause", Some(matches)) => Some(cli::pause_command(&api_options(matches), matches.value_of("RUN").unwrap(), true).await),
+        ("resume", Some(matches)) => Some(cli::pause_command(&api_options(matches), matches.value_of("RUN").unwrap(), false).await),
+        _ => None,
+    };
+    if let Some(result) = result {
+        if let Err(e) = result {
+            fail(e);
         }
+        return;
+    }
 
-        if let Some(url) = matches.value_of("URL") {
-            config.url = Some(url.parse().expect("Failed to parse url"));
-        } else if config.url.is_none() {
-            config.url = Some(
-                format!("http://{}", config.address)
-                    .parse()
-                    .expect("Failed to parse address as url"),
-            );
+    let config = match config::Config::read(&config_file) {
+        Ok(c) => {
+            if c.log_format != config::LogFormat::Text {
+                log = cli::logger(c.log_format, level);
+            }
+            info!(log, "Configuration loaded from {}", config_file.to_string_lossy());
+            c
+        }
+        Err(e) => {
+            crit!(log, "Failed to read configuration file {}", config_file.to_string_lossy(); "error" => e.to_string());
+            return;
         }
+    };
 
-        if matches.occurrences_of("BRANCH") > 0 {
-            config.branches = matches.values_of_lossy("BRANCH").unwrap();
-        } else if config.branches.is_empty() {
-            config.branches = ["master", "develop"]
-                .iter()
-                .map(|s| s.to_string())
-                .collect();
+    match matches.subcommand() {
+        ("checkout", Some(matches)) => {
+            let options = cli::CheckoutOptions {
+                dir: PathBuf::from(matches.value_of_os("DIR").unwrap()),
+                repo: matches.value_of("REPO").unwrap().to_string(),
+                branch: matches.value_of("BRANCH").unwrap().to_string(),
+                commit: matches.value_of("COMMIT").map(String::from),
+            };
+            if let Err(e) = cli::checkout_command(options, &config, &log).await {
+                fail(format!("Cannot check out: {}", e));
+            }
+        }
+        ("hfuzz", Some(matches)) => {
+            let options = cli::HfuzzOptions {
+                dir: PathBuf::from(matches.value_of_os("DIR").unwrap()),
+                corpus: matches.value_of_lossy("CORPUS").map(|corpus| corpus.into_owned()),
+                targets: matches.values_of_lossy("TARGET").unwrap_or_default(),
+                run_args: matches.value_of_lossy("HFUZZ_RUN_ARGS").unwrap_or_default().into_owned(),
+                tui: matches.is_present("tui"),
+            };
+            if let Err(e) = cli::hfuzz_command(options, &config, &log).await {
+                fail(e);
+            }
+        }
+        ("corpus", Some(matches)) => {
+            let command = match matches.subcommand() {
+                ("stats", Some(matches)) => cli::CorpusCommand::Stats { json: matches.is_present("json") },
+                ("minimize", Some(matches)) => cli::CorpusCommand::Minimize {
+                    dir: PathBuf::from(matches.value_of_os("DIR").unwrap()),
+                    run_args: matches.value_of_lossy("HFUZZ_RUN_ARGS").unwrap_or_default().into_owned(),
+                    targets: matches.values_of_lossy("TARGET").unwrap(),
+                },
+                ("merge", Some(matches)) => cli::CorpusCommand::Merge {
+                    dir: PathBuf::from(matches.value_of_os("DIR").unwrap()),
+                    target: matches.value_of("TARGET").map(String::from),
+                },
+                ("dedup", Some(_)) => cli::CorpusCommand::Dedup,
+                ("restore", Some(matches)) => cli::CorpusCommand::Restore {
+                    backup: matches.value_of_os("BACKUP").map(PathBuf::from),
+                },
+                _ => {
+                    println!("{}", matches.usage());
+                    return;
+                }
+            };
+            cli::corpus_command(command, &config, &log).await
+        }
+        ("replay", Some(matches)) => {
+            let options = cli::ReplayOptions {
+                checkout: matches.value_of_os("CHECKOUT").map_or_else(|| PathBuf::from("."), PathBuf::from),
+                project: matches.value_of("PROJECT").unwrap().to_string(),
+                target: matches.value_of("TARGET").unwrap().to_string(),
+                input: PathBuf::from(matches.value_of_os("INPUT").unwrap()),
+                build: !matches.is_present("no_build"),
+            };
+            if let Err(e) = cli::replay_command(options, &config, &log).await {
+                fail(e);
+            }
         }
+        ("report", Some(matches)) => cli::report_command(matches.values_of_lossy("BRANCH"), &config, &log).await,
+        ("artifacts", Some(matches)) => match matches.subcommand_matches("verify") {
+            Some(matches) => {
+                let store = matches.value_of_os("STORE").map(std::path::Path::new);
+                if !cli::verify_artifacts(store, matches.values_of_lossy("BRANCH"), &config, &log).await {
+                    exit(1);
+                }
+            }
+            None => println!("{}", matches.usage()),
+        },
+        ("simulate", Some(matches)) => {
+            let options = cli::SimulateOptions {
+                url: parsed(matches, "URL"),
+                repo: matches.value_of("REPO").map(String::from),
+                branch: matches.value_of("BRANCH").unwrap().to_string(),
+                commit: matches.value_of("COMMIT").map(String::from),
+                message: matches.value_of("MESSAGE").map(String::from),
+                forced: matches.is_present("FORCED"),
+            };
+            if let Err(e) = cli::simulate_command(options, &config).await {
+                fail(format!("Cannot send push event: {}", e));
+            }
+        }
+        ("server", Some(matches)) => {
+            let overrides = server::Overrides {
+                address: matches.value_of("ADDR").map(String::from),
+                url: parsed(matches, "URL"),
+                branches: matches.values_of_lossy("BRANCHES"),
+            };
+            if let Err(e) = cli::server_command(overrides, config_file, config, log).await {
+                fail(e);
+            }
+        }
+        _ => println!("{}", matches.usage()),
+    }
+}
+
+/// Prints the error and exits with a failure status.
+fn fail(e: impl Display) -> ! {
+    eprintln!("{}", e);
+    exit(1);
+}
+
+/// Value of the optional argument, failing if it cannot be parsed.
+fn parsed<T: FromStr>(matches: &ArgMatches<'_>, name: &str) -> Option<T> {
+    let value = matches.value_of(name)?;
+    Some(value.parse().unwrap_or_else(|_| fail(format!("invalid {} `{}`", name.to_lowercase(), value))))
+}
 
-        server::start(config, log).await;
-    } else {
-        println!("{}", matches.usage());
+/// Server and API token of commands using the API of a running server.
+fn api_options(matches: &ArgMatches<'_>) -> cli::ApiOptions {
+    cli::ApiOptions {
+        server: parsed(matches, "SERVER").unwrap(),
+        token: matches.value_of("TOKEN").map(String::from),
     }
 }
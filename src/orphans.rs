@@ -0,0 +1,142 @@
+//! Pid files of fuzzing processes, so fuzzers left running by a crashed server are found
+//! and killed by the next server instance.
+
+use std::{
+    ffi::OsString,
+    io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use slog::{debug, error, info, warn, Logger};
+
+use crate::{common, process, resources};
+
+/// Process identity surviving pid reuse, the pid along with the process start time.
+#[derive(Clone, Copy, Debug)]
+struct Process {
+    pid: u32,
+    start: u64,
+}
+
+impl Process {
+    fn of(pid: u32) -> io::Result<Self> {
+        let start = resources::start_time(pid)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no process {}", pid)))?;
+        Ok(Self { pid, start })
+    }
+
+    fn is_alive(&self) -> bool {
+        resources::start_time(self.pid) == Some(self.start)
+    }
+}
+
+/// Pid files of fuzzing processes of a run.
+#[derive(Clone, Debug)]
+pub struct Pids {
+    dir: PathBuf,
+    run: OsString,
+}
+
+impl Pids {
+    /// Pid files of the run `id`, kept in the directory `dir` along with ones of other runs.
+    pub fn new(dir: impl Into<PathBuf>, id: &str) -> Self {
+        Self {
+            dir: dir.into(),
+            run: common::sanitize_path_segment(id),
+        }
+    }
+
+    /// Records the process `pid` fuzzing the target `name`, until the returned pid file is dropped.
+    pub fn track(&self, name: &str, pid: u32) -> io::Result<PidFile> {
+        let process = Process::of(pid)?;
+        let owner = Process::of(std::process::id())?;
+        let mut file = self.run.clone();
+        file.push(".");
+        file.push(common::sanitize_path_segment(name));
+        file.push(".pid");
+        let path = self.dir.join(file);
+        std::fs::create_dir_all(&self.dir)?;
+        std::fs::write(&path, format!("{} {} {} {}\n", process.pid, process.start, owner.pid, owner.start))?;
+        Ok(PidFile { path })
+    }
+}
+
+/// Pid file of a running process, removed when dropped.
+pub struct PidFile {
+    path: PathBuf,
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// The process and the server instance that started it, as recorded in the pid file.
+fn read(path: &Path) -> Option<(Process, Process)> {
+    let content = std::fs::read_to_string(path).ok()?;
+    let fields = content.split_whitespace().map(|f| f.parse().ok()).collect::<Option<Vec<u64>>>()?;
+    match fields.as_slice() {
+        [pid, start, owner_pid, owner_start] => Some((
+            Process { pid: *pid as u32, start: *start },
+            Process { pid: *owner_pid as u32, start: *owner_start },
+        )),
+        _ => None,
+    }
+}
+
+/// Kills process groups of fuzzing processes recorded in pid files in the directory `dir`
+/// by server instances that are no longer running, and removes pid files of exited processes.
+///
+/// Returns pids of the killed processes.
+pub fn kill_orphans(dir: &Path, log: &Logger) -> io::Result<Vec<u32>> {
+    let mut killed = vec![];
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(killed),
+        Err(e) => return Err(e),
+    };
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension() != Some("pid".as_ref()) {
+            continue;
+        }
+        let (process, owner) = match read(&path) {
+            Some(processes) => processes,
+            // can be still being written
+            None => continue,
+        };
+        if !process.is_alive() {
+            debug!(log, "Removing stale pid file"; "file" => path.to_str());
+        } else if !owner.is_alive() {
+            info!(log, "Killing orphaned fuzzing process"; "pid" => process.pid, "file" => path.to_str());
+            // the process leads a group with the fuzzer it started, e.g. honggfuzz run by cargo
+            if process::kill_group(process.pid).is_err() {
+                process::signal_all(&[process.pid], libc::SIGKILL);
+            }
+            killed.push(process.pid);
+        } else {
+            continue;
+        }
+        let _ = std::fs::remove_file(&path);
+    }
+    Ok(killed)
+}
+
+/// Kills orphaned fuzzing processes, logging errors.
+pub fn cleanup(dir: &Path, log: &Logger) {
+    match kill_orphans(dir, log) {
+        Ok(killed) if !killed.is_empty() => warn!(log, "Killed orphaned fuzzing processes"; "count" => killed.len()),
+        Ok(_) => (),
+        Err(e) => error!(log, "Cannot kill orphaned fuzzing processes"; "error" => e.to_string()),
+    }
+}
+
+/// Periodically kills orphaned fuzzing processes.
+pub async fn watch(dir: PathBuf, interval: Duration, log: Logger) {
+    loop {
+        tokio::time::sleep(interval).await;
+        cleanup(&dir, &log);
+    }
+}
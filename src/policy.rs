@@ -0,0 +1,274 @@
+//! Verdict of finished runs, computed by configured rules over their final statistics,
+//! so exit codes, GitHub statuses and check runs, and notifications agree on which runs
+//! fail.
+
+use std::{convert::TryFrom, fmt, str::FromStr, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::runs::RunStatus;
+
+/// Final statistic of a run, the rules are evaluated on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Metric {
+    /// Crashes found by all targets
+    Crashes,
+    /// Change of covered edges of the target losing most of them since the previous run,
+    /// in percents, negative, or 0 if none loses them
+    CoverageDelta,
+    /// Fuzzing projects that failed to build
+    BuildFailures,
+    /// Duration of the run, in seconds
+    Duration,
+}
+
+impl Metric {
+    const ALL: [Metric; 4] = [Metric::Crashes, Metric::CoverageDelta, Metric::BuildFailures, Metric::Duration];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Metric::Crashes => "crashes",
+            Metric::CoverageDelta => "coverage_delta",
+            Metric::BuildFailures => "build_failures",
+            Metric::Duration => "duration",
+        }
+    }
+}
+
+/// Comparison of a metric with the value of a condition.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+}
+
+impl Comparison {
+    // longer operators first, so `<=` isn't taken for `<`
+    const ALL: [Comparison; 6] = [
+        Comparison::LessOrEqual,
+        Comparison::GreaterOrEqual,
+        Comparison::Equal,
+        Comparison::NotEqual,
+        Comparison::Less,
+        Comparison::Greater,
+    ];
+
+    pub fn operator(&self) -> &'static str {
+        match self {
+            Comparison::Less => "<",
+            Comparison::LessOrEqual => "<=",
+            Comparison::Greater => ">",
+            Comparison::GreaterOrEqual => ">=",
+            Comparison::Equal => "==",
+            Comparison::NotEqual => "!=",
+        }
+    }
+
+    fn holds(&self, left: f64, right: f64) -> bool {
+        match self {
+            Comparison::Less => left < right,
+            Comparison::LessOrEqual => left <= right,
+            Comparison::Greater => left > right,
+            Comparison::GreaterOrEqual => left >= right,
+            Comparison::Equal => left == right,
+            Comparison::NotEqual => left != right,
+        }
+    }
+}
+
+/// Condition over a final statistic of a run, like `crashes > 0`.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(try_from = "String")]
+pub struct Condition {
+    pub metric: Metric,
+    pub comparison: Comparison,
+    pub value: f64,
+}
+
+impl Condition {
+    pub fn holds(&self, stats: &RunStats) -> bool {
+        self.comparison.holds(stats.value(self.metric), self.value)
+    }
+}
+
+impl FromStr for Condition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (comparison, (metric, value)) = Comparison::ALL
+            .iter()
+            .find_map(|c| Some((*c, s.split_once(c.operator())?)))
+            .ok_or_else(|| format!("no comparison in condition `{}`, expecting `<metric> <operator> <value>`", s))?;
+        let metric = Metric::ALL.iter().find(|m| m.name() == metric.trim()).copied().ok_or_else(|| {
+            let names = Metric::ALL.iter().map(Metric::name).collect::<Vec<_>>();
+            format!("unknown metric `{}` in condition `{}`, expecting one of {}", metric.trim(), s, names.join(", "))
+        })?;
+        let value = value
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid value `{}` in condition `{}`", value.trim(), s))?;
+        Ok(Self { metric, comparison, value })
+    }
+}
+
+impl TryFrom<String> for Condition {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.metric.name(), self.comparison.operator(), self.value)
+    }
+}
+
+/// Rule failing runs whose final statistics meet its condition.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Rule {
+    pub when: Condition,
+    /// Description of the failure, with `{value}` replaced by the actual value of the
+    /// metric, or the condition with the value if not set
+    pub message: Option<String>,
+}
+
+impl Rule {
+    fn failure(&self, stats: &RunStats) -> String {
+        let value = stats.value(self.when.metric);
+        match &self.message {
+            Some(message) => message.replace("{value}", &value.to_string()),
+            None => format!("{} ({} is {})", self.when, self.when.metric.name(), value),
+        }
+    }
+}
+
+/// Final statistics of a run.
+#[derive(Clone, Debug, Default)]
+pub struct RunStats {
+    pub crashes: u32,
+    pub coverage_delta: f64,
+    pub build_failures: u32,
+    pub duration: Duration,
+}
+
+impl RunStats {
+    pub fn value(&self, metric: Metric) -> f64 {
+        match metric {
+            Metric::Crashes => f64::from(self.crashes),
+            Metric::CoverageDelta => self.coverage_delta,
+            Metric::BuildFailures => f64::from(self.build_failures),
+            Metric::Duration => self.duration.as_secs() as f64,
+        }
+    }
+}
+
+/// Overall verdict of a finished run.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verdict {
+    /// The run completed and no rule fails it
+    Pass,
+    /// The run completed, but a rule fails it
+    Fail,
+    /// The run failed, e.g. its checkout
+    Error,
+    /// The run is cancelled
+    Cancelled,
+}
+
+impl Verdict {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Verdict::Pass => "pass",
+            Verdict::Fail => "fail",
+            Verdict::Error => "error",
+            Verdict::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [Verdict::Pass, Verdict::Fail, Verdict::Error, Verdict::Cancelled]
+            .iter()
+            .find(|v| v.name() == name)
+            .copied()
+    }
+}
+
+/// Verdict of the run finished with the status and statistics, with its description
+/// listing failures of rules.
+pub fn verdict(rules: &[Rule], status: RunStatus, stats: &RunStats) -> (Verdict, String) {
+    match status {
+        RunStatus::Failure => return (Verdict::Error, "Fuzzing failed".to_string()),
+        RunStatus::Cancelled => return (Verdict::Cancelled, "Fuzzing is cancelled".to_string()),
+        RunStatus::Success | RunStatus::Running => (),
+    }
+    let failures = rules
+        .iter()
+        .filter(|rule| rule.when.holds(stats))
+        .map(|rule| rule.failure(stats))
+        .collect::<Vec<_>>();
+    if failures.is_empty() {
+        (Verdict::Pass, "Fuzzing passed the policy".to_string())
+    } else {
+        (Verdict::Fail, failures.join("; "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn condition(s: &str) -> (Metric, Comparison, f64) {
+        let condition = s.parse::<Condition>().unwrap();
+        (condition.metric, condition.comparison, condition.value)
+    }
+
+    #[test]
+    fn parses_longest_operators() {
+        assert_eq!(condition("crashes <= 1"), (Metric::Crashes, Comparison::LessOrEqual, 1.0));
+        assert_eq!(condition("crashes >= 1"), (Metric::Crashes, Comparison::GreaterOrEqual, 1.0));
+        assert_eq!(condition("crashes < 1"), (Metric::Crashes, Comparison::Less, 1.0));
+        assert_eq!(condition("crashes > 1"), (Metric::Crashes, Comparison::Greater, 1.0));
+        assert_eq!(condition("crashes == 1"), (Metric::Crashes, Comparison::Equal, 1.0));
+        assert_eq!(condition("crashes != 1"), (Metric::Crashes, Comparison::NotEqual, 1.0));
+    }
+
+    #[test]
+    fn parses_metrics_and_values() {
+        assert_eq!(condition("coverage_delta<-5.5"), (Metric::CoverageDelta, Comparison::Less, -5.5));
+        assert_eq!(condition(" build_failures  >  0 "), (Metric::BuildFailures, Comparison::Greater, 0.0));
+        assert_eq!(condition("duration >= 3600"), (Metric::Duration, Comparison::GreaterOrEqual, 3600.0));
+        assert_eq!("crashes>=1".parse::<Condition>().unwrap().to_string(), "crashes >= 1");
+    }
+
+    #[test]
+    fn rejects_invalid_conditions() {
+        assert!("crashes".parse::<Condition>().unwrap_err().contains("no comparison"));
+        assert!("crashes = 1".parse::<Condition>().unwrap_err().contains("no comparison"));
+        assert!("hangs > 0".parse::<Condition>().unwrap_err().contains("unknown metric `hangs`"));
+        assert!("crashes > many".parse::<Condition>().unwrap_err().contains("invalid value `many`"));
+        assert!("crashes >= =1".parse::<Condition>().unwrap_err().contains("invalid value `=1`"));
+    }
+
+    #[test]
+    fn verdict_lists_failed_rules() {
+        let rules = vec![
+            Rule { when: "crashes > 0".parse().unwrap(), message: Some("{value} crashes".to_string()) },
+            Rule { when: "coverage_delta < -5".parse().unwrap(), message: None },
+        ];
+        let stats = RunStats { crashes: 2, coverage_delta: -10.0, ..RunStats::default() };
+        assert_eq!(
+            verdict(&rules, RunStatus::Success, &stats),
+            (Verdict::Fail, "2 crashes; coverage_delta < -5 (coverage_delta is -10)".to_string())
+        );
+        assert_eq!(verdict(&rules, RunStatus::Success, &RunStats::default()).0, Verdict::Pass);
+        assert_eq!(verdict(&rules, RunStatus::Failure, &stats).0, Verdict::Error);
+        assert_eq!(verdict(&rules, RunStatus::Cancelled, &stats).0, Verdict::Cancelled);
+    }
+}
@@ -0,0 +1,94 @@
+use std::{io, process::Output, process::Stdio, time::Duration};
+
+use tokio::process::Command;
+
+use crate::{
+    cancel::Scope,
+    resources::{Sampler, Usage, SAMPLE_INTERVAL},
+};
+
+/// Makes the command to be spawned as a leader of a new process group, so the
+/// whole process tree it creates can be signalled at once.
+pub fn new_process_group(command: &mut Command) -> &mut Command {
+    unsafe {
+        command.pre_exec(|| {
+            if libc::setpgid(0, 0) == 0 {
+                Ok(())
+            } else {
+                Err(io::Error::last_os_error())
+            }
+        })
+    }
+}
+
+/// Sends the signal to the process group led by the process `pid`.
+pub fn signal_group(pid: u32, signal: libc::c_int) -> io::Result<()> {
+    if unsafe { libc::killpg(pid as libc::pid_t, signal) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Sends the signal to each of the processes, ignoring ones that have already exited.
+pub fn signal_all(pids: &[u32], signal: libc::c_int) {
+    for pid in pids {
+        unsafe { libc::kill(*pid as libc::pid_t, signal) };
+    }
+}
+
+/// Kills the process group led by the process `pid`.
+pub fn kill_group(pid: u32) -> io::Result<()> {
+    signal_group(pid, libc::SIGKILL)
+}
+
+/// Runs the command in a new process group collecting its output.
+///
+/// If the command doesn't complete within the `timeout`, the whole process
+/// group is killed and [io::ErrorKind::TimedOut] error is returned. The same
+/// happens with [io::ErrorKind::Interrupted] error if the `cancel` scope is cancelled.
+///
+/// The peak resource usage of the process tree is sampled while the command runs.
+pub async fn output_with_usage(command: &mut Command, timeout: Duration, cancel: &Scope) -> io::Result<(Output, Usage)> {
+    let child = new_process_group(command)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    let pid = child.id();
+    let mut sampler = pid.map(Sampler::new);
+    if let Some(sampler) = &mut sampler {
+        // the baseline for CPU usage
+        let _ = sampler.sample();
+    }
+    let mut peak = Usage::default();
+    let output = child.wait_with_output();
+    tokio::pin!(output);
+    let deadline = tokio::time::sleep(timeout);
+    tokio::pin!(deadline);
+    loop {
+        tokio::select! {
+            output = &mut output => return Ok((output?, peak)),
+            _ = tokio::time::sleep(SAMPLE_INTERVAL) => {
+                if let Some(usage) = sampler.as_mut().and_then(|s| s.sample().ok()) {
+                    peak = peak.max(usage);
+                }
+            }
+            _ = &mut deadline => {
+                if let Some(pid) = pid {
+                    let _ = kill_group(pid);
+                }
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("timed out after {}s", timeout.as_secs()),
+                ));
+            }
+            _ = cancel.cancelled() => {
+                if let Some(pid) = pid {
+                    let _ = kill_group(pid);
+                }
+                return Err(io::Error::new(io::ErrorKind::Interrupted, "cancelled"));
+            }
+        }
+    }
+}
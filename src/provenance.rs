@@ -0,0 +1,218 @@
+//! Provenance of fuzzing runs: toolchain versions, checked out commits and the effective
+//! build environment, recorded in run manifests so results can be reproduced later.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use slog::{debug, Logger};
+use tokio::process::Command;
+use url::Url;
+
+use crate::{
+    checkout,
+    config::{Config, Engine},
+    hfuzz,
+};
+
+/// Toolchain, sources and environment a run is built and fuzzed with.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Provenance {
+    /// Version of the fuzzing CI
+    pub fuzz_ci: String,
+    /// Url of the checked out repository, without credentials
+    #[serde(default)]
+    pub repository: Option<String>,
+    /// Checkout directory, that paths in the environment are within
+    #[serde(default)]
+    pub checkout: Option<String>,
+    /// Commit of the checkout, i.e. of the repository with fuzzing projects
+    pub checkout_commit: Option<String>,
+    /// Commit of the fuzzed project, different from the checkout one if `code_path`
+    /// is a separate repository
+    pub project_commit: Option<String>,
+    /// `rustc --version` in the checkout, reflecting its toolchain file
+    pub rustc: Option<String>,
+    pub cargo: Option<String>,
+    pub cargo_hfuzz: Option<String>,
+    pub kcov: Option<String>,
+    /// Environment of builds and fuzzers of all projects
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    #[serde(default)]
+    pub projects: BTreeMap<String, ProjectProvenance>,
+}
+
+/// Build and run parameters of a fuzzing project.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ProjectProvenance {
+    #[serde(default)]
+    pub engine: Engine,
+    /// Directory of the project, relative to the checkout
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Version of the `honggfuzz` crate fuzz targets are built with, from `Cargo.lock`
+    pub honggfuzz: Option<String>,
+    /// Honggfuzz run arguments, besides ones set for each target run
+    pub run_args: Option<String>,
+    /// Binaries of fuzz targets, by the target name
+    #[serde(default)]
+    pub targets: BTreeMap<String, String>,
+    /// Additional `cargo hfuzz` arguments of build variants, by the variant name
+    #[serde(default)]
+    pub variants: BTreeMap<String, Vec<String>>,
+    /// Environment of builds and fuzzers of the project, added to the run one
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+}
+
+/// Environment as recorded, with values of variables named like secrets redacted, as
+/// manifests are served with reports.
+fn recorded_env<'a>(env: impl IntoIterator<Item = (&'a String, &'a String)>) -> BTreeMap<String, String> {
+    env.into_iter()
+        .map(|(name, value)| {
+            let upper = name.to_uppercase();
+            let secret = ["TOKEN", "SECRET", "PASSWORD", "KEY"].iter().any(|word| upper.contains(word));
+            (name.clone(), if secret { "<redacted>".to_string() } else { value.clone() })
+        })
+        .collect()
+}
+
+/// Url of the repository with credentials removed, as provenance is served with reports.
+fn recorded_url(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(mut url) => {
+            let _ = url.set_username("");
+            let _ = url.set_password(None);
+            url.to_string()
+        }
+        // local paths and scp-like urls
+        Err(_) => url.to_string(),
+    }
+}
+
+/// First line of the output of the version command run in `dir`, if it succeeds.
+async fn version(
+    program: &str,
+    mut command: Command,
+    dir: &Path,
+    env: &HashMap<String, String>,
+    log: &Logger,
+) -> Option<String> {
+    let output = match command.current_dir(dir).envs(env).output().await {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => {
+            debug!(log, "Cannot get version"; "program" => program, "status" => output.status.to_string());
+            return None;
+        }
+        Err(e) => {
+            debug!(log, "Cannot get version"; "program" => program, "error" => e.to_string());
+            return None;
+        }
+    };
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout.lines().next().map(|line| line.trim().to_string()).filter(|line| !line.is_empty())
+}
+
+/// Command printing the version of the program.
+fn version_command(program: &str) -> Command {
+    let mut command = Command::new(program);
+    command.arg("--version");
+    command
+}
+
+/// Version of the `honggfuzz` package in the `Cargo.lock` of the project, looked up from
+/// the project directory up to the checkout.
+async fn honggfuzz_crate(project: &Path, checkout: &Path) -> Option<String> {
+    #[derive(Deserialize)]
+    struct Lock {
+        #[serde(default)]
+        package: Vec<Package>,
+    }
+    #[derive(Deserialize)]
+    struct Package {
+        name: String,
+        version: String,
+    }
+    for dir in project.ancestors().take_while(|dir| dir.starts_with(checkout)) {
+        if let Ok(lock) = tokio::fs::read(dir.join("Cargo.lock")).await {
+            let lock = toml::from_slice::<Lock>(&lock).ok()?;
+            return lock.package.into_iter().find(|p| p.name == "honggfuzz").map(|p| p.version);
+        }
+    }
+    None
+}
+
+async fn commit(dir: &Path, log: &Logger) -> Option<String> {
+    match checkout::head(dir).await {
+        Ok(commit) => Some(commit),
+        Err(e) => {
+            debug!(log, "Cannot get the checked out commit"; "dir" => dir.to_str(), "error" => e.to_string());
+            None
+        }
+    }
+}
+
+impl Provenance {
+    /// Collects the provenance of the run in the checkout of the repository `url`, with the
+    /// run environment `env` and fuzzing projects in `project_dirs`.
+    pub async fn collect(
+        config: &Config,
+        url: &str,
+        checkout: &Path,
+        env: &HashMap<String, String>,
+        project_dirs: &HashMap<String, PathBuf>,
+        log: &Logger,
+    ) -> Self {
+        let mut cargo_hfuzz = hfuzz::cargo_hfuzz(config.honggfuzz.cargo_hfuzz.as_deref());
+        cargo_hfuzz.arg("version");
+        let cargo_hfuzz = version("cargo-hfuzz", cargo_hfuzz, checkout, env, log).await;
+        let kcov = match config.kcov {
+            Some(_) => version("kcov", version_command("kcov"), checkout, env, log).await,
+            None => None,
+        };
+        let mut projects = BTreeMap::new();
+        for (name, conf) in &config.targets {
+            let dir = match project_dirs.get(name) {
+                Some(dir) => dir,
+                None => continue,
+            };
+            let variants = conf
+                .variants()
+                .iter()
+                .map(|variant| {
+                    let name = if variant.name.is_empty() { "default" } else { &variant.name };
+                    (name.to_string(), variant.cargo_args())
+                })
+                .collect();
+            let honggfuzz = conf.engine == Engine::Honggfuzz;
+            projects.insert(
+                name.clone(),
+                ProjectProvenance {
+                    engine: conf.engine,
+                    path: dir.strip_prefix(checkout).ok().map(|path| path.to_string_lossy().into_owned()),
+                    targets: conf.targets.iter().map(|target| (target.clone(), conf.binary_name(target))).collect(),
+                    honggfuzz: if honggfuzz { honggfuzz_crate(dir, checkout).await } else { None },
+                    run_args: honggfuzz.then(|| conf.hfuzz_config(&config.honggfuzz).run_args),
+                    variants,
+                    env: recorded_env(&conf.build_env(&config.build, dir)),
+                },
+            );
+        }
+        Self {
+            fuzz_ci: env!("CARGO_PKG_VERSION").to_string(),
+            repository: Some(recorded_url(url)),
+            checkout: Some(checkout.to_string_lossy().into_owned()),
+            checkout_commit: commit(checkout, log).await,
+            project_commit: commit(&config.code_dir(checkout), log).await,
+            rustc: version("rustc", version_command("rustc"), checkout, env, log).await,
+            cargo: version("cargo", version_command("cargo"), checkout, env, log).await,
+            cargo_hfuzz,
+            kcov,
+            env: recorded_env(env),
+            projects,
+        }
+    }
+}
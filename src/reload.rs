@@ -0,0 +1,88 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc,
+    time::Duration,
+};
+
+use notify::{DebouncedEvent, RecursiveMode, Watcher};
+use slog::{debug, error, info, Logger};
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    sync::{mpsc::unbounded_channel, watch},
+};
+
+use crate::{config::Config, server::Overrides};
+
+/// Reloads the configuration file on `SIGHUP` or when the file changes.
+///
+/// Returns the receiver of the most recent valid configuration. Invalid
+/// configuration files are reported and ignored.
+pub fn watch(file: PathBuf, config: Config, overrides: Overrides, log: Logger) -> watch::Receiver<Config> {
+    let (tx, rx) = watch::channel(config);
+    let (changed_tx, mut changed) = unbounded_channel();
+
+    {
+        let file = file.clone();
+        let log = log.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = watch_file(&file, || changed_tx.send(()).is_ok()) {
+                error!(log, "Cannot watch configuration file"; "error" => e.to_string());
+            }
+        });
+    }
+
+    tokio::spawn(async move {
+        let mut hangup = match signal(SignalKind::hangup()) {
+            Ok(hangup) => Some(hangup),
+            Err(e) => {
+                error!(log, "Cannot handle SIGHUP"; "error" => e.to_string());
+                None
+            }
+        };
+        loop {
+            tokio::select! {
+                Some(_) = async { hangup.as_mut()?.recv().await } => debug!(log, "SIGHUP received"),
+                Some(_) = changed.recv() => debug!(log, "Configuration file changed"),
+                else => break,
+            }
+            let mut config = match Config::read(&file) {
+                Ok(config) => config,
+                Err(e) => {
+                    error!(log, "Cannot reload configuration, keeping the current one"; "error" => e.to_string());
+                    continue;
+                }
+            };
+            overrides.apply(&mut config);
+            // the server keeps listening on the address it was started with
+            config.address = tx.borrow().address.clone();
+            info!(log, "Configuration reloaded"; "file" => file.to_string_lossy().into_owned());
+            if tx.send(config).is_err() {
+                break;
+            }
+        }
+    });
+
+    rx
+}
+
+/// Calls `changed` each time the file is modified, until it returns `false`.
+///
+/// The parent directory is watched, so the file being replaced (e.g. by an
+/// editor) is noticed too.
+fn watch_file(file: &Path, mut changed: impl FnMut() -> bool) -> notify::Result<()> {
+    let file = file.canonicalize()?;
+    let dir = file.parent().unwrap_or_else(|| Path::new("/"));
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::watcher(tx, Duration::from_secs(1))?;
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+    for event in rx {
+        let path = match event {
+            DebouncedEvent::Write(path) | DebouncedEvent::Create(path) | DebouncedEvent::Rename(_, path) => path,
+            _ => continue,
+        };
+        if path == file && !changed() {
+            break;
+        }
+    }
+    Ok(())
+}
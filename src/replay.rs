@@ -0,0 +1,94 @@
+use std::{
+    path::{Path, PathBuf},
+    process::{ExitStatus, Stdio},
+};
+
+use crate::error::{Error, ResultExt};
+use slog::{debug, info, Logger};
+use tokio::process::Command;
+
+use crate::{
+    build::Builder,
+    config::{Config, Engine},
+    server::{project_dir, run_env},
+    workspace::Metadata,
+};
+
+/// Runs the fuzz target with the input, e.g. a crash reported by the CI, printing the target output.
+///
+/// The target is referred by its id used in reports, i.e. `target@variant` for
+/// non-default variants. Unless `build` is `false`, the target is built first.
+pub async fn replay(
+    config: &Config,
+    checkout: &Path,
+    project: &str,
+    target: &str,
+    input: &Path,
+    build: bool,
+    log: &Logger,
+) -> Result<ExitStatus, Error> {
+    let conf = config
+        .targets
+        .get(project)
+        .ok_or_else(|| Error::other(format!("unknown fuzzing project `{}`", project)))?;
+    if conf.engine != Engine::Honggfuzz {
+        return Err(Error::other(format!("inputs of `{}` can only be replayed with honggfuzz engine", project)));
+    }
+    let (name, variant_name) = target.split_once('@').unwrap_or((target, ""));
+    if !conf.targets.iter().any(|t| t == name) {
+        return Err(Error::other(format!("unknown target `{}` of `{}`", name, project)));
+    }
+    let variant = conf
+        .variants()
+        .into_iter()
+        .find(|v| v.name == variant_name)
+        .ok_or_else(|| Error::other(format!("unknown variant `{}` of `{}`", variant_name, project)))?;
+    let input = input.canonicalize()?;
+    let checkout = &checkout.canonicalize()?;
+
+    let metadata = Metadata::read_opt(checkout, log).await?;
+    let dir = project_dir(checkout, project, conf, &metadata);
+    let mut env = run_env(config, checkout, log);
+    env.extend(conf.build_env(&config.build, &dir));
+
+    if build {
+        info!(log, "Building fuzz targets"; "dir" => dir.to_str());
+        Builder::new(None, &config.build, log.clone())
+            .with_cargo_hfuzz(config.honggfuzz.cargo_hfuzz.clone())
+            .build(&dir, &variant, &env)
+            .await?;
+    }
+    let variant_env = variant.env(&env);
+    env.extend(variant_env);
+    let target_dir = dir.join(env.get("CARGO_TARGET_DIR").map(String::as_str).unwrap_or("hfuzz_target"));
+    let binary = find_binary(&target_dir, &conf.binary_name(name))?;
+
+    // outside of honggfuzz, fuzz targets read the input from the standard input
+    debug!(log, "Running target"; "binary" => binary.to_str(), "input" => input.to_str());
+    let mut command = Command::new(&binary);
+    command
+        .envs(&env)
+        .current_dir(&dir)
+        .stdin(Stdio::from(std::fs::File::open(&input)?));
+    if !env.contains_key("RUST_BACKTRACE") {
+        command.env("RUST_BACKTRACE", "1");
+    }
+    Ok(command.status().await?)
+}
+
+/// Locates the target binary built by `cargo hfuzz` within the target directory.
+fn find_binary(target_dir: &Path, name: &str) -> Result<PathBuf, Error> {
+    let read_dir = std::fs::read_dir(target_dir)
+        .context(|| format!("cannot read {}", target_dir.to_string_lossy()))?;
+    for entry in read_dir {
+        let binary = entry?.path().join("release").join(name);
+        if binary.is_file() {
+            return Ok(binary);
+        }
+    }
+    Err(Error::other(format!(
+        "binary for `{}` is not found in {}, build it first",
+        name,
+        target_dir.to_string_lossy()
+    )))
+}
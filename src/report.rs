@@ -1,12 +1,13 @@
 use std::{
+    cmp::Reverse,
     collections::HashMap,
-    ffi::OsStr,
+    ffi::{OsStr, OsString},
     fmt::Write,
     path::{Path, PathBuf},
-    time::SystemTime,
+    time::{Duration, SystemTime},
 };
 
-use failure::ResultExt;
+use chrono::{DateTime, Utc};
 use handlebars::Handlebars;
 use percent_encoding::{percent_encode, NON_ALPHANUMERIC};
 use reqwest::Url;
@@ -16,13 +17,21 @@ use tokio::{
     io::{AsyncReadExt, AsyncWriteExt},
 };
 
-use crate::error::Error;
+use crate::{bisect::Bisection, config, error::{output_tail, Error, Result}, provenance::Provenance, repro, resources::Resources, runs::Trigger, state::TargetRun};
 
 #[derive(Clone, Copy, derive_new::new, Default, serde::Serialize, serde::Deserialize)]
 pub struct TargetStatus {
     pub total: u32,
     pub covered: u32,
     pub errors: u32,
+    /// Difference between the largest and the smallest measured total
+    #[serde(default)]
+    #[new(default)]
+    pub spread: u32,
+    /// Whether measured totals vary too much to compare coverage with other builds
+    #[serde(default)]
+    #[new(default)]
+    pub unreliable: bool,
 }
 
 #[derive(Clone, Copy, derive_new::new, Default, serde::Serialize, serde::Deserialize)]
@@ -79,6 +88,107 @@ struct TargetStatusDiff {
     prev_run: Option<TargetStatus>,
     /// delta with previous run coverage
     delta_run: Option<TargetStatusDelta>,
+    /// whether coverage can't be compared with the previous run, as measured totals vary
+    #[new(default)]
+    unreliable: bool,
+    /// owner, description and harness source of the target, if configured
+    #[new(default)]
+    #[serde(default)]
+    metadata: Option<config::TargetMetadata>,
+}
+
+impl TargetStatusDiff {
+    /// Crashes found since the previous report, or all of them in the first one.
+    fn new_errors(&self) -> u32 {
+        match (&self.prev, &self.delta) {
+            (Some(_), Some(delta)) => delta.errors.max(0) as u32,
+            _ => self.curr.errors,
+        }
+    }
+
+    /// Summary line of the coverage change since the previous report, or since the
+    /// previous run before the first report, with its magnitude.
+    fn coverage_change(&self) -> Option<(i32, String)> {
+        if let (Some(_), Some(delta)) = (self.prev, self.delta) {
+            if delta.covered != 0 {
+                return Some((
+                    delta.covered.abs(),
+                    format!("*{:+}* {}: edges covered since previous report", delta.covered, self.name),
+                ));
+            }
+        } else if let (Some(_), Some(delta), false) = (self.prev_run, self.delta_run, self.unreliable) {
+            if (delta.covered, delta.total) != (0, 0) {
+                return Some((
+                    delta.covered.abs().max(delta.total.abs()),
+                    format!(
+                        "*{}/{}* {}: covered/total number of edges changed since previous run",
+                        delta.covered, delta.total, self.name
+                    ),
+                ));
+            }
+        }
+        None
+    }
+}
+
+/// Summary lines of the diff, listing new crashes first, then the largest coverage
+/// gains and losses, with the rest of targets counted.
+fn summarize(diff: &[TargetStatusDiff]) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut crashed = diff
+        .iter()
+        .map(|d| (d.new_errors(), &d.name))
+        .filter(|(errors, _)| *errors > 0)
+        .collect::<Vec<_>>();
+    crashed.sort_by_key(|(errors, _)| Reverse(*errors));
+    for (errors, name) in crashed {
+        lines.push(format!("*{}* {}: new crashes since previous report", errors, name));
+    }
+    let mut changes = diff.iter().filter_map(TargetStatusDiff::coverage_change).collect::<Vec<_>>();
+    changes.sort_by_key(|(magnitude, _)| Reverse(*magnitude));
+    let (changed, unchanged) = (changes.len(), diff.len() - changes.len());
+    lines.extend(changes.into_iter().take(SUMMARY_HIGHLIGHTS).map(|(_, line)| line));
+    if changed > SUMMARY_HIGHLIGHTS {
+        lines.push(format!("{} other targets changed", changed - SUMMARY_HIGHLIGHTS));
+    }
+    if changed == 0 {
+        lines.push("No changed detected".to_string());
+    } else if unchanged > 0 {
+        lines.push(format!("{} other targets unchanged", unchanged));
+    }
+    lines
+}
+
+/// Markdown table of coverage and crashes of targets with changes of covered edges since the
+/// previous report and the start of the run, and of covered/total edges since the previous run.
+fn markdown(diff: &[TargetStatusDiff]) -> String {
+    fn change(delta: &Option<TargetStatusDelta>) -> String {
+        match delta {
+            Some(delta) => format!("{:+}", delta.covered),
+            None => "—".to_string(),
+        }
+    }
+    let mut table = String::from(
+        "| Target | Covered | Total | Crashes | Since previous report | Since start | Since previous run |\n\
+         |---|---:|---:|---:|---:|---:|---:|\n",
+    );
+    for diff in diff {
+        table += &format!(
+            "| `{}` | {} | {} | {} | {} | {} | {} |\n",
+            diff.name,
+            diff.curr.covered,
+            diff.curr.total,
+            diff.curr.errors,
+            change(&diff.delta),
+            change(&diff.delta_init),
+            match diff.delta_run {
+                Some(_) if diff.unreliable => "unreliable".to_string(),
+                Some(delta) => format!("{:+}/{:+}", delta.covered, delta.total),
+                None => "—".to_string(),
+            },
+        );
+    }
+    table
 }
 
 impl From<(TargetStatus, TargetStatus)> for TargetStatusDelta {
@@ -113,6 +223,7 @@ impl
         let delta = prev.map(|s| (curr, s).into());
         let delta_init = init.map(|s| (curr, s).into());
         let delta_run = prev_run.map(|s| (curr, s).into());
+        let unreliable = curr.unreliable || prev_run.map_or(false, |s| s.unreliable);
         Self {
             name,
             curr,
@@ -122,12 +233,33 @@ impl
             delta_init,
             prev_run,
             delta_run,
+            unreliable,
+            metadata: None,
         }
     }
 }
 
+/// Summary of a report update.
+pub struct Summary {
+    /// Lines of the most notable changes, for feedback messages
+    pub text: String,
+    /// Markdown table of all targets, for GitHub check runs
+    pub table: String,
+}
+
+/// Status of fuzz targets, by target.
 pub type FuzzingStatus = HashMap<String, TargetStatus>;
 
+/// Change of the initial status, the baseline fuzz targets coverage is compared with.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Baseline {
+    /// Removes the baseline, so the status of the next report becomes one
+    Reset,
+    /// Uses the most recently reported status as the baseline
+    Current,
+}
+
 use static_init::dynamic;
 
 #[dynamic]
@@ -135,6 +267,8 @@ static HANDLEBARS: Handlebars<'static> = {
     let mut hb = Handlebars::new();
     hb.register_template_string("report", REPORT)
         .expect("error in template");
+    hb.register_template_string("target", TARGET)
+        .expect("error in template");
     hb
 };
 
@@ -149,6 +283,18 @@ const REPORT: &str = r#"
 
 <h1>Honggfuzz Coverage Report</h1>
 
+{{#if trigger}}
+<p>Triggered by: {{trigger}}</p>
+{{/if}}
+
+{{#if stale}}
+<p class="regression"><b>This report is stale:</b> {{stale}}. Later runs are not compared with it.</p>
+{{/if}}
+
+{{#each bisections}}
+<p class="regression"><b>Coverage regression:</b> {{this}}</p>
+{{/each}}
+
 This table shows each fuzzing target with covered/total edges as reported by Honggfuzz,
 covered edges and their increment with the first and previous reports (to see if fuzzing
 discovers new coverage) and coverage information for the previous run on the same branch
@@ -157,7 +303,8 @@ and difference for covered/total edges.
 <p>
 
 Note that edge-based coverage might be slightly different from build to build, so both
-covered and total number of edges may vary.
+covered and total number of edges may vary. Targets whose total number of edges varies
+between measurements too much aren't compared with the previous run.
 
 <p>
 
@@ -172,9 +319,9 @@ covered and total number of edges may vary.
       <th>Coverage from previous run</th>
       <th>Delta with previous run</th>
     </tr>
-    {{#each this}}
+    {{#each targets}}
     <tr>
-      <td>{{name}}</td>
+      <td><a href="targets/{{name}}.html">{{name}}</a>{{#with metadata}}{{#if description}}<br><small>{{description}}</small>{{/if}}{{#if owner}}<br><small>Owner: {{owner}}</small>{{/if}}{{#if source}}<br><small><a href="{{source}}">Harness source</a></small>{{/if}}{{/with}}</td>
       <td>{{curr.covered}}/{{curr.total}}</td>
       {{#if prev}}
       <td class="{{delta.trend}}">{{prev.covered}}</td>
@@ -190,6 +337,10 @@ covered and total number of edges may vary.
       <td>N/A</td>
       <td>N/A</td>
       {{/if}}
+      {{#if unreliable}}
+      <td class="unreliable">{{#if prev_run}}{{prev_run.covered}}/{{prev_run.total}}{{else}}N/A{{/if}}</td>
+      <td class="unreliable">unreliable (total spread {{curr.spread}})</td>
+      {{else}}
       {{#if prev_run}}
       <td class="{{delta_run.trend}}">{{prev_run.covered}}/{{prev_run.total}}</td>
       <td class="{{delta_run.trend}}">{{delta_run.covered}}/{{delta_run.total}}</td>
@@ -197,9 +348,110 @@ covered and total number of edges may vary.
       <td>N/A</td>
       <td>N/A</td>
       {{/if}}
+      {{/if}}
     </tr>
     {{/each}}
   </table>
+
+<p>
+
+Updated at {{updated}}.
+
+  </body>
+</html>
+"#;
+
+/// Coverage of a target at a point of the run.
+#[derive(serde::Serialize)]
+struct HistoryPoint {
+    time: String,
+    #[serde(flatten)]
+    status: TargetStatus,
+}
+
+/// Page of a single target of the run.
+#[derive(serde::Serialize)]
+struct TargetPage {
+    name: String,
+    /// Owner, description and harness source of the target, if configured
+    metadata: Option<config::TargetMetadata>,
+    history: Vec<HistoryPoint>,
+    crashes: Vec<Crash>,
+    /// Kcov page of the target source, relative to the run report directory
+    kcov: Option<String>,
+    updated: String,
+}
+
+/// Crashing input of a target.
+#[derive(serde::Serialize)]
+struct Crash {
+    /// Input file, relative to the run report directory
+    input: String,
+    /// Script reproducing the crash, relative to the run report directory
+    repro: Option<String>,
+}
+
+const TARGET: &str = r#"
+<html>
+<head>
+<link rel="stylesheet" type="text/css" href="/styles/hfuzz.css"/>
+</head>
+<body>
+
+<h1>Fuzzing target {{name}}</h1>
+
+<p><a href="../index.html">All targets</a></p>
+
+{{#with metadata}}
+{{#if description}}<p>{{description}}</p>{{/if}}
+{{#if owner}}<p>Owner: {{owner}}</p>{{/if}}
+{{#if source}}<p><a href="{{source}}">Harness source</a></p>{{/if}}
+{{/with}}
+
+<h2>Edge history</h2>
+
+  <table>
+    <tr>
+      <th>Time</th>
+      <th>Covered edges</th>
+      <th>Total edges</th>
+      <th>Crashes</th>
+    </tr>
+    {{#each history}}
+    <tr>
+      <td>{{time}}</td>
+      <td>{{covered}}</td>
+      <td>{{total}}</td>
+      <td>{{errors}}</td>
+    </tr>
+    {{/each}}
+  </table>
+
+<h2>Crashes</h2>
+
+{{#if crashes}}
+<ul>
+  {{#each crashes}}
+  <li><a href="../../{{input}}">{{input}}</a>{{#if repro}} (<a href="../../{{repro}}" download>reproduction script</a>){{/if}}</li>
+  {{/each}}
+</ul>
+{{else}}
+<p>No crashes are found.</p>
+{{/if}}
+
+<h2>Coverage of the target source</h2>
+
+{{#if kcov}}
+<p><a href="../../{{kcov}}">Open in kcov report</a></p>
+<iframe src="../../{{kcov}}" width="100%" height="800"></iframe>
+{{else}}
+<p>No kcov report is found for the target source.</p>
+{{/if}}
+
+<p>
+
+Updated at {{updated}}.
+
   </body>
 </html>
 "#;
@@ -207,19 +459,90 @@ covered and total number of edges may vary.
 const CURR_STATUS_FILE: &str = "hfuzz-report/hfuzz-status.toml";
 const INIT_STATUS_FILE: &str = "hfuzz-report/hfuzz-init-status.toml";
 const REPORT_FILE: &str = "hfuzz-report/index.html";
+const RESOURCES_FILE: &str = "hfuzz-report/resources.toml";
+const HISTORY_FILE: &str = "hfuzz-report/history.toml";
+const STALE_FILE: &str = "hfuzz-report/stale.toml";
+const BASELINES_DIR: &str = "hfuzz-report/baselines";
+const MANIFEST_FILE: &str = "hfuzz-report/run.toml";
+const BISECTIONS_FILE: &str = "hfuzz-report/bisections.toml";
+const COVERAGE_FILE: &str = "hfuzz-report/coverage.csv";
+const CRASHES_FILE: &str = "hfuzz-report/crashes.toml";
+const TARGETS_DIR: &str = "hfuzz-report/targets";
+/// Number of coverage changes listed in progress summaries, the rest are counted
+const SUMMARY_HIGHLIGHTS: usize = 5;
+
+/// Run of the report, ordered among runs of the same branch by its sequence number,
+/// so the previous run is found regardless of overlapping runs or skewed clocks.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Manifest {
+    sequence: u64,
+    /// Fuzzed commit, if the checkout is a git repository
+    #[serde(default)]
+    commit: Option<String>,
+    /// What started the run
+    #[serde(default)]
+    trigger: Option<Trigger>,
+    /// Toolchain, commits and environment of the run, once it is checked out
+    #[serde(default)]
+    provenance: Option<Provenance>,
+    /// Owners, descriptions and harness sources of fuzzed targets
+    #[serde(default)]
+    targets: HashMap<String, config::TargetMetadata>,
+}
+
+/// Commits found to lose coverage during the run.
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+struct Bisections {
+    entries: Vec<Bisection>,
+}
+
+/// Mark of a report whose run is invalidated, e.g. by a force-push amending its history.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Stale {
+    reason: String,
+}
 
+/// Statuses reported during the run.
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+struct History {
+    entries: Vec<HistoryEntry>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct HistoryEntry {
+    /// Unix time the status is reported at
+    time: i64,
+    status: FuzzingStatus,
+}
+
+/// Crashes found by a target, as summarized in report archives.
+#[derive(serde::Serialize)]
+struct CrashSummary {
+    crashes: u32,
+    /// Crash inputs, relative to the report directory
+    inputs: Vec<String>,
+}
+
+/// Coverage report of a fuzzing run.
 pub struct Report {
     reports_dir: PathBuf,
     reports_url: Option<Url>,
     previous: Option<FuzzingStatus>,
+    /// Fuzzed commit of the previous run
+    previous_commit: Option<String>,
+    time: config::Time,
     log: Logger,
 }
 
 impl Report {
+    /// Report of the run in `current_path`. A new report gets the `sequence` number of its
+    /// run, ordering it among runs of the branch, or 0 if it is not numbered.
     pub async fn new<'a>(
         reports_dir: &'a Path,
         reports_url: &'a Option<Url>,
         current_path: &'a Path,
+        sequence: Option<u64>,
+        time: &'a config::Time,
         log: Logger,
     ) -> Result<Self, Error> {
         let reports_dir = reports_dir.join(&current_path);
@@ -229,32 +552,38 @@ impl Report {
             reports_dir.to_string_lossy()
         );
 
+        // a new run keeps its number, existing ones are ordered by the numbers they got
+        if !reports_dir.join(MANIFEST_FILE).exists() && !reports_dir.join(CURR_STATUS_FILE).exists() {
+            let sequence = sequence.unwrap_or(0);
+            let manifest_file = reports_dir.join(MANIFEST_FILE);
+            Self::save(&toml::to_vec(&Manifest { sequence, commit: None, trigger: None, provenance: None, targets: HashMap::new() })?, &manifest_file)
+                .await
+                .map_err(Self::file_error(&manifest_file))?;
+        }
         let parent = reports_dir.parent();
         let previous = if let Some(parent) = parent {
             Self::find_previous(&parent, &reports_dir, &log).await?
         } else {
             None
         };
-        let previous = if let Some(previous) = previous {
-            Self::load(&previous.join(CURR_STATUS_FILE)).await?
+        let (previous, previous_commit) = if let Some(previous) = previous {
+            let commit = Self::manifest(&previous).await?.and_then(|manifest| manifest.commit);
+            (Self::load(&previous.join(CURR_STATUS_FILE)).await?, commit)
         } else {
-            None
+            (None, None)
         };
 
-        let reports_url = if let Some(reports_url) = reports_url {
-            let mut reports_url = reports_url.clone();
-            for segment in current_path {
-                reports_url = reports_url.join(&(Self::escape_segment(segment) + "/"))?
-            }
-            Some(reports_url)
-        } else {
-            None
+        let reports_url = match reports_url {
+            Some(reports_url) => Some(Self::run_url(reports_url, current_path)?),
+            None => None,
         };
 
         Ok(Self {
             reports_dir,
             reports_url,
             previous,
+            previous_commit,
+            time: time.clone(),
             log,
         })
     }
@@ -281,25 +610,165 @@ impl Report {
             Ok(r) => r,
             Err(_) => return Ok(None),
         };
-        let mut latest: Option<(PathBuf, SystemTime)> = None;
+        // when re-rendering an existing report, only earlier runs are considered
+        let current_order = Self::order(current.as_ref()).await.ok();
+        let mut latest: Option<(PathBuf, (u64, OsString))> = None;
         while let Some(entry) = read_dir.next_entry().await? {
             if entry.file_type().await?.is_dir()
                 && entry.path() != current.as_ref()
                 && entry.path().join(CURR_STATUS_FILE).exists()
+                && !entry.path().join(STALE_FILE).exists()
             {
-                let (path, created) = (entry.path(), entry.metadata().await?.created()?);
+                let path = entry.path();
+                let order = Self::order(&path).await?;
+                if matches!(&current_order, Some(current) if &order > current) {
+                    continue;
+                }
                 if let Some(ref latest) = latest {
-                    if latest.1 > created {
+                    if latest.1 > order {
                         continue;
                     }
                 }
-                latest = Some((path, created));
+                latest = Some((path, order));
             }
         }
         trace!(log, "found {:?}", latest);
         Ok(latest.map(|o| o.0))
     }
 
+    /// Order of the report among reports of the branch: its run sequence number, then its
+    /// directory name. Reports created before runs were numbered have sequence number 0.
+    async fn order(dir: &Path) -> Result<(u64, OsString), Error> {
+        let sequence = Self::manifest(dir).await?.map_or(0, |manifest| manifest.sequence);
+        Ok((sequence, dir.file_name().unwrap_or_default().to_os_string()))
+    }
+
+    async fn manifest(dir: &Path) -> Result<Option<Manifest>, Error> {
+        match tokio::fs::read(dir.join(MANIFEST_FILE)).await {
+            Ok(bytes) => Ok(Some(toml::from_slice(&bytes)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Records the fuzzed commit, so the next run can be compared with it.
+    pub async fn set_commit(&self, commit: &str) -> Result<()> {
+        let commit = commit.to_string();
+        self.update_manifest(|manifest| manifest.commit = Some(commit)).await
+    }
+
+    /// Records what started the run, shown in the report.
+    pub async fn set_trigger(&self, trigger: Trigger) -> Result<()> {
+        self.update_manifest(|manifest| manifest.trigger = Some(trigger)).await
+    }
+
+    /// Records the provenance of the run, so its results can be reproduced.
+    pub async fn set_provenance(&self, provenance: Provenance) -> Result<()> {
+        self.update_manifest(|manifest| manifest.provenance = Some(provenance)).await
+    }
+
+    /// Records owners, descriptions and harness sources of fuzzed targets, shown in the report.
+    pub async fn set_targets(&self, targets: HashMap<String, config::TargetMetadata>) -> Result<()> {
+        self.update_manifest(|manifest| manifest.targets = targets).await
+    }
+
+    async fn update_manifest(&self, update: impl FnOnce(&mut Manifest)) -> Result<()> {
+        let manifest_file = self.reports_dir.join(MANIFEST_FILE);
+        let mut manifest = Self::manifest(&self.reports_dir)
+            .await
+            .map_err(Self::file_error(&manifest_file))?
+            .unwrap_or(Manifest { sequence: 0, commit: None, trigger: None, provenance: None, targets: HashMap::new() });
+        update(&mut manifest);
+        Self::save(&toml::to_vec(&manifest)?, &manifest_file)
+            .await
+            .map_err(Self::file_error(&manifest_file))
+    }
+
+    /// Fuzzed commit recorded for the run, if known.
+    pub async fn commit(&self) -> Result<Option<String>> {
+        let manifest_file = self.reports_dir.join(MANIFEST_FILE);
+        Ok(Self::manifest(&self.reports_dir)
+            .await
+            .map_err(Self::file_error(&manifest_file))?
+            .and_then(|manifest| manifest.commit))
+    }
+
+    /// URL of the reports of the run in `current_path`.
+    fn run_url(reports_url: &Url, current_path: &Path) -> Result<Url> {
+        let mut url = reports_url.clone();
+        for segment in current_path {
+            url = url.join(&(Self::escape_segment(segment) + "/"))?
+        }
+        Ok(url)
+    }
+
+    /// URL of the report page of the run in `current_path`.
+    pub fn page_url(reports_url: &Url, current_path: &Path) -> Result<Url> {
+        Ok(Self::run_url(reports_url, current_path)?.join(REPORT_FILE)?)
+    }
+
+    /// URL of the report page, if the reports URL is configured.
+    pub fn url(&self) -> Result<Option<Url>> {
+        Ok(match &self.reports_url {
+            Some(url) => Some(url.join(REPORT_FILE)?),
+            None => None,
+        })
+    }
+
+    /// Directory of the run report.
+    pub fn dir(&self) -> &Path {
+        &self.reports_dir
+    }
+
+    /// Final status and fuzzed commit of the previous run, if known.
+    pub fn previous_run(&self) -> Option<(&FuzzingStatus, Option<&str>)> {
+        Some((self.previous.as_ref()?, self.previous_commit.as_deref()))
+    }
+
+    /// Records the commit found to lose coverage, shown in the report and its summaries.
+    pub async fn add_bisection(&self, bisection: Bisection) -> Result<()> {
+        let file = self.reports_dir.join(BISECTIONS_FILE);
+        let mut bisections = self.bisections().await.map_err(Self::file_error(&file))?;
+        bisections.entries.push(bisection);
+        Self::save(&toml::to_vec(&bisections)?, &file)
+            .await
+            .map_err(Self::file_error(&file))
+    }
+
+    async fn bisections(&self) -> Result<Bisections> {
+        match tokio::fs::read(self.reports_dir.join(BISECTIONS_FILE)).await {
+            Ok(bytes) => Ok(toml::from_slice(&bytes)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Bisections::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// The highest run sequence number among reports in the reports directory, 0 if there
+    /// are none, so runs started after a restart are numbered after them.
+    pub async fn last_sequence(reports_dir: &Path) -> Result<u64, Error> {
+        let mut last = 0;
+        let mut dirs = vec![reports_dir.to_path_buf()];
+        while let Some(dir) = dirs.pop() {
+            let mut read_dir = match read_dir(&dir).await {
+                Ok(r) => r,
+                Err(_) => continue,
+            };
+            while let Some(entry) = read_dir.next_entry().await? {
+                if !entry.file_type().await?.is_dir() {
+                    continue;
+                }
+                match tokio::fs::read(entry.path().join(MANIFEST_FILE)).await {
+                    Ok(bytes) => last = last.max(toml::from_slice::<Manifest>(&bytes)?.sequence),
+                    // reports of runs before they were numbered
+                    Err(_) if entry.path().join(CURR_STATUS_FILE).exists() => (),
+                    // reports of branches are nested in directories of repositories and releases
+                    Err(_) => dirs.push(entry.path()),
+                }
+            }
+        }
+        Ok(last)
+    }
+
     fn serialize(status: &FuzzingStatus) -> Result<Vec<u8>, Error> {
         //serde_json::to_vec_pretty(&status)
         Ok(toml::to_vec(status)?)
@@ -310,6 +779,14 @@ impl Report {
         Ok(toml::from_slice(bytes)?)
     }
 
+    /// Wraps errors of reading or writing the report file, adding its path.
+    fn file_error(file: &Path) -> impl FnOnce(Error) -> Error + '_ {
+        move |e| Error::Report {
+            path: file.to_path_buf(),
+            source: Box::new(e),
+        }
+    }
+
     async fn save(data: &[u8], file: impl AsRef<Path>) -> Result<(), Error> {
         if let Some(parent) = file.as_ref().parent() {
             if !parent.exists() {
@@ -337,46 +814,46 @@ impl Report {
     ///
     /// Returns summary of what has been changed (new edges since previous report
     /// or different coverage compared to the previous run).
-    pub async fn update(&self, status: &FuzzingStatus) -> Result<String, failure::Error> {
+    pub async fn update(&self, status: &FuzzingStatus) -> Result<Summary> {
         debug!(self.log, "Updating current fuzzing status",);
 
         // load previously reported status and save the new one
         let status_file = self.reports_dir.join(CURR_STATUS_FILE);
         let init_status_file = self.reports_dir.join(INIT_STATUS_FILE);
-        let init_status = Self::load(&init_status_file)
+        let mut init_status = Self::load(&init_status_file)
             .await
-            .with_context(|e| format!("error loading {}: {}", status_file.to_string_lossy(), e))?;
+            .map_err(Self::file_error(&init_status_file))?;
         let prev_status = Self::load(&status_file)
             .await
-            .with_context(|e| format!("error loading {}: {}", status_file.to_string_lossy(), e))?;
+            .map_err(Self::file_error(&status_file))?;
         Self::save_status(status, &status_file)
             .await
-            .with_context(|e| format!("error saving {}: {}", status_file.to_string_lossy(), e))?;
-        if init_status.is_none() {
-            Self::save_status(status, &init_status_file)
+            .map_err(Self::file_error(&status_file))?;
+        // targets without the baseline, e.g. after it is reset, get the current status as one
+        let init = init_status.get_or_insert_with(FuzzingStatus::new);
+        let missing = status.iter().filter(|(name, _)| !init.contains_key(*name)).collect::<Vec<_>>();
+        if init.is_empty() || !missing.is_empty() {
+            for (name, target) in missing {
+                init.insert(name.clone(), *target);
+            }
+            Self::save_status(init, &init_status_file)
                 .await
-                .with_context(|e| {
-                    format!("error saving {}: {}", status_file.to_string_lossy(), e)
-                })?;
+                .map_err(Self::file_error(&init_status_file))?;
         }
-
-        // construct report table containing current and reference data
-        let mut diff: Vec<TargetStatusDiff> = status
-            .iter()
-            .map(|(k, s)| self.get_diff(k, s, &prev_status, &init_status))
-            .collect();
-        diff.sort_by(|a, b| a.name.cmp(&b.name));
-        let report = HANDLEBARS.render("report", &diff)?;
-        let report_file = self.reports_dir.join(REPORT_FILE);
-        Self::save(report.as_bytes(), report_file)
+        let history_file = self.reports_dir.join(HISTORY_FILE);
+        let mut history = self
+            .load_history()
+            .await
+            .map_err(Self::file_error(&history_file))?;
+        history.entries.push(HistoryEntry {
+            time: Utc::now().timestamp(),
+            status: status.clone(),
+        });
+        Self::save(&toml::to_vec(&history)?, &history_file)
             .await
-            .with_context(|e| {
-                format!(
-                    "cannot create report file {}: {}",
-                    self.reports_dir.join(REPORT_FILE).to_string_lossy(),
-                    e
-                )
-            })?;
+            .map_err(Self::file_error(&history_file))?;
+
+        let diff = self.render(status, &prev_status, &init_status, Utc::now()).await?;
 
         // produce summary
         let mut summary = String::new();
@@ -389,33 +866,364 @@ impl Report {
         } else {
             writeln!(summary, "Summary of the report:")?;
         }
-        let mut changed = false;
-        for diff in diff {
-            if let (Some(_), Some(delta)) = (diff.prev, diff.delta) {
-                if delta.covered != 0 {
-                    writeln!(
-                        summary,
-                        "*+{}* {}: new edges covered since previous report",
-                        delta.covered, diff.name
-                    )?;
-                    changed = true;
-                }
-            } else if let (Some(_), Some(delta)) = (diff.prev_run, diff.delta_run) {
-                if (delta.covered, delta.total) != (0, 0) {
-                    writeln!(
-                        summary,
-                        "*{}/{}* {}: covered/total number of edges changed since previous run",
-                        delta.covered, delta.total, diff.name
-                    )?;
-                    changed = true;
+        for line in summarize(&diff) {
+            writeln!(summary, "{}", line)?;
+        }
+        for bisection in self.bisections().await?.entries {
+            writeln!(summary, "*Coverage regression*: {}", bisection)?;
+        }
+
+        Ok(Summary {
+            text: summary,
+            table: markdown(&diff),
+        })
+    }
+
+    /// Appends covered edges of targets at the time `time` to the CSV file of the report,
+    /// so coverage over the run can be analyzed outside of the CI.
+    pub async fn export_coverage(&self, status: &FuzzingStatus, time: DateTime<Utc>) -> Result<()> {
+        let file = self.reports_dir.join(COVERAGE_FILE);
+        let mut csv = String::new();
+        if !file.exists() {
+            csv += "time,target,covered,total,errors\n";
+        }
+        let mut targets = status.iter().collect::<Vec<_>>();
+        targets.sort_by_key(|(target, _)| *target);
+        for (target, status) in targets {
+            writeln!(csv, "{},{},{},{},{}", time.timestamp(), target, status.covered, status.total, status.errors)?;
+        }
+        let append = async {
+            if let Some(parent) = file.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&file)
+                .await?
+                .write_all(csv.as_bytes())
+                .await
+        };
+        append.await.map_err(|e| Self::file_error(&file)(e.into()))
+    }
+
+    /// Packs the report with its status, crash inputs and the summary of crashes of targets
+    /// into the gzipped tarball `archive`.
+    pub async fn archive(&self, status: &FuzzingStatus, archive: &Path) -> Result<()> {
+        let mut crashes = std::collections::BTreeMap::new();
+        for (target, status) in status {
+            let mut inputs = vec![];
+            if let Ok(mut dir) = read_dir(self.reports_dir.join("failures").join(target)).await {
+                while let Some(entry) = dir.next_entry().await? {
+                    inputs.push(format!("failures/{}/{}", target, entry.file_name().to_string_lossy()));
                 }
             }
+            inputs.sort();
+            crashes.insert(target.clone(), CrashSummary { crashes: status.errors, inputs });
+        }
+        let file = self.reports_dir.join(CRASHES_FILE);
+        Self::save(&toml::to_vec(&crashes)?, &file)
+            .await
+            .map_err(Self::file_error(&file))?;
+        let mut command = tokio::process::Command::new("tar");
+        command.arg("-czf").arg(archive).arg("-C").arg(&self.reports_dir).arg("hfuzz-report");
+        if self.reports_dir.join("failures").is_dir() {
+            command.arg("failures");
+        }
+        let output = command.output().await?;
+        if !output.status.success() {
+            return Err(Error::other(format!("cannot archive the report: {}", output_tail(&output.stderr))));
+        }
+        Ok(())
+    }
+
+    /// Stores resource usage of the run processes along with the status.
+    pub async fn save_resources(&self, resources: &Resources) -> Result<()> {
+        let file = self.reports_dir.join(RESOURCES_FILE);
+        Self::save(&toml::to_vec(resources)?, &file)
+            .await
+            .map_err(Self::file_error(&file))
+    }
+
+    /// Renders the report table containing current and reference data.
+    async fn render(
+        &self,
+        status: &FuzzingStatus,
+        prev_status: &Option<FuzzingStatus>,
+        init_status: &Option<FuzzingStatus>,
+        updated: DateTime<Utc>,
+    ) -> Result<Vec<TargetStatusDiff>> {
+        let mut diff: Vec<TargetStatusDiff> = status
+            .iter()
+            .map(|(k, s)| self.get_diff(k, s, prev_status, init_status))
+            .collect();
+        diff.sort_by(|a, b| a.name.cmp(&b.name));
+        let stale = self.stale().await?.map(|stale| stale.reason);
+        let bisections = self.bisections().await?.entries.iter().map(ToString::to_string).collect::<Vec<_>>();
+        let manifest = Self::manifest(&self.reports_dir).await?;
+        let trigger = manifest.as_ref().and_then(|manifest| manifest.trigger.as_ref()).map(|trigger| trigger.name());
+        let targets = manifest.map(|manifest| manifest.targets).unwrap_or_default();
+        for target in &mut diff {
+            target.metadata = config::TargetMetadata::of(&targets, &target.name).cloned();
+        }
+        let report = HANDLEBARS.render(
+            "report",
+            &serde_json::json!({
+                "targets": &diff,
+                "updated": self.time.format(&updated),
+                "stale": stale,
+                "bisections": bisections,
+                "trigger": trigger,
+            }),
+        )?;
+        let report_file = self.reports_dir.join(REPORT_FILE);
+        Self::save(report.as_bytes(), &report_file)
+            .await
+            .map_err(Self::file_error(&report_file))?;
+        self.render_targets(status, &targets, &updated).await?;
+        Ok(diff)
+    }
+
+    async fn stale(&self) -> Result<Option<Stale>> {
+        match tokio::fs::read(self.reports_dir.join(STALE_FILE)).await {
+            Ok(bytes) => Ok(Some(toml::from_slice(&bytes)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Location of the most recent report among reports of a branch in `branch_loc`, relative
+    /// to the reports directory, skipping stale ones.
+    pub async fn latest(reports_dir: &Path, branch_loc: &Path, log: &Logger) -> Result<Option<PathBuf>> {
+        let latest = Self::find_previous(reports_dir.join(branch_loc), Path::new(""), log).await?;
+        Ok(latest.and_then(|dir| Some(dir.strip_prefix(reports_dir).ok()?.to_path_buf())))
+    }
+
+    /// Time the most recent report among reports of a branch in `branch_loc` was last
+    /// updated, i.e. when the branch was last fuzzed.
+    pub async fn last_updated(reports_dir: &Path, branch_loc: &Path, log: &Logger) -> Result<Option<SystemTime>> {
+        let latest = match Self::find_previous(reports_dir.join(branch_loc), Path::new(""), log).await? {
+            Some(latest) => latest,
+            None => return Ok(None),
+        };
+        Ok(Some(tokio::fs::metadata(latest.join(CURR_STATUS_FILE)).await?.modified()?))
+    }
+
+    /// Marks the report as stale for the reason, so later runs aren't compared with it,
+    /// and re-renders it with the reason shown.
+    pub async fn mark_stale(&self, reason: impl Into<String>) -> Result<()> {
+        let stale_file = self.reports_dir.join(STALE_FILE);
+        let stale = Stale { reason: reason.into() };
+        Self::save(&toml::to_vec(&stale)?, &stale_file)
+            .await
+            .map_err(Self::file_error(&stale_file))?;
+        self.regenerate().await?;
+        Ok(())
+    }
+
+    /// Changes the initial status of the `targets`, or of all targets if empty, archiving the
+    /// replaced one in the report directory, and re-renders the report.
+    ///
+    /// Returns targets whose baseline is changed.
+    pub async fn rebaseline(&self, targets: &[String], baseline: Baseline) -> Result<Vec<String>> {
+        let init_status_file = self.reports_dir.join(INIT_STATUS_FILE);
+        let mut init_status = Self::load(&init_status_file)
+            .await
+            .map_err(Self::file_error(&init_status_file))?
+            .unwrap_or_default();
+        let status = match baseline {
+            Baseline::Reset => FuzzingStatus::new(),
+            Baseline::Current => {
+                let status_file = self.reports_dir.join(CURR_STATUS_FILE);
+                Self::load(&status_file)
+                    .await
+                    .map_err(Self::file_error(&status_file))?
+                    .unwrap_or_default()
+            }
+        };
+        let selected = |name: &String| targets.is_empty() || targets.contains(name);
+        let mut changed = init_status
+            .keys()
+            .chain(status.keys())
+            .filter(|name| selected(name))
+            .cloned()
+            .collect::<Vec<_>>();
+        changed.sort();
+        changed.dedup();
+        if changed.is_empty() {
+            return Ok(changed);
+        }
+
+        if init_status_file.exists() {
+            let archived = self
+                .reports_dir
+                .join(BASELINES_DIR)
+                .join(format!("{}.toml", Utc::now().format("%Y%m%d%H%M%S%3f")));
+            Self::save_status(&init_status, &archived)
+                .await
+                .map_err(Self::file_error(&archived))?;
+        }
+        for name in &changed {
+            match status.get(name) {
+                Some(target) => init_status.insert(name.clone(), *target),
+                None => init_status.remove(name),
+            };
+        }
+        Self::save_status(&init_status, &init_status_file)
+            .await
+            .map_err(Self::file_error(&init_status_file))?;
+        info!(self.log, "Baseline changed"; "targets" => changed.join(", "), "baseline" => format!("{:?}", baseline));
+        self.regenerate().await?;
+        Ok(changed)
+    }
+
+    async fn load_history(&self) -> Result<History> {
+        match tokio::fs::read(self.reports_dir.join(HISTORY_FILE)).await {
+            Ok(bytes) => Ok(toml::from_slice(&bytes)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(History::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Renders a page of each target, with its coverage history, crashes and kcov report.
+    async fn render_targets(
+        &self,
+        status: &FuzzingStatus,
+        targets: &HashMap<String, config::TargetMetadata>,
+        updated: &DateTime<Utc>,
+    ) -> Result<()> {
+        let history_file = self.reports_dir.join(HISTORY_FILE);
+        let history = self
+            .load_history()
+            .await
+            .map_err(Self::file_error(&history_file))?;
+        let provenance = Self::manifest(&self.reports_dir).await.ok().flatten().and_then(|manifest| manifest.provenance);
+        for name in status.keys() {
+            let history = history
+                .entries
+                .iter()
+                .filter_map(|entry| {
+                    Some(HistoryPoint {
+                        time: self.time.format(&(SystemTime::UNIX_EPOCH + Duration::from_secs(entry.time as u64)).into()),
+                        status: *entry.status.get(name)?,
+                    })
+                })
+                .collect();
+            let page = TargetPage {
+                name: name.clone(),
+                metadata: config::TargetMetadata::of(targets, name).cloned(),
+                history,
+                crashes: self.crashes(name, provenance.as_ref()).await,
+                kcov: self.kcov_page(name),
+                updated: self.time.format(updated),
+            };
+            let page = HANDLEBARS.render("target", &page)?;
+            let page_file = self.reports_dir.join(TARGETS_DIR).join(format!("{}.html", name));
+            Self::save(page.as_bytes(), &page_file)
+                .await
+                .map_err(Self::file_error(&page_file))?;
+        }
+        Ok(())
+    }
+
+    /// Crashing inputs of the target copied to the report directory, with their
+    /// reproduction scripts, written once the provenance of the run is known.
+    async fn crashes(&self, target: &str, provenance: Option<&Provenance>) -> Vec<Crash> {
+        let dir = Path::new("failures").join(target);
+        let mut names = std::fs::read_dir(self.reports_dir.join(&dir))
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| Some(entry.ok()?.file_name().to_string_lossy().into_owned()))
+            .collect::<Vec<_>>();
+        names.sort();
+        let mut crashes = vec![];
+        for name in names {
+            let input = dir.join(&name);
+            let repro = match provenance {
+                Some(provenance) => self.repro(provenance, target, &name, &input).await,
+                None => None,
+            };
+            crashes.push(Crash {
+                input: input.to_string_lossy().into_owned(),
+                repro: repro.map(|repro| repro.to_string_lossy().into_owned()),
+            });
+        }
+        crashes
+    }
+
+    /// Reproduction script of the crash with the input, relative to the report directory,
+    /// written unless it exists. Returns `None` if the crash cannot be reproduced by a script.
+    async fn repro(&self, provenance: &Provenance, target: &str, name: &str, input: &Path) -> Option<PathBuf> {
+        let repro = Path::new(repro::REPRO_DIR).join(target).join(format!("{}.sh", name));
+        let file = self.reports_dir.join(&repro);
+        if file.is_file() {
+            return Some(repro);
+        }
+        let input = match tokio::fs::read(self.reports_dir.join(input)).await {
+            Ok(input) => input,
+            Err(e) => {
+                debug!(self.log, "Cannot read crash input"; "file" => input.to_str(), "error" => e.to_string());
+                return None;
+            }
+        };
+        let script = repro::script(provenance, target, name, &input)?;
+        match Self::save(script.as_bytes(), &file).await {
+            Ok(()) => Some(repro),
+            Err(e) => {
+                error!(self.log, "Cannot write reproduction script"; "file" => file.to_str(), "error" => e.to_string());
+                None
+            }
         }
-        if !changed {
-            writeln!(summary, "No changed detected")?;
+    }
+
+    /// Kcov page of the target source file, i.e. `<target>.rs`, looked up in coverage
+    /// reports of fuzzing projects copied to the report directory.
+    fn kcov_page(&self, target: &str) -> Option<String> {
+        // variants of a target share its source
+        let prefix = format!("{}.rs.", target.split('@').next().unwrap_or(target));
+        let mut dirs = std::fs::read_dir(&self.reports_dir)
+            .ok()?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name() != "hfuzz-report" && entry.file_name() != "failures")
+            .map(|entry| entry.path())
+            .collect::<Vec<_>>();
+        while let Some(dir) = dirs.pop() {
+            for entry in std::fs::read_dir(&dir).into_iter().flatten().filter_map(|entry| entry.ok()) {
+                let path = entry.path();
+                let name = entry.file_name().to_string_lossy().into_owned();
+                if path.is_dir() {
+                    dirs.push(path);
+                } else if name.starts_with(&prefix) && name.ends_with(".html") {
+                    return path
+                        .strip_prefix(&self.reports_dir)
+                        .ok()
+                        .map(|p| p.to_string_lossy().into_owned());
+                }
+            }
         }
+        None
+    }
 
-        Ok(summary)
+    /// Re-renders the report from the stored status, e.g. after the template is changed.
+    ///
+    /// Returns `false` if there is no stored status. Reports are compared with the
+    /// initial status and the previous run only, as intermediate statuses aren't stored.
+    pub async fn regenerate(&self) -> Result<bool> {
+        let status_file = self.reports_dir.join(CURR_STATUS_FILE);
+        let status = match Self::load(&status_file)
+            .await
+            .map_err(Self::file_error(&status_file))?
+        {
+            Some(status) => status,
+            None => return Ok(false),
+        };
+        let init_status_file = self.reports_dir.join(INIT_STATUS_FILE);
+        let init_status = Self::load(&init_status_file)
+            .await
+            .map_err(Self::file_error(&init_status_file))?;
+        let updated = std::fs::metadata(&status_file)?.modified()?;
+        self.render(&status, &None, &init_status, updated.into()).await?;
+        Ok(true)
     }
 
     fn get_diff(
@@ -445,30 +1253,21 @@ impl Report {
     }
 
     /// Adds the specified error input to the report directory and returns a message with a link to it.
-    pub fn add_error(&self, target: &str, error_input: &str) -> Result<String, failure::Error> {
+    pub fn add_error(&self, target: &str, error_input: &str) -> Result<String> {
         let source = PathBuf::from(error_input);
         let name = source
             .file_name()
-            .ok_or(failure::format_err!("File name is missing in {:?}", source))?;
+            .ok_or_else(|| Error::other(format!("File name is missing in {:?}", source)))?;
         let name = name
             .to_str()
-            .ok_or(failure::format_err!("Cannot stringify path {:?}", name))?;
+            .ok_or_else(|| Error::other(format!("Cannot stringify path {:?}", name)))?;
         let dest_dir = self.reports_dir.join("failures").join(target);
         let dest = dest_dir.join(name);
-        let url: Option<Url> = self
-            .reports_url
-            .as_ref()
-            .map(|u| Result::<Url, url::ParseError>::Ok(u.join(&format!("failures/{}/{}", target, name))?))
-            .transpose()?;
-        let res = match url {
-            Some(url) => format!("New error detected for `{}`. Input is available at {}", target, url.as_str()),
-            None => format!(
-                "New error detected for `{}`. Input is available at `{}`",
-                target,
-                dest.to_str()
-                    .ok_or(failure::format_err!("Cannot stringify path {:?}", dest))?
-            ),
-        };
+        let res = format!(
+            "New error detected for `{}`. Input is available at {}",
+            target,
+            self.error_location(target, name)?
+        );
         let log = self.log.clone();
         tokio::spawn(async move {
             if let Err(err) = tokio::fs::create_dir_all(&dest_dir).await {
@@ -480,4 +1279,122 @@ impl Report {
         });
         Ok(res)
     }
+
+    /// Location of the error input file `name` of the target added to the report, for
+    /// messages: its URL if reports are served, or its quoted path.
+    pub fn error_location(&self, target: &str, name: &str) -> Result<String> {
+        if let Some(url) = &self.reports_url {
+            return Ok(url.join(&format!("failures/{}/{}", target, name))?.to_string());
+        }
+        let dest = self.reports_dir.join("failures").join(target).join(name);
+        let path = dest
+            .to_str()
+            .ok_or_else(|| Error::other(format!("Cannot stringify path {:?}", dest)))?;
+        Ok(format!("`{}`", path))
+    }
+}
+
+/// Crashes found per CPU hour of fuzzing, of each target in total and over recent weeks,
+/// and CPU time spent per newly covered edge, from the targets of finished runs.
+#[derive(serde::Serialize)]
+pub struct CrashRates {
+    /// First days of the weeks, oldest first
+    pub weeks: Vec<String>,
+    /// Targets, the most productive first
+    pub targets: Vec<TargetCrashRates>,
+}
+
+#[derive(serde::Serialize)]
+pub struct TargetCrashRates {
+    pub target: String,
+    pub crashes: u32,
+    pub cpu_hours: String,
+    /// Crashes per CPU hour
+    pub rate: String,
+    /// Edges covered beyond the previous runs, summed over runs
+    pub new_edges: u32,
+    /// CPU minutes per new edge, `-` for targets without new edges
+    pub edge_cost: String,
+    /// Crashes per CPU hour in each week, `-` for weeks the target wasn't fuzzed
+    pub weeks: Vec<String>,
+}
+
+impl CrashRates {
+    pub fn new(runs: &[TargetRun], now: DateTime<Utc>, weeks: usize) -> Self {
+        let week = chrono::Duration::weeks(1);
+        let start = now - week * weeks as i32;
+        let mut totals: HashMap<&str, (Fuzzed, Vec<Fuzzed>)> = HashMap::new();
+        for run in runs {
+            let (total, by_week) = totals
+                .entry(&run.target)
+                .or_insert_with(|| (Fuzzed::default(), vec![Fuzzed::default(); weeks]));
+            total.add(run);
+            let since = run.finished - start.timestamp();
+            if since >= 0 {
+                if let Some(fuzzed) = by_week.get_mut((since / week.num_seconds()) as usize) {
+                    fuzzed.add(run);
+                }
+            }
+        }
+        let mut targets = totals.into_iter().collect::<Vec<_>>();
+        targets.sort_by(|(a, (a_total, _)), (b, (b_total, _))| {
+            let (a_rate, b_rate) = (a_total.rate().unwrap_or(0.0), b_total.rate().unwrap_or(0.0));
+            b_rate.partial_cmp(&a_rate).unwrap_or(std::cmp::Ordering::Equal).then(a.cmp(b))
+        });
+        Self {
+            weeks: (0..weeks)
+                .map(|w| (start + week * w as i32).format("%Y-%m-%d").to_string())
+                .collect(),
+            targets: targets
+                .into_iter()
+                .map(|(target, (total, by_week))| TargetCrashRates {
+                    target: target.to_string(),
+                    crashes: total.crashes,
+                    cpu_hours: format!("{:.1}", total.cpu_time / 3600.0),
+                    rate: total.format_rate(),
+                    new_edges: total.new_edges,
+                    edge_cost: total.format_edge_cost(),
+                    weeks: by_week.iter().map(Fuzzed::format_rate).collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Crashes found by a target, CPU time it used and edges it newly covered, summed over runs.
+#[derive(Clone, Copy, Default)]
+struct Fuzzed {
+    crashes: u32,
+    cpu_time: f64,
+    new_edges: u32,
+}
+
+impl Fuzzed {
+    fn add(&mut self, run: &TargetRun) {
+        self.crashes += run.crashes;
+        self.cpu_time += run.cpu_time;
+        self.new_edges += run.new_edges;
+    }
+
+    /// CPU minutes per new edge, if the target covered any.
+    fn format_edge_cost(&self) -> String {
+        if self.new_edges > 0 {
+            format!("{:.1}", self.cpu_time / 60.0 / f64::from(self.new_edges))
+        } else {
+            "-".to_string()
+        }
+    }
+
+    /// Crashes per CPU hour, if the target used any CPU time.
+    fn rate(&self) -> Option<f64> {
+        if self.cpu_time > 0.0 {
+            Some(self.crashes as f64 * 3600.0 / self.cpu_time)
+        } else {
+            None
+        }
+    }
+
+    fn format_rate(&self) -> String {
+        self.rate().map_or("-".to_string(), |r| format!("{:.2}", r))
+    }
 }
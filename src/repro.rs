@@ -0,0 +1,180 @@
+//! Reproduction scripts of crashes, checking out the fuzzed commit, building the fuzz
+//! target with the toolchain and environment of the run and running it on the crashing
+//! input, so a crash can be reproduced outside of the CI with a single command.
+
+use crate::{config::Engine, provenance::Provenance};
+
+/// Directory of reproduction scripts, within the run report directory.
+pub const REPRO_DIR: &str = "repro";
+
+/// Value of recorded environment variables named like secrets.
+const REDACTED: &str = "<redacted>";
+
+/// Quotes the string for the shell.
+fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// Quotes the value of an environment variable, with paths within the checkout of the
+/// run replaced by ones within the local checkout in `$DIR`.
+fn quote_value(value: &str, checkout: Option<&str>) -> String {
+    match checkout.filter(|checkout| !checkout.is_empty()) {
+        Some(checkout) => value.split(checkout).map(quote).collect::<Vec<_>>().join("\"$DIR\""),
+        None => quote(value),
+    }
+}
+
+/// Rustup toolchain of the stable `rustc --version`, e.g. `1.52.1` of
+/// `rustc 1.52.1 (9bc8c42bb 2021-05-09)`. Nightly and beta toolchains are not pinned, as
+/// their versions don't name them.
+fn stable_toolchain(rustc: &str) -> Option<&str> {
+    let version = rustc.strip_prefix("rustc ")?.split(' ').next()?;
+    if version.contains('-') {
+        None
+    } else {
+        Some(version)
+    }
+}
+
+/// Shell script reproducing the crash with the input `name` of the fuzz target, referred
+/// by its id used in reports, in the run with the provenance. Returns `None` if the run
+/// doesn't record how to build the target, e.g. it's not fuzzed with honggfuzz.
+pub fn script(provenance: &Provenance, target: &str, name: &str, input: &[u8]) -> Option<String> {
+    let repository = provenance.repository.as_deref()?;
+    let commit = provenance.checkout_commit.as_deref()?;
+    let (target_name, variant) = target.split_once('@').unwrap_or((target, ""));
+    let (project, binary) = provenance
+        .projects
+        .values()
+        .filter(|project| project.engine == Engine::Honggfuzz)
+        .find_map(|project| Some((project, project.targets.get(target_name)?)))?;
+    let path = project.path.as_deref()?;
+    let cargo_args = project.variants.get(if variant.is_empty() { "default" } else { variant })?;
+    let checkout = provenance.checkout.as_deref();
+
+    let mut script = format!(
+        "#!/bin/sh\n\
+         # Reproduces the crash `{name}` of the fuzz target `{target}`, found by fuzz-ci {version}\n\
+         # at commit {commit} of {repository}.\n\
+         #\n\
+         # Usage: sh {file} [DIR]\n\
+         #\n\
+         # The repository is checked out to DIR, `fuzz-ci-repro` by default, or an existing\n\
+         # checkout there is used. Exits with the status of the crashing fuzz target.\n",
+        name = name,
+        target = target,
+        version = provenance.fuzz_ci,
+        commit = commit,
+        repository = repository,
+        file = quote(&format!("{}.sh", name)),
+    );
+    let versions = [&provenance.rustc, &provenance.cargo, &provenance.cargo_hfuzz];
+    let mut versions = versions.iter().filter_map(|version| version.as_deref()).peekable();
+    if versions.peek().is_some() {
+        script.push_str("#\n# The run was built with:\n");
+        for version in versions {
+            script.push_str(&format!("#   {}\n", version));
+        }
+    }
+    if let Some(project_commit) = provenance.project_commit.as_deref().filter(|c| *c != commit) {
+        script.push_str(&format!("#\n# The fuzzed code is a separate repository, at commit {}.\n", project_commit));
+    }
+
+    script.push_str(&format!(
+        "set -eu\n\n\
+         DIR=\"${{1:-fuzz-ci-repro}}\"\n\
+         [ -d \"$DIR\" ] || git clone {repository} \"$DIR\"\n\
+         cd \"$DIR\"\n\
+         git cat-file -e {commit}^{{commit}} 2> /dev/null || git fetch origin {commit}\n\
+         git checkout --quiet --detach {commit}\n\
+         DIR=\"$(pwd)\"\n\n\
+         # environment of the run, secrets should be set as in the CI\n",
+        repository = quote(repository),
+        commit = commit,
+    ));
+    for (key, value) in provenance.env.iter().chain(&project.env) {
+        if value == REDACTED {
+            script.push_str(&format!("# export {}=...\n", key));
+        } else {
+            script.push_str(&format!("export {}={}\n", key, quote_value(value, checkout)));
+        }
+    }
+    if !variant.is_empty() {
+        script.push_str(&format!(
+            "export CARGO_TARGET_DIR=\"${{CARGO_TARGET_DIR:-hfuzz_target}}/{variant}\"\n\
+             export HFUZZ_WORKSPACE=\"${{HFUZZ_WORKSPACE:-hfuzz_workspace}}/{variant}\"\n",
+            variant = variant,
+        ));
+    }
+    script.push('\n');
+
+    if let Some(rustc) = provenance.rustc.as_deref() {
+        match stable_toolchain(rustc) {
+            Some(toolchain) => script.push_str(&format!("export RUSTUP_TOOLCHAIN={}\n", toolchain)),
+            None => script.push_str("# the toolchain is expected to be selected by the toolchain file of the checkout\n"),
+        }
+        script.push_str(&format!(
+            "EXPECTED={}\n\
+             [ \"$(rustc --version)\" = \"$EXPECTED\" ] || \
+             echo \"warning: the crash was found with $EXPECTED, reproducing with $(rustc --version)\" >&2\n",
+            quote(rustc)
+        ));
+    }
+    let version = match &project.honggfuzz {
+        Some(version) => format!(" --version {}", quote(&format!("={}", version))),
+        None => String::new(),
+    };
+    let build = std::iter::once("cargo hfuzz build".to_string())
+        .chain(cargo_args.iter().map(|arg| quote(arg)))
+        .collect::<Vec<_>>()
+        .join(" ");
+    script.push_str(&format!(
+        "command -v cargo-hfuzz > /dev/null || cargo install honggfuzz{version}\n\n\
+         cd {path}\n\
+         {build}\n\
+         BINARY=\"$(find \"${{CARGO_TARGET_DIR:-hfuzz_target}}\" -path {binary} -type f | head -n 1)\"\n\n\
+         INPUT=\"$(mktemp)\"\n\
+         base64 -d > \"$INPUT\" << 'EOF'\n",
+        version = version,
+        path = quote(path),
+        build = build,
+        binary = quote(&format!("*/release/{}", binary)),
+    ));
+    for line in base64::encode(input).as_bytes().chunks(76) {
+        script.push_str(&String::from_utf8_lossy(line));
+        script.push('\n');
+    }
+    script.push_str(
+        "EOF\n\n\
+         # outside of honggfuzz, fuzz targets read the input from the standard input\n\
+         RUST_BACKTRACE=\"${RUST_BACKTRACE:-1}\" \"$BINARY\" < \"$INPUT\"\n",
+    );
+    Some(script)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_for_shell() {
+        assert_eq!(quote(""), "''");
+        assert_eq!(quote("a b $HOME `id` \"x\""), "'a b $HOME `id` \"x\"'");
+        assert_eq!(quote("it's"), r"'it'\''s'");
+        assert_eq!(quote("''"), r"''\'''\'''");
+    }
+
+    #[test]
+    fn quotes_values_with_checkout_paths() {
+        assert_eq!(quote_value("/run/1/lib:/usr/lib", Some("/run/1")), "''\"$DIR\"'/lib:/usr/lib'");
+        assert_eq!(quote_value("/run/1/lib", Some("")), "'/run/1/lib'");
+        assert_eq!(quote_value("/run/1/lib", None), "'/run/1/lib'");
+    }
+
+    #[test]
+    fn pins_stable_toolchains() {
+        assert_eq!(stable_toolchain("rustc 1.52.1 (9bc8c42bb 2021-05-09)"), Some("1.52.1"));
+        assert_eq!(stable_toolchain("rustc 1.54.0-nightly (676ee1472 2021-05-06)"), None);
+        assert_eq!(stable_toolchain("cargo 1.52.0"), None);
+    }
+}
@@ -0,0 +1,202 @@
+use std::{
+    collections::BTreeMap,
+    fmt, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{corpus, feedback::Feedback};
+
+/// Interval between resource usage samples of running processes.
+pub const SAMPLE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Resource usage of a process along with its descendants.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+pub struct Usage {
+    /// CPU usage, in percents of a single core
+    pub cpu: f64,
+    /// CPU time used by the process so far, in seconds
+    #[serde(default)]
+    pub cpu_time: f64,
+    /// Resident memory, in bytes
+    pub rss: u64,
+    /// Disk space used by the process data, e.g. the target corpus, in bytes
+    pub disk: Option<u64>,
+}
+
+impl Usage {
+    /// Usage combining the highest values of both.
+    pub fn max(self, other: Self) -> Self {
+        Self {
+            cpu: self.cpu.max(other.cpu),
+            cpu_time: self.cpu_time.max(other.cpu_time),
+            rss: self.rss.max(other.rss),
+            disk: self.disk.max(other.disk),
+        }
+    }
+}
+
+impl fmt::Display for Usage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CPU {:.0}%, RSS {}", self.cpu, mib(self.rss))?;
+        if let Some(disk) = self.disk {
+            write!(f, ", disk {}", mib(disk))?;
+        }
+        Ok(())
+    }
+}
+
+fn mib(bytes: u64) -> String {
+    format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+/// Resource usage of fuzzing processes of a run, stored along with its report.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Resources {
+    /// Peak usage of build steps, by fuzzing project
+    pub builds: BTreeMap<String, Usage>,
+    /// Recent usage of fuzz targets, by target
+    pub targets: BTreeMap<String, Usage>,
+}
+
+/// Samples resource usage of a process tree, with CPU usage averaged since the previous sample.
+pub struct Sampler {
+    pid: u32,
+    last: Option<(Instant, u64)>,
+}
+
+impl Sampler {
+    pub fn new(pid: u32) -> Self {
+        Self { pid, last: None }
+    }
+
+    pub fn sample(&mut self) -> io::Result<Usage> {
+        let stats = process_stats()?;
+        let mut pids = vec![self.pid];
+        let (mut ticks, mut pages) = (0, 0);
+        while let Some(pid) = pids.pop() {
+            for stat in &stats {
+                if stat.pid == pid {
+                    ticks += stat.ticks;
+                    pages += stat.rss_pages;
+                } else if stat.ppid == pid {
+                    pids.push(stat.pid);
+                }
+            }
+        }
+        let now = Instant::now();
+        let cpu = match self.last {
+            Some((time, last_ticks)) if now > time => {
+                let secs = ticks.saturating_sub(last_ticks) as f64 / clock_ticks();
+                secs * 100.0 / now.duration_since(time).as_secs_f64()
+            }
+            _ => 0.0,
+        };
+        self.last = Some((now, ticks));
+        Ok(Usage {
+            cpu,
+            cpu_time: ticks as f64 / clock_ticks(),
+            rss: pages * page_size(),
+            disk: None,
+        })
+    }
+}
+
+/// Periodically reports resource usage of the fuzz target process `pid` and the size of its corpus.
+pub async fn sample_target(pid: u32, id: String, corpus: Option<PathBuf>, feedback: Arc<Feedback>) {
+    let mut sampler = Sampler::new(pid);
+    // the baseline for CPU usage
+    let _ = sampler.sample();
+    loop {
+        tokio::time::sleep(SAMPLE_INTERVAL).await;
+        let mut usage = match sampler.sample() {
+            Ok(usage) => usage,
+            Err(_) => continue,
+        };
+        if let Some(corpus) = &corpus {
+            usage.disk = corpus::size(corpus).await.ok().map(|(_, bytes)| bytes);
+        }
+        feedback.set_target_usage(&id, usage);
+    }
+}
+
+/// Processes with the working directory within `dir`, like builds and fuzz targets
+/// of a run within its checkout.
+pub fn processes_in(dir: &Path) -> io::Result<Vec<u32>> {
+    let mut pids = vec![];
+    for entry in std::fs::read_dir("/proc")? {
+        let entry = entry?;
+        if let Some(pid) = entry.file_name().to_str().and_then(|n| n.parse().ok()) {
+            // processes can exit while being listed
+            if let Ok(cwd) = std::fs::read_link(entry.path().join("cwd")) {
+                if cwd.starts_with(dir) {
+                    pids.push(pid);
+                }
+            }
+        }
+    }
+    Ok(pids)
+}
+
+/// Start time of the process `pid`, in clock ticks since boot, telling it apart from a later
+/// process reusing its pid. `None` if there is no such process, or it has exited and is
+/// waiting to be reaped.
+pub fn start_time(pid: u32) -> Option<u64> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    let (_, fields) = stat.rsplit_once(')')?;
+    let fields = fields.split_whitespace().collect::<Vec<_>>();
+    match fields.as_slice() {
+        ["Z", ..] => None,
+        _ => fields.get(22 - 3)?.parse().ok(),
+    }
+}
+
+struct ProcessStat {
+    pid: u32,
+    ppid: u32,
+    /// user and system CPU time
+    ticks: u64,
+    rss_pages: u64,
+}
+
+/// Statistics of all processes, read from `/proc`.
+fn process_stats() -> io::Result<Vec<ProcessStat>> {
+    let mut stats = vec![];
+    for entry in std::fs::read_dir("/proc")? {
+        let entry = entry?;
+        if let Some(pid) = entry.file_name().to_str().and_then(|n| n.parse().ok()) {
+            // processes can exit while being listed
+            if let Some(stat) = std::fs::read_to_string(entry.path().join("stat"))
+                .ok()
+                .and_then(|s| parse_stat(pid, &s))
+            {
+                stats.push(stat);
+            }
+        }
+    }
+    Ok(stats)
+}
+
+fn parse_stat(pid: u32, stat: &str) -> Option<ProcessStat> {
+    // the command name can contain spaces, so fields are counted from its closing parenthesis
+    let (_, fields) = stat.rsplit_once(')')?;
+    let fields = fields.split_whitespace().collect::<Vec<_>>();
+    let field = |n: usize| fields.get(n - 3).and_then(|f| f.parse::<u64>().ok());
+    Some(ProcessStat {
+        pid,
+        ppid: field(4)? as u32,
+        ticks: field(14)? + field(15)?,
+        rss_pages: field(24)?,
+    })
+}
+
+fn clock_ticks() -> f64 {
+    (unsafe { libc::sysconf(libc::_SC_CLK_TCK) }).max(1) as f64
+}
+
+fn page_size() -> u64 {
+    (unsafe { libc::sysconf(libc::_SC_PAGESIZE) }).max(1) as u64
+}
@@ -0,0 +1,314 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use slog::{error, Logger};
+
+use crate::{policy::Verdict, state::Store};
+
+/// Status of a fuzzing run.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunStatus {
+    Running,
+    Success,
+    Failure,
+    Cancelled,
+}
+
+impl RunStatus {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RunStatus::Running => "running",
+            RunStatus::Success => "success",
+            RunStatus::Failure => "failure",
+            RunStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "running" => Some(RunStatus::Running),
+            "success" => Some(RunStatus::Success),
+            "failure" => Some(RunStatus::Failure),
+            "cancelled" => Some(RunStatus::Cancelled),
+            _ => None,
+        }
+    }
+}
+
+/// What started a fuzzing run.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Trigger {
+    /// A push to the branch
+    Push,
+    /// An API request
+    Api,
+    /// A re-run of a previous run
+    Rerun,
+    /// Resuming of a run interrupted by a server restart
+    Resume,
+    /// Re-fuzzing of a branch not fuzzed for a while
+    Staleness,
+    /// A tagged release
+    Release,
+    /// Scheduled distillation of corpora
+    Distillation,
+}
+
+impl Trigger {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Trigger::Push => "push",
+            Trigger::Api => "api",
+            Trigger::Rerun => "rerun",
+            Trigger::Resume => "resume",
+            Trigger::Staleness => "staleness",
+            Trigger::Release => "release",
+            Trigger::Distillation => "distillation",
+        }
+    }
+}
+
+/// Fuzzing run started by the server, as reported by the API.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Run {
+    pub id: String,
+    pub branch: String,
+    pub commit: Option<String>,
+    pub description: String,
+    pub status: RunStatus,
+    pub report_url: Option<String>,
+    /// Verdict of the policy, once the run is finished
+    #[serde(default)]
+    pub verdict: Option<Verdict>,
+}
+
+/// Fuzz target excluded from runs by an admin, until enabled again.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DisabledTarget {
+    pub target: String,
+    /// Caller who disabled the target
+    pub by: String,
+    pub since: DateTime<Utc>,
+}
+
+/// Issues opened for crash signatures, by target and signature, or `None` while opened.
+type CrashIssues = HashMap<(String, String), Option<u64>>;
+
+/// Registry of fuzzing runs, in order they are started, of disabled targets and of crash
+/// signatures issues are opened for.
+///
+/// Without the state store, only runs started since the server start are kept, and
+/// disabled targets are enabled again and crash signatures forgotten by a restart.
+#[derive(Clone)]
+pub struct Runs {
+    runs: Arc<RwLock<Vec<Run>>>,
+    /// Repository urls of runs, by run id
+    urls: Arc<RwLock<HashMap<String, String>>>,
+    disabled: Arc<RwLock<BTreeMap<String, DisabledTarget>>>,
+    crashes: Arc<RwLock<CrashIssues>>,
+    counter: Arc<AtomicU64>,
+    store: Option<Store>,
+    log: Logger,
+}
+
+impl Runs {
+    pub fn new(store: Option<Store>, log: Logger) -> Self {
+        Self {
+            runs: Arc::default(),
+            urls: Arc::default(),
+            disabled: Arc::default(),
+            crashes: Arc::default(),
+            counter: Arc::default(),
+            store,
+            log,
+        }
+    }
+
+    /// Generates a new run number, greater than numbers of earlier runs, also ones started
+    /// before the server restart if the counter is stored or seeded.
+    pub async fn next_number(&self) -> u64 {
+        if let Some(store) = &self.store {
+            match store.next_run_number().await {
+                Ok(n) => return n,
+                Err(e) => error!(self.log, "Cannot get run number from the state store"; "error" => e),
+            }
+        }
+        self.counter.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    /// Id of the run with the number, unique across server restarts.
+    pub fn id(number: u64) -> String {
+        format!("{}-{}", chrono::Utc::now().format("%Y%m%d%H%M%S"), number)
+    }
+
+    /// Makes later run numbers greater than `number`, e.g. the last one found in reports.
+    pub async fn seed(&self, number: u64) {
+        self.counter.fetch_max(number, Ordering::SeqCst);
+        if let Some(store) = &self.store {
+            if let Err(e) = store.seed_run_number(number).await {
+                error!(self.log, "Cannot seed run number in the state store"; "error" => e);
+            }
+        }
+    }
+
+    /// Adds the new run of the repository `url`.
+    pub async fn insert(&self, run: Run, url: &str) {
+        if let Some(store) = &self.store {
+            if let Err(e) = store.insert(&run, url).await {
+                error!(self.log, "Cannot store run"; "id" => &run.id, "error" => e);
+            }
+        }
+        self.urls.write().unwrap().insert(run.id.clone(), url.to_string());
+        self.runs.write().unwrap().push(run);
+    }
+
+    /// Repository url of the run.
+    pub async fn url(&self, id: &str) -> Option<String> {
+        if let Some(store) = &self.store {
+            match store.url(id).await {
+                Ok(url) => return url,
+                Err(e) => error!(self.log, "Cannot read run from the state store"; "id" => id, "error" => e),
+            }
+        }
+        self.urls.read().unwrap().get(id).cloned()
+    }
+
+    pub async fn get(&self, id: &str) -> Option<Run> {
+        if let Some(store) = &self.store {
+            match store.get(id).await {
+                Ok(run) => return run,
+                Err(e) => error!(self.log, "Cannot read run from the state store"; "id" => id, "error" => e),
+            }
+        }
+        self.runs.read().unwrap().iter().find(|r| r.id == id).cloned()
+    }
+
+    pub async fn list(&self) -> Vec<Run> {
+        if let Some(store) = &self.store {
+            match store.list().await {
+                Ok(runs) => return runs,
+                Err(e) => error!(self.log, "Cannot read runs from the state store"; "error" => e),
+            }
+        }
+        self.runs.read().unwrap().clone()
+    }
+
+    pub async fn finish(&self, id: &str, status: RunStatus, verdict: Option<Verdict>) {
+        if let Some(store) = &self.store {
+            if let Err(e) = store.finish(id, status, verdict).await {
+                error!(self.log, "Cannot store run status"; "id" => id, "error" => e);
+            }
+        }
+        if let Some(run) = self.runs.write().unwrap().iter_mut().find(|r| r.id == id) {
+            run.status = status;
+            run.verdict = verdict;
+        }
+    }
+
+    /// Targets excluded from runs, by name.
+    pub async fn disabled_targets(&self) -> Vec<DisabledTarget> {
+        if let Some(store) = &self.store {
+            match store.disabled_targets().await {
+                Ok(targets) => return targets,
+                Err(e) => error!(self.log, "Cannot read disabled targets from the state store"; "error" => e),
+            }
+        }
+        self.disabled.read().unwrap().values().cloned().collect()
+    }
+
+    /// Excludes the target from runs started later on behalf of the caller, returning
+    /// `false` if it is already disabled.
+    pub async fn disable_target(&self, target: &str, by: &str) -> bool {
+        let disabled = DisabledTarget {
+            target: target.to_string(),
+            by: by.to_string(),
+            since: Utc::now(),
+        };
+        let stored = match &self.store {
+            Some(store) => match store.disable_target(&disabled).await {
+                Ok(added) => Some(added),
+                Err(e) => {
+                    error!(self.log, "Cannot store disabled target"; "target" => target, "error" => e);
+                    None
+                }
+            },
+            None => None,
+        };
+        let mut targets = self.disabled.write().unwrap();
+        let added = !targets.contains_key(target);
+        targets.entry(target.to_string()).or_insert(disabled);
+        stored.unwrap_or(added)
+    }
+
+    /// Includes the disabled target in runs started later again, returning `false` if it
+    /// is not disabled.
+    pub async fn enable_target(&self, target: &str) -> bool {
+        let stored = match &self.store {
+            Some(store) => match store.enable_target(target).await {
+                Ok(removed) => Some(removed),
+                Err(e) => {
+                    error!(self.log, "Cannot remove disabled target from the state store"; "target" => target, "error" => e);
+                    None
+                }
+            },
+            None => None,
+        };
+        let removed = self.disabled.write().unwrap().remove(target).is_some();
+        stored.unwrap_or(removed)
+    }
+
+    /// Claims opening an issue for the crash signature of the target, returning `false` if
+    /// it is already claimed, so each crash signature gets a single issue.
+    pub async fn claim_crash(&self, target: &str, signature: &str) -> bool {
+        let stored = match &self.store {
+            Some(store) => match store.claim_crash(target, signature).await {
+                Ok(added) => Some(added),
+                Err(e) => {
+                    error!(self.log, "Cannot store crash signature"; "target" => target, "error" => e);
+                    None
+                }
+            },
+            None => None,
+        };
+        let mut crashes = self.crashes.write().unwrap();
+        let key = (target.to_string(), signature.to_string());
+        let added = !crashes.contains_key(&key);
+        crashes.entry(key).or_insert(None);
+        stored.unwrap_or(added)
+    }
+
+    /// Records the issue opened for the claimed crash signature of the target.
+    pub async fn set_crash_issue(&self, target: &str, signature: &str, issue: u64) {
+        if let Some(store) = &self.store {
+            if let Err(e) = store.set_crash_issue(target, signature, issue).await {
+                error!(self.log, "Cannot store crash issue"; "target" => target, "issue" => issue, "error" => e);
+            }
+        }
+        self.crashes.write().unwrap().insert((target.to_string(), signature.to_string()), Some(issue));
+    }
+
+    /// Releases the claim of the crash signature of the target whose issue cannot be
+    /// opened, so a later crash with it opens one.
+    pub async fn release_crash(&self, target: &str, signature: &str) {
+        if let Some(store) = &self.store {
+            if let Err(e) = store.release_crash(target, signature).await {
+                error!(self.log, "Cannot remove crash signature from the state store"; "target" => target, "error" => e);
+            }
+        }
+        self.crashes.write().unwrap().remove(&(target.to_string(), signature.to_string()));
+    }
+
+    /// The state store shared with other server processes, if configured.
+    pub fn store(&self) -> Option<&Store> {
+        self.store.as_ref()
+    }
+}
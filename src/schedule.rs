@@ -0,0 +1,224 @@
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use tokio::sync::watch;
+
+use crate::config;
+
+/// Scheduling state of a run.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SlotState {
+    /// Waiting for a free run slot
+    Waiting,
+    /// Fuzzing in its run slot
+    Running,
+    /// Stopped to give its slot to a higher priority run, with the id of that run
+    Paused(String),
+}
+
+struct Entry {
+    priority: i32,
+    /// Order the run is registered in, earlier runs of the same priority go first
+    seq: u64,
+    state: SlotState,
+    sender: watch::Sender<SlotState>,
+}
+
+#[derive(Default)]
+struct Entries {
+    next_seq: u64,
+    map: HashMap<String, Entry>,
+}
+
+/// Limits the number of runs fuzzing at once, giving free slots to runs of higher
+/// priority branches first.
+///
+/// With preemption enabled, a run that has no free slot pauses the running one of
+/// the lowest priority if it is lower than its own, taking its slot. The paused run
+/// continues once a slot is free again.
+pub struct Scheduler {
+    max_runs: usize,
+    preempt: bool,
+    entries: Mutex<Entries>,
+}
+
+impl Scheduler {
+    pub fn new(config: &config::Scheduling) -> Self {
+        Self {
+            max_runs: config.max_runs,
+            preempt: config.preempt,
+            entries: Mutex::new(Entries::default()),
+        }
+    }
+
+    /// Registers the run with the priority, returning its ticket. The run should start
+    /// fuzzing once the ticket is running, and the slot is released when it is dropped.
+    pub fn register(self: &Arc<Self>, id: impl Into<String>, priority: i32) -> Ticket {
+        let id = id.into();
+        let (sender, receiver) = watch::channel(SlotState::Waiting);
+        let mut entries = self.entries.lock().unwrap();
+        let seq = entries.next_seq;
+        entries.next_seq += 1;
+        entries.map.insert(
+            id.clone(),
+            Entry {
+                priority,
+                seq,
+                state: SlotState::Waiting,
+                sender,
+            },
+        );
+        self.rebalance(&mut entries);
+        Ticket {
+            scheduler: self.clone(),
+            id,
+            state: receiver,
+        }
+    }
+
+    /// Gives free slots to waiting or paused runs, pausing lower priority runs if needed.
+    fn rebalance(&self, entries: &mut Entries) {
+        let set = |entry: &mut Entry, state: SlotState| {
+            entry.state = state.clone();
+            let _ = entry.sender.send(state);
+        };
+        loop {
+            let running = entries.map.values().filter(|e| e.state == SlotState::Running).count();
+            let next = entries
+                .map
+                .iter()
+                .filter(|(_, e)| e.state != SlotState::Running)
+                .max_by_key(|(_, e)| (e.priority, Reverse(e.seq)))
+                .map(|(id, e)| (id.clone(), e.priority));
+            let (next, priority) = match next {
+                Some(next) => next,
+                None => return,
+            };
+            if self.max_runs == 0 || running < self.max_runs {
+                set(entries.map.get_mut(&next).unwrap(), SlotState::Running);
+                continue;
+            }
+            if !self.preempt {
+                return;
+            }
+            let victim = entries
+                .map
+                .iter_mut()
+                .filter(|(_, e)| e.state == SlotState::Running)
+                .min_by_key(|(_, e)| (e.priority, Reverse(e.seq)))
+                .filter(|(_, e)| e.priority < priority);
+            match victim {
+                Some((_, victim)) => set(victim, SlotState::Paused(next)),
+                None => return,
+            }
+        }
+    }
+}
+
+/// Place of a run in the [Scheduler].
+pub struct Ticket {
+    scheduler: Arc<Scheduler>,
+    id: String,
+    state: watch::Receiver<SlotState>,
+}
+
+impl Ticket {
+    pub fn state(&self) -> SlotState {
+        self.state.borrow().clone()
+    }
+
+    /// Receiver of changes of the run state.
+    pub fn subscribe(&self) -> watch::Receiver<SlotState> {
+        self.state.clone()
+    }
+
+    /// Waits until the run gets a slot.
+    pub async fn running(&mut self) {
+        while *self.state.borrow() != SlotState::Running {
+            if self.state.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+impl Drop for Ticket {
+    fn drop(&mut self) {
+        let mut entries = self.scheduler.entries.lock().unwrap();
+        entries.map.remove(&self.id);
+        self.scheduler.rebalance(&mut entries);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scheduler(max_runs: usize, preempt: bool) -> Arc<Scheduler> {
+        Arc::new(Scheduler::new(&config::Scheduling { max_runs, preempt, ..config::Scheduling::default() }))
+    }
+
+    #[test]
+    fn runs_are_unlimited_by_default() {
+        let scheduler = scheduler(0, true);
+        let tickets = (0..5).map(|i| scheduler.register(i.to_string(), 0)).collect::<Vec<_>>();
+        assert!(tickets.iter().all(|t| t.state() == SlotState::Running));
+    }
+
+    #[test]
+    fn free_slots_go_to_higher_priority_runs() {
+        let scheduler = scheduler(1, false);
+        let a = scheduler.register("a", 0);
+        let b = scheduler.register("b", 0);
+        let c = scheduler.register("c", 5);
+        let d = scheduler.register("d", 5);
+        assert_eq!(a.state(), SlotState::Running);
+        assert_eq!(c.state(), SlotState::Waiting);
+        drop(a);
+        assert_eq!(c.state(), SlotState::Running);
+        assert_eq!(b.state(), SlotState::Waiting);
+        drop(c);
+        assert_eq!(d.state(), SlotState::Running);
+        drop(d);
+        assert_eq!(b.state(), SlotState::Running);
+    }
+
+    #[test]
+    fn preempts_lower_priority_runs() {
+        let scheduler = scheduler(2, true);
+        let a = scheduler.register("a", 0);
+        let b = scheduler.register("b", 1);
+        let c = scheduler.register("c", 10);
+        // the lowest priority run is paused
+        assert_eq!(a.state(), SlotState::Paused("c".to_string()));
+        assert_eq!(b.state(), SlotState::Running);
+        assert_eq!(c.state(), SlotState::Running);
+        // runs of the same or a lower priority don't preempt
+        let d = scheduler.register("d", 1);
+        assert_eq!(d.state(), SlotState::Waiting);
+        let e = scheduler.register("e", 10);
+        assert_eq!(b.state(), SlotState::Paused("e".to_string()));
+        assert_eq!(e.state(), SlotState::Running);
+        // the paused run of a higher priority continues first
+        drop(c);
+        assert_eq!(b.state(), SlotState::Running);
+        assert_eq!(d.state(), SlotState::Waiting);
+        drop(e);
+        assert_eq!(d.state(), SlotState::Running);
+        assert_eq!(a.state(), SlotState::Paused("c".to_string()));
+        drop(b);
+        assert_eq!(a.state(), SlotState::Running);
+    }
+
+    #[test]
+    fn does_not_preempt_if_disabled() {
+        let scheduler = scheduler(1, false);
+        let a = scheduler.register("a", 0);
+        let b = scheduler.register("b", 10);
+        assert_eq!(a.state(), SlotState::Running);
+        assert_eq!(b.state(), SlotState::Waiting);
+    }
+}
@@ -0,0 +1,93 @@
+use std::{
+    path::{Path, PathBuf},
+};
+
+use slog::{debug, Logger};
+use tokio::{fs, process::Command};
+
+use crate::{
+    corpus,
+    error::{output_tail, Error, Result},
+};
+
+const ARCHIVE_EXTENSIONS: &[&str] = &[".tar", ".tar.gz", ".tgz", ".tar.xz", ".tar.bz2"];
+
+fn is_archive(name: &str) -> bool {
+    ARCHIVE_EXTENSIONS.iter().any(|ext| name.ends_with(ext))
+}
+
+/// Imports seed inputs from the `source` into the target corpus directory.
+///
+/// The source is either an HTTP(S) URL or a local path, pointing to a single
+/// input file, a tarball or (for local paths) a directory. Occurrences of
+/// `{target}` in the source are replaced with the target name. Files from
+/// tarballs and directories are imported recursively.
+///
+/// Returns number of added files.
+pub async fn import(source: &str, target: &str, corpus: impl AsRef<Path>, log: &Logger) -> Result<usize> {
+    let source = source.replace("{target}", target);
+    debug!(log, "Importing seeds"; "source" => &source, "target" => target);
+    let tmp = tempfile::tempdir()?;
+    let path = if source.starts_with("http://") || source.starts_with("https://") {
+        download(&source, tmp.path()).await?
+    } else {
+        PathBuf::from(&source)
+    };
+
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    if path.is_dir() {
+        merge_tree(&path, corpus.as_ref()).await
+    } else if is_archive(&name) {
+        let unpacked = tmp.path().join("unpacked");
+        fs::create_dir(&unpacked).await?;
+        let output = Command::new("tar")
+            .arg("-xf")
+            .arg(&path)
+            .arg("-C")
+            .arg(&unpacked)
+            .output()
+            .await?;
+        if !output.status.success() {
+            return Err(Error::other(format!("cannot unpack {}: {}", source, output_tail(&output.stderr))));
+        }
+        merge_tree(&unpacked, corpus.as_ref()).await
+    } else {
+        let dst = corpus.as_ref().join(&*name);
+        if dst.exists() {
+            return Ok(0);
+        }
+        fs::copy(&path, dst).await?;
+        Ok(1)
+    }
+}
+
+/// Downloads the url into the directory, keeping the last path segment as the file name.
+async fn download(url: &str, dir: &Path) -> Result<PathBuf> {
+    let response = reqwest::get(url).await?.error_for_status()?;
+    let name = response
+        .url()
+        .path_segments()
+        .and_then(|mut s| s.next_back())
+        .filter(|n| !n.is_empty())
+        .unwrap_or("seed")
+        .to_string();
+    let path = dir.join(name);
+    fs::write(&path, response.bytes().await?).await?;
+    Ok(path)
+}
+
+/// Merges files from the directory and all its subdirectories into the corpus.
+async fn merge_tree(src: &Path, dst: &Path) -> Result<usize> {
+    let mut added = corpus::merge(src, dst).await?;
+    let mut dirs = vec![src.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let mut read_dir = fs::read_dir(&dir).await?;
+        while let Some(entry) = read_dir.next_entry().await? {
+            if entry.file_type().await?.is_dir() {
+                added += corpus::merge(entry.path(), dst).await?;
+                dirs.push(entry.path());
+            }
+        }
+    }
+    Ok(added)
+}
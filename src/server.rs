@@ -1,15 +1,45 @@
-use std::{collections::HashMap, ffi::OsStr, io, net::SocketAddr, path::{Path, PathBuf}, sync::{Arc, RwLock}};
+use std::{collections::{BTreeSet, HashMap, HashSet}, convert::Infallible, ffi::OsStr, io, net::SocketAddr, path::{Path, PathBuf}, sync::{Arc, RwLock}, time::{Duration, Instant}};
 
 use derive_new::new;
-use failure::Error;
 use serde::{Deserialize, Serialize};
 use slog::{debug, error, info, o, trace, warn, Logger};
-use tokio::{process::Command, sync::{Mutex, Notify, broadcast::{self, Sender}}};
-use warp::Filter;
+use tokio::{process::Command, sync::{Notify, watch}};
+use url::Url;
+use percent_encoding::percent_decode_str;
+use tonic::body::BoxBody;
+use warp::{
+    http::{Request, StatusCode},
+    hyper::{service::{make_service_fn, service_fn, Service}, Body, Server},
+    Filter,
+};
 
-use crate::{build::Builder, common::{self, u8_slice_to_string}, config::{self, Config}, feedback::{Feedback, FeedbackClient, FeedbackLevel, LoggerClient}, slack::SlackClient};
+use crate::{aggregate::{Aggregator, ServerRuns}, api, artifacts::{self, RunArtifacts}, assets, audit::AuditLog, backup::Backup, benchmark::{Bench, Comparison}, bisect::{self, Bisection, Replay}, build::{BuildSlots, Builder}, cancel::{Scope, ScopeError}, common, config::{self, Config, Engine, TargetConfig, Variant}, error::{output_tail, Error}, corpus, dashboard, deliveries::{Deliveries, Delivery, DeliveryStatus}, distill::Distiller, feedback::{Feedback, FeedbackClient, FeedbackLevel, LoggerClient, MultiClient, RegressionsOnly}, github::{self, CheckConclusion, CheckRun, Comments, CrashIssues, CommitState, Deployments, GithubStatusClient, Releases}, grpc, harness::{self, HarnessCoverage}, hfuzz::{self, OutputFormat}, hooks::{self, Stage}, orphans::{self, Pids}, policy::{self, Verdict}, process, provenance::Provenance, report::{self, CrashRates}, resources, reload, runs::{DisabledTarget, Run, RunStatus, Runs, Trigger}, schedule::{Scheduler, SlotState}, seeds, slack::{self, SlackClient}, standby::{self, Role}, state::{self, Store, TargetRun}, storage::Storage, workspace::Metadata};
 
 const RUN_PATH: &str = "run";
+/// Maximum size of webhook payloads, GitHub caps them at 25 MB.
+const WEBHOOK_BODY_LIMIT: u64 = 25 * 1024 * 1024;
+/// Header with the HMAC signature of Bitbucket webhook request bodies, in the format of GitHub ones.
+const BITBUCKET_SIGNATURE_HEADER: &str = "X-Hub-Signature";
+/// Processed GitHub webhook events, by the `X-GitHub-Event` header.
+const GITHUB_EVENTS: [&str; 5] = ["ping", "push", "delete", "create", "release"];
+/// Processed Bitbucket webhook events, by the `X-Event-Key` header.
+const BITBUCKET_EVENTS: [&str; 1] = ["repo:push"];
+
+/// Number of recent weeks shown on the crash rates page.
+const CRASH_RATES_WEEKS: usize = 8;
+
+/// Interval of checking whether fuzzing of a run stalled.
+const WATCHDOG_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Marker of the comment with coverage changes, identifying it among other comments of a pull request.
+const COVERAGE_COMMENT_MARKER: &str = "<!-- fuzz-ci coverage -->";
+
+/// Directory of reports of release runs, within the reports directory.
+const RELEASES_DIR: &str = "releases";
+/// Directory of reports of distillation runs, within the reports directory.
+const DISTILLATION_DIR: &str = "distillation";
+/// Interval between checks whether corpora are due for distillation.
+const DISTILLATION_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
 
 #[derive(Serialize, Deserialize)]
 struct PingEvent {
@@ -23,6 +53,43 @@ struct PushEvent {
     repository: Repository,
     commits: Vec<Commit>,
     head_commit: Option<Commit>,
+    /// Whether the push deletes the branch
+    #[serde(default)]
+    deleted: bool,
+    /// Whether the push is not a fast-forward, rewriting the branch history
+    #[serde(default)]
+    forced: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct DeleteEvent {
+    #[serde(alias = "ref")]
+    ref_: String,
+    /// `branch` or `tag`
+    ref_type: String,
+    repository: Repository,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CreateEvent {
+    #[serde(alias = "ref")]
+    ref_: String,
+    /// `branch` or `tag`
+    ref_type: String,
+    repository: Repository,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ReleaseEvent {
+    /// `published`, `created`, `edited`, etc.
+    action: String,
+    release: GithubRelease,
+    repository: Repository,
+}
+
+#[derive(Serialize, Deserialize)]
+struct GithubRelease {
+    tag_name: String,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -46,6 +113,133 @@ struct Author {
     username: String,
 }
 
+/// Bitbucket Cloud push event, sent with `X-Event-Key: repo:push`, normalized into
+/// push events of its changes.
+#[derive(Deserialize)]
+struct BitbucketPushEvent {
+    repository: BitbucketRepository,
+    push: BitbucketPush,
+}
+
+#[derive(Deserialize)]
+struct BitbucketRepository {
+    /// `<workspace>/<name>`
+    full_name: String,
+    links: BitbucketLinks,
+}
+
+#[derive(Deserialize)]
+struct BitbucketLinks {
+    html: BitbucketLink,
+}
+
+#[derive(Deserialize)]
+struct BitbucketLink {
+    href: String,
+}
+
+#[derive(Deserialize)]
+struct BitbucketPush {
+    changes: Vec<BitbucketChange>,
+}
+
+#[derive(Deserialize)]
+struct BitbucketChange {
+    /// The branch or tag after the push, missing if it's deleted
+    new: Option<BitbucketRef>,
+    /// The branch or tag before the push, missing if it's created
+    old: Option<BitbucketRef>,
+    #[serde(default)]
+    commits: Vec<BitbucketCommit>,
+    #[serde(default)]
+    closed: bool,
+    #[serde(default)]
+    forced: bool,
+}
+
+#[derive(Deserialize)]
+struct BitbucketRef {
+    /// `branch` or `tag`
+    #[serde(rename = "type")]
+    type_: String,
+    name: String,
+    target: Option<BitbucketCommit>,
+}
+
+#[derive(Deserialize)]
+struct BitbucketCommit {
+    hash: String,
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    date: String,
+    author: Option<BitbucketAuthor>,
+}
+
+#[derive(Deserialize)]
+struct BitbucketAuthor {
+    /// `Name <email>`
+    raw: String,
+    user: Option<BitbucketUser>,
+}
+
+#[derive(Deserialize)]
+struct BitbucketUser {
+    nickname: Option<String>,
+    display_name: Option<String>,
+}
+
+impl From<BitbucketCommit> for Commit {
+    fn from(commit: BitbucketCommit) -> Self {
+        let (raw, user) = match commit.author {
+            Some(author) => (author.raw, author.user),
+            None => (String::new(), None),
+        };
+        let (name, email) = match raw.split_once('<') {
+            Some((name, email)) => (name.trim().to_string(), email.trim_end_matches('>').trim().to_string()),
+            None => (raw.trim().to_string(), String::new()),
+        };
+        let username = user.and_then(|user| user.nickname.or(user.display_name)).unwrap_or_else(|| name.clone());
+        Commit {
+            id: commit.hash,
+            message: commit.message,
+            timestamp: commit.date,
+            author: Author { name, email, username },
+        }
+    }
+}
+
+impl BitbucketPushEvent {
+    /// Push events of the changed branches and tags.
+    fn into_pushes(self) -> Vec<PushEvent> {
+        let repository = self.repository;
+        self.push
+            .changes
+            .into_iter()
+            .filter_map(|change| {
+                let reference = change.new.as_ref().or(change.old.as_ref())?;
+                let ref_ = match reference.type_.as_str() {
+                    "branch" => format!("refs/heads/{}", reference.name),
+                    "tag" => format!("refs/tags/{}", reference.name),
+                    _ => return None,
+                };
+                let head_commit = change.new.and_then(|new| new.target).map(Commit::from);
+                Some(PushEvent {
+                    ref_,
+                    repository: Repository {
+                        ssh_url: format!("git@bitbucket.org:{}.git", repository.full_name),
+                        url: repository.links.html.href.clone(),
+                    },
+                    commits: change.commits.into_iter().map(Commit::from).collect(),
+                    head_commit,
+                    deleted: change.closed,
+                    forced: change.forced,
+                })
+            })
+            .collect()
+    }
+}
+
 fn get_sync(
     notifies: Arc<RwLock<HashMap<String, Synch>>>,
     branch: &String,
@@ -54,25 +248,16 @@ fn get_sync(
     {
         let map = notifies.read().unwrap();
         if let Some(sync) = map.get(branch) {
-            trace!(
-                log,
-                "Found broadcast notification, notifying it to stop previous run"
-            );
-            match sync.bcast.send(()) {
-                Ok(_) => {
-                    debug!(log, "Notification is sent, waiting for fuzzing to complete");
-                }
-                Err(e) => warn!(log, "Notification is not sent"; "error" => e.to_string()),
-            };
+            debug!(log, "Cancelling previous run, waiting for fuzzing to complete");
+            sync.cancel.read().unwrap().cancel();
             return (sync.clone(), true);
         }
     }
 
-    trace!(log, "Creating new broadcast channel");
+    trace!(log, "Creating new branch synchronization");
     let notify = Synch::new();
     let mut map = notifies.write().unwrap();
     map.insert(branch.clone(), notify.clone());
-    trace!(log, "Added new broadcast channel");
     (notify, false)
 }
 
@@ -112,71 +297,374 @@ fn make_relative_to_repo(root: &Path, p: &str) -> Option<String> {
     }
 }
 
-async fn run_fuzzers<'a>(
-    url: String,
-    builder: Arc<Mutex<Builder>>,
-    config: Config,
-    feedback: Arc<Feedback>,
-    reports_path: &'a Path,
-    branch: &'a str,
-    stop_bc: Sender<()>,
-    log: Logger,
-) -> Result<(), Error> {
-    slog::info!(log, "A branch has been checked out"; "branch" => branch);
-    let path = std::env::current_dir()?.join(common::sanitize_path_segment(branch));
-    if path.exists() {
-        std::fs::remove_dir_all(&path)?;
-    }
-
+/// Environment for building and fuzzing projects in the checkout, with
+/// `path_env` paths resolved relative to it.
+pub(crate) fn run_env(config: &Config, checkout: &Path, log: &Logger) -> HashMap<String, String> {
     let mut env = config.env.clone();
+    env.extend(config.build.cargo_env());
     env.extend(config.path_env.iter().map(|(k, v)| (k.clone(), v.split(":").filter_map(|s| {
-        let abs = make_relative_to_repo(&path, s);
+        let abs = make_relative_to_repo(checkout, s);
         if abs.is_none() {
             error!(log, "Cannot map path to absolute: {}", s);
         }
         abs
     }).collect::<Vec<_>>().join(":"))));
+    env
+}
+
+/// Resolves fuzzing project directory within the checkout.
+///
+/// Explicitly configured path is used if specified, otherwise the project is
+/// looked up by its name among the checkout workspace members, falling back
+/// to the directory named after the project.
+pub(crate) fn project_dir(root: &Path, name: &str, conf: &TargetConfig, metadata: &Option<Metadata>) -> PathBuf {
+    if let Some(path) = &conf.path {
+        return root.join(path);
+    }
+    metadata
+        .as_ref()
+        .and_then(|m| m.member(name))
+        .map(|p| p.dir().to_path_buf())
+        .unwrap_or_else(|| root.join(name))
+}
+
+/// Fuzzing projects found among workspace members of the checkout by [config::Discovery],
+/// excluding configured ones, with paths relative to the checkout.
+pub(crate) fn discover_projects(root: &Path, config: &Config, metadata: &Option<Metadata>) -> Vec<(String, TargetConfig)> {
+    let discovery = match &config.discovery {
+        Some(discovery) => discovery,
+        None => return vec![],
+    };
+    let configured_dirs = config
+        .targets
+        .iter()
+        .map(|(name, conf)| project_dir(root, name, conf, metadata))
+        .collect::<HashSet<_>>();
+    let configured_targets = config.targets.values().flat_map(|c| c.targets.iter()).collect::<HashSet<_>>();
+    let mut projects = metadata
+        .iter()
+        .flat_map(Metadata::members)
+        .filter(|p| {
+            !discovery.exclude.contains(&p.name)
+                && !config.targets.contains_key(&p.name)
+                && !configured_dirs.contains(p.dir())
+        })
+        .filter_map(|p| {
+            // targets of the same name would share the corpus
+            let mut targets = p.fuzz_targets();
+            targets.retain(|t| !configured_targets.contains(t));
+            if targets.is_empty() {
+                return None;
+            }
+            let path = p.dir().strip_prefix(root).unwrap_or_else(|_| p.dir());
+            Some((p.name.clone(), TargetConfig::new(Some(path.to_string_lossy().into_owned()), targets)))
+        })
+        .collect::<Vec<_>>();
+    projects.sort_by(|(a, _), (b, _)| a.cmp(b));
+    projects
+}
+
+/// Fuzzing run parameters.
+#[derive(Clone)]
+struct RunInfo {
+    /// target project repository url
+    url: String,
+    branch: String,
+    /// commit to check out instead of the branch head
+    commit: Option<String>,
+    run_id: String,
+    /// directory the fuzzing project is checked out to
+    checkout_dir: PathBuf,
+    /// location of the run reports relative to the reports directory
+    reports_loc: PathBuf,
+    /// pid files of fuzzing processes, so they are killed if the server crashes
+    pids: Option<Pids>,
+    /// targets disabled by admins when the run is started
+    disabled_targets: BTreeSet<String>,
+    /// targets selected to fuzz, all if empty
+    targets: BTreeSet<String>,
+    /// number of the run, ordering its report among reports of the branch
+    number: u64,
+    /// whether corpora are distilled with builds of the run instead of fuzzing
+    distill: bool,
+    /// fuzzing time of each project in seconds, instead of `--run_time` of its run arguments
+    duration: Option<u64>,
+}
+
+impl RunInfo {
+    fn new(
+        url: String,
+        branch: String,
+        commit: Option<String>,
+        run_id: String,
+        repo: Option<&str>,
+        workdir: &config::Workdir,
+        id: &str,
+    ) -> io::Result<Self> {
+        // in multi-repo mode, directories of each repository are kept separately
+        let namespace = repo.into_iter().chain(std::iter::once(branch.as_str())).collect::<Vec<_>>();
+        let checkout_dir = std::env::current_dir()?
+            .join(&workdir.path)
+            .join(common::new_local_path(&namespace))
+            .join(id);
+        let mut reports_loc = common::new_local_path(&namespace);
+        reports_loc.push(common::sanitize_path_segment(&run_id));
+        let pids = workdir.kill_orphans.then(|| Pids::new(workdir.pids_dir(), id));
+        Ok(Self {
+            url,
+            branch,
+            commit,
+            run_id,
+            checkout_dir,
+            reports_loc,
+            pids,
+            disabled_targets: BTreeSet::new(),
+            targets: BTreeSet::new(),
+            number: 0,
+            distill: false,
+            duration: None,
+        })
+    }
+
+    /// Environment variables describing the run, passed to hook commands.
+    fn hooks_env(&self, config: &Config) -> HashMap<String, String> {
+        let mut env = config.env.clone();
+        env.extend(config.build.cargo_env());
+        env.insert("FUZZ_CI_REPO_URL".into(), self.url.clone());
+        env.insert("FUZZ_CI_BRANCH".into(), self.branch.clone());
+        env.insert("FUZZ_CI_RUN_ID".into(), self.run_id.clone());
+        env.insert("FUZZ_CI_CHECKOUT_DIR".into(), self.checkout_dir.to_string_lossy().into_owned());
+        env.insert("FUZZ_CI_CODE_DIR".into(), config.code_dir(&self.checkout_dir).to_string_lossy().into_owned());
+        env.insert(
+            "FUZZ_CI_REPORTS_DIR".into(),
+            config.reports_path.join(&self.reports_loc).to_string_lossy().into_owned(),
+        );
+        env
+    }
+}
+
+/// Removes working directories of the branch according to the cleanup policy,
+/// once the run is completed. Directories of runs still in progress are kept.
+async fn cleanup_workdirs(run: &RunInfo, workdir: &config::Workdir, success: bool, runs: &Runs, log: &Logger) {
+    let remove = |dir: &Path| {
+        debug!(log, "Removing working directory"; "dir" => dir.to_str());
+        if let Err(e) = std::fs::remove_dir_all(dir) {
+            warn!(log, "Cannot remove working directory"; "dir" => dir.to_str(), "error" => e.to_string());
+        }
+    };
+    match workdir.cleanup {
+        config::Cleanup::Always => remove(&run.checkout_dir),
+        config::Cleanup::OnSuccess if success => remove(&run.checkout_dir),
+        config::Cleanup::OnSuccess => (),
+        config::Cleanup::KeepLast => {
+            let branch_dir = match run.checkout_dir.parent() {
+                Some(dir) => dir,
+                None => return,
+            };
+            let entries = match std::fs::read_dir(branch_dir) {
+                Ok(entries) => entries
+                    .filter_map(|e| e.ok())
+                    .filter(|e| e.path().is_dir())
+                    .filter_map(|e| Some((e.file_name(), e.metadata().ok()?.modified().ok()?, e.path())))
+                    .collect::<Vec<_>>(),
+                Err(e) => {
+                    warn!(log, "Cannot list working directories"; "dir" => branch_dir.to_str(), "error" => e.to_string());
+                    return;
+                }
+            };
+            let mut dirs = vec![];
+            for (name, modified, dir) in entries {
+                let run = match name.to_str() {
+                    Some(id) => runs.get(id).await,
+                    None => None,
+                };
+                if run.map_or(true, |r| r.status != RunStatus::Running) {
+                    dirs.push((modified, dir));
+                }
+            }
+            dirs.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+            for (_, dir) in dirs.iter().skip(workdir.keep) {
+                remove(dir);
+            }
+        }
+    }
+}
+
+async fn run_fuzzers(
+    run: &RunInfo,
+    build_slots: Arc<BuildSlots>,
+    output_format: OutputFormat,
+    mut config: Config,
+    feedback: Arc<Feedback>,
+    paused: watch::Receiver<bool>,
+    cancel: Scope,
+    log: Logger,
+) -> Result<(), Error> {
+    let branch = &run.branch;
+    config.targets.retain(|_, conf| conf.is_scheduled(branch));
+    let reports_path = &run.reports_loc;
+    slog::info!(log, "A branch has been checked out");
+    let path = run.checkout_dir.clone();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let env = run_env(&config, &path, &log);
 
     trace!(log, "Environment: {:?}", env);
 
-    super::checkout::checkout(&path, url, &branch, log.new(slog::o!("stage" => "checkout"))).await?;
+    super::checkout::checkout(
+        &path,
+        &run.url,
+        &branch,
+        run.commit.as_deref(),
+        config.checkout_script.as_deref(),
+        log.new(slog::o!("stage" => "checkout")),
+    )
+    .await?;
+    match super::checkout::head(config.code_dir(&path)).await {
+        Ok(commit) => {
+            feedback.set_commit(&commit).await;
+            if let Some(github) = config.github.as_ref().filter(|github| github.statuses) {
+                publish_status(github, run, &commit, CommitState::Pending, "Fuzzing is in progress", &feedback, &log).await;
+            }
+            if let Some(github) = config.github.as_ref().filter(|github| github.checks) {
+                create_check_run(github, run, &commit, &feedback, &log).await;
+            }
+        }
+        Err(e) => debug!(log, "Cannot get the fuzzed commit"; "error" => e.to_string()),
+    }
+    let builder = Builder::new(config.kcov.clone(), &config.build, log.new(o!("component" => "builder")))
+        .with_cargo_hfuzz(config.honggfuzz.cargo_hfuzz.clone());
+    builder.configure_sources(&path).await?;
+    cancelled(&cancel, "run")?;
+    let hooks_env = run.hooks_env(&config);
+    hooks::run(&config.hooks, Stage::AfterCheckout, &path, &hooks_env, &log).await?;
     let mut handles = vec![];
-    let tezedge_root = path.join("code/tezedge");
 
-    if let Some(ref corpus) = config.corpus {
-        info!(log, "Preparing corpus directory {}...", corpus);
-        for (name, conf) in &config.targets {
+    let metadata = match Metadata::read_opt(&path, &log).await {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            warn!(log, "Cannot read checkout workspace metadata"; "error" => e.to_string());
+            None
+        }
+    };
+    for (name, conf) in discover_projects(&path, &config, &metadata) {
+        info!(log, "Discovered fuzzing project"; "project" => &name, "path" => &conf.path, "targets" => conf.targets.len());
+        feedback.debug(format!("Fuzzing discovered project `{}` with {} targets", name, conf.targets.len()));
+        config.targets.insert(name, conf);
+    }
+    if !run.targets.is_empty() {
+        for conf in config.targets.values_mut() {
+            conf.targets.retain(|target| run.targets.contains(target));
+        }
+        config.targets.retain(|_, conf| !conf.targets.is_empty());
+    }
+    let mut disabled = vec![];
+    for conf in config.targets.values_mut() {
+        disabled.extend(conf.targets.iter().filter(|t| run.disabled_targets.contains(*t)).cloned());
+        conf.targets.retain(|target| !run.disabled_targets.contains(target));
+    }
+    if !disabled.is_empty() {
+        info!(log, "Skipping disabled targets"; "targets" => disabled.join(", "));
+        feedback.message(format!("Disabled targets are not fuzzed: {}", disabled.join(", ")));
+    }
+    let project_dirs = config
+        .targets
+        .iter()
+        .map(|(name, conf)| (name.clone(), project_dir(&path, name, conf, &metadata)))
+        .collect::<HashMap<_, _>>();
+    feedback.set_provenance(Provenance::collect(&config, &run.url, &path, &env, &project_dirs, &log).await).await;
+    feedback
+        .set_targets(config.targets.values().flat_map(|conf| conf.metadata.clone()).collect())
+        .await;
+    // scopes of all projects and targets exist from the start, so a name to cancel is
+    // resolved among all of them, not only among ones started by then
+    for (name, conf) in &config.targets {
+        let project = cancel.child(name);
+        for variant in conf.variants() {
             for target in &conf.targets {
-                let corpus = Path::new(corpus).join(target);
-                if !corpus.is_dir() {
-                    if corpus.exists() {
-                        return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("is not a directory: {}", corpus.to_string_lossy())).into());
-                    }
-                    let source = path.join(&conf.path.as_ref().unwrap_or(name)).join("hfuzz_workspace").join(target).join("input");
+                project.child(&variant.target_id(target));
+            }
+        }
+    }
+
+    for (name, conf) in &config.targets {
+        let corpus = match conf.corpus_dir(config.corpus.as_deref()) {
+            Some(corpus) => corpus,
+            None => continue,
+        };
+        info!(log, "Preparing corpus directory {}...", corpus; "project" => name);
+        for target in &conf.targets {
+            let corpus = Path::new(corpus).join(target);
+            if !corpus.is_dir() {
+                if corpus.exists() {
+                    return Err(Error::other(format!("corpus {} is not a directory", corpus.to_string_lossy())));
+                }
+                let project = &project_dirs[name];
+                let workspace = conf.build_env(&config.build, project).remove("HFUZZ_WORKSPACE");
+                let source = project.join(workspace.as_deref().unwrap_or("hfuzz_workspace")).join(target).join("input");
+                // with seeds configured, the project isn't required to provide its own input files,
+                // and projects of other engines don't keep them in the honggfuzz workspace
+                if conf.engine == Engine::Honggfuzz && (conf.seeds.is_empty() || source.is_dir()) {
                     debug!(log, "Copying input files from {:?} to {:?}", source, corpus);
                     let output = Command::new("cp").args(&[OsStr::new("-r"), source.as_os_str(), corpus.as_os_str()]).output().await?;
                     if !output.status.success() {
-                        error!(log, "Cannot copy input files for {}", target; "stderr" => u8_slice_to_string(&output.stderr));
-                        return Err(io::Error::new(io::ErrorKind::Other, format!("Cannot copy input files for {}", target)).into());
+                        return Err(Error::other(format!(
+                            "cannot copy input files for {} from {}: {}",
+                            target,
+                            source.to_string_lossy(),
+                            output_tail(&output.stderr)
+                        )));
+                    }
+                }
+                tokio::fs::create_dir_all(&corpus).await?;
+                for source in &conf.seeds {
+                    match seeds::import(source, target, &corpus, &log).await {
+                        Ok(added) => info!(log, "Imported seed inputs"; "target" => target, "source" => source, "added" => added),
+                        Err(e) => {
+                            error!(log, "Error importing seed inputs"; "target" => target, "source" => source, "error" => e.to_string());
+                            feedback.warning(format!("Cannot import seeds for `{}` from {}: {}", target, source, e));
+                        }
+                    }
+                }
+            }
+        }
+
+        if config.maintenance.dedup {
+            for target in &conf.targets {
+                match corpus::dedup(Path::new(corpus).join(target)).await {
+                    Ok(stats) if stats.removed > 0 => {
+                        info!(log, "Removed duplicate inputs"; "target" => target, "removed" => stats.removed, "bytes" => stats.reclaimed);
+                        feedback.debug(format!(
+                            "Removed {} of {} inputs from `{}` corpus as duplicates, {} bytes reclaimed",
+                            stats.removed, stats.files, target, stats.reclaimed
+                        ));
                     }
-                    tokio::fs::create_dir_all(corpus).await?;
+                    Ok(_) => (),
+                    Err(e) => error!(log, "Error deduplicating corpus for {}: {}", target, e),
                 }
             }
         }
     }
 
-    if config.kcov.is_some() {
+    if let Some(kcov) = config.kcov.as_ref().filter(|_| !run.distill) {
         debug!(log, "Generating coverage reports");
         let mut some = false;
-        for (name, conf) in &config.targets {
-            let path = path.join(conf.path.as_ref().unwrap_or(&name));
-
-            let builder = builder.lock().await;
-
-            match builder.kcov(&tezedge_root, &path).await {
+        // coverage reports are rendered for cargo tests, so only of honggfuzz projects
+        for (name, conf) in config.targets.iter().filter(|(_, conf)| conf.engine == Engine::Honggfuzz) {
+            let path = &project_dirs[name];
+            let project = cancel.child(name);
+            let _slot = tokio::select! {
+                slot = build_slots.acquire(name) => slot,
+                _ = project.cancelled() => continue,
+            };
+            let mut kcov_env = env.clone();
+            kcov_env.extend(conf.kcov_env(kcov));
+            match builder.clone().with_cancel(project).kcov(&path, conf.corpus_dir(config.corpus.as_deref()), &kcov_env).await {
                 Ok(_) => {
                     if let Err(e) = copy_cov_files(
-                        &path,
+                        path,
                         config.reports_path.join(reports_path).join(&name),
                         &log,
                     )
@@ -187,16 +675,20 @@ async fn run_fuzzers<'a>(
                         some = true;
                     }
                 }
+                Err(Error::Cancelled(_)) => (),
                 Err(e) => {
                     error!(log, "Error running kcov: {}", e);
+                    if e.is_timeout() {
+                        feedback.warning(format!("Coverage report for `{}`: {}", name, e));
+                    }
                 }
             }
         }
         if some {
-            if let Some(url) = config.url {
+            if let Some(url) = &config.url {
                 feedback.message(format!(
                     "Coverage reports are ready: {}",
-                    common::reports_url(&url, reports_path)?
+                    common::reports_url(url, reports_path)?
                 ));
             }
         }
@@ -207,165 +699,2096 @@ async fn run_fuzzers<'a>(
         if conf.targets.is_empty() {
             continue;
         }
-        let path = path.join(conf.path.as_ref().unwrap_or(&name));
-        let _ = builder.lock().await.clean(&path).await;
-        let _ = builder.lock().await.build(&path).await;
+        let path = &project_dirs[name];
+        let project = cancel.child(name);
+        let _slot = tokio::select! {
+            slot = build_slots.acquire(name) => slot,
+            _ = project.cancelled() => continue,
+        };
+        let builder = builder.clone().with_cancel(project);
+        if let Some(command) = &conf.command {
+            match builder.build_command(&path, &command.build, &conf.build_env(&config.build, path)).await {
+                Ok(usage) => {
+                    info!(log, "Built {}", name; "usage" => usage.to_string());
+                    feedback.set_build_usage(name, usage);
+                }
+                Err(Error::Cancelled(_)) => (),
+                Err(e) => {
+                    error!(log, "Error building {}: {}", name, e);
+                    feedback.build_failed(name, e);
+                }
+            }
+            continue;
+        }
+        match builder.clean(&path).await {
+            Ok(_) => (),
+            Err(Error::Cancelled(_)) => continue,
+            Err(e) => error!(log, "Error cleaning {}: {}", name, e),
+        }
+        let build_env = conf.build_env(&config.build, path);
+        for variant in conf.variants() {
+            match builder.build(&path, &variant, &build_env).await {
+                Ok(usage) => {
+                    info!(log, "Built {}", name; "variant" => &variant.name, "usage" => usage.to_string());
+                    feedback.set_build_usage(&variant.target_id(name), usage);
+                }
+                Err(Error::Cancelled(_)) => break,
+                Err(e) => {
+                    error!(log, "Error building {}: {}", name, e);
+                    feedback.build_failed(name, e);
+                }
+            }
+        }
     }
 
-    for (name, conf) in config.targets {
-        if conf.targets.is_empty() {
-            continue;
+    if run.distill {
+        cancelled(&cancel, "run")?;
+        let distillation = config
+            .distillation
+            .as_ref()
+            .ok_or_else(|| Error::other("corpus distillation is not configured"))?;
+        let log = log.new(o!("stage" => "distillation"));
+        let replay = Replay::new(env.clone(), Duration::from_secs(distillation.timeout), output_format, cancel.clone(), log.clone())
+            .with_cargo_hfuzz(config.honggfuzz.cargo_hfuzz.clone());
+        return run_distillation(&config, distillation, &project_dirs, &replay, &feedback, &log).await;
+    }
+
+    if let Some(benchmark) = config.benchmark.as_ref().filter(|b| b.is_scheduled(branch)) {
+        cancelled(&cancel, "run")?;
+        let log = log.new(o!("stage" => "benchmark"));
+        let bench = Bench::new(env.clone(), Duration::from_secs(benchmark.duration), output_format, cancel.clone(), log.clone())
+            .with_cargo_hfuzz(config.honggfuzz.cargo_hfuzz.clone());
+        let base_dir = sibling_checkout_dir(&path, "base");
+        match run_benchmark(run, &config, &builder, &build_slots, bench, &feedback, &log).await {
+            Ok(_) | Err(Error::Cancelled(_)) => (),
+            Err(e) => {
+                error!(log, "Error benchmarking fuzz targets"; "error" => e.to_string());
+                feedback.warning(format!("Cannot benchmark fuzz targets: {}", e));
+            }
         }
-        let path = path.join(conf.path.as_ref().unwrap_or(&name));
-        let env = env.clone();
-        let hfuzz_config = if let Some(hfuzz_config) = config.honggfuzz.clone() {
-            hfuzz_config
-        } else {
+        if let Err(e) = tokio::fs::remove_dir_all(&base_dir).await {
+            debug!(log, "Cannot remove base checkout"; "error" => e.to_string());
+        }
+    }
+
+    if let Some(comparison) = config.harness_comparison.as_ref().filter(|c| c.is_scheduled(branch)) {
+        cancelled(&cancel, "run")?;
+        let log = log.new(o!("stage" => "harness"));
+        let replay = Replay::new(env.clone(), Duration::from_secs(comparison.timeout), output_format, cancel.clone(), log.clone())
+            .with_cargo_hfuzz(config.honggfuzz.cargo_hfuzz.clone());
+        let previous_dir = sibling_checkout_dir(&path, "harness");
+        match compare_harnesses(run, &config, &builder, &build_slots, replay, &feedback, &log).await {
+            Ok(_) | Err(Error::Cancelled(_)) => (),
+            Err(e) => {
+                error!(log, "Error comparing changed fuzzing harnesses"; "error" => e.to_string());
+                feedback.warning(format!("Cannot compare changed fuzzing harnesses: {}", e));
+            }
+        }
+        if let Err(e) = tokio::fs::remove_dir_all(&previous_dir).await {
+            debug!(log, "Cannot remove previous harness checkout"; "error" => e.to_string());
+        }
+    }
+
+    if let Some(bisect) = config.bisect.as_ref().filter(|b| b.is_scheduled(branch)) {
+        cancelled(&cancel, "run")?;
+        let log = log.new(o!("stage" => "bisect"));
+        let bisector = Bisector {
+            run,
+            config: &config,
+            builder: &builder,
+            build_slots: &build_slots,
+            replay: Replay::new(env.clone(), Duration::from_secs(bisect.timeout), output_format, cancel.clone(), log.clone())
+                .with_cargo_hfuzz(config.honggfuzz.cargo_hfuzz.clone()),
+            dir: sibling_checkout_dir(&path, "bisect"),
+            log: log.clone(),
+        };
+        match run_bisection(&bisector, bisect, &feedback).await {
+            Ok(_) | Err(Error::Cancelled(_)) => (),
+            Err(e) => {
+                error!(log, "Error bisecting coverage regressions"; "error" => e.to_string());
+                feedback.warning(format!("Cannot bisect coverage regressions: {}", e));
+            }
+        }
+        if let Err(e) = tokio::fs::remove_dir_all(&bisector.dir).await {
+            debug!(log, "Cannot remove bisection checkout"; "error" => e.to_string());
+        }
+    }
+
+    // targets of a branch are fuzzed with a corpus overlay when branch isolation is configured,
+    // run corpora are kept per project along with the corpus their new inputs are merged back to
+    let mut run_corpora = HashMap::new();
+    for (name, conf) in &config.targets {
+        let run_corpus = match conf.corpus_dir(config.corpus.as_deref()) {
+            Some(corpus) if config.branch_corpus => {
+                let overlay = corpus::overlay_dir(corpus, branch);
+                for target in &conf.targets {
+                    let added = corpus::merge(Path::new(corpus).join(target), overlay.join(target)).await?;
+                    debug!(log, "Seeded corpus overlay"; "target" => target, "added" => added);
+                }
+                Some(overlay.to_string_lossy().into_owned())
+            }
+            corpus => corpus.map(String::from),
+        };
+        run_corpora.insert(name.clone(), run_corpus);
+    }
+    // corpora of targets stored after fuzzing
+    let snapshots = match &config.artifacts {
+        Some(artifacts) if artifacts.snapshot_corpus => run_corpora
+            .iter()
+            .filter_map(|(name, corpus)| Some((corpus.clone()?, config.targets[name].targets.clone())))
+            .collect(),
+        _ => vec![],
+    };
+    // corpora of related targets copied into each other while fuzzing
+    let pollinated = match &config.cross_pollination {
+        Some(cross_pollination) => cross_pollination
+            .groups
+            .iter()
+            .map(|group| {
+                group
+                    .iter()
+                    .filter_map(|target| {
+                        let (name, _) = config.targets.iter().find(|(_, c)| c.targets.contains(target))?;
+                        Some(Path::new(run_corpora.get(name)?.as_ref()?).join(target))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .filter(|dirs| dirs.len() > 1)
+            .collect::<Vec<_>>(),
+        None => vec![],
+    };
+    let merged_back = config
+        .targets
+        .iter()
+        .filter_map(|(name, c)| Some((name.clone(), c.corpus_dir(config.corpus.as_deref())?.to_string(), c.clone())))
+        .collect::<Vec<_>>();
+
+    cancelled(&cancel, "run")?;
+    hooks::run(&config.hooks, Stage::BeforeFuzzing, &path, &hooks_env, &log).await?;
+
+    let projects = config.targets.keys().cloned().collect::<Vec<_>>();
+    for (name, mut conf) in config.targets {
+        let project = cancel.child(&name);
+        if conf.targets.is_empty() || project.is_cancelled() {
             continue;
+        }
+        conf.corpus_limits = conf.corpus_limits.or(Some(config.maintenance.limits));
+        // fuzzing time is enforced here rather than by honggfuzz, so paused time isn't counted
+        let run_time = match conf.engine {
+            Engine::Honggfuzz => {
+                let mut hfuzz_config = conf.hfuzz_config(&config.honggfuzz);
+                let run_time = hfuzz_config.take_run_time();
+                conf.run_args = Some(hfuzz_config.run_args);
+                run.duration.or(run_time)
+            }
+            Engine::Command => run.duration,
         };
+        if let Some(run_time) = run_time {
+            let (project, paused) = (project.clone(), paused.clone());
+            tokio::spawn(async move {
+                tokio::select! {
+                    _ = fuzzing_time(paused, Duration::from_secs(run_time)) => project.cancel(),
+                    _ = project.cancelled() => (),
+                }
+            });
+        }
+        let path = project_dirs[&name].clone();
+        let mut env = env.clone();
+        env.extend(conf.build_env(&config.build, &path));
+        let hfuzz_config = config.honggfuzz.clone();
         let feedback = feedback.clone();
         let log = log.new(slog::o!("stage" => "hfuzz"));
-        let corpus = config.corpus.clone();
-        let stop_bc = stop_bc.clone();
+        let corpus = run_corpora.remove(&name).flatten();
+        let pids = run.pids.clone();
         handles.push(tokio::spawn(async move {
-            super::hfuzz::run(path, env, conf, hfuzz_config, corpus, feedback, stop_bc, log).await
+            match conf.engine {
+                Engine::Honggfuzz => super::hfuzz::run(path, env, conf, hfuzz_config, output_format, corpus, feedback, pids, project, log).await,
+                Engine::Command => super::command::run(path, env, conf, corpus, feedback, pids, project, log).await,
+            }
         }));
     }
-    feedback.started();
-    for handle in handles {
-        match handle.await {
-            Ok(r) => match r {
-                Ok(_) => (),
-                Err(e) => error!(log, "Fuzzer finished with error: {}", e),
-            },
-            Err(e) => error!(log, "Fuzzer panicked with error: {}", e),
+    feedback.started().await;
+    let fuzzing = async {
+        for handle in handles {
+            match handle.await {
+                Ok(r) => match r {
+                    Ok(_) => (),
+                    Err(e) => error!(log, "Fuzzer finished with error: {}", e),
+                },
+                Err(e) => error!(log, "Fuzzer panicked with error: {}", e),
+            }
         }
+    };
+    tokio::pin!(fuzzing);
+    let pollination = config.cross_pollination.as_ref().map(|c| {
+        let interval = Duration::from_secs(c.interval);
+        tokio::spawn(cross_pollinate(pollinated, interval, log.new(o!("stage" => "cross-pollination"))))
+    });
+    let stalled = tokio::select! {
+        _ = &mut fuzzing => None,
+        since = watchdog(&path, &feedback, Duration::from_secs(config.feedback.stall_timeout)) => Some(since),
+    };
+    if let Some(pollination) = pollination {
+        pollination.abort();
+    }
+    if let Some(since) = stalled {
+        // stops targets still waiting, e.g. for output of their exited processes
+        for name in &projects {
+            cancel.child(name).cancel();
+        }
+        fuzzing.await;
+        let message = format!(
+            "No coverage updates for {}s and no fuzzing processes are left, the run is failed",
+            since.as_secs()
+        );
+        error!(log, "Run stalled"; "since_update" => since.as_secs());
+        feedback.critical(&message);
+        return Err(Error::other(message));
     }
-    Ok(())
-}
 
-/// Unique run ID, containing commit message, commit ID, committer and this run timestamp
-fn get_run_id(commit: &Commit) -> String {
-    // 5-char commit id
-    let (id, _) = commit.id.split_at(5);
-    // first line of the commit message
-    let message = commit.message.split('\n').next().unwrap();
-    format!(
-        "_{}_ - {} by {} at {}",
-        message,
-        id,
-        commit.author.username,
-        chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string()
-    )
+    for (corpus, targets) in &snapshots {
+        for target in targets {
+            feedback.add_corpus_snapshot(target, &Path::new(corpus).join(target)).await;
+        }
+    }
+
+    if config.branch_corpus && config.protected_branches.contains(branch) {
+        let log = log.new(o!("stage" => "merge-back"));
+        let distillation = config.distillation.as_ref();
+        let timeout = distillation.map_or(config::Distillation::default_timeout(), |d| d.timeout);
+        let batch = distillation.map_or(config::Distillation::default_batch(), |d| d.batch);
+        let replay = Replay::new(env.clone(), Duration::from_secs(timeout), output_format, cancel.clone(), log.clone())
+            .with_cargo_hfuzz(config.honggfuzz.cargo_hfuzz.clone());
+        let mut merged = 0;
+        for (name, corpus, conf) in &merged_back {
+            let overlay = corpus::overlay_dir(corpus, branch);
+            let project = &project_dirs[name];
+            let build_env = conf.build_env(&config.build, project);
+            let variant = conf.variants().into_iter().next();
+            for target in &conf.targets {
+                let (main, run_corpus) = (Path::new(corpus).join(target), overlay.join(target));
+                let added = match (&variant, conf.engine) {
+                    // only overlay inputs adding coverage to the main corpus are merged
+                    (Some(variant), Engine::Honggfuzz) => {
+                        let binary = conf.binary_name(target);
+                        let distiller = Distiller {
+                            replay: &replay,
+                            project,
+                            env: &build_env,
+                            variant,
+                            target,
+                            binary: &binary,
+                            batch,
+                        };
+                        match distiller.new_inputs(&main, &run_corpus).await {
+                            Ok(inputs) => corpus::add(&inputs, &main).await.map_err(Error::from),
+                            Err(e) => Err(e),
+                        }
+                    }
+                    // coverage of command engines isn't replayed, so all their new inputs are merged
+                    _ => corpus::merge(&run_corpus, &main).await.map_err(Error::from),
+                };
+                match added {
+                    Ok(added) => merged += added,
+                    Err(e) => error!(log, "Error merging corpus overlay for {}: {}", target, e),
+                }
+            }
+        }
+        if !merged_back.is_empty() {
+            info!(log, "Merged corpus overlay into the main corpus"; "inputs" => merged);
+            feedback.message(format!("{} new inputs adding coverage are merged into the main corpus", merged));
+        }
+    }
+    Ok(())
 }
 
-async fn create_feedback(
-    config: &config::Config,
-    description: &str,
-    reports_loc: &Path,
-    stop_bc: &Sender<()>,
+/// Distills the main corpus of each target of honggfuzz projects built in the run checkout,
+/// replaying it on the first build variant of the project.
+async fn run_distillation(
+    config: &Config,
+    distillation: &config::Distillation,
+    project_dirs: &HashMap<String, PathBuf>,
+    replay: &Replay,
+    feedback: &Feedback,
     log: &Logger,
-) -> Arc<Feedback> {
-    let client: Box<dyn FeedbackClient + Sync + Send> = if let Some(config) = &config.slack {
-        Box::new(SlackClient::new(
-            description,
-            &config.channel,
-            &config.token,
-            if config.verbose { FeedbackLevel::Info } else { FeedbackLevel::Error },
-            log.clone(),
-        ))
-    } else {
-        Box::new(LoggerClient::new(description, log.clone()))
-    };
-    let feedback = Feedback::new(
-        &config.feedback,
-        client,
-        &config.reports_path,
-        &config.url,
-        &reports_loc,
-        log.clone(),
-    )
-    .await
-    .expect("can't create feedback");
-    let feedback = Arc::new(feedback);
-    {
-        let feedback = feedback.clone();
-        let mut stop = stop_bc.subscribe();
-        let log = log.clone();
-        tokio::spawn(async move {
-            if let Err(e) = stop.recv().await {
-                error!(log, "Error receiving broadcast"; "error" => e.to_string());
+) -> Result<(), Error> {
+    for (name, conf) in config.targets.iter().filter(|(_, conf)| conf.engine == Engine::Honggfuzz) {
+        let (variant, corpus) = match (conf.variants().into_iter().next(), conf.corpus_dir(config.corpus.as_deref())) {
+            (Some(variant), Some(corpus)) => (variant, corpus),
+            _ => continue,
+        };
+        let project = &project_dirs[name];
+        let env = conf.build_env(&config.build, project);
+        for target in &conf.targets {
+            let id = variant.target_id(target);
+            let binary = conf.binary_name(target);
+            let distiller = Distiller {
+                replay,
+                project,
+                env: &env,
+                variant: &variant,
+                target,
+                binary: &binary,
+                batch: distillation.batch,
+            };
+            match distiller.distill(&Path::new(corpus).join(target)).await {
+                Ok(stats) => {
+                    info!(log, "Distilled corpus"; "target" => &id, "before" => stats.before, "after" => stats.after, "edges" => stats.edges);
+                    feedback.message(format!(
+                        "Corpus of `{}` is distilled from {} to {} inputs covering {} edges",
+                        id, stats.before, stats.after, stats.edges
+                    ));
+                }
+                Err(e @ Error::Cancelled(_)) => return Err(e),
+                Err(e) => {
+                    error!(log, "Error distilling corpus"; "target" => &id, "error" => e.to_string());
+                    feedback.warning(format!("Cannot distill corpus of `{}`: {}", id, e));
+                }
             }
-            feedback.stopped();
-        });
+        }
     }
-    feedback
+    Ok(())
 }
 
-#[derive(Clone)]
-struct Synch {
-    bcast: broadcast::Sender<()>,
+/// Directory next to the run checkout another commit is checked out to, e.g. the base
+/// commit for benchmarking with the `base` suffix.
+fn sibling_checkout_dir(checkout: &Path, suffix: &str) -> PathBuf {
+    let mut name = checkout.file_name().unwrap_or_default().to_os_string();
+    name.push("-");
+    name.push(suffix);
+    checkout.with_file_name(name)
+}
+
+/// Benchmarks fuzz targets of honggfuzz projects built in the run checkout against ones of
+/// the base commit checked out next to it, reporting slowdowns over the threshold.
+///
+/// Only the first build variant of each project is benchmarked.
+async fn run_benchmark(
+    run: &RunInfo,
+    config: &Config,
+    builder: &Builder,
+    build_slots: &BuildSlots,
+    bench: Bench,
+    feedback: &Feedback,
+    log: &Logger,
+) -> Result<(), Error> {
+    let benchmark = match &config.benchmark {
+        Some(benchmark) => benchmark,
+        None => return Ok(()),
+    };
+    let base_dir = &sibling_checkout_dir(&run.checkout_dir, "base");
+    let (base_branch, base_commit) = match &benchmark.base_branch {
+        Some(base_branch) => (base_branch.as_str(), None),
+        None => (
+            run.branch.as_str(),
+            Some(super::checkout::parent(config.code_dir(&run.checkout_dir), run.commit.as_deref()).await?),
+        ),
+    };
+    let base = base_commit.as_deref().unwrap_or(base_branch).to_string();
+    info!(log, "Benchmarking fuzz targets"; "base" => &base);
+    super::checkout::checkout(base_dir, &run.url, base_branch, base_commit.as_deref(), config.checkout_script.as_deref(), log.clone()).await?;
+    builder.configure_sources(base_dir).await?;
+    let read_metadata = |dir| async move {
+        Metadata::read_opt(dir, log).await.unwrap_or_else(|e| {
+            warn!(log, "Cannot read checkout workspace metadata"; "error" => e.to_string());
+            None
+        })
+    };
+    let head_metadata = read_metadata(run.checkout_dir.as_path()).await;
+    let base_metadata = read_metadata(base_dir).await;
+
+    let mut comparisons = vec![];
+    for (name, conf) in config.targets.iter().filter(|(_, conf)| conf.engine == Engine::Honggfuzz) {
+        let variant = match conf.variants().into_iter().next() {
+            Some(variant) if !conf.targets.is_empty() => variant,
+            _ => continue,
+        };
+        let head_project = project_dir(&run.checkout_dir, name, conf, &head_metadata);
+        let base_project = project_dir(base_dir, name, conf, &base_metadata);
+        let (head_env, base_env) = (conf.build_env(&config.build, &head_project), conf.build_env(&config.build, &base_project));
+        {
+            let _slot = build_slots.acquire(name).await;
+            if let Err(e) = builder.build(&base_project, &variant, &base_env).await {
+                if let Error::Cancelled(_) = e {
+                    return Err(e);
+                }
+                error!(log, "Error building base of {}: {}", name, e);
+                feedback.warning(format!("Cannot benchmark `{}`, building base {} failed: {}", name, base, e));
+                continue;
+            }
+        }
+        for target in &conf.targets {
+            let binary = conf.binary_name(target);
+            let corpus = conf.corpus_dir(config.corpus.as_deref()).map(|c| Path::new(c).join(target));
+            let measure = |dir, env| bench.measure(dir, env, &variant, target, &binary, corpus.as_deref());
+            match (measure(&base_project, &base_env).await, measure(&head_project, &head_env).await) {
+                (Ok(base), Ok(head)) => comparisons.push(Comparison {
+                    target: variant.target_id(target),
+                    base,
+                    head,
+                }),
+                (Err(e @ Error::Cancelled(_)), _) | (_, Err(e @ Error::Cancelled(_))) => return Err(e),
+                (Err(e), _) | (_, Err(e)) => {
+                    error!(log, "Error benchmarking {}: {}", target, e);
+                    feedback.warning(format!("Cannot benchmark `{}`: {}", target, e));
+                }
+            }
+        }
+    }
+
+    if comparisons.is_empty() {
+        return Ok(());
+    }
+    let lines = comparisons.iter().map(|c| format!("- {}", c)).collect::<Vec<_>>();
+    feedback.message(format!("Benchmark against {}:\n{}", base, lines.join("\n")));
+    for comparison in comparisons.iter().filter(|c| c.is_regression(benchmark.threshold)) {
+        warn!(log, "Performance regression"; "target" => &comparison.target, "comparison" => comparison.to_string());
+        feedback.regression(FeedbackLevel::Warning, format!("Performance regression of {}", comparison));
+    }
+    Ok(())
+}
+
+/// Compares coverage of honggfuzz projects changed by the fuzzed commit with their versions
+/// of the parent commit, replaying the corpus of each target on both. The previous versions
+/// are built in a checkout of the fuzzed commit next to the run checkout with only the
+/// project directories restored, so both harnesses fuzz the same code.
+///
+/// Only the first build variant of each project is compared.
+async fn compare_harnesses(
+    run: &RunInfo,
+    config: &Config,
+    builder: &Builder,
+    build_slots: &BuildSlots,
+    replay: Replay,
+    feedback: &Feedback,
+    log: &Logger,
+) -> Result<(), Error> {
+    let comparison = match &config.harness_comparison {
+        Some(comparison) => comparison,
+        None => return Ok(()),
+    };
+    let checkout = &run.checkout_dir;
+    let head = super::checkout::head(checkout).await?;
+    let parent = super::checkout::parent(checkout, Some(&head)).await?;
+    let changed = super::checkout::changed_files(checkout, &parent, &head).await?;
+    let read_metadata = |dir| async move {
+        Metadata::read_opt(dir, log).await.unwrap_or_else(|e| {
+            warn!(log, "Cannot read checkout workspace metadata"; "error" => e.to_string());
+            None
+        })
+    };
+    let head_metadata = read_metadata(checkout.as_path()).await;
+    // a project in the root of the checkout can't be told apart from the fuzzed code
+    let projects = config
+        .targets
+        .iter()
+        .filter(|(_, conf)| conf.engine == Engine::Honggfuzz && !conf.targets.is_empty())
+        .filter_map(|(name, conf)| {
+            let dir = project_dir(checkout, name, conf, &head_metadata);
+            let relative = dir.strip_prefix(checkout).ok()?.to_path_buf();
+            let changed = !relative.as_os_str().is_empty() && changed.iter().any(|file| file.starts_with(&relative));
+            if changed {
+                Some((name, conf, relative))
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+    if projects.is_empty() {
+        return Ok(());
+    }
+
+    let previous_dir = &sibling_checkout_dir(checkout, "harness");
+    info!(log, "Comparing changed fuzzing harnesses"; "previous" => &parent, "projects" => projects.len());
+    super::checkout::checkout(previous_dir, &run.url, &run.branch, Some(&head), config.checkout_script.as_deref(), log.clone()).await?;
+    let mut restored = vec![];
+    for (name, conf, relative) in projects {
+        match super::checkout::restore(previous_dir, &parent, &relative).await {
+            Ok(()) => restored.push((name, conf)),
+            // a project added by the commit
+            Err(e) => debug!(log, "No previous version of the project"; "project" => name, "error" => e.to_string()),
+        }
+    }
+    builder.configure_sources(previous_dir).await?;
+    let previous_metadata = read_metadata(previous_dir).await;
+
+    let mut coverages = vec![];
+    for (name, conf) in restored {
+        let variant = match conf.variants().into_iter().next() {
+            Some(variant) => variant,
+            None => continue,
+        };
+        let corpus = match conf.corpus_dir(config.corpus.as_deref()) {
+            Some(corpus) => Path::new(corpus),
+            None => {
+                feedback.warning(format!("Cannot compare `{}` harness without a corpus", name));
+                continue;
+            }
+        };
+        let changed_project = project_dir(checkout, name, conf, &head_metadata);
+        let previous_project = project_dir(previous_dir, name, conf, &previous_metadata);
+        let changed_env = conf.build_env(&config.build, &changed_project);
+        let previous_env = conf.build_env(&config.build, &previous_project);
+        {
+            let _slot = build_slots.acquire(name).await;
+            if let Err(e) = builder.build(&previous_project, &variant, &previous_env).await {
+                if let Error::Cancelled(_) = e {
+                    return Err(e);
+                }
+                error!(log, "Error building previous harness of {}: {}", name, e);
+                feedback.warning(format!("Cannot compare `{}` harness, building its previous version failed: {}", name, e));
+                continue;
+            }
+        }
+        for target in &conf.targets {
+            let binary = conf.binary_name(target);
+            let corpus = corpus.join(target);
+            let coverage = |dir, env| replay.coverage(dir, env, &variant, target, &binary, &corpus);
+            match (coverage(&previous_project, &previous_env).await, coverage(&changed_project, &changed_env).await) {
+                (Ok(previous), Ok(changed)) => coverages.push(HarnessCoverage {
+                    target: variant.target_id(target),
+                    previous,
+                    changed,
+                }),
+                (Err(e @ Error::Cancelled(_)), _) | (_, Err(e @ Error::Cancelled(_))) => return Err(e),
+                (Err(e), _) | (_, Err(e)) => {
+                    error!(log, "Error comparing harness of {}: {}", target, e);
+                    feedback.warning(format!("Cannot compare `{}` harness: {}", target, e));
+                }
+            }
+        }
+    }
+
+    if coverages.is_empty() {
+        return Ok(());
+    }
+    let lines = coverages.iter().map(|c| format!("- {}", c)).collect::<Vec<_>>();
+    feedback.message(format!("Coverage of changed fuzzing harnesses compared with {}:\n{}", parent, lines.join("\n")));
+    for coverage in coverages.iter().filter(|c| c.is_regression(comparison.threshold)) {
+        warn!(log, "Changed harness loses coverage"; "target" => &coverage.target, "comparison" => coverage.to_string());
+        feedback.regression(FeedbackLevel::Warning, format!("Changed fuzzing harness loses coverage: {}", coverage));
+    }
+    if let Some(github) = config.github.as_ref().filter(|_| comparison.comment) {
+        let repository = match github_repository(github, run, log) {
+            Some(repository) => repository,
+            None => return Ok(()),
+        };
+        let comment = harness::comment(&parent, &head, &coverages, comparison.threshold);
+        match Comments::new(github, log.clone()).comment(&repository, &head, &comment).await {
+            Ok(comments) => info!(log, "Commented harness comparison"; "comments" => comments),
+            Err(e) => error!(log, "Error commenting harness comparison"; "error" => e.to_string()),
+        }
+    }
+    Ok(())
+}
+
+/// Replays corpora of fuzz targets built on commits checked out next to the run checkout.
+struct Bisector<'a> {
+    run: &'a RunInfo,
+    config: &'a Config,
+    builder: &'a Builder,
+    build_slots: &'a BuildSlots,
+    replay: Replay,
+    /// Directory commits are checked out to
+    dir: PathBuf,
+    log: Logger,
+}
+
+impl Bisector<'_> {
+    /// Edges of the target of the project covered by its corpus on the commit.
+    async fn coverage_at(&self, commit: &str, name: &str, conf: &TargetConfig, variant: &Variant, target: &str) -> Result<u32, Error> {
+        debug!(self.log, "Testing commit"; "commit" => commit, "target" => target);
+        if self.dir.exists() {
+            tokio::fs::remove_dir_all(&self.dir).await?;
+        }
+        super::checkout::checkout(
+            &self.dir,
+            &self.run.url,
+            &self.run.branch,
+            Some(commit),
+            self.config.checkout_script.as_deref(),
+            self.log.clone(),
+        )
+        .await?;
+        self.builder.configure_sources(&self.dir).await?;
+        let metadata = Metadata::read_opt(&self.dir, &self.log).await.unwrap_or_else(|e| {
+            warn!(self.log, "Cannot read checkout workspace metadata"; "error" => e.to_string());
+            None
+        });
+        let project = project_dir(&self.dir, name, conf, &metadata);
+        let env = conf.build_env(&self.config.build, &project);
+        {
+            let _slot = self.build_slots.acquire(name).await;
+            self.builder.build(&project, variant, &env).await?;
+        }
+        let corpus = conf
+            .corpus_dir(self.config.corpus.as_deref())
+            .map(|c| Path::new(c).join(target))
+            .ok_or_else(|| Error::other(format!("no corpus for `{}`", target)))?;
+        self.replay
+            .coverage(&project, &env, variant, target, &conf.binary_name(target), &corpus)
+            .await
+    }
+}
+
+/// Bisects commits since the previous run of the branch for fuzz targets of honggfuzz projects
+/// whose corpus covers considerably fewer edges than in the previous run, reporting commits
+/// that lost coverage.
+async fn run_bisection(bisector: &Bisector<'_>, bisect: &config::Bisect, feedback: &Feedback) -> Result<(), Error> {
+    let (run, config, log) = (bisector.run, bisector.config, &bisector.log);
+    let (previous, good) = match feedback.previous_run() {
+        Some((status, Some(commit))) => (status, commit),
+        _ => return Ok(()),
+    };
+    let code_dir = config.code_dir(&run.checkout_dir);
+    let head = super::checkout::head(&code_dir).await?;
+    // an empty list if the previous commit isn't an ancestor, e.g. after a force-push
+    let commits = super::checkout::commits_between(&code_dir, &good, &head).await?;
+    if commits.is_empty() {
+        return Ok(());
+    }
+    let metadata = Metadata::read_opt(&run.checkout_dir, log).await.unwrap_or_else(|e| {
+        warn!(log, "Cannot read checkout workspace metadata"; "error" => e.to_string());
+        None
+    });
+
+    for (name, conf) in config.targets.iter().filter(|(_, conf)| conf.engine == Engine::Honggfuzz) {
+        let variant = match conf.variants().into_iter().next() {
+            Some(variant) => variant,
+            None => continue,
+        };
+        let project = project_dir(&run.checkout_dir, name, conf, &metadata);
+        let env = conf.build_env(&config.build, &project);
+        for target in &conf.targets {
+            let id = variant.target_id(target);
+            let (before, corpus) = match (previous.get(&id), conf.corpus_dir(config.corpus.as_deref())) {
+                (Some(status), Some(corpus)) => (status.covered, Path::new(corpus).join(target)),
+                _ => continue,
+            };
+            let after = match bisector.replay.coverage(&project, &env, &variant, target, &conf.binary_name(target), &corpus).await {
+                Ok(after) => after,
+                Err(e @ Error::Cancelled(_)) => return Err(e),
+                Err(e) => {
+                    error!(log, "Error replaying corpus of {}: {}", id, e);
+                    continue;
+                }
+            };
+            if !bisect::is_regression(before, after, bisect.threshold) {
+                continue;
+            }
+
+            info!(log, "Bisecting coverage regression"; "target" => &id, "before" => before, "after" => after, "commits" => commits.len());
+            let bisection = async {
+                // coverage of the previous commit is measured the same way as of others
+                let good_edges = bisector.coverage_at(&good, name, conf, &variant, target).await?;
+                if !bisect::is_regression(good_edges, after, bisect.threshold) {
+                    info!(log, "Coverage regression is not reproduced by corpus replay"; "target" => &id, "good" => good_edges);
+                    return Ok(None);
+                }
+                let measured = std::sync::Mutex::new(HashMap::new());
+                let (index, exact) = bisect::search(&commits, bisect.max_steps, |commit| {
+                    let commit = commit.to_string();
+                    let (measured, variant) = (&measured, &variant);
+                    async move {
+                        let edges = bisector.coverage_at(&commit, name, conf, variant, target).await?;
+                        measured.lock().unwrap().insert(commit, edges);
+                        Ok(bisect::is_regression(good_edges, edges, bisect.threshold))
+                    }
+                })
+                .await?;
+                let commit = commits[index].clone();
+                let edges = measured.lock().unwrap().get(&commit).copied().unwrap_or(after);
+                Ok::<_, Error>(Some(Bisection {
+                    target: id.clone(),
+                    good: good.clone(),
+                    commit,
+                    before: good_edges,
+                    after: edges,
+                    approximate: !exact,
+                }))
+            };
+            match bisection.await {
+                Ok(Some(bisection)) => {
+                    warn!(log, "Coverage regression found"; "bisection" => bisection.to_string());
+                    feedback.add_bisection(bisection).await;
+                }
+                Ok(None) => (),
+                Err(e @ Error::Cancelled(_)) => return Err(e),
+                Err(e) => {
+                    error!(log, "Error bisecting {}: {}", id, e);
+                    feedback.warning(format!("Cannot bisect coverage regression of `{}`: {}", id, e));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Fails if the run is cancelled, so its remaining stages are skipped.
+fn cancelled(cancel: &Scope, what: &str) -> Result<(), Error> {
+    if cancel.is_cancelled() {
+        Err(Error::Cancelled(what.to_string()))
+    } else {
+        Ok(())
+    }
+}
+
+/// Unique run ID, containing commit message, commit ID, committer and this run timestamp
+fn get_run_id(commit: &Commit, time: &config::Time) -> String {
+    // 5-char commit id
+    let (id, _) = commit.id.split_at(5);
+    // first line of the commit message
+    let message = commit.message.split('\n').next().unwrap();
+    format!(
+        "_{}_ - {} by {} at {}",
+        message,
+        id,
+        commit.author.username,
+        time.now()
+    )
+}
+
+async fn create_feedback(
+    config: &config::Config,
+    id: &str,
+    description: &str,
+    run: &RunInfo,
+    crash_issues: Option<CrashIssues>,
+    cancel: &Scope,
+    log: &Logger,
+) -> Arc<Feedback> {
+    let reports_loc = &run.reports_loc;
+    let logger = RegressionsOnly::with_policy(
+        Box::new(LoggerClient::new(description, log.clone()).with_level(config.feedback.log_level)),
+        config.feedback.log_policy,
+    );
+    let client: Box<dyn FeedbackClient + Sync + Send> = if let Some(slack) = &config.slack {
+        let client = SlackClient::new(
+            description,
+            &slack.channel,
+            &slack.token,
+            slack.level(),
+            log.clone(),
+        )
+        .with_long_messages(slack.max_length, slack.long_messages);
+        let client = match config.url.as_ref().map(|url| report::Report::page_url(url, reports_loc)) {
+            Some(Ok(url)) => client.with_report(url),
+            _ => client,
+        };
+        let client: Box<dyn FeedbackClient + Sync + Send> = if slack.signing_secret.is_some() {
+            Box::new(client.with_controls(id))
+        } else {
+            Box::new(client)
+        };
+        let client = RegressionsOnly::with_policy(client, slack.policy);
+        Box::new(MultiClient::new(vec![logger, client]))
+    } else {
+        logger
+    };
+    let report = report::Report::new(
+        &config.reports_path,
+        &config.url,
+        reports_loc,
+        Some(run.number),
+        &config.time,
+        log.new(o!("role" => "report")),
+    )
+    .await
+    .expect("can't create report");
+    let feedback = Feedback::new(&config.feedback, &config.time, client, report, log.clone());
+    let feedback = match &config.artifacts {
+        Some(artifacts) => feedback.with_artifacts(RunArtifacts::new(
+            artifacts::Store::new(&artifacts.path),
+            config.reports_path.join(reports_loc),
+        )),
+        None => feedback,
+    };
+    let feedback = match crash_issues {
+        Some(crash_issues) => feedback.with_crash_issues(crash_issues),
+        None => feedback,
+    };
+    let feedback = Arc::new(feedback);
+    {
+        let feedback = feedback.clone();
+        let cancel = cancel.clone();
+        tokio::spawn(async move {
+            cancel.cancelled().await;
+            feedback.stopped();
+        });
+    }
+    feedback
+}
+
+/// Synchronization of successive runs of a branch.
+#[derive(Clone)]
+struct Synch {
+    /// Cancellation scope of the current run
+    cancel: Arc<RwLock<Scope>>,
+    /// Notified when the current run completes
     notify: Arc<Notify>,
 }
 
 impl Synch {
     fn new() -> Self {
-        let bcast = broadcast::channel(1).0;
+        let cancel = Arc::new(RwLock::new(Scope::new()));
         let notify = Arc::new(Notify::new());
-        Self { bcast, notify }
+        Self { cancel, notify }
+    }
+}
+
+/// Controls of a run in progress.
+struct Control {
+    cancel: Scope,
+    /// Who the run is paused by, if it is paused manually
+    pause: watch::Sender<Option<String>>,
+    feedback: Arc<Feedback>,
+    slot: watch::Receiver<SlotState>,
+    branch: String,
+    started: Instant,
+    /// Link to the run report
+    link: String,
+}
+
+/// Run in progress, as shown on the overview page.
+#[derive(Serialize)]
+struct LiveRun {
+    id: String,
+    branch: String,
+    state: String,
+    elapsed: String,
+    link: String,
+    crashes: u32,
+    targets: Vec<LiveTarget>,
+}
+
+#[derive(Serialize)]
+struct LiveTarget {
+    name: String,
+    covered: u32,
+    total: u32,
+    crashes: u32,
+}
+
+/// Options of a run, besides the fuzzed repository, branch and commit.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct RunOptions {
+    /// Whether the run follows a force-push, invalidating the previous report
+    pub forced: bool,
+    /// Targets to fuzz, all if empty
+    pub targets: Vec<String>,
+    /// Fuzzing time of each target, in seconds, not counting paused time, as configured if not set
+    pub duration: Option<u64>,
+}
+
+/// Starts fuzzing runs, stopping the previous run of the same branch first.
+#[derive(Clone)]
+pub(crate) struct Runner {
+    configs: watch::Receiver<Config>,
+    build_slots: Arc<BuildSlots>,
+    /// Format of honggfuzz output, detected at startup
+    output_format: OutputFormat,
+    scheduler: Arc<Scheduler>,
+    branches: Arc<RwLock<HashMap<String, Synch>>>,
+    /// Controls of runs in progress, by run id
+    controls: Arc<RwLock<HashMap<String, Control>>>,
+    /// Releases fuzzed since the server start, so each one is fuzzed once
+    releases: Arc<RwLock<HashSet<String>>>,
+    runs: Runs,
+    audit: Arc<AuditLog>,
+    /// Webhook deliveries, if they are stored
+    deliveries: Option<Arc<Deliveries>>,
+    log: Logger,
+}
+
+impl Runner {
+    /// The most recent configuration.
+    pub(crate) fn config(&self) -> Config {
+        self.configs.borrow().clone()
+    }
+
+    pub(crate) async fn run(&self, id: &str) -> Option<Run> {
+        self.runs.get(id).await
+    }
+
+    pub(crate) async fn runs(&self) -> Vec<Run> {
+        self.runs.list().await
+    }
+
+    pub(crate) fn audit_log(&self) -> &AuditLog {
+        &self.audit
+    }
+
+    /// Stored webhook deliveries, if they are stored.
+    pub(crate) fn deliveries(&self) -> Option<&Deliveries> {
+        self.deliveries.as_deref()
+    }
+
+    /// Processes the stored webhook delivery again. Returns `None` if there is no such
+    /// delivery, or deliveries are not stored.
+    pub(crate) async fn replay_delivery(&self, id: &str) -> Option<Result<Delivery, Error>> {
+        let mut delivery = match self.deliveries.as_ref()?.get(id).await {
+            Ok(delivery) => delivery?,
+            Err(e) => return Some(Err(e)),
+        };
+        delivery.replays += 1;
+        let log = self.log.new(o!("delivery" => delivery.id.clone()));
+        // the outcome is recorded in the delivery
+        let _ = process_delivery(self, &mut delivery, &log).await;
+        Some(Ok(delivery))
+    }
+
+    /// Records the control action of the caller in the audit log.
+    pub(crate) async fn audit(&self, caller: &str, action: &str, subject: &str, status: u16) {
+        if let Err(e) = self.audit.record(caller, action, subject, status).await {
+            error!(self.log, "Cannot record audit log entry"; "caller" => caller, "action" => action, "error" => e.to_string());
+        }
+    }
+
+    /// Cancels the run in progress, or only its fuzzing project or target at the `scope`
+    /// path, like `project/target`, or with the `scope` name if it is unique in the run.
+    ///
+    /// Returns `None` if there is no such run in progress, and the error if the scope
+    /// cannot be cancelled.
+    pub(crate) fn cancel(&self, id: &str, scope: Option<&str>) -> Option<Result<(), ScopeError>> {
+        let controls = self.controls.read().unwrap();
+        let run = &controls.get(id)?.cancel;
+        match scope {
+            Some(scope) => Some(run.cancel_child(scope)),
+            None => {
+                run.cancel();
+                Some(Ok(()))
+            }
+        }
+    }
+
+    /// Pauses the run in progress, stopping its processes until it is resumed, or resumes
+    /// it if `by` is `None`. A paused run keeps its run slot.
+    ///
+    /// Returns `None` if there is no such run in progress, and `Some(false)` if the run
+    /// is already paused or running.
+    pub(crate) fn pause(&self, id: &str, by: Option<&str>) -> Option<bool> {
+        let controls = self.controls.read().unwrap();
+        let pause = &controls.get(id)?.pause;
+        if pause.borrow().is_some() == by.is_some() {
+            return Some(false);
+        }
+        let _ = pause.send(by.map(String::from));
+        Some(true)
+    }
+
+    /// Targets excluded from runs by admins.
+    pub(crate) async fn disabled_targets(&self) -> Vec<DisabledTarget> {
+        self.runs.disabled_targets().await
+    }
+
+    /// Disables the target in runs started later on behalf of `by`, or enables it if `by`
+    /// is `None`. Runs in progress are not affected.
+    ///
+    /// Returns `None` if the disabled target is not configured, unless fuzzing projects
+    /// are discovered, and `Some(false)` if it is already disabled or enabled.
+    pub(crate) async fn set_disabled(&self, target: &str, by: Option<&str>) -> Option<bool> {
+        match by {
+            Some(by) => {
+                let config = self.config();
+                if config.discovery.is_none() && !config.targets.values().any(|conf| conf.targets.iter().any(|t| t == target)) {
+                    return None;
+                }
+                Some(self.runs.disable_target(target, by).await)
+            }
+            None => Some(self.runs.enable_target(target).await),
+        }
+    }
+
+    /// Whether a run of the branch, with the repository name prefix in multi-repo mode,
+    /// is in progress.
+    fn is_fuzzing(&self, key: &str) -> bool {
+        self.controls.read().unwrap().values().any(|control| control.branch == key)
+    }
+
+    /// Starts a new run of the repository, branch and commit of the run, like a manual
+    /// run of them. Returns `None` if there is no such run.
+    pub(crate) async fn rerun(&self, id: &str) -> Option<io::Result<Run>> {
+        let run = self.runs.get(id).await?;
+        let url = self.runs.url(id).await?;
+        let run_id = format!("re-run of {} at {}", run.id, self.config().time.now());
+        Some(self.start(url, run.branch, run.commit, run_id, false, Trigger::Rerun).await)
+    }
+
+    /// Stops fuzzing of the branch, with the repository name prefix in multi-repo mode,
+    /// waiting for its run to complete. Returns whether a run of the branch was in progress.
+    async fn stop_branch(&self, key: &str) -> bool {
+        let running = self.controls.read().unwrap().values().any(|control| control.branch == key);
+        let (sync, existing) = get_sync(self.branches.clone(), &key.to_string(), &self.log);
+        if existing {
+            sync.notify.notified().await;
+        }
+        // the next run of a re-created branch starts without waiting
+        self.branches.write().unwrap().remove(key);
+        running
+    }
+
+    /// Runs in progress with their current coverage, ordered by branch.
+    fn live(&self) -> Vec<LiveRun> {
+        let controls = self.controls.read().unwrap();
+        let mut runs = controls
+            .iter()
+            .map(|(id, control)| {
+                let state = match (&*control.pause.borrow(), &*control.slot.borrow()) {
+                    (Some(by), _) => format!("Paused by {}", by),
+                    (None, SlotState::Waiting) => "Waiting for a run slot".to_string(),
+                    (None, SlotState::Running) => "Fuzzing".to_string(),
+                    (None, SlotState::Paused(by)) => format!("Paused for run {}", by),
+                };
+                let mut targets = control
+                    .feedback
+                    .status()
+                    .into_iter()
+                    .map(|(name, status)| LiveTarget {
+                        name,
+                        covered: status.covered,
+                        total: status.total,
+                        crashes: status.errors,
+                    })
+                    .collect::<Vec<_>>();
+                targets.sort_by(|a, b| a.name.cmp(&b.name));
+                LiveRun {
+                    id: id.clone(),
+                    branch: control.branch.clone(),
+                    state,
+                    elapsed: dashboard::format_duration(control.started.elapsed().as_secs()),
+                    link: control.link.clone(),
+                    crashes: targets.iter().map(|t| t.crashes).sum(),
+                    targets,
+                }
+            })
+            .collect::<Vec<_>>();
+        runs.sort_by(|a, b| (&a.branch, &a.id).cmp(&(&b.branch, &b.id)));
+        runs
+    }
+
+    /// Starts fuzzing of the branch of the repository, optionally at the specific commit.
+    pub(crate) async fn start(
+        &self,
+        url: String,
+        branch: String,
+        commit: Option<String>,
+        run_id: String,
+        forced: bool,
+        trigger: Trigger,
+    ) -> io::Result<Run> {
+        let options = RunOptions { forced, ..RunOptions::default() };
+        self.start_with(url, branch, commit, run_id, trigger, options).await
+    }
+
+    /// Starts fuzzing of the branch of the repository with the options.
+    pub(crate) async fn start_with(
+        &self,
+        url: String,
+        branch: String,
+        commit: Option<String>,
+        run_id: String,
+        trigger: Trigger,
+        options: RunOptions,
+    ) -> io::Result<Run> {
+        let mut config = self.config();
+        let release = trigger == Trigger::Release;
+        if release {
+            config.for_release();
+        }
+        let distill = trigger == Trigger::Distillation;
+        if distill {
+            // the commit isn't fuzzed, so there's nothing to report on it
+            config.github = None;
+        }
+        let repo = config.repository_name(&url).map(String::from);
+        let subject = if release { "Release" } else { "Branch" };
+        // runs of the same branch in different repositories don't interfere
+        let number = self.runs.next_number().await;
+        let (id, key, description) = match &repo {
+            Some(repo) => (
+                format!("{}-{}", repo, Runs::id(number)),
+                format!("{}/{}", repo, branch),
+                format!("Repository `{}`, {} `{}`, {}", repo, subject.to_lowercase(), branch, run_id),
+            ),
+            None => (Runs::id(number), branch.clone(), format!("{} `{}`, {}", subject, branch, run_id)),
+        };
+        // nor do runs of releases and branches named like their tags
+        let key = match (release, distill) {
+            (true, _) => format!("{}/{}", RELEASES_DIR, key),
+            (_, true) => format!("{}/{}", DISTILLATION_DIR, key),
+            _ => key,
+        };
+        let log = self.log.new(o!("branch" => key.clone(), "run_id" => id.clone()));
+        trace!(log, "Starting fuzzing on branch {}", key);
+        let (sync, existing) = get_sync(self.branches.clone(), &key, &log);
+        if existing {
+            sync.notify.notified().await;
+        }
+        let cancel = Scope::new();
+        *sync.cancel.write().unwrap() = cancel.clone();
+        if let Some(store) = self.runs.store() {
+            supersede(store, &key, &id, &log).await;
+        }
+
+        let mut run = match RunInfo::new(url, branch.clone(), commit.clone(), run_id.clone(), repo.as_deref(), &config.workdir, &id) {
+            Ok(run) => run,
+            Err(e) => {
+                error!(log, "Cannot initialize run"; "error" => e.to_string());
+                sync.notify.notify_one();
+                return Err(e);
+            }
+        };
+        if release {
+            run.reports_loc = Path::new(RELEASES_DIR).join(&run.reports_loc);
+        } else if distill {
+            run.reports_loc = Path::new(DISTILLATION_DIR).join(&run.reports_loc);
+        }
+        run.distill = distill;
+        run.duration = options.duration;
+        run.disabled_targets = self.runs.disabled_targets().await.into_iter().map(|d| d.target).collect();
+        run.targets = options.targets.into_iter().collect();
+        run.number = number;
+        let state = Run {
+            id,
+            branch,
+            commit,
+            description: run_id,
+            status: RunStatus::Running,
+            verdict: None,
+            report_url: config
+                .url
+                .as_ref()
+                .and_then(|url| common::reports_url(url, &run.reports_loc).ok())
+                .map(|url| url.to_string()),
+        };
+        self.runs.insert(state.clone(), &run.url).await;
+
+        // the superseded run is finished by now, so its report is complete
+        let stale = if options.forced { invalidate_superseded(&config, &run, &log).await } else { None };
+        let crash_issues = config.github.as_ref().filter(|github| github.crash_issues).and_then(|github| {
+            let repository = github_repository(github, &run, &log)?;
+            Some(CrashIssues::new(github, repository, run.branch.clone(), self.runs.clone(), log.new(o!("component" => "issues"))))
+        });
+        let feedback = create_feedback(&config, &state.id, &description, &run, crash_issues, &cancel, &log).await;
+        feedback.set_trigger(trigger).await;
+        if let Some(stale) = stale {
+            feedback.message(format!(
+                "Force-push detected, the report `{}` of the superseded history is marked stale",
+                stale.to_string_lossy()
+            ));
+        }
+        let priority = config.scheduling.priority(repo.as_deref(), &state.branch);
+        let mut ticket = self.scheduler.register(&state.id, priority);
+        if ticket.state() == SlotState::Running {
+            feedback.debug("Preparing for fuzzing");
+        } else {
+            info!(log, "Waiting for a run slot"; "priority" => priority);
+            feedback.message("Waiting for a run slot taken by runs of the same or higher priority");
+        }
+        trace!(log, "Spawning fuzzer");
+        let notify = sync.notify.clone();
+        let controls = self.controls.clone();
+        let (pause, paused) = watch::channel(None);
+        let (pausing, fuzzing_paused) = watch::channel(false);
+        // without the server url, reports are linked relative to the overview page
+        let link = state.report_url.clone().unwrap_or_else(|| {
+            run.reports_loc
+                .iter()
+                .fold("reports/".to_string(), |link, segment| link + &common::sanitize_url_path_segment(segment) + "/")
+        });
+        controls.write().unwrap().insert(
+            state.id.clone(),
+            Control {
+                cancel: cancel.clone(),
+                pause,
+                feedback: feedback.clone(),
+                slot: ticket.subscribe(),
+                branch: key.clone(),
+                started: Instant::now(),
+                link,
+            },
+        );
+        let build_slots = self.build_slots.clone();
+        let output_format = self.output_format;
+        let runs = self.runs.clone();
+        let id = state.id.clone();
+        let heartbeat = runs
+            .store()
+            .cloned()
+            .map(|store| tokio::spawn(heartbeat(store, key, id.clone(), cancel.clone(), log.clone())));
+        tokio::spawn(async move {
+            let hooks = config.hooks.clone();
+            let workdir = config.workdir.clone();
+            let github = config.github.clone();
+            let tag = release || is_tag_run(&config, &run.branch);
+            let mut hooks_env = run.hooks_env(&config);
+            tokio::spawn(follow_pauses(
+                ticket.subscribe(),
+                paused,
+                pausing,
+                run.checkout_dir.clone(),
+                feedback.clone(),
+                cancel.clone(),
+                log.clone(),
+            ));
+            let rules = config.policy.rules.clone();
+            let mut started = Instant::now();
+            let result = tokio::select! {
+                _ = ticket.running() => {
+                    started = Instant::now();
+                    run_fuzzers(&run, build_slots, output_format, config, feedback.clone(), fuzzing_paused, cancel.clone(), log.clone()).await
+                }
+                _ = cancel.cancelled() => Err(Error::Cancelled("run".to_string())),
+            };
+            let status = match result {
+                Ok(_) if cancel.is_cancelled() => RunStatus::Cancelled,
+                Ok(_) => RunStatus::Success,
+                Err(Error::Cancelled(_)) => {
+                    info!(log, "Run cancelled");
+                    RunStatus::Cancelled
+                }
+                Err(e) => {
+                    error!(log, "Error running fuzzers"; "error" => e.to_string());
+                    RunStatus::Failure
+                }
+            };
+            let (verdict, description) = policy::verdict(&rules, status, &feedback.run_stats(started.elapsed()));
+            feedback.verdict(verdict, &description);
+            match &github {
+                Some(github) if matches!(status, RunStatus::Success | RunStatus::Failure) => {
+                    publish_deployment(github, &run, &feedback, verdict == Verdict::Pass, &log).await;
+                    if github.pr_comments {
+                        publish_coverage_comment(github, &run, &feedback, &log).await;
+                    }
+                    if github.release_assets && tag {
+                        publish_release_asset(github, &run, &feedback, &log).await;
+                    }
+                }
+                _ => (),
+            }
+            if let Some(github) = github.as_ref().filter(|github| github.statuses) {
+                if let Some(commit) = feedback.commit().await {
+                    let state = match verdict {
+                        Verdict::Pass => CommitState::Success,
+                        Verdict::Fail => CommitState::Failure,
+                        Verdict::Error | Verdict::Cancelled => CommitState::Error,
+                    };
+                    publish_status(github, &run, &commit, state, &description, &feedback, &log).await;
+                }
+            }
+            let conclusion = match verdict {
+                Verdict::Pass => CheckConclusion::Success,
+                Verdict::Fail | Verdict::Error => CheckConclusion::Failure,
+                Verdict::Cancelled => CheckConclusion::Cancelled,
+            };
+            feedback.complete_check_run(conclusion, &description).await;
+            hooks_env.insert("FUZZ_CI_RUN_STATUS".into(), status.name().into());
+            hooks_env.insert("FUZZ_CI_RUN_VERDICT".into(), verdict.name().into());
+            let dir = if run.checkout_dir.is_dir() { run.checkout_dir.clone() } else { PathBuf::from(".") };
+            if let Err(e) = hooks::run(&hooks, Stage::AfterRun, dir, &hooks_env, &log).await {
+                error!(log, "Error running after-run hooks"; "error" => e.to_string());
+            }
+            controls.write().unwrap().remove(&id);
+            // releases tasks waiting for cancellation
+            cancel.cancel();
+            if let Some(heartbeat) = heartbeat {
+                heartbeat.abort();
+            }
+            drop(ticket);
+            if let Some(store) = runs.store() {
+                record_targets(store, &id, &feedback, &log).await;
+            }
+            runs.finish(&id, status, Some(verdict)).await;
+            cleanup_workdirs(&run, &workdir, status == RunStatus::Success, &runs, &log).await;
+            notify.notify_one();
+        });
+        Ok(state)
+    }
+}
+
+/// Whether the run fuzzes a tag rather than a branch.
+fn is_tag_run(config: &Config, branch: &str) -> bool {
+    config.is_fuzzed_tag(branch) && !config.branches.iter().any(|b| b == branch)
+}
+
+/// GitHub repository of the run as `<owner>/<name>`, the configured one or one of its url.
+fn github_repository(config: &config::Github, run: &RunInfo, log: &Logger) -> Option<String> {
+    let repository = config.repository.clone().or_else(|| github::repository_name(&run.url));
+    if repository.is_none() {
+        warn!(log, "Cannot tell GitHub repository of the run"; "url" => &run.url);
+    }
+    repository
+}
+
+/// Creates a GitHub deployment of the fuzzed commit pointing at the run report.
+async fn publish_deployment(config: &config::Github, run: &RunInfo, feedback: &Feedback, success: bool, log: &Logger) {
+    let repository = match github_repository(config, run, log) {
+        Some(repository) => repository,
+        None => return,
+    };
+    let (commit, report) = match feedback.deployment().await {
+        Some(deployment) => deployment,
+        None => {
+            debug!(log, "No fuzzed commit or report URL for the deployment");
+            return;
+        }
+    };
+    match Deployments::new(config, log.clone()).publish(&repository, &commit, &report, success).await {
+        Ok(id) => info!(log, "Published report deployment"; "id" => id, "url" => report.as_str()),
+        Err(e) => error!(log, "Error publishing report deployment"; "error" => e.to_string()),
+    }
+}
+
+/// Comments coverage changes of targets on open pull requests of the fuzzed commit,
+/// replacing the comment of an earlier run.
+async fn publish_coverage_comment(config: &config::Github, run: &RunInfo, feedback: &Feedback, log: &Logger) {
+    let repository = match github_repository(config, run, log) {
+        Some(repository) => repository,
+        None => return,
+    };
+    let commit = match feedback.commit().await {
+        Some(commit) => commit,
+        None => {
+            debug!(log, "No fuzzed commit to comment coverage of");
+            return;
+        }
+    };
+    if feedback.status().is_empty() {
+        debug!(log, "No fuzzed targets to comment coverage of");
+        return;
+    }
+    let table = match feedback.update_report().await {
+        Ok(summary) => summary.table,
+        Err(e) => {
+            error!(log, "Error updating final report"; "error" => e.to_string());
+            return;
+        }
+    };
+    let report = match feedback.report_url() {
+        Some(url) => format!(", see the [report]({})", url),
+        None => String::new(),
+    };
+    let body = format!(
+        "### Fuzzing coverage\n\nCoverage of fuzz targets at {} on `{}`{}:\n\n{}",
+        commit, run.branch, report, table
+    );
+    match Comments::new(config, log.clone()).replace_comment(&repository, &commit, COVERAGE_COMMENT_MARKER, &body).await {
+        Ok(pulls) => info!(log, "Commented coverage on pull requests"; "pulls" => pulls),
+        Err(e) => error!(log, "Error commenting coverage"; "error" => e.to_string()),
+    }
+}
+
+/// Sets the GitHub status of the fuzzed commit, linking the run report.
+async fn publish_status(
+    config: &config::Github,
+    run: &RunInfo,
+    commit: &str,
+    state: CommitState,
+    description: &str,
+    feedback: &Feedback,
+    log: &Logger,
+) {
+    let repository = match github_repository(config, run, log) {
+        Some(repository) => repository,
+        None => return,
+    };
+    let report = feedback.report_url();
+    let client = GithubStatusClient::new(config, log.clone());
+    match client.set_status(&repository, commit, state, description, report.as_ref()).await {
+        Ok(()) => info!(log, "Set commit status"; "state" => state.name(), "commit" => commit),
+        Err(e) => error!(log, "Error setting commit status"; "error" => e.to_string()),
+    }
+}
+
+/// Creates a GitHub check run of the fuzzed commit, updated with coverage of targets as
+/// the run report is updated.
+async fn create_check_run(config: &config::Github, run: &RunInfo, commit: &str, feedback: &Feedback, log: &Logger) {
+    let repository = match github_repository(config, run, log) {
+        Some(repository) => repository,
+        None => return,
+    };
+    let report = feedback.report_url();
+    match CheckRun::create(config, &repository, commit, report.as_ref(), log.clone()).await {
+        Ok(check_run) => {
+            info!(log, "Created check run"; "commit" => commit);
+            feedback.set_check_run(check_run);
+        }
+        Err(e) => error!(log, "Error creating check run"; "error" => e.to_string()),
+    }
+}
+
+/// Attaches the archive of the report of the run of a tag to the GitHub release of the tag.
+async fn publish_release_asset(config: &config::Github, run: &RunInfo, feedback: &Feedback, log: &Logger) {
+    let repository = match github_repository(config, run, log) {
+        Some(repository) => repository,
+        None => return,
+    };
+    let name = format!("fuzzing-{}.tar.gz", common::sanitize_path_segment(&run.branch).to_string_lossy());
+    let publish = async {
+        let dir = tempfile::tempdir()?;
+        let archive = dir.path().join(&name);
+        feedback.archive_report(&archive).await?;
+        Releases::new(config, log.clone()).upload(&repository, &run.branch, &name, &archive).await
+    };
+    match publish.await {
+        Ok(Some(id)) => info!(log, "Attached report to the release"; "release" => id, "asset" => &name),
+        Ok(None) => warn!(log, "No GitHub release of the tag to attach the report to"; "tag" => &run.branch),
+        Err(e) => error!(log, "Error attaching report to the release"; "error" => e.to_string()),
+    }
+}
+
+/// Resolves when fuzzing stalls, with the time since the last coverage update: there are no
+/// updates for `timeout` and no processes are left in the run checkout `dir`, e.g. when
+/// honggfuzz crashes on startup. Never resolves if `timeout` is zero.
+async fn watchdog(dir: &Path, feedback: &Feedback, timeout: Duration) -> Duration {
+    if timeout == Duration::from_secs(0) {
+        return std::future::pending().await;
+    }
+    let interval = std::cmp::min(timeout, WATCHDOG_INTERVAL);
+    loop {
+        tokio::time::sleep(interval).await;
+        let since = feedback.since_update();
+        // paused processes are stopped, but still there
+        if since >= timeout && matches!(resources::processes_in(dir), Ok(pids) if pids.is_empty()) {
+            return since;
+        }
+    }
+}
+
+/// Periodically copies corpus inputs between target corpus directories of each group.
+async fn cross_pollinate(groups: Vec<Vec<PathBuf>>, interval: Duration, log: Logger) {
+    if groups.is_empty() {
+        return;
+    }
+    loop {
+        tokio::time::sleep(interval).await;
+        for dirs in &groups {
+            match corpus::cross_pollinate(dirs).await {
+                Ok(0) => (),
+                Ok(added) => info!(log, "Cross-pollinated corpora"; "dirs" => format!("{:?}", dirs), "added" => added),
+                Err(e) => error!(log, "Error cross-pollinating corpora"; "dirs" => format!("{:?}", dirs), "error" => e.to_string()),
+            }
+        }
+    }
+}
+
+/// Completes once the run is fuzzing, i.e. not paused, for the duration in total.
+async fn fuzzing_time(mut paused: watch::Receiver<bool>, duration: Duration) {
+    let mut left = duration;
+    loop {
+        while *paused.borrow() {
+            if paused.changed().await.is_err() {
+                break;
+            }
+        }
+        let resumed = Instant::now();
+        let changed = tokio::select! {
+            _ = tokio::time::sleep(left) => return,
+            changed = paused.changed() => changed,
+        };
+        left = left.checked_sub(resumed.elapsed()).unwrap_or_default();
+        if changed.is_err() {
+            // pauses are not followed anymore
+            tokio::time::sleep(left).await;
+            return;
+        }
+    }
+}
+
+/// Pauses processes of the run, found by their working directory within the run checkout,
+/// while the run is paused by the scheduler or manually, until the run completes, telling
+/// whether it is paused to `pausing`.
+async fn follow_pauses(
+    mut slot: watch::Receiver<SlotState>,
+    mut manual: watch::Receiver<Option<String>>,
+    pausing: watch::Sender<bool>,
+    dir: PathBuf,
+    feedback: Arc<Feedback>,
+    cancel: Scope,
+    log: Logger,
+) {
+    let signal = |signal| match resources::processes_in(&dir) {
+        Ok(pids) => process::signal_all(&pids, signal),
+        Err(e) => warn!(log, "Cannot list processes of the run"; "error" => e.to_string()),
+    };
+    let pause_reason = |slot: &watch::Receiver<SlotState>, manual: &watch::Receiver<Option<String>>| {
+        if let Some(by) = &*manual.borrow() {
+            return Some(format!("by {}", by));
+        }
+        match &*slot.borrow() {
+            SlotState::Paused(by) => Some(format!("by higher-priority run `{}`", by)),
+            _ => None,
+        }
+    };
+    loop {
+        if let Some(reason) = pause_reason(&slot, &manual) {
+            info!(log, "Run is paused"; "reason" => &reason);
+            feedback.message(format!("Fuzzing is paused {}", reason));
+            let paused = Instant::now();
+            let _ = pausing.send(true);
+            // processes started while paused, e.g. by the next build step, are stopped as well
+            loop {
+                signal(libc::SIGSTOP);
+                let ended = tokio::select! {
+                    changed = slot.changed() => changed.is_err(),
+                    changed = manual.changed() => changed.is_err(),
+                    _ = tokio::time::sleep(Duration::from_secs(1)) => false,
+                    _ = cancel.cancelled() => true,
+                };
+                if ended || pause_reason(&slot, &manual).is_none() {
+                    break;
+                }
+            }
+            signal(libc::SIGCONT);
+            let _ = pausing.send(false);
+            if cancel.is_cancelled() {
+                return;
+            }
+            let paused = paused.elapsed().as_secs();
+            info!(log, "Run is resumed"; "paused" => paused);
+            feedback.message(format!(
+                "Fuzzing is resumed after {}s, which don't count towards the fuzzing time of targets",
+                paused
+            ));
+            continue;
+        }
+        let ended = tokio::select! {
+            changed = slot.changed() => changed.is_err(),
+            changed = manual.changed() => changed.is_err(),
+            _ = cancel.cancelled() => true,
+        };
+        if ended {
+            return;
+        }
+    }
+}
+
+/// Makes the run current for its branch in the state store, waiting for the previous run,
+/// possibly started by another server, to notice it is superseded and complete.
+async fn supersede(store: &Store, branch: &str, id: &str, log: &Logger) {
+    let previous = match store.set_current(branch, id).await {
+        Ok(previous) => previous,
+        Err(e) => {
+            error!(log, "Cannot store the current run of the branch"; "error" => e);
+            return;
+        }
+    };
+    if let Some(previous) = previous {
+        while store.is_live(&previous).await.unwrap_or(false) {
+            debug!(log, "Waiting for the previous run to complete"; "previous" => &previous);
+            tokio::time::sleep(state::HEARTBEAT_INTERVAL).await;
+        }
+    }
+}
+
+/// Records crashes and CPU time of the run targets, for crash rates reports.
+async fn record_targets(store: &Store, id: &str, feedback: &Feedback, log: &Logger) {
+    let finished = chrono::Utc::now().timestamp();
+    let status = feedback.status();
+    let resources = feedback.resources();
+    let previous = feedback.previous_run().map(|(previous, _)| previous);
+    let targets = status
+        .keys()
+        .chain(resources.targets.keys())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .map(|target| TargetRun {
+            target: target.clone(),
+            finished,
+            crashes: status.get(target).map_or(0, |s| s.errors),
+            cpu_time: resources.targets.get(target).map_or(0.0, |u| u.cpu_time),
+            new_edges: status.get(target).map_or(0, |s| {
+                let before = previous.as_ref().and_then(|p| p.get(target)).map_or(0, |p| p.covered);
+                s.covered.saturating_sub(before)
+            }),
+        })
+        // targets that weren't fuzzed, e.g. in a failed build
+        .filter(|t| t.crashes > 0 || t.cpu_time > 0.0 || t.new_edges > 0)
+        .collect::<Vec<_>>();
+    if let Err(e) = store.record_targets(id, &targets).await {
+        error!(log, "Cannot store crashes of targets"; "error" => e);
+    }
+}
+
+/// Keeps the run alive in the state store, cancelling it when another run of the
+/// branch becomes current.
+async fn heartbeat(store: Store, branch: String, id: String, cancel: Scope, log: Logger) {
+    loop {
+        tokio::time::sleep(state::HEARTBEAT_INTERVAL).await;
+        if let Err(e) = store.heartbeat(&id).await {
+            warn!(log, "Cannot store run heartbeat"; "error" => e);
+        }
+        match store.current(&branch).await {
+            Ok(Some(current)) if current != id && !cancel.is_cancelled() => {
+                info!(log, "Run is superseded, cancelling"; "current" => current);
+                cancel.cancel();
+            }
+            Ok(_) => (),
+            Err(e) => warn!(log, "Cannot read the current run of the branch"; "error" => e),
+        }
+    }
+}
+
+/// Name identifying this server in the state store across restarts.
+fn owner(config: &Config) -> String {
+    let mut name = [0u8; 256];
+    let host = if unsafe { libc::gethostname(name.as_mut_ptr() as *mut libc::c_char, name.len()) } == 0 {
+        let len = name.iter().position(|b| *b == 0).unwrap_or(name.len());
+        String::from_utf8_lossy(&name[..len]).into_owned()
+    } else {
+        "localhost".to_string()
+    };
+    format!("{}/{}", host, config.address)
+}
+
+/// Restarts runs interrupted by a restart of this server or a crash of any server sharing the state.
+async fn resume_runs(runner: &Runner, resume: bool, log: &Logger) {
+    let store = match runner.runs.store() {
+        Some(store) => store,
+        None => return,
+    };
+    let interrupted = match store.take_interrupted().await {
+        Ok(interrupted) => interrupted,
+        Err(e) => {
+            error!(log, "Cannot read interrupted runs"; "error" => e);
+            return;
+        }
+    };
+    let config = runner.config();
+    for (run, url) in interrupted {
+        if !resume || !(config.branches.contains(&run.branch) || config.is_fuzzed_tag(&run.branch)) {
+            info!(log, "Run was interrupted"; "id" => &run.id, "branch" => &run.branch);
+            continue;
+        }
+        info!(log, "Resuming interrupted run"; "id" => &run.id, "branch" => &run.branch);
+        let description = format!("{} (resumed)", run.description.trim_end_matches(" (resumed)"));
+        if let Err(e) = runner.start(url, run.branch, run.commit, description, false, Trigger::Resume).await {
+            error!(log, "Cannot resume run"; "id" => &run.id, "error" => e.to_string());
+        }
+    }
+}
+
+/// Periodically starts runs of branches configured for re-fuzzing that are not fuzzed
+/// for the configured number of days, while the server is active.
+async fn refuzz_stale(runner: Runner, role: Role, log: Logger) {
+    loop {
+        let interval = runner.config().scheduling.refuzz.map_or(config::Refuzz::default_interval(), |r| r.interval);
+        tokio::time::sleep(Duration::from_secs(interval)).await;
+        let config = runner.config();
+        let refuzz = match &config.scheduling.refuzz {
+            Some(refuzz) if role.is_active() => refuzz,
+            _ => continue,
+        };
+        for branch in &refuzz.branches {
+            refuzz_branch(&runner, &config, branch, refuzz.after_days, &log).await;
+        }
+    }
+}
+
+/// Starts a run of the branch, or `repository/branch` in multi-repo mode, if it's not
+/// fuzzed for `after_days` days.
+async fn refuzz_branch(runner: &Runner, config: &Config, entry: &str, after_days: u64, log: &Logger) {
+    let (repo, branch) = match entry.split_once('/') {
+        Some((repo, branch)) if config.repositories.contains_key(repo) => (Some(repo), branch),
+        _ => (None, entry),
+    };
+    let url = match repo.or(config.repository.as_deref()) {
+        Some(repo) => config.repository_url(repo),
+        None => {
+            warn!(log, "Cannot re-fuzz the branch without `repository`"; "branch" => entry);
+            return;
+        }
+    };
+    let namespace = repo.into_iter().chain(std::iter::once(branch)).collect::<Vec<_>>();
+    if runner.is_fuzzing(&namespace.join("/")) {
+        return;
+    }
+    let last = match report::Report::last_updated(&config.reports_path, &common::new_local_path(&namespace), log).await {
+        Ok(last) => last,
+        Err(e) => {
+            error!(log, "Cannot find when the branch was fuzzed"; "branch" => entry, "error" => e.to_string());
+            return;
+        }
+    };
+    let days = last.and_then(|last| last.elapsed().ok()).map(|age| age.as_secs() / (24 * 60 * 60));
+    let run_id = match days {
+        Some(days) if days < after_days => return,
+        Some(days) => format!("staleness re-run, not fuzzed for {} days, at {}", days, config.time.now()),
+        None => format!("staleness re-run, never fuzzed, at {}", config.time.now()),
+    };
+    info!(log, "Re-fuzzing the branch not fuzzed recently"; "branch" => entry, "days" => days);
+    if let Err(e) = runner.start(url, branch.to_string(), None, run_id, false, Trigger::Staleness).await {
+        error!(log, "Cannot start re-fuzzing"; "branch" => entry, "error" => e.to_string());
+    }
+}
+
+/// Periodically starts a distillation run of the configured branch, once the configured
+/// interval passes since the last one was started, while the server is active.
+async fn schedule_distillation(runner: Runner, role: Role, log: Logger) {
+    loop {
+        tokio::time::sleep(DISTILLATION_CHECK_INTERVAL).await;
+        let config = runner.config();
+        let distillation = match &config.distillation {
+            Some(distillation) if role.is_active() => distillation,
+            _ => continue,
+        };
+        let stamp = config.workdir.distill_stamp();
+        let due = match tokio::fs::metadata(&stamp).await.and_then(|m| m.modified()) {
+            Ok(time) => time.elapsed().map_or(false, |elapsed| elapsed.as_secs() >= distillation.interval),
+            Err(_) => true,
+        };
+        if due {
+            distill_branch(&runner, &config, &distillation.branch, &stamp, &log).await;
+        }
+    }
+}
+
+/// Starts a distillation run of the branch, or `repository/branch` in multi-repo mode,
+/// unless one is running, recording the time it's started in the stamp file.
+async fn distill_branch(runner: &Runner, config: &Config, entry: &str, stamp: &Path, log: &Logger) {
+    let (repo, branch) = match entry.split_once('/') {
+        Some((repo, branch)) if config.repositories.contains_key(repo) => (Some(repo), branch),
+        _ => (None, entry),
+    };
+    let url = match repo.or(config.repository.as_deref()) {
+        Some(repo) => config.repository_url(repo),
+        None => {
+            warn!(log, "Cannot distill corpora without `repository`"; "branch" => entry);
+            return;
+        }
+    };
+    let namespace = std::iter::once(DISTILLATION_DIR).chain(repo).chain(std::iter::once(branch)).collect::<Vec<_>>();
+    if runner.is_fuzzing(&namespace.join("/")) {
+        return;
+    }
+    if let Some(parent) = stamp.parent() {
+        if let Err(e) = tokio::fs::create_dir_all(parent).await {
+            error!(log, "Cannot create the working directory"; "error" => e.to_string());
+            return;
+        }
+    }
+    if let Err(e) = tokio::fs::write(stamp, b"").await {
+        error!(log, "Cannot record the distillation time"; "stamp" => stamp.to_str(), "error" => e.to_string());
+        return;
+    }
+    info!(log, "Distilling corpora"; "branch" => entry);
+    let run_id = format!("distillation at {}", config.time.now());
+    if let Err(e) = runner.start(url, branch.to_string(), None, run_id, false, Trigger::Distillation).await {
+        error!(log, "Cannot start distillation"; "branch" => entry, "error" => e.to_string());
     }
 }
 
-async fn push_hook(
-    push: PushEvent,
-    config: Config,
-    builder: Arc<Mutex<Builder>>,
-    stop_bcs: Arc<RwLock<HashMap<String, Synch>>>,
+/// Stores the webhook delivery of the event, if deliveries are stored, and processes it.
+async fn receive_webhook(
+    event: String,
+    id: Option<String>,
+    payload: serde_json::Value,
+    runner: Runner,
     log: Logger,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let url = push.repository.url;
-    let branch = match push.ref_.strip_prefix("refs/heads/") {
-        Some(branch) => branch.to_string(),
-        None => return Err(warp::reject()),
-    };
-    trace!(log, "Push event"; "repo" => &url, "branch" => &branch);
-    if config.branches.contains(&branch) {
-        let log = log.new(o!("branch" => branch.clone()));
-        trace!(log, "Starting fuzzing on branch {}", branch);
-        let (sync, existing) = get_sync(stop_bcs, &branch, &log);
-        if existing {
-            sync.notify.notified().await;
+    let stored = match &runner.deliveries {
+        Some(deliveries) => match deliveries.store(id.as_deref(), &event, payload.clone()).await {
+            Ok(delivery) => Some(delivery),
+            Err(e) => {
+                // the event is still processed, it only cannot be replayed
+                error!(log, "Cannot store webhook delivery"; "event" => &event, "error" => e.to_string());
+                None
+            }
+        },
+        None => None,
+    };
+    match stored {
+        Some(mut delivery) => {
+            let log = log.new(o!("delivery" => delivery.id.clone()));
+            process_delivery(&runner, &mut delivery, &log).await?
+        }
+        None => dispatch(&event, &payload, &runner, &log).await?,
+    }
+    Ok(warp::reply())
+}
+
+/// Processes the webhook delivery, recording its outcome.
+async fn process_delivery(runner: &Runner, delivery: &mut Delivery, log: &Logger) -> Result<(), warp::Rejection> {
+    let result = dispatch(&delivery.event, &delivery.payload, runner, log).await;
+    delivery.status = if result.is_ok() { DeliveryStatus::Processed } else { DeliveryStatus::Failed };
+    if let Some(deliveries) = &runner.deliveries {
+        if let Err(e) = deliveries.save(delivery).await {
+            error!(log, "Cannot store webhook delivery"; "error" => e.to_string());
+        }
+    }
+    result
+}
+
+/// Webhook payload of the event.
+fn parse_payload<'a, T: Deserialize<'a>>(payload: &'a serde_json::Value, log: &Logger) -> Result<T, warp::Rejection> {
+    T::deserialize(payload).map_err(|e| {
+        debug!(log, "Invalid webhook payload"; "error" => e.to_string());
+        warp::reject::reject()
+    })
+}
+
+/// Processes the webhook event with the payload.
+async fn dispatch(event: &str, payload: &serde_json::Value, runner: &Runner, log: &Logger) -> Result<(), warp::Rejection> {
+    let log = log.new(o!("event" => event.to_string()));
+    match event {
+        "ping" => {
+            let ping = parse_payload::<PingEvent>(payload, &log)?;
+            debug!(log, "Incoming ping"; "body" => serde_json::to_string(&ping).unwrap());
+        }
+        "push" => {
+            push_hook(parse_payload(payload, &log)?, runner.clone(), log).await?;
+        }
+        "delete" => {
+            delete_hook(parse_payload(payload, &log)?, runner.clone(), log).await?;
+        }
+        "create" => {
+            create_hook(parse_payload(payload, &log)?, runner.clone(), log).await?;
+        }
+        "release" => {
+            release_hook(parse_payload(payload, &log)?, runner.clone(), log).await?;
+        }
+        "repo:push" => {
+            bitbucket_hook(parse_payload(payload, &log)?, runner.clone(), log).await?;
+        }
+        _ => return Err(warp::reject()),
+    }
+    Ok(())
+}
+
+/// Processes webhook deliveries received before the server start and left pending by a
+/// restart, oldest first.
+async fn process_pending(runner: &Runner, started: chrono::DateTime<chrono::Utc>, log: &Logger) {
+    let deliveries = match &runner.deliveries {
+        Some(deliveries) => deliveries,
+        None => return,
+    };
+    let pending = match deliveries.pending().await {
+        Ok(pending) => pending,
+        Err(e) => {
+            error!(log, "Cannot read pending webhook deliveries"; "error" => e.to_string());
+            return;
+        }
+    };
+    for mut delivery in pending.into_iter().filter(|delivery| delivery.received < started) {
+        info!(log, "Processing webhook delivery left pending"; "delivery" => &delivery.id, "event" => &delivery.event);
+        let log = log.new(o!("delivery" => delivery.id.clone()));
+        if process_delivery(runner, &mut delivery, &log).await.is_err() {
+            warn!(log, "Cannot process webhook delivery");
         }
+    }
+}
 
+async fn push_hook(push: PushEvent, runner: Runner, log: Logger) -> Result<impl warp::Reply, warp::Rejection> {
+    let url = push.repository.url;
+    // runs of tags are kept like ones of branches named by the tag
+    let (branch, tag) = match (push.ref_.strip_prefix("refs/heads/"), push.ref_.strip_prefix("refs/tags/")) {
+        (Some(branch), _) => (branch.to_string(), false),
+        (None, Some(tag)) => (tag.to_string(), true),
+        (None, None) => return Err(warp::reject()),
+    };
+    trace!(log, "Push event"; "repo" => &url, "branch" => &branch, "tag" => tag);
+    if push.deleted {
+        if !tag {
+            tokio::spawn(async move { remove_branch(&runner, &url, &branch, &log).await });
+        }
+        return Ok(warp::reply());
+    }
+    let config = runner.config();
+    let fuzzed = if tag { config.is_fuzzed_tag(&branch) } else { config.branches.contains(&branch) };
+    if !config.repositories.is_empty() && config.repository_name(&url).is_none() {
+        debug!(log, "Skipping repository"; "repo" => &url);
+    } else if fuzzed {
         let run_id = if let Some(commit) = &push.head_commit {
-            get_run_id(commit)
+            get_run_id(commit, &config.time)
         } else if let Some(commit) = push.commits.first() {
-            get_run_id(commit)
+            get_run_id(commit, &config.time)
         } else {
             "no commit".to_string()
         };
-
-        let reports_loc = common::new_local_path(&[&branch, &run_id]);
-        let description = format!("Branch `{}`, {}", branch, run_id);
-
-        let feedback = create_feedback(&config, &description, &reports_loc, &sync.bcast, &log).await;
-        feedback.message("Preparing for fuzzing".to_string());
-        trace!(log, "Spawning fuzzer");
-        let bcast = sync.bcast.clone();
-        let notify = sync.notify.clone();
-        tokio::spawn(async move {
-            match run_fuzzers(url, builder, config, feedback, &reports_loc, &branch, bcast, log.clone()).await {
-                Ok(_) => (),
-                Err(e) => error!(log, "Error running fuzzers"; "error" => e.to_string()),
-            }
-            notify.notify_one();
-        });
+        if push.forced {
+            info!(log, "Force-push, invalidating the previous report"; "repo" => &url, "branch" => &branch);
+        }
+        if runner.start(url, branch, None, run_id, push.forced, Trigger::Push).await.is_err() {
+            return Err(warp::reject());
+        }
     } else {
         debug!(log, "Skipping branch");
     }
     Ok(warp::reply())
 }
 
+/// Marks the most recent report of the branch as stale when its history is rewritten
+/// by a force-push, so it's not compared with as if it was an ancestor. Returns its location.
+async fn invalidate_superseded(config: &Config, run: &RunInfo, log: &Logger) -> Option<PathBuf> {
+    let branch_loc = run.reports_loc.parent()?;
+    let marked = async {
+        let loc = match report::Report::latest(&config.reports_path, branch_loc, log).await? {
+            Some(loc) => loc,
+            None => return Ok(None),
+        };
+        let report = report::Report::new(&config.reports_path, &config.url, &loc, None, &config.time, log.clone()).await?;
+        report
+            .mark_stale(format!("the branch history is rewritten by a force-push at {}", config.time.now()))
+            .await?;
+        Ok::<_, Error>(Some(loc))
+    };
+    match marked.await {
+        Ok(loc) => loc,
+        Err(e) => {
+            error!(log, "Cannot mark the superseded report stale"; "error" => e.to_string());
+            None
+        }
+    }
+}
+
+async fn bitbucket_hook(
+    push: BitbucketPushEvent,
+    runner: Runner,
+    log: Logger,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    for push in push.into_pushes() {
+        push_hook(push, runner.clone(), log.clone()).await?;
+    }
+    Ok(warp::reply())
+}
+
+async fn delete_hook(delete: DeleteEvent, runner: Runner, log: Logger) -> Result<impl warp::Reply, warp::Rejection> {
+    trace!(log, "Delete event"; "repo" => &delete.repository.url, "ref" => &delete.ref_, "ref_type" => &delete.ref_type);
+    if delete.ref_type == "branch" {
+        // the run of the branch is waited for, so the webhook is answered right away
+        tokio::spawn(async move { remove_branch(&runner, &delete.repository.url, &delete.ref_, &log).await });
+    }
+    Ok(warp::reply())
+}
+
+async fn create_hook(create: CreateEvent, runner: Runner, log: Logger) -> Result<impl warp::Reply, warp::Rejection> {
+    trace!(log, "Create event"; "repo" => &create.repository.url, "ref" => &create.ref_, "ref_type" => &create.ref_type);
+    if create.ref_type == "tag" {
+        fuzz_release(&runner, create.repository.url, create.ref_, &log).await?;
+    }
+    Ok(warp::reply())
+}
+
+async fn release_hook(release: ReleaseEvent, runner: Runner, log: Logger) -> Result<impl warp::Reply, warp::Rejection> {
+    trace!(log, "Release event"; "repo" => &release.repository.url, "tag" => &release.release.tag_name, "action" => &release.action);
+    if release.action == "published" {
+        fuzz_release(&runner, release.repository.url, release.release.tag_name, &log).await?;
+    }
+    Ok(warp::reply())
+}
+
+/// Starts a release run of the tag, unless its release is not fuzzed or is fuzzed already,
+/// as both creating the tag and publishing its release request it.
+async fn fuzz_release(runner: &Runner, url: String, tag: String, log: &Logger) -> Result<(), warp::Rejection> {
+    let config = runner.config();
+    let repo = config.repository_name(&url);
+    if !config.repositories.is_empty() && repo.is_none() {
+        debug!(log, "Skipping repository"; "repo" => &url);
+        return Ok(());
+    }
+    if !config.is_fuzzed_release(&tag) {
+        debug!(log, "Skipping release"; "tag" => &tag);
+        return Ok(());
+    }
+    let namespace = std::iter::once(RELEASES_DIR).chain(repo).chain(std::iter::once(tag.as_str())).collect::<Vec<_>>();
+    let (reports, key) = (config.reports_path.join(common::new_local_path(&namespace)), namespace.join("/"));
+    if reports.is_dir() || !runner.releases.write().unwrap().insert(key.clone()) {
+        debug!(log, "Release is fuzzed already"; "tag" => &tag);
+        return Ok(());
+    }
+    info!(log, "Fuzzing release"; "repo" => &url, "tag" => &tag);
+    let run_id = format!("release {} at {}", tag, config.time.now());
+    if runner.start(url, tag, None, run_id, false, Trigger::Release).await.is_err() {
+        runner.releases.write().unwrap().remove(&key);
+        return Err(warp::reject());
+    }
+    Ok(())
+}
+
+/// Stops fuzzing of the branch deleted upstream, archiving or pruning its reports and
+/// corpus overlay as configured, and posts a notification.
+async fn remove_branch(runner: &Runner, url: &str, branch: &str, log: &Logger) {
+    let config = runner.config();
+    let repo = config.repository_name(url);
+    if (!config.repositories.is_empty() && repo.is_none()) || !config.branches.iter().any(|b| b == branch) {
+        debug!(log, "Skipping deleted branch"; "repo" => url, "branch" => branch);
+        return;
+    }
+    let namespace = repo.into_iter().chain(std::iter::once(branch)).collect::<Vec<_>>();
+    let key = namespace.join("/");
+    info!(log, "Branch is deleted, stopping fuzzing"; "branch" => &key);
+    let mut actions = vec![];
+    if runner.stop_branch(&key).await {
+        actions.push("fuzzing is stopped".to_string());
+    }
+
+    let reports = config.reports_path.join(common::new_local_path(&namespace));
+    if reports.is_dir() {
+        let removed = match config.deleted_branches.reports {
+            config::Retention::Keep => Ok(None),
+            config::Retention::Archive => {
+                let mut archived = config.reports_path.join("archived").join(common::new_local_path(&namespace)).into_os_string();
+                archived.push(format!("-{}", chrono::Utc::now().format("%Y%m%d%H%M%S")));
+                let archived = PathBuf::from(archived);
+                async {
+                    if let Some(parent) = archived.parent() {
+                        tokio::fs::create_dir_all(parent).await?;
+                    }
+                    tokio::fs::rename(&reports, &archived).await
+                }
+                .await
+                .map(|_| Some(format!("reports are archived to {}", archived.to_string_lossy())))
+            }
+            config::Retention::Prune => tokio::fs::remove_dir_all(&reports).await.map(|_| Some("reports are removed".to_string())),
+        };
+        match removed {
+            Ok(action) => actions.extend(action),
+            Err(e) => error!(log, "Cannot remove reports of the deleted branch"; "dir" => reports.to_str(), "error" => e.to_string()),
+        }
+    }
+
+    if config.branch_corpus && config.deleted_branches.prune_overlay {
+        let corpora = config
+            .targets
+            .values()
+            .filter_map(|conf| conf.corpus_dir(config.corpus.as_deref()))
+            .collect::<BTreeSet<_>>();
+        let mut pruned = false;
+        for corpus in corpora {
+            let overlay = corpus::overlay_dir(corpus, branch);
+            if !overlay.is_dir() {
+                continue;
+            }
+            match tokio::fs::remove_dir_all(&overlay).await {
+                Ok(_) => pruned = true,
+                Err(e) => error!(log, "Cannot remove corpus overlay of the deleted branch"; "dir" => overlay.to_str(), "error" => e.to_string()),
+            }
+        }
+        if pruned {
+            actions.push("corpus overlay is removed".to_string());
+        }
+    }
+
+    let description = match repo {
+        Some(repo) => format!("Repository `{}`, branch `{}`", repo, branch),
+        None => format!("Branch `{}`", branch),
+    };
+    let client: Box<dyn FeedbackClient + Sync + Send> = match &config.slack {
+        Some(slack) => Box::new(
+            SlackClient::new(&description, &slack.channel, &slack.token, FeedbackLevel::Info, log.clone())
+                .with_long_messages(slack.max_length, slack.long_messages),
+        ),
+        None => Box::new(LoggerClient::new(&description, log.clone()).with_level(config.feedback.log_level)),
+    };
+    let mut message = "The branch is deleted upstream".to_string();
+    if !actions.is_empty() {
+        message = format!("{}, {}", message, actions.join(", "));
+    }
+    if let Err(e) = client.message(FeedbackLevel::Info, &message).await {
+        error!(log, "Cannot post notification about the deleted branch"; "error" => e.to_string());
+    }
+}
+
 #[derive(Serialize)]
 struct BranchReports {
     name: String,
@@ -402,6 +2825,115 @@ impl BranchReports {
     }
 }
 
+const OVERVIEW: &str = r#"
+<meta http-equiv="refresh" content="30">
+<h1>Fuzzing in progress</h1>
+{{#each this}}
+<h2>{{branch}}</h2>
+<p>{{state}}, running for {{elapsed}}, {{crashes}} crashes found
+  <a href="{{link}}"><button>Live report</button></a></p>
+<table>
+<tr><th>Target</th><th>Covered edges</th><th>Total edges</th><th>Crashes</th></tr>
+{{#each targets}}
+<tr><td>{{name}}</td><td>{{covered}}</td><td>{{total}}</td><td>{{crashes}}</td></tr>
+{{/each}}
+</table>
+{{else}}
+<p>No branches are being fuzzed now.</p>
+{{/each}}
+<p><a href="reports/">All reports</a>, <a href="crash-rates">crash rates and fuzzing budget of targets</a>,
+  <a href="targets">disabled targets</a></p>
+"#;
+
+const CRASH_RATES: &str = r#"
+<h1>Crashes and new edges per CPU hour</h1>
+{{#if this}}
+<table>
+<tr><th>Target</th><th>Crashes</th><th>CPU hours</th><th>Crashes per CPU hour</th><th>New edges</th><th>CPU minutes per new edge</th>{{#each weeks}}<th>Week of {{this}}</th>{{/each}}</tr>
+{{#each targets}}
+<tr><td>{{target}}</td><td>{{crashes}}</td><td>{{cpu_hours}}</td><td>{{rate}}</td><td>{{new_edges}}</td><td>{{edge_cost}}</td>{{#each weeks}}<td>{{this}}</td>{{/each}}</tr>
+{{/each}}
+</table>
+{{else}}
+<p>Crash rates are kept in the state store, configure it in the <code>[state]</code> section.</p>
+{{/if}}
+"#;
+
+/// Fuzz target as shown on the targets page.
+#[derive(Serialize)]
+struct TargetState {
+    name: String,
+    /// Who disabled the target and when, if it is disabled
+    disabled: Option<String>,
+    /// Action of the form disabling or enabling the target, if the API is enabled
+    action: Option<String>,
+}
+
+const TARGETS: &str = r#"
+<h1>Fuzz targets</h1>
+<p>Disabled targets are not fuzzed by runs started later, until they are enabled again.</p>
+<table>
+<tr><th>Target</th><th>State</th><th></th></tr>
+{{#each this}}
+<tr><td>{{name}}</td><td>{{#if disabled}}Disabled by {{disabled}}{{else}}Enabled{{/if}}</td><td>
+{{#if action}}
+<form method="post" action="{{action}}">
+  <input type="password" name="token" placeholder="Admin API token">
+  <button>{{#if disabled}}Enable{{else}}Disable{{/if}}</button>
+</form>
+{{/if}}
+</td></tr>
+{{/each}}
+</table>
+"#;
+
+/// Configured and disabled targets, in order of their names.
+async fn target_states(runner: &Runner) -> Vec<TargetState> {
+    let config = runner.config();
+    let mut disabled = runner
+        .disabled_targets()
+        .await
+        .into_iter()
+        .map(|d| (d.target.clone(), d))
+        .collect::<HashMap<_, _>>();
+    let mut names = config.targets.values().flat_map(|conf| conf.targets.iter().cloned()).collect::<BTreeSet<_>>();
+    names.extend(disabled.keys().cloned());
+    names
+        .into_iter()
+        .map(|name| {
+            let disabled = disabled.remove(&name);
+            let action = config.api.is_enabled().then(|| {
+                let toggle = if disabled.is_some() { "enable" } else { "disable" };
+                action_url(&config, &format!("targets/{}/{}", common::sanitize_url_path_segment(OsStr::new(&name)), toggle))
+            });
+            TargetState {
+                disabled: disabled.map(|d| format!("{} at {}", d.by, d.since.format("%Y-%m-%d %H:%M UTC"))),
+                action,
+                name,
+            }
+        })
+        .collect()
+}
+
+const PEERS: &str = r#"
+<meta http-equiv="refresh" content="60">
+<h1>Fuzzing runs of all servers</h1>
+{{#each this}}
+<h2>{{#if url}}<a href="{{url}}">{{name}}</a>{{else}}{{name}}{{/if}}</h2>
+{{#if error}}<p>Cannot pull runs: {{error}}</p>{{/if}}
+{{#if updated}}<p>Pulled at {{updated}}</p>{{/if}}
+<table>
+<tr><th>Run</th><th>Branch</th><th>Commit</th><th>Status</th><th>Verdict</th><th>Report</th></tr>
+{{#each runs}}
+<tr><td>{{id}}</td><td>{{branch}}</td><td>{{commit}}</td><td>{{status}}</td><td>{{verdict}}</td>
+  <td>{{#if report_url}}<a href="{{report_url}}">{{description}}</a>{{else}}{{description}}{{/if}}</td></tr>
+{{else}}
+<tr><td colspan="6">No runs</td></tr>
+{{/each}}
+</table>
+{{/each}}
+"#;
+
 const REPORTS: &str = r#"
 <h1>Fuzzing coverage reports</h1>
 {{#each this}}
@@ -421,6 +2953,9 @@ struct Report {
     branch: String,
     time: String,
     projects: Vec<String>,
+    /// Action of the form re-running the run, if it is finished
+    #[new(default)]
+    rerun: Option<String>,
 }
 
 const REPORT: &str = r#"
@@ -431,6 +2966,50 @@ const REPORT: &str = r#"
 <tr><td><a href="./{{this}}/index.html">{{this}}</a></td></tr>
 {{/each}}
 </table>
+{{#if rerun}}
+<form method="post" action="{{rerun}}">
+  <input type="password" name="token" placeholder="API token">
+  <button>Re-run</button>
+</form>
+{{/if}}
+"#;
+
+/// Action of the form re-running the finished run reported at `branch`/`time` of the reports
+/// directory, if the API is enabled.
+async fn rerun_action(runner: &Runner, repo: Option<&str>, branch: &str, time: &str) -> Option<String> {
+    let config = runner.config();
+    if !config.api.is_enabled() {
+        return None;
+    }
+    let decode = |s: &str| percent_decode_str(s).decode_utf8_lossy().into_owned();
+    let (branch, time) = (decode(branch), decode(time));
+    let run = runner.runs().await.into_iter().rev().find(|run| {
+        run.status != RunStatus::Running
+            && repo.iter().all(|repo| run.id.starts_with(&format!("{}-", repo)))
+            && common::sanitize_path_segment(&run.branch) == *branch
+            && common::sanitize_path_segment(&run.description) == *time
+    })?;
+    let path = format!("rerun/{}", common::sanitize_url_path_segment(OsStr::new(&run.id)));
+    Some(action_url(&config, &path))
+}
+
+/// Url of the server path, absolute if the server url is configured.
+fn action_url(config: &Config, path: &str) -> String {
+    match config.url.as_ref().and_then(|url| url.join(path).ok()) {
+        Some(url) => url.to_string(),
+        None => format!("/{}", path),
+    }
+}
+
+#[derive(Serialize)]
+struct Rerun {
+    message: String,
+    home: String,
+}
+
+const RERUN: &str = r#"
+<p>{{message}}</p>
+<p><a href="{{home}}">Runs in progress</a></p>
 "#;
 
 use handlebars::Handlebars;
@@ -457,56 +3036,286 @@ fn branches(dir: String) -> HashMap<String, Vec<String>> {
 }
  */
 
-pub(crate) async fn start(config: Config, log: slog::Logger) {
+/// Server settings specified on the command line, taking precedence over the configuration file.
+#[derive(Clone, Default)]
+pub struct Overrides {
+    pub address: Option<String>,
+    pub url: Option<Url>,
+    pub branches: Option<Vec<String>>,
+}
+
+impl Overrides {
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(address) = &self.address {
+            config.address = address.clone();
+        } else if config.address.is_empty() {
+            config.address = "0.0.0.0:3030".to_string();
+        }
+
+        if let Some(url) = &self.url {
+            config.url = Some(url.clone());
+        } else if config.url.is_none() {
+            config.url = Some(
+                format!("http://{}", config.address)
+                    .parse()
+                    .expect("Failed to parse address as url"),
+            );
+        }
+
+        if let Some(branches) = &self.branches {
+            config.branches = branches.clone();
+        } else if config.branches.is_empty() {
+            config.branches = ["master", "develop"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect();
+        }
+    }
+}
+
+#[derive(Debug)]
+struct InvalidSignature;
+
+impl warp::reject::Reject for InvalidSignature {}
+
+#[derive(Debug)]
+struct InvalidPayload;
+
+impl warp::reject::Reject for InvalidPayload {}
+
+/// Parses the JSON body of a webhook request, rejecting it unless it is signed with the
+/// webhook secret in the signature header, if the secret is configured.
+fn signed_json<T: serde::de::DeserializeOwned + Send>(
+    signature_header: &'static str,
+    configs: watch::Receiver<Config>,
+    log: Logger,
+) -> impl Filter<Extract = (T,), Error = warp::Rejection> + Clone {
+    warp::header::optional::<String>(signature_header)
+        .and(warp::body::content_length_limit(WEBHOOK_BODY_LIMIT))
+        .and(warp::body::bytes())
+        .and_then(move |signature: Option<String>, body: warp::hyper::body::Bytes| {
+            let secret = configs.borrow().webhook_secret.clone();
+            let log = log.clone();
+            async move {
+                if let Some(secret) = secret {
+                    let verified = match &signature {
+                        Some(signature) => github::verify_webhook(&secret, &body, signature),
+                        None => false,
+                    };
+                    if !verified {
+                        warn!(log, "Webhook request with missing or invalid signature");
+                        return Err(warp::reject::custom(InvalidSignature));
+                    }
+                }
+                serde_json::from_slice(&body).map_err(|e| {
+                    debug!(log, "Invalid webhook payload"; "error" => e.to_string());
+                    warp::reject::custom(InvalidPayload)
+                })
+            }
+        })
+}
+
+/// The event of a webhook request named by the header, rejecting requests of other events.
+fn webhook_event(header: &'static str, events: &'static [&'static str]) -> impl Filter<Extract = (String,), Error = warp::Rejection> + Clone {
+    warp::header::<String>(header).and_then(move |event: String| async move {
+        if events.contains(&event.as_str()) {
+            Ok(event)
+        } else {
+            Err(warp::reject())
+        }
+    })
+}
+
+/// Responds to webhook requests with invalid signatures with 401, and to ones with
+/// invalid payloads with 400.
+async fn handle_webhook_rejection(rejection: warp::Rejection) -> Result<impl warp::Reply, warp::Rejection> {
+    if rejection.find::<InvalidSignature>().is_some() {
+        Ok(warp::reply::with_status("Invalid signature", StatusCode::UNAUTHORIZED))
+    } else if rejection.find::<InvalidPayload>().is_some() {
+        Ok(warp::reply::with_status("Invalid payload", StatusCode::BAD_REQUEST))
+    } else {
+        Err(rejection)
+    }
+}
+
+/// Runs the webhook server, fuzzing configured branches on pushes.
+///
+/// The configuration is reloaded when the `config_file` changes.
+pub async fn start(config_file: PathBuf, mut config: Config, overrides: Overrides, log: slog::Logger) -> Result<(), Error> {
     pretty_env_logger::init();
+    overrides.apply(&mut config);
 
     info!(log, "Starting server"; "address" => &config.address);
-    let addr = match config.address.parse::<SocketAddr>() {
-        Ok(a) => a,
-        Err(e) => {
-            error!(log, "Cannot parse address {}", config.address; "error" => e.to_string());
-            return;
-        }
+    let addr = config
+        .address
+        .parse::<SocketAddr>()
+        .map_err(|e| Error::other(format!("cannot parse address {}: {}", config.address, e)))?;
+
+    if let (Some(storage), Some(corpus)) = (&config.storage, &config.corpus) {
+        let interval = Duration::from_secs(storage.sync_interval);
+        let storage = Storage::new(storage, log.new(o!("component" => "storage")));
+        tokio::spawn(storage.sync_corpus(corpus.clone(), interval));
+    }
+
+    if let (Some(backup), Some(corpus)) = (&config.backup, &config.corpus) {
+        let interval = Duration::from_secs(backup.interval);
+        let backup = Backup::new(corpus, backup, log.new(o!("component" => "backup")));
+        tokio::spawn(backup.run(interval));
+    }
+
+    let aggregator = config.aggregate.as_ref().map(|aggregate| {
+        let aggregator = Aggregator::new(aggregate, log.new(o!("component" => "aggregate")));
+        tokio::spawn(aggregator.clone().run(Duration::from_secs(aggregate.interval)));
+        aggregator
+    });
+
+    if config.workdir.kill_orphans {
+        // fuzzers of runs interrupted by a crash are killed before runs are resumed
+        let dir = config.workdir.pids_dir();
+        let log = log.new(o!("component" => "orphans"));
+        orphans::cleanup(&dir, &log);
+        tokio::spawn(orphans::watch(dir, Duration::from_secs(config.workdir.orphans_interval), log));
+    }
+
+    // runs in progress keep the configuration they were started with
+    let configs = reload::watch(config_file, config.clone(), overrides, log.new(o!("component" => "reload")));
+
+    // fail early rather than in the middle of a run if honggfuzz output cannot be parsed,
+    // while servers fuzzing only with commands don't need honggfuzz installed
+    let uses_honggfuzz = config.discovery.is_some() || config.targets.values().any(|conf| conf.engine == Engine::Honggfuzz);
+    let output_format = if uses_honggfuzz {
+        let (version, format) = hfuzz::detect(config.honggfuzz.cargo_hfuzz.as_deref())
+            .await
+            .map_err(|e| Error::other(format!("cannot use installed cargo-hfuzz: {}", e)))?;
+        info!(log, "Detected cargo-hfuzz"; "version" => version.to_string(), "format" => format.to_string());
+        format
+    } else {
+        OutputFormat::default()
     };
 
-    let ping_log = log.new(slog::o!("event" => "ping"));
-    let ping = warp::header::exact("X-GitHub-Event", "ping")
-        .and(warp::body::json::<PingEvent>())
-        .map(move |body| {
-            debug!(ping_log, "Incoming ping"; "body" => serde_json::to_string(&body).unwrap());
-            warp::reply()
+    let store = match &config.state {
+        Some(state) => Some(
+            Store::open(&state.path, owner(&config))
+                .await
+                .map_err(|e| Error::other(format!("cannot open state store {}: {}", state.path.to_string_lossy(), e)))?,
+        ),
+        None => None,
+    };
+    let runner = Runner {
+        configs: configs.clone(),
+        build_slots: Arc::new(BuildSlots::new(config.build.parallelism)),
+        output_format,
+        scheduler: Arc::new(Scheduler::new(&config.scheduling)),
+        branches: Arc::new(RwLock::new(HashMap::new())),
+        controls: Arc::new(RwLock::new(HashMap::new())),
+        releases: Arc::new(RwLock::new(HashSet::new())),
+        runs: Runs::new(store, log.new(o!("component" => "runs"))),
+        audit: Arc::new(AuditLog::new(&config.api.audit_log)),
+        deliveries: config.deliveries.as_ref().map(|deliveries| Arc::new(Deliveries::new(deliveries))),
+        log: log.clone(),
+    };
+    // runs are numbered after ones of existing reports, also if the counter isn't stored
+    match report::Report::last_sequence(&config.reports_path).await {
+        Ok(last) => runner.runs.seed(last).await,
+        Err(e) => error!(log, "Cannot find the last run number in reports"; "error" => e.to_string()),
+    }
+    let standby = config.state.as_ref().and_then(|s| s.standby.clone());
+    let role = Role::new(standby.is_none());
+    {
+        let runner = runner.clone();
+        let role = role.clone();
+        let resume = config.state.as_ref().map_or(false, |s| s.resume);
+        let started = chrono::Utc::now();
+        let log = log.new(o!("component" => "resume"));
+        tokio::spawn(async move {
+            if let Some(standby) = standby {
+                info!(log, "Following the primary server"; "primary" => standby.primary.as_str());
+                standby::follow(&standby, &log.new(o!("component" => "standby"))).await;
+                role.activate();
+                warn!(log, "Primary server is down, taking over"; "primary" => standby.primary.as_str());
+                // runs of the primary are interrupted once their heartbeats are stale
+                tokio::time::sleep(state::STALE_AFTER).await;
+            }
+            resume_runs(&runner, resume, &log).await;
+            process_pending(&runner, started, &log).await
         });
-
-    let push = {
-        let config = config.clone();
-        let builder = Arc::new(Mutex::new(Builder::new(
-            config.corpus.clone(),
-            config.kcov.clone(),
-            log.new(o!("component" => "builder")),
-        )));
-        let notifies = Arc::new(RwLock::new(HashMap::new()));
-        let push_log = log.new(slog::o!("event" => "push"));
-        warp::header::exact("X-GitHub-Event", "push")
-            .and(warp::body::json::<PushEvent>())
-            .and(warp::any().map(move || config.clone()))
-            .and(warp::any().map(move || builder.clone()))
-            .and(warp::any().map(move || notifies.clone()))
-            .and(warp::any().map(move || push_log.clone()))
-            .and_then(push_hook)
+    }
+    tokio::spawn(refuzz_stale(runner.clone(), role.clone(), log.new(o!("component" => "refuzz"))));
+    tokio::spawn(schedule_distillation(runner.clone(), role.clone(), log.new(o!("component" => "distillation"))));
+    let webhook = {
+        let runner = runner.clone();
+        let webhook_log = log.new(o!("component" => "webhook"));
+        let github = webhook_event("X-GitHub-Event", &GITHUB_EVENTS)
+            .and(warp::header::optional::<String>("X-GitHub-Delivery"))
+            .and(signed_json::<serde_json::Value>(github::SIGNATURE_HEADER, configs.clone(), webhook_log.clone()));
+        let bitbucket = webhook_event("X-Event-Key", &BITBUCKET_EVENTS)
+            .and(warp::header::optional::<String>("X-Request-UUID"))
+            .and(signed_json::<serde_json::Value>(BITBUCKET_SIGNATURE_HEADER, configs.clone(), webhook_log.clone()));
+        github
+            .or(bitbucket)
+            .unify()
+            .and(warp::any().map(move || runner.clone()))
+            .and(warp::any().map(move || webhook_log.clone()))
+            .and_then(receive_webhook)
     };
 
     let mut hb = Handlebars::new();
+    hb.register_template_string("overview", OVERVIEW).unwrap();
+    hb.register_template_string("crash_rates", CRASH_RATES).unwrap();
     hb.register_template_string("reports", REPORTS).unwrap();
     hb.register_template_string("report", REPORT).unwrap();
+    hb.register_template_string("rerun", RERUN).unwrap();
+    hb.register_template_string("targets", TARGETS).unwrap();
+    hb.register_template_string("peers", PEERS).unwrap();
     let hb = Arc::new(hb);
 
+    let overview = {
+        let runner = runner.clone();
+        let hb = hb.clone();
+        warp::get().and(warp::path::end()).map(move || render("overview", runner.live(), hb.clone()))
+    };
+
+    let crash_rates = {
+        let store = runner.runs.store().cloned();
+        let hb = hb.clone();
+        let log = log.clone();
+        warp::get().and(warp::path!("crash-rates")).and_then(move || {
+            let (store, hb, log) = (store.clone(), hb.clone(), log.clone());
+            async move {
+                let rates = match &store {
+                    Some(store) => match store.target_runs().await {
+                        Ok(runs) => Some(CrashRates::new(&runs, chrono::Utc::now(), CRASH_RATES_WEEKS)),
+                        Err(e) => {
+                            error!(log, "Cannot read crashes of targets"; "error" => e);
+                            None
+                        }
+                    },
+                    None => None,
+                };
+                Ok::<_, warp::Rejection>(render("crash_rates", rates, hb))
+            }
+        })
+    };
+
     let reports = {
-        let mut branches = config.branches.clone();
-        branches.sort();
+        let configs = configs.clone();
         let dir = PathBuf::from(&config.reports_path);
         let log = log.clone();
         let reports = move |hb| {
-            let reports = BranchReports::read(dir.clone(), branches.clone(), log.clone());
+            let config = configs.borrow();
+            let mut branches = if config.repositories.is_empty() {
+                config.branches.clone()
+            } else {
+                // reports of each repository are kept in its own directory
+                config
+                    .repositories
+                    .keys()
+                    .flat_map(|repo| config.branches.iter().map(move |branch| format!("{}/{}", repo, branch)))
+                    .collect()
+            };
+            drop(config);
+            branches.sort();
+            let reports = BranchReports::read(dir.clone(), branches, log.clone());
             render("reports", reports, hb)
         };
         let hb = hb.clone();
@@ -517,20 +3326,230 @@ pub(crate) async fn start(config: Config, log: slog::Logger) {
     };
 
     let report = {
-        let mut projects = config.targets.keys().cloned().collect::<Vec<_>>();
-        projects.sort();
+        let configs = configs.clone();
+        let runner = runner.clone();
+        let hb = hb.clone();
+        warp::path!("reports" / String / String).and_then(move |branch: String, time: String| {
+            let mut projects = configs.borrow().targets.keys().cloned().collect::<Vec<_>>();
+            projects.sort();
+            let (runner, hb) = (runner.clone(), hb.clone());
+            async move {
+                let rerun = rerun_action(&runner, None, &branch, &time).await;
+                let mut report = Report::new(branch, time, projects);
+                report.rerun = rerun;
+                Ok::<_, warp::Rejection>(render("report", report, hb))
+            }
+        })
+    };
+
+    let repo_report = {
+        let configs = configs.clone();
+        let runner = runner.clone();
+        let hb = hb.clone();
+        warp::path!("reports" / String / String / String).and_then(move |repo: String, branch: String, time: String| {
+            let config = configs.borrow();
+            let found = config.repositories.contains_key(&repo);
+            let mut projects = config.targets.keys().cloned().collect::<Vec<_>>();
+            drop(config);
+            projects.sort();
+            let (runner, hb) = (runner.clone(), hb.clone());
+            async move {
+                if found {
+                    let rerun = rerun_action(&runner, Some(&repo), &branch, &time).await;
+                    let mut report = Report::new(format!("{}/{}", repo, branch), time, projects);
+                    report.rerun = rerun;
+                    Ok(render("report", report, hb))
+                } else {
+                    Err(warp::reject::not_found())
+                }
+            }
+        })
+    };
+
+    let rerun = {
+        let runner = runner.clone();
+        let hb = hb.clone();
+        let log = log.new(o!("endpoint" => "rerun"));
+        warp::post()
+            .and(warp::path!("rerun" / String))
+            .and(warp::body::content_length_limit(4096))
+            .and(warp::body::form::<HashMap<String, String>>())
+            .and_then(move |id: String, form: HashMap<String, String>| {
+                let (runner, hb, log) = (runner.clone(), hb.clone(), log.clone());
+                async move {
+                    let config = runner.config();
+                    if !config.api.is_enabled() {
+                        return Err(warp::reject::not_found());
+                    }
+                    let token = form.get("token").map(String::as_str).unwrap_or_default();
+                    let (status, message) = match api::caller(&config.api, token) {
+                        None => (StatusCode::UNAUTHORIZED, "Invalid API token".to_string()),
+                        Some(caller) => {
+                            let (status, message) = match runner.rerun(&id).await {
+                                None => (StatusCode::NOT_FOUND, format!("Unknown run {}", id)),
+                                Some(Ok(run)) => {
+                                    info!(log, "Run started again"; "id" => &run.id, "previous" => &id);
+                                    (StatusCode::OK, format!("Run {} of branch {} is started", run.id, run.branch))
+                                }
+                                Some(Err(e)) => (StatusCode::INTERNAL_SERVER_ERROR, format!("Cannot start run: {}", e)),
+                            };
+                            runner.audit(&caller, "rerun", &id, status.as_u16()).await;
+                            (status, message)
+                        }
+                    };
+                    let home = config.url.as_ref().map_or_else(|| "/".to_string(), Url::to_string);
+                    Ok(warp::reply::with_status(render("rerun", Rerun { message, home }, hb), status))
+                }
+            })
+    };
+
+    let targets = {
+        let runner = runner.clone();
+        let hb = hb.clone();
+        warp::get()
+            .and(warp::path!("targets"))
+            .and_then(move || {
+                let (runner, hb) = (runner.clone(), hb.clone());
+                async move { Ok::<_, warp::Rejection>(render("targets", target_states(&runner).await, hb)) }
+            })
+    };
+
+    let peers = {
+        let runner = runner.clone();
         let hb = hb.clone();
-        warp::path!("reports" / String / String).map(move |branch, time| {
-            let report = Report::new(branch, time, projects.clone());
-            render("report", report, hb.clone())
+        warp::get().and(warp::path!("peers")).and_then(move || {
+            let (runner, aggregator, hb) = (runner.clone(), aggregator.clone(), hb.clone());
+            async move {
+                let servers = match aggregator {
+                    Some(aggregator) => {
+                        let url = runner.config().url.as_ref().map(Url::to_string);
+                        let mut servers = vec![ServerRuns::new("This server".to_string(), url, runner.runs().await)];
+                        servers.extend(aggregator.runs());
+                        Some(servers)
+                    }
+                    None => None,
+                };
+                match servers {
+                    Some(servers) => Ok(render("peers", servers, hb)),
+                    None => Err(warp::reject::not_found()),
+                }
+            }
         })
     };
 
-    let coverage = reports.or(warp::path!("reports" / ..).and(warp::fs::dir(config.reports_path)));
+    let toggle_target = {
+        let runner = runner.clone();
+        let hb = hb.clone();
+        let log = log.new(o!("endpoint" => "targets"));
+        let disable = warp::path!("targets" / String / "disable").map(|target| (target, true));
+        let enable = warp::path!("targets" / String / "enable").map(|target| (target, false));
+        warp::post()
+            .and(disable.or(enable).unify())
+            .and(warp::body::content_length_limit(4096))
+            .and(warp::body::form::<HashMap<String, String>>())
+            .and_then(move |(target, disable): (String, bool), form: HashMap<String, String>| {
+                let (runner, hb, log) = (runner.clone(), hb.clone(), log.clone());
+                async move {
+                    let config = runner.config();
+                    if !config.api.is_enabled() {
+                        return Err(warp::reject::not_found());
+                    }
+                    let target = percent_decode_str(&target).decode_utf8_lossy().into_owned();
+                    let token = form.get("token").map(String::as_str).unwrap_or_default();
+                    let (status, message) = match api::caller(&config.api, token) {
+                        None => (StatusCode::UNAUTHORIZED, "Invalid API token".to_string()),
+                        Some(caller) if !api::is_admin(&config.api, &caller) => {
+                            (StatusCode::FORBIDDEN, "The API token does not allow this request".to_string())
+                        }
+                        Some(caller) => {
+                            let action = if disable { "disable" } else { "enable" };
+                            let disabled_by = if disable { Some(caller.as_str()) } else { None };
+                            let (status, message) = match runner.set_disabled(&target, disabled_by).await {
+                                None => (StatusCode::NOT_FOUND, format!("Unknown target {}", target)),
+                                Some(false) => (StatusCode::CONFLICT, format!("Target {} is already {}d", target, action)),
+                                Some(true) => {
+                                    info!(log, "Target {}d", action; "target" => &target, "caller" => &caller);
+                                    (StatusCode::OK, format!("Target {} is {}d for runs started later", target, action))
+                                }
+                            };
+                            runner.audit(&caller, &format!("{}_target", action), &target, status.as_u16()).await;
+                            (status, message)
+                        }
+                    };
+                    let home = config.url.as_ref().map_or_else(|| "/".to_string(), Url::to_string);
+                    Ok(warp::reply::with_status(render("rerun", Rerun { message, home }, hb), status))
+                }
+            })
+    };
+
+    let slack_routes = slack::routes(
+        config.slack.as_ref().and_then(|s| s.signing_secret.clone()),
+        runner.clone(),
+        log.new(o!("component" => "slack")),
+    );
+    let grpc = grpc::service(&config, runner.clone(), log.new(o!("component" => "grpc")));
+    let api_routes = api::routes(&config, runner, log.new(o!("component" => "api")));
+
+    let coverage = reports
+        .or(assets::routes(config.reports_path.clone()))
+        .or(warp::path!("reports" / ..).and(warp::fs::dir(config.reports_path)));
+
+    let health = {
+        let role = role.clone();
+        warp::get()
+            .and(warp::path(standby::HEALTH_PATH))
+            .and(warp::path::end())
+            .map(move || warp::reply::json(&serde_json::json!({ "status": "ok", "role": role.name() })))
+    };
+
+    // a standby doesn't process webhooks nor control actions until it takes over
+    let standby_reply = warp::post().and_then(move || {
+        let active = role.is_active();
+        async move {
+            if active {
+                Err(warp::reject())
+            } else {
+                Ok(warp::reply::with_status("Standby server", StatusCode::SERVICE_UNAVAILABLE))
+            }
+        }
+    });
 
-    let webhook_routes = warp::post().and(warp::path(RUN_PATH)).and(ping.or(push));
-    let reports_routes = report.or(coverage);
-    let routes = reports_routes.or(webhook_routes);
+    let webhook_routes = warp::post()
+        .and(warp::path(RUN_PATH))
+        .and(webhook)
+        .recover(handle_webhook_rejection);
+    let reports_routes = overview
+        .or(crash_rates)
+        .or(targets)
+        .or(toggle_target)
+        .or(peers)
+        .or(rerun)
+        .or(report)
+        .or(repo_report)
+        .or(coverage);
+    let routes = health
+        .or(standby_reply)
+        .or(reports_routes)
+        .or(webhook_routes)
+        .or(api_routes)
+        .or(slack_routes);
 
-    warp::serve(routes).run(addr).await
+    // gRPC calls are served on the same address, told apart from HTTP requests by their content type
+    let routes = warp::service(routes.boxed());
+    let make_service = make_service_fn(move |_| {
+        let (routes, grpc) = (routes.clone(), grpc.clone());
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                let (mut routes, grpc) = (routes.clone(), grpc.clone());
+                async move {
+                    match grpc {
+                        Some(mut grpc) if grpc::is_call(&request) => grpc.call(request).await.map_err(|never| match never {}),
+                        _ => routes.call(request).await.map(|response| response.map(BoxBody::map_from)),
+                    }
+                }
+            }))
+        }
+    });
+    let server = Server::try_bind(&addr).map_err(|e| Error::other(format!("cannot listen on {}: {}", addr, e)))?;
+    server.serve(make_service).await.map_err(|e| Error::other(format!("server error: {}", e)))
 }
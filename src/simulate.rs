@@ -0,0 +1,69 @@
+use crate::error::Error;
+use reqwest::header::CONTENT_TYPE;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use crate::{config::Config, github};
+
+/// Parameters of a synthetic push event.
+pub struct Push<'a> {
+    pub repo: &'a str,
+    pub branch: &'a str,
+    pub commit: Option<&'a str>,
+    pub message: &'a str,
+    /// Whether the push rewrites the branch history
+    pub forced: bool,
+}
+
+/// Webhook url of the server described by the configuration.
+pub fn webhook_url(config: &Config) -> Result<Url, Error> {
+    // the server listening on all interfaces is reachable on the loopback one
+    let address = config.address.replace("0.0.0.0", "127.0.0.1");
+    Ok(format!("http://{}/run", address).parse()?)
+}
+
+/// GitHub-style push event payload, containing fields used by the server.
+pub(crate) fn payload(push: &Push) -> serde_json::Value {
+    let id = match push.commit {
+        Some(commit) => commit.to_string(),
+        None => format!("{:x}", Sha256::digest(chrono::Utc::now().to_rfc3339().as_bytes()))[..40].to_string(),
+    };
+    let commit = json!({
+        "id": id,
+        "message": push.message,
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "author": {
+            "name": "Fuzzing CI",
+            "email": "fuzz-ci@localhost",
+            "username": "fuzz-ci",
+        },
+    });
+    json!({
+        "ref": format!("refs/heads/{}", push.branch),
+        "repository": {
+            "ssh_url": push.repo,
+            "url": push.repo,
+        },
+        "commits": [commit.clone()],
+        "head_commit": commit,
+        "forced": push.forced,
+    })
+}
+
+/// Sends the push event to the webhook endpoint, signed with the webhook secret if any.
+pub async fn send(url: Url, push: &Push<'_>, secret: Option<&str>) -> Result<(), Error> {
+    let body = serde_json::to_vec(&payload(push))?;
+    let mut request = reqwest::Client::new()
+        .post(url)
+        .header("X-GitHub-Event", "push")
+        .header(CONTENT_TYPE, "application/json");
+    if let Some(secret) = secret {
+        request = request.header(github::SIGNATURE_HEADER, github::webhook_signature(secret, &body));
+    }
+    let response = request.body(body).send().await?;
+    if !response.status().is_success() {
+        return Err(Error::other(format!("server responded with {}", response.status())));
+    }
+    Ok(())
+}
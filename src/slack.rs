@@ -1,59 +1,155 @@
-use std::{borrow::Cow, collections::HashMap, io};
+use std::{borrow::Cow, sync::Arc, time::{SystemTime, UNIX_EPOCH}};
 
+use async_trait::async_trait;
+use hmac::{Hmac, Mac, NewMac};
 use reqwest::header::AUTHORIZATION;
-use slog::{Logger, error, info, trace, warn};
+use serde::Deserialize;
+use serde_json::json;
+use sha2::Sha256;
+use slog::{Logger, debug, error, info, trace, warn};
+use url::Url;
+use warp::{hyper::body::Bytes, Filter, Rejection, Reply};
 
-use crate::feedback::{FeedbackClient, FeedbackLevel};
+use crate::{
+    api::constant_time_eq,
+    error::{Error, Result},
+    feedback::{FeedbackClient, FeedbackLevel},
+    server::Runner,
+};
 
 const POST_MESSAGE_URL: &str = "https://slack.com/api/chat.postMessage";
+const CONVERSATIONS_INFO_URL: &str = "https://slack.com/api/conversations.info";
+
+/// Maximum age of an interaction request, in seconds, so captured requests can't be replayed.
+const MAX_REQUEST_AGE: u64 = 5 * 60;
+
+/// Length of messages, in characters, above which Slack truncates them.
+pub const MAX_MESSAGE_LENGTH: usize = 40000;
+
+/// Maximum length of the text of a section block, in characters.
+const MAX_BLOCK_TEXT: usize = 3000;
+
+const PAUSE_ACTION: &str = "pause_run";
+const RESUME_ACTION: &str = "resume_run";
 
 pub struct SlackClient {
     desc: String,
     channel: String,
     token: String,
     level: FeedbackLevel,
+    /// Id of the run that can be paused and resumed with message buttons
+    controls: Option<String>,
+    /// Maximum length of a message, in characters
+    max_length: usize,
+    long_messages: LongMessages,
+    /// Report page linked from truncated messages
+    report: Option<Url>,
     log: Logger,
 }
 
-impl FeedbackClient for SlackClient {
-    fn message(&self, level: FeedbackLevel, message: &str) {
-        if level < self.level {
+/// How messages longer than the maximum length are posted.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LongMessages {
+    /// Truncated, with a link to the full report
+    Truncate,
+    /// Split at line ends into several messages
+    Split,
+}
+
+impl Default for LongMessages {
+    fn default() -> Self {
+        Self::Truncate
+    }
+}
+
+impl SlackClient {
+    async fn post(&self, level: FeedbackLevel, message: &str, controls: bool) -> Result<()> {
+        let controls = if controls { self.controls.as_deref() } else { None };
+        // messages with controls are posted regardless of the level, so runs can be paused
+        if level < self.level && controls.is_none() {
             info!(self.log, "Skipped message"; "message" => message);
-            return;
+            return Ok(());
         }
         let message = format!("{}: {}", self.desc, message);
-        let token = self.token.clone();
-        let log = self.log.clone();
-        let json = self.message_json(&message);
-        tokio::spawn(async move {
-            trace!(log, "Sending to slack"; "text" => &message);
-            let client = reqwest::Client::new();
-            let response = client
+        let chunks = self.fit(&message, controls.is_some());
+        let last = chunks.len() - 1;
+        for (i, chunk) in chunks.into_iter().enumerate() {
+            // buttons go with the end of the message
+            let json = self.message_json(&chunk, controls.filter(|_| i == last));
+            trace!(self.log, "Sending to slack"; "text" => &chunk);
+            let response = reqwest::Client::new()
                 .post(POST_MESSAGE_URL)
-                .header(AUTHORIZATION, token)
+                .header(AUTHORIZATION, &self.token)
                 .json(&json)
                 .send()
-                .await
-                .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?
+                .await?
                 .json::<JsonResponse>()
-                .await
-                .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
-
-            trace!(log, "Sent to slack"; "response" => format!("{:?}", response));
+                .await?;
+            trace!(self.log, "Sent to slack"; "response" => format!("{:?}", response));
+            match response.into_result()? {
+                Some(warn) if warn != "missing_charset" => warn!(self.log, "Posting message"; "warning" => warn),
+                _ => (),
+            }
+        }
+        Ok(())
+    }
 
-            if response.ok {
-                if let Some(warn) = response.warning {
-                    if warn != "missing_charset" {
-                        warn!(log, "Posting message"; "warning" => warn);
-                    }
-                }
-                Ok(())
-            } else {
-                let error = response.error.unwrap_or("unknown error".to_string());
-                error!(log, "Posting message"; "error" => &error);
-                Err(io::Error::new(io::ErrorKind::Other, error))
+    /// Truncates or splits the message so no part exceeds the maximum length, lower
+    /// for messages with buttons, as their text goes to a section block.
+    fn fit(&self, message: &str, blocks: bool) -> Vec<String> {
+        let max = if blocks { self.max_length.min(MAX_BLOCK_TEXT) } else { self.max_length };
+        if message.chars().count() <= max {
+            return vec![message.to_string()];
+        }
+        debug!(self.log, "Message is too long"; "length" => message.chars().count(), "max" => max);
+        match self.long_messages {
+            LongMessages::Split => split(message, max),
+            LongMessages::Truncate => {
+                let more = match &self.report {
+                    Some(url) => format!("\n… <{}|see full report>", url),
+                    None => "\n…".to_string(),
+                };
+                let text = truncate(message, max.saturating_sub(more.chars().count()));
+                vec![format!("{}{}", text, more)]
             }
-        });
+        }
+    }
+}
+
+/// Prefix of the text of at most `max` characters, cut at the last line end if any.
+fn truncate(text: &str, max: usize) -> &str {
+    let end = text.char_indices().nth(max).map_or(text.len(), |(i, _)| i);
+    let prefix = &text[..end];
+    if end == text.len() {
+        return prefix;
+    }
+    match prefix.rfind('\n') {
+        Some(line_end) if line_end > 0 => &prefix[..line_end],
+        _ => prefix,
+    }
+}
+
+/// Splits the text into parts of at most `max` characters, at line ends where possible.
+fn split(mut text: &str, max: usize) -> Vec<String> {
+    let mut parts = Vec::new();
+    while !text.is_empty() {
+        let part = truncate(text, max.max(1));
+        parts.push(part.to_string());
+        let rest = &text[part.len()..];
+        text = rest.strip_prefix('\n').unwrap_or(rest);
+    }
+    parts
+}
+
+#[async_trait]
+impl FeedbackClient for SlackClient {
+    async fn message(&self, level: FeedbackLevel, message: &str) -> Result<()> {
+        self.post(level, message, false).await
+    }
+
+    async fn message_with_controls(&self, level: FeedbackLevel, message: &str) -> Result<()> {
+        self.post(level, message, true).await
     }
 }
 
@@ -70,21 +166,71 @@ impl SlackClient {
             channel: channel.as_ref().into(),
             token: format!("Bearer {}", token.as_ref()),
             level,
+            controls: None,
+            max_length: 4000,
+            long_messages: LongMessages::default(),
+            report: None,
             log,
         }
     }
 
-    fn message_json<'a>(&self, text: impl Into<Cow<'a, str>>) -> HashMap<String, String> {
-        [
-            ("channel", self.channel.clone()),
-            ("text", text.into().into_owned()),
-        ]
-        .iter()
-        .map(|(k, v)| (k.to_string(), v.clone()))
-        .collect()
+    /// Sets how messages longer than `max_length` characters are posted.
+    pub fn with_long_messages(mut self, max_length: usize, long_messages: LongMessages) -> Self {
+        self.max_length = max_length;
+        self.long_messages = long_messages;
+        self
+    }
+
+    /// Links the report page from truncated messages.
+    pub fn with_report(mut self, url: Url) -> Self {
+        self.report = Some(url);
+        self
+    }
+
+    /// Adds buttons pausing and resuming the run to messages that support them.
+    pub fn with_controls(mut self, run: impl Into<String>) -> Self {
+        self.controls = Some(run.into());
+        self
+    }
+
+    fn message_json<'a>(&self, text: impl Into<Cow<'a, str>>, controls: Option<&str>) -> serde_json::Value {
+        let text = text.into();
+        let mut json = json!({
+            "channel": self.channel,
+            "text": text,
+        });
+        if let Some(run) = controls {
+            let button = |text, action| {
+                json!({
+                    "type": "button",
+                    "text": { "type": "plain_text", "text": text },
+                    "action_id": action,
+                    "value": run,
+                })
+            };
+            json["blocks"] = json!([
+                { "type": "section", "text": { "type": "mrkdwn", "text": text } },
+                { "type": "actions", "elements": [button("Pause", PAUSE_ACTION), button("Resume", RESUME_ACTION)] },
+            ]);
+        }
+        json
     }
 }
 
+/// Checks that the channel exists and is accessible with the token.
+pub async fn check_channel(channel: &str, token: &str) -> Result<()> {
+    reqwest::Client::new()
+        .get(CONVERSATIONS_INFO_URL)
+        .header(AUTHORIZATION, format!("Bearer {}", token))
+        .query(&[("channel", channel)])
+        .send()
+        .await?
+        .json::<JsonResponse>()
+        .await?
+        .into_result()
+        .map(|_| ())
+}
+
 #[derive(serde::Deserialize, Debug)]
 pub struct JsonResponse {
     ok: bool,
@@ -92,6 +238,142 @@ pub struct JsonResponse {
     error: Option<String>,
 }
 
+impl JsonResponse {
+    /// Slack API error, or the warning of a successful call.
+    fn into_result(self) -> Result<Option<String>> {
+        if self.ok {
+            Ok(self.warning)
+        } else {
+            Err(Error::Feedback(self.error.unwrap_or_else(|| "unknown error".to_string())))
+        }
+    }
+}
+
+/// Checks the signature of a request sent by Slack, see
+/// https://api.slack.com/authentication/verifying-requests-from-slack
+fn verify_signature(secret: &str, timestamp: &str, body: &[u8], signature: &str) -> bool {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+    match timestamp.parse::<u64>() {
+        Ok(time) if time + MAX_REQUEST_AGE >= now && now + MAX_REQUEST_AGE >= time => (),
+        _ => return false,
+    }
+    let mut mac = match Hmac::<Sha256>::new_varkey(secret.as_bytes()) {
+        Ok(mac) => mac,
+        Err(_) => return false,
+    };
+    mac.update(format!("v0:{}:", timestamp).as_bytes());
+    mac.update(body);
+    let expected = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .fold(String::from("v0="), |s, b| s + &format!("{:02x}", b));
+    constant_time_eq(expected.as_bytes(), signature.as_bytes())
+}
+
+#[derive(Deserialize)]
+struct ActionPayload {
+    user: ActionUser,
+    actions: Vec<Action>,
+    response_url: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ActionUser {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct Action {
+    action_id: String,
+    value: Option<String>,
+}
+
+/// Pauses or resumes runs as requested with message buttons, replying to the user
+/// who pressed the button.
+async fn handle_actions(payload: ActionPayload, runner: Runner, log: Logger) {
+    for action in payload.actions {
+        let run = match action.value {
+            Some(run) => run,
+            None => continue,
+        };
+        let by = format!("<@{}>", payload.user.id);
+        let pause = match action.action_id.as_str() {
+            PAUSE_ACTION => true,
+            RESUME_ACTION => false,
+            action => {
+                warn!(log, "Unknown Slack action"; "action" => action);
+                continue;
+            }
+        };
+        let paused = runner.pause(&run, if pause { Some(&by) } else { None });
+        // recorded with the status the API would respond with
+        let status = match paused {
+            Some(true) => 202,
+            Some(false) => 409,
+            None => 404,
+        };
+        let caller = format!("slack:{}", payload.user.id);
+        runner.audit(&caller, if pause { "pause" } else { "resume" }, &run, status).await;
+        let reply = match paused {
+            Some(true) => continue,
+            Some(false) if pause => format!("Run {} is already paused", run),
+            Some(false) => format!("Run {} is not paused", run),
+            None => format!("Run {} is not in progress", run),
+        };
+        info!(log, "{}", reply; "user" => &payload.user.id);
+        if let Some(url) = &payload.response_url {
+            let response = json!({ "response_type": "ephemeral", "replace_original": false, "text": reply });
+            if let Err(e) = reqwest::Client::new().post(url).json(&response).send().await {
+                error!(log, "Cannot reply to Slack action"; "error" => e.to_string());
+            }
+        }
+    }
+}
+
+/// Route of Slack interactions with message buttons, `/slack/actions` to be configured as
+/// the request URL of the Slack app, enabled if the signing secret is set.
+pub(crate) fn routes(
+    signing_secret: Option<String>,
+    runner: Runner,
+    log: Logger,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    let secret = Arc::new(signing_secret);
+    warp::post()
+        .and(warp::path!("slack" / "actions"))
+        .and(warp::header::optional::<String>("x-slack-request-timestamp"))
+        .and(warp::header::optional::<String>("x-slack-signature"))
+        .and(warp::body::content_length_limit(64 * 1024))
+        .and(warp::body::bytes())
+        .and_then(move |timestamp: Option<String>, signature: Option<String>, body: Bytes| {
+            let secret = secret.clone();
+            let runner = runner.clone();
+            let log = log.clone();
+            async move {
+                let secret = secret.as_deref().ok_or_else(warp::reject::not_found)?;
+                let verified = match (&timestamp, &signature) {
+                    (Some(timestamp), Some(signature)) => verify_signature(secret, timestamp, &body, signature),
+                    _ => false,
+                };
+                if !verified {
+                    warn!(log, "Slack request with invalid signature");
+                    return Ok::<_, Rejection>(warp::http::StatusCode::UNAUTHORIZED);
+                }
+                let payload = url::form_urlencoded::parse(&body)
+                    .find(|(key, _)| key == "payload")
+                    .and_then(|(_, payload)| serde_json::from_str::<ActionPayload>(&payload).ok());
+                match payload {
+                    Some(payload) => {
+                        // Slack expects a response within 3 seconds
+                        tokio::spawn(handle_actions(payload, runner, log));
+                        Ok(warp::http::StatusCode::OK)
+                    }
+                    None => Ok(warp::http::StatusCode::BAD_REQUEST),
+                }
+            }
+        })
+}
+
 /*
 impl SlackFeedback {
     pub async fn start(config: &Slack, log: Logger) -> io::Result<Self> {
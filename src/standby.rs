@@ -0,0 +1,89 @@
+//! Warm standby of a server, following the primary one sharing the state store and
+//! taking over when the health endpoint of the primary stops responding.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use slog::{debug, warn, Logger};
+
+use crate::config;
+
+/// Path of the health endpoint of servers.
+pub const HEALTH_PATH: &str = "health";
+
+/// Whether the server processes webhooks and starts runs, or only follows the primary.
+#[derive(Clone)]
+pub struct Role {
+    active: Arc<AtomicBool>,
+}
+
+impl Role {
+    pub fn new(active: bool) -> Self {
+        Self {
+            active: Arc::new(AtomicBool::new(active)),
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::SeqCst)
+    }
+
+    pub fn activate(&self) {
+        self.active.store(true, Ordering::SeqCst);
+    }
+
+    pub fn name(&self) -> &'static str {
+        if self.is_active() {
+            "active"
+        } else {
+            "standby"
+        }
+    }
+}
+
+/// Whether the primary responds to its health check in time with a success.
+async fn is_healthy(client: &reqwest::Client, config: &config::Standby, log: &Logger) -> bool {
+    let url = match config.primary.join(HEALTH_PATH) {
+        Ok(url) => url,
+        Err(e) => {
+            warn!(log, "Invalid health endpoint of the primary"; "error" => e.to_string());
+            return false;
+        }
+    };
+    let response = client
+        .get(url)
+        .timeout(Duration::from_secs(config.interval))
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status);
+    match response {
+        Ok(_) => true,
+        Err(e) => {
+            debug!(log, "Primary health check failed"; "error" => e.to_string());
+            false
+        }
+    }
+}
+
+/// Checks health of the primary until `failures` checks in a row fail.
+pub async fn follow(config: &config::Standby, log: &Logger) {
+    let client = reqwest::Client::new();
+    let mut failures = 0;
+    loop {
+        if is_healthy(&client, config, log).await {
+            failures = 0;
+        } else {
+            failures += 1;
+            warn!(log, "Primary is not healthy"; "failures" => failures, "primary" => config.primary.as_str());
+            if failures >= config.failures {
+                return;
+            }
+        }
+        tokio::time::sleep(Duration::from_secs(config.interval)).await;
+    }
+}
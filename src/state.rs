@@ -0,0 +1,473 @@
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::{TimeZone, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+
+use crate::{
+    error::{Error, Result},
+    policy::Verdict,
+    runs::{DisabledTarget, Run, RunStatus},
+};
+
+/// Interval between heartbeats of runs in progress.
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Runs without a heartbeat for this long are considered interrupted, e.g. by a crash
+/// of the server running them.
+pub const STALE_AFTER: Duration = Duration::from_secs(6 * HEARTBEAT_INTERVAL.as_secs());
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id TEXT PRIMARY KEY,
+    url TEXT NOT NULL,
+    branch TEXT NOT NULL,
+    commit_id TEXT,
+    description TEXT NOT NULL,
+    status TEXT NOT NULL,
+    report_url TEXT,
+    owner TEXT NOT NULL,
+    heartbeat INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS branches (
+    branch TEXT PRIMARY KEY,
+    run_id TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS targets (
+    run_id TEXT NOT NULL,
+    target TEXT NOT NULL,
+    finished INTEGER NOT NULL,
+    crashes INTEGER NOT NULL,
+    cpu_time REAL NOT NULL,
+    new_edges INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (run_id, target)
+);
+CREATE TABLE IF NOT EXISTS disabled_targets (
+    target TEXT PRIMARY KEY,
+    disabled_by TEXT NOT NULL,
+    disabled_at INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS crash_issues (
+    target TEXT NOT NULL,
+    signature TEXT NOT NULL,
+    issue INTEGER,
+    reported_at INTEGER NOT NULL,
+    PRIMARY KEY (target, signature)
+);
+CREATE TABLE IF NOT EXISTS counters (
+    name TEXT PRIMARY KEY,
+    value INTEGER NOT NULL
+);
+";
+
+const RUN_COLUMNS: &str = "id, branch, commit_id, description, status, report_url, verdict";
+
+/// Columns added to tables of databases created by older versions, with their definitions.
+const MIGRATIONS: &[(&str, &str, &str)] = &[
+    ("targets", "new_edges", "INTEGER NOT NULL DEFAULT 0"),
+    ("runs", "verdict", "TEXT"),
+];
+
+/// Crashes found by a fuzz target during a run, CPU time it used and edges it newly covered.
+#[derive(Clone, Debug)]
+pub struct TargetRun {
+    pub target: String,
+    /// Time the run finished, as a Unix timestamp
+    pub finished: i64,
+    pub crashes: u32,
+    /// CPU time, in seconds
+    pub cpu_time: f64,
+    /// Edges covered beyond the coverage of the previous run
+    pub new_edges: u32,
+}
+
+/// Scheduler state kept in an SQLite database: runs with their status, crashes and
+/// CPU time of their targets, the current run of each branch, disabled targets and
+/// crash signatures issues are opened for.
+///
+/// The database can be shared by several server processes, so a push handled by one
+/// of them cancels the previous run of the branch started by another. Queries run on
+/// blocking threads, as they wait for locks of other processes up to the busy timeout.
+#[derive(Clone)]
+pub struct Store {
+    conn: Arc<Mutex<Connection>>,
+    owner: String,
+}
+
+impl Store {
+    /// Opens the database, creating it if needed. Runs are recorded as owned by `owner`,
+    /// which should identify the server process across restarts.
+    pub async fn open(path: impl AsRef<Path>, owner: impl Into<String>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let conn = blocking(move || {
+            let conn = Connection::open(path)?;
+            conn.busy_timeout(Duration::from_secs(5))?;
+            conn.execute_batch(SCHEMA)?;
+            migrate(&conn)?;
+            Ok(conn)
+        })
+        .await?;
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+            owner: owner.into(),
+        })
+    }
+
+    /// Runs the query with the connection on a blocking thread.
+    async fn query<T, F>(&self, query: F) -> Result<T>
+    where
+        F: FnOnce(&mut Connection) -> rusqlite::Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let conn = self.conn.clone();
+        blocking(move || query(&mut conn.lock().unwrap())).await
+    }
+
+    /// Next number for run ids, unique across all processes sharing the database.
+    pub async fn next_run_number(&self) -> Result<u64> {
+        self.query(|conn| {
+            let tx = conn.transaction()?;
+            tx.execute(
+                "INSERT INTO counters (name, value) VALUES ('runs', 1)
+                 ON CONFLICT (name) DO UPDATE SET value = value + 1",
+                params![],
+            )?;
+            let n: i64 = tx.query_row("SELECT value FROM counters WHERE name = 'runs'", params![], |row| row.get(0))?;
+            tx.commit()?;
+            Ok(n as u64)
+        })
+        .await
+    }
+
+    /// Makes later run numbers greater than `number`.
+    pub async fn seed_run_number(&self, number: u64) -> Result<()> {
+        self.query(move |conn| {
+            conn.execute(
+                "INSERT INTO counters (name, value) VALUES ('runs', ?1)
+                 ON CONFLICT (name) DO UPDATE SET value = max(value, ?1)",
+                params![number as i64],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Records the new run of the repository `url`.
+    pub async fn insert(&self, run: &Run, url: &str) -> Result<()> {
+        let (run, url, owner) = (run.clone(), url.to_string(), self.owner.clone());
+        self.query(move |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO runs (id, url, branch, commit_id, description, status, report_url, owner, heartbeat)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    run.id,
+                    url,
+                    run.branch,
+                    run.commit,
+                    run.description,
+                    run.status.name(),
+                    run.report_url,
+                    owner,
+                    Utc::now().timestamp()
+                ],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn finish(&self, id: &str, status: RunStatus, verdict: Option<Verdict>) -> Result<()> {
+        let id = id.to_string();
+        self.query(move |conn| {
+            conn.execute(
+                "UPDATE runs SET status = ?2, verdict = ?3 WHERE id = ?1",
+                params![id, status.name(), verdict.as_ref().map(Verdict::name)],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn get(&self, id: &str) -> Result<Option<Run>> {
+        let id = id.to_string();
+        self.query(move |conn| {
+            conn.query_row(&format!("SELECT {} FROM runs WHERE id = ?1", RUN_COLUMNS), params![id], run)
+                .optional()
+        })
+        .await
+    }
+
+    /// Repository url of the run.
+    pub async fn url(&self, id: &str) -> Result<Option<String>> {
+        let id = id.to_string();
+        self.query(move |conn| {
+            conn.query_row("SELECT url FROM runs WHERE id = ?1", params![id], |row| row.get(0))
+                .optional()
+        })
+        .await
+    }
+
+    /// All recorded runs, in order they are started.
+    pub async fn list(&self) -> Result<Vec<Run>> {
+        self.query(|conn| {
+            let mut statement = conn.prepare(&format!("SELECT {} FROM runs ORDER BY rowid", RUN_COLUMNS))?;
+            let runs = statement.query_map(params![], run)?.collect();
+            runs
+        })
+        .await
+    }
+
+    /// Records crashes, CPU time and new edges of targets of the finished run.
+    pub async fn record_targets(&self, run_id: &str, targets: &[TargetRun]) -> Result<()> {
+        let (run_id, targets) = (run_id.to_string(), targets.to_vec());
+        self.query(move |conn| {
+            let tx = conn.transaction()?;
+            for target in targets {
+                tx.execute(
+                    "INSERT OR REPLACE INTO targets (run_id, target, finished, crashes, cpu_time, new_edges)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![
+                        run_id,
+                        target.target,
+                        target.finished,
+                        target.crashes,
+                        target.cpu_time,
+                        target.new_edges
+                    ],
+                )?;
+            }
+            tx.commit()
+        })
+        .await
+    }
+
+    /// Targets of all finished runs, in order they are recorded.
+    pub async fn target_runs(&self) -> Result<Vec<TargetRun>> {
+        self.query(|conn| {
+            let mut statement = conn.prepare("SELECT target, finished, crashes, cpu_time, new_edges FROM targets ORDER BY rowid")?;
+            let targets = statement
+                .query_map(params![], |row| {
+                    Ok(TargetRun {
+                        target: row.get(0)?,
+                        finished: row.get(1)?,
+                        crashes: row.get(2)?,
+                        cpu_time: row.get(3)?,
+                        new_edges: row.get(4)?,
+                    })
+                })?
+                .collect();
+            targets
+        })
+        .await
+    }
+
+    /// Makes the run the current one of its branch, returning the previous one.
+    pub async fn set_current(&self, branch: &str, id: &str) -> Result<Option<String>> {
+        let (branch, id) = (branch.to_string(), id.to_string());
+        self.query(move |conn| {
+            let tx = conn.transaction()?;
+            let previous = tx
+                .query_row("SELECT run_id FROM branches WHERE branch = ?1", params![branch], |row| row.get(0))
+                .optional()?;
+            tx.execute(
+                "INSERT OR REPLACE INTO branches (branch, run_id) VALUES (?1, ?2)",
+                params![branch, id],
+            )?;
+            tx.commit()?;
+            Ok(previous)
+        })
+        .await
+    }
+
+    /// Current run of the branch, the one that should be running.
+    pub async fn current(&self, branch: &str) -> Result<Option<String>> {
+        let branch = branch.to_string();
+        self.query(move |conn| {
+            conn.query_row("SELECT run_id FROM branches WHERE branch = ?1", params![branch], |row| row.get(0))
+                .optional()
+        })
+        .await
+    }
+
+    /// Targets excluded from runs, by name.
+    pub async fn disabled_targets(&self) -> Result<Vec<DisabledTarget>> {
+        self.query(|conn| {
+            let mut statement = conn.prepare("SELECT target, disabled_by, disabled_at FROM disabled_targets ORDER BY target")?;
+            let targets = statement
+                .query_map(params![], |row| {
+                    Ok(DisabledTarget {
+                        target: row.get(0)?,
+                        by: row.get(1)?,
+                        since: Utc.timestamp_opt(row.get(2)?, 0).single().unwrap_or_else(Utc::now),
+                    })
+                })?
+                .collect();
+            targets
+        })
+        .await
+    }
+
+    /// Records the target as disabled, returning `false` if it already is.
+    pub async fn disable_target(&self, disabled: &DisabledTarget) -> Result<bool> {
+        let disabled = disabled.clone();
+        self.query(move |conn| {
+            let added = conn.execute(
+                "INSERT OR IGNORE INTO disabled_targets (target, disabled_by, disabled_at) VALUES (?1, ?2, ?3)",
+                params![disabled.target, disabled.by, disabled.since.timestamp()],
+            )?;
+            Ok(added > 0)
+        })
+        .await
+    }
+
+    /// Removes the target from disabled ones, returning `false` if it is not disabled.
+    pub async fn enable_target(&self, target: &str) -> Result<bool> {
+        let target = target.to_string();
+        self.query(move |conn| {
+            let removed = conn.execute("DELETE FROM disabled_targets WHERE target = ?1", params![target])?;
+            Ok(removed > 0)
+        })
+        .await
+    }
+
+    /// Claims opening an issue for the crash signature of the target, returning `false` if
+    /// it is already claimed.
+    pub async fn claim_crash(&self, target: &str, signature: &str) -> Result<bool> {
+        let (target, signature) = (target.to_string(), signature.to_string());
+        self.query(move |conn| {
+            let added = conn.execute(
+                "INSERT OR IGNORE INTO crash_issues (target, signature, reported_at) VALUES (?1, ?2, ?3)",
+                params![target, signature, Utc::now().timestamp()],
+            )?;
+            Ok(added > 0)
+        })
+        .await
+    }
+
+    /// Records the issue opened for the claimed crash signature.
+    pub async fn set_crash_issue(&self, target: &str, signature: &str, issue: u64) -> Result<()> {
+        let (target, signature) = (target.to_string(), signature.to_string());
+        self.query(move |conn| {
+            conn.execute(
+                "UPDATE crash_issues SET issue = ?3 WHERE target = ?1 AND signature = ?2",
+                params![target, signature, issue as i64],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Releases the claim of the crash signature.
+    pub async fn release_crash(&self, target: &str, signature: &str) -> Result<()> {
+        let (target, signature) = (target.to_string(), signature.to_string());
+        self.query(move |conn| {
+            conn.execute(
+                "DELETE FROM crash_issues WHERE target = ?1 AND signature = ?2",
+                params![target, signature],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Marks the run as being alive.
+    pub async fn heartbeat(&self, id: &str) -> Result<()> {
+        let id = id.to_string();
+        self.query(move |conn| {
+            conn.execute("UPDATE runs SET heartbeat = ?2 WHERE id = ?1", params![id, Utc::now().timestamp()])?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Whether the run is in progress, in this or another process.
+    pub async fn is_live(&self, id: &str) -> Result<bool> {
+        let id = id.to_string();
+        self.query(move |conn| {
+            let live: Option<i64> = conn
+                .query_row(
+                    "SELECT 1 FROM runs WHERE id = ?1 AND status = 'running' AND heartbeat >= ?2",
+                    params![id, Utc::now().timestamp() - STALE_AFTER.as_secs() as i64],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            Ok(live.is_some())
+        })
+        .await
+    }
+
+    /// Marks runs interrupted by a restart of this server, or by a crash of any server,
+    /// as failed, returning the ones still current for their branches along with their
+    /// repository urls, so they can be resumed.
+    pub async fn take_interrupted(&self) -> Result<Vec<(Run, String)>> {
+        let owner = self.owner.clone();
+        let interrupted = self
+            .query(move |conn| {
+                let tx = conn.transaction()?;
+                let stale = Utc::now().timestamp() - STALE_AFTER.as_secs() as i64;
+                let interrupted = {
+                    let mut statement = tx.prepare(&format!(
+                        "SELECT {}, url, id IN (SELECT run_id FROM branches) FROM runs
+                         WHERE status = 'running' AND (owner = ?1 OR heartbeat < ?2) ORDER BY rowid",
+                        RUN_COLUMNS
+                    ))?;
+                    let rows = statement.query_map(params![owner, stale], |row| {
+                        Ok((run(row)?, row.get::<_, String>(7)?, row.get::<_, bool>(8)?))
+                    })?;
+                    rows.collect::<Result<Vec<_>, _>>()?
+                };
+                tx.execute(
+                    "UPDATE runs SET status = 'failure' WHERE status = 'running' AND (owner = ?1 OR heartbeat < ?2)",
+                    params![owner, stale],
+                )?;
+                tx.commit()?;
+                Ok(interrupted)
+            })
+            .await?;
+        Ok(interrupted
+            .into_iter()
+            .filter(|(_, _, current)| *current)
+            .map(|(run, url, _)| (run, url))
+            .collect())
+    }
+}
+
+/// Runs the database operation on a blocking thread of the runtime.
+async fn blocking<T, F>(operation: F) -> Result<T>
+where
+    F: FnOnce() -> rusqlite::Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    match tokio::task::spawn_blocking(operation).await {
+        Ok(result) => Ok(result?),
+        Err(e) => Err(Error::other(format!("state store operation failed: {}", e))),
+    }
+}
+
+fn run(row: &Row<'_>) -> rusqlite::Result<Run> {
+    let status: String = row.get(4)?;
+    Ok(Run {
+        id: row.get(0)?,
+        branch: row.get(1)?,
+        commit: row.get(2)?,
+        description: row.get(3)?,
+        status: RunStatus::from_name(&status).unwrap_or(RunStatus::Failure),
+        report_url: row.get(5)?,
+        verdict: row.get::<_, Option<String>>(6)?.as_deref().and_then(Verdict::from_name),
+    })
+}
+
+/// Adds columns missing in databases created by older versions.
+fn migrate(conn: &Connection) -> rusqlite::Result<()> {
+    for (table, column, definition) in MIGRATIONS {
+        let mut statement = conn.prepare(&format!("SELECT name FROM pragma_table_info('{}')", table))?;
+        let columns = statement.query_map(params![], |row| row.get::<_, String>(0))?.collect::<Result<Vec<_>, _>>()?;
+        if !columns.iter().any(|c| c == column) {
+            conn.execute_batch(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,209 @@
+use std::{collections::BTreeSet, path::Path, time::Duration};
+
+use slog::{debug, error, info, FnValue, Logger};
+use tokio::{fs, process::Command};
+use url::Url;
+
+use crate::{
+    config, corpus,
+    error::{output_tail, Error, Result},
+};
+
+/// Maximum number of files removed from the bucket by a single command.
+const DELETE_CHUNK: usize = 1000;
+
+/// Object storage (S3 or GCS bucket) accessed via its command line tool.
+pub struct Storage {
+    url: Url,
+    args: Vec<String>,
+    log: Logger,
+}
+
+impl Storage {
+    pub fn new(config: &config::Storage, log: Logger) -> Self {
+        Self {
+            url: config.url.clone(),
+            args: config.args.clone(),
+            log,
+        }
+    }
+
+    /// Joins the relative path to the bucket url.
+    fn url(&self, path: &str) -> String {
+        let base = self.url.as_str().trim_end_matches('/');
+        if path.is_empty() {
+            base.to_string()
+        } else {
+            format!("{}/{}", base, path)
+        }
+    }
+
+    /// Command copying new and updated files from `src` to `dst`, where one of them is a bucket url.
+    ///
+    /// Hidden files and directories (like corpus backups) are not synchronized.
+    fn sync_command(&self, src: &str, dst: &str) -> Result<Command> {
+        let mut command = match self.url.scheme() {
+            "s3" => {
+                let mut command = Command::new("aws");
+                command.args(&["s3", "sync", "--only-show-errors", "--exclude", ".*"]);
+                command
+            }
+            "gs" => {
+                let mut command = Command::new("gsutil");
+                command.args(&["-m", "-q", "rsync", "-r", "-x", r"^\."]);
+                command
+            }
+            scheme => {
+                return Err(Error::other(format!("unsupported storage scheme `{}`", scheme)))
+            }
+        };
+        command.args(&self.args).arg(src).arg(dst);
+        Ok(command)
+    }
+
+    /// Command removing the `deleted` files, relative to both `dir` and the bucket url, from the bucket.
+    ///
+    /// The local directory is synchronized to the bucket with deletions, limited to these files.
+    fn delete_command(&self, dir: &str, deleted: &[String]) -> Result<Command> {
+        let mut command = match self.url.scheme() {
+            "s3" => {
+                let mut command = Command::new("aws");
+                command.args(&["s3", "sync", "--only-show-errors", "--delete", "--exclude", "*"]);
+                for file in deleted {
+                    command.arg("--include").arg(file);
+                }
+                command
+            }
+            "gs" => {
+                let files = deleted.iter().map(|file| regex::escape(file)).collect::<Vec<_>>();
+                let mut command = Command::new("gsutil");
+                command
+                    .args(&["-m", "-q", "rsync", "-r", "-d", "-x"])
+                    .arg(format!("^(?!(?:{})$)", files.join("|")));
+                command
+            }
+            scheme => {
+                return Err(Error::other(format!("unsupported storage scheme `{}`", scheme)))
+            }
+        };
+        command.args(&self.args).arg(dir).arg(self.url(""));
+        Ok(command)
+    }
+
+    async fn sync(&self, src: &str, dst: &str) -> Result<()> {
+        let command = self.sync_command(src, dst)?;
+        self.run(command, src, dst).await
+    }
+
+    async fn run(&self, mut command: Command, src: &str, dst: &str) -> Result<()> {
+        debug!(self.log, "Synchronizing"; "command" => FnValue(|_| format!("{:?}", command)));
+        let output = command.output().await?;
+        if !output.status.success() {
+            return Err(Error::other(format!(
+                "error synchronizing {} to {}: {}",
+                src,
+                dst,
+                output_tail(&output.stderr)
+            )));
+        }
+        Ok(())
+    }
+
+    /// Downloads files from the bucket `path` that are missing in the local directory.
+    pub async fn download(&self, path: &str, dir: impl AsRef<Path>) -> Result<()> {
+        tokio::fs::create_dir_all(dir.as_ref()).await?;
+        self.sync(&self.url(path), &dir.as_ref().to_string_lossy())
+            .await
+    }
+
+    /// Uploads files from the local directory that are missing in the bucket `path`.
+    pub async fn upload(&self, dir: impl AsRef<Path>, path: &str) -> Result<()> {
+        self.sync(&dir.as_ref().to_string_lossy(), &self.url(path))
+            .await
+    }
+
+    /// Removes files of the corpus directory deleted since its last synchronization from the bucket.
+    pub async fn delete(&self, dir: impl AsRef<Path>, deleted: &[String]) -> Result<()> {
+        let dir = dir.as_ref().to_string_lossy();
+        for files in deleted.chunks(DELETE_CHUNK) {
+            let command = self.delete_command(&dir, files)?;
+            self.run(command, &dir, &self.url("")).await?;
+        }
+        Ok(())
+    }
+
+    /// Periodically synchronizes the corpus directory with the bucket in both directions.
+    ///
+    /// Inputs found on other hosts are merged into the local corpus and vice versa. Inputs
+    /// removed from the local corpus since the previous synchronization (evicted, minimized
+    /// away or quarantined) are removed from the bucket before downloading, so they don't
+    /// come back. Downloaded copies of quarantined inputs are removed from both.
+    pub async fn sync_corpus(self, corpus: String, interval: Duration) {
+        loop {
+            info!(self.log, "Synchronizing corpus with {}", self.url);
+            if let Err(e) = self.sync_corpus_once(Path::new(&corpus)).await {
+                error!(self.log, "Error synchronizing corpus"; "error" => e.to_string());
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    async fn sync_corpus_once(&self, corpus: &Path) -> Result<()> {
+        let synced_list = corpus::synced_list(corpus);
+        fs::create_dir_all(corpus).await?;
+        let mut synced = corpus_files(corpus).await?;
+        let deleted = match fs::read_to_string(&synced_list).await {
+            Ok(previous) => previous
+                .lines()
+                .filter(|file| !synced.contains(*file))
+                .map(|file| file.to_string())
+                .collect::<Vec<_>>(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => vec![],
+            Err(e) => return Err(e.into()),
+        };
+        if !deleted.is_empty() {
+            info!(self.log, "Removing {} deleted corpus files from {}", deleted.len(), self.url);
+            self.delete(corpus, &deleted).await?;
+        }
+        self.download("", corpus).await?;
+        let mut quarantined = vec![];
+        for (target, dir) in corpus::target_dirs(corpus).await? {
+            for name in corpus::remove_quarantined(&dir).await? {
+                quarantined.push(format!("{}/{}", target, name));
+            }
+        }
+        if !quarantined.is_empty() {
+            info!(self.log, "Removing {} quarantined corpus files from {}", quarantined.len(), self.url);
+            self.delete(corpus, &quarantined).await?;
+        }
+        // inputs deleted locally during synchronization are still known to be in the bucket
+        synced.extend(corpus_files(corpus).await?);
+        self.upload(corpus, "").await?;
+        synced.extend(corpus_files(corpus).await?);
+        let list = synced.into_iter().map(|file| file + "\n").collect::<String>();
+        fs::write(&synced_list, list).await?;
+        Ok(())
+    }
+}
+
+/// Paths of files within the corpus directory relative to it, as synchronized with the bucket.
+async fn corpus_files(dir: &Path) -> Result<BTreeSet<String>> {
+    let mut files = BTreeSet::new();
+    let mut dirs = vec![(dir.to_path_buf(), String::new())];
+    while let Some((dir, prefix)) = dirs.pop() {
+        let mut read_dir = fs::read_dir(&dir).await?;
+        while let Some(entry) = read_dir.next_entry().await? {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if prefix.is_empty() && name.starts_with('.') {
+                continue;
+            }
+            let file_type = entry.file_type().await?;
+            if file_type.is_dir() {
+                dirs.push((entry.path(), format!("{}{}/", prefix, name)));
+            } else if file_type.is_file() {
+                files.insert(format!("{}{}", prefix, name));
+            }
+        }
+    }
+    Ok(files)
+}
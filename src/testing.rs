@@ -0,0 +1,270 @@
+//! Helpers for exercising the fuzzing pipeline without real fuzzers: a feedback
+//! client recording messages, a fake `cargo hfuzz` replaying scripted events,
+//! and builders of webhook payloads.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ffi::OsString,
+    io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use serde_json::json;
+
+use crate::{
+    error::Error,
+    feedback::{FeedbackClient, FeedbackLevel},
+    github,
+    simulate::{self, Push},
+};
+
+/// Feedback client keeping all messages it receives, to be inspected later.
+///
+/// Clones share the recorded messages, so one can be passed to [crate::feedback::Feedback]
+/// while another is kept for assertions.
+#[derive(Clone, Default)]
+pub struct RecordingClient {
+    messages: Arc<Mutex<Vec<(FeedbackLevel, String)>>>,
+}
+
+impl RecordingClient {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// All messages received so far, in order.
+    pub fn messages(&self) -> Vec<(FeedbackLevel, String)> {
+        self.messages.lock().unwrap().clone()
+    }
+
+    /// Texts of messages received so far with the level.
+    pub fn texts(&self, level: FeedbackLevel) -> Vec<String> {
+        self.messages
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(l, _)| *l == level)
+            .map(|(_, m)| m.clone())
+            .collect()
+    }
+
+    /// Whether a received message contains the text.
+    pub fn contains(&self, text: &str) -> bool {
+        self.messages.lock().unwrap().iter().any(|(_, m)| m.contains(text))
+    }
+}
+
+#[async_trait]
+impl FeedbackClient for RecordingClient {
+    async fn message(&self, level: FeedbackLevel, message: &str) -> Result<(), Error> {
+        self.messages.lock().unwrap().push((level, message.to_string()));
+        Ok(())
+    }
+}
+
+/// Event reported by a fake fuzz target.
+#[derive(Clone, Debug)]
+pub enum Event {
+    /// New edges are covered
+    Coverage(u32),
+    /// A crash is found and its input saved to the file, relative to the project directory
+    Crash(String),
+}
+
+/// Fake fuzz engine, a `cargo` executable that mimics `cargo hfuzz` replaying
+/// scripted events for each target in the honggfuzz output format.
+///
+/// Install it into a directory and put that directory first in `PATH` of the process
+/// running the server:
+///
+/// ```no_run
+/// # use fuzzing_ci::testing::{Event, FakeEngine};
+/// let dir = tempfile::tempdir().unwrap();
+/// FakeEngine::new()
+///     .total("target", 100)
+///     .event("target", Event::Coverage(10))
+///     .event("target", Event::Crash("hfuzz_workspace/target/crash.fuzz".into()))
+///     .install(dir.path())
+///     .unwrap();
+/// std::env::set_var("PATH", FakeEngine::path_env(dir.path()));
+/// ```
+///
+/// Other cargo commands, like builds, succeed without doing anything.
+#[derive(Clone, Debug)]
+pub struct FakeEngine {
+    version: String,
+    totals: BTreeMap<String, u32>,
+    events: BTreeMap<String, Vec<Event>>,
+}
+
+impl Default for FakeEngine {
+    fn default() -> Self {
+        Self {
+            version: "0.5.52".to_string(),
+            totals: BTreeMap::new(),
+            events: BTreeMap::new(),
+        }
+    }
+}
+
+impl FakeEngine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the version reported by `cargo hfuzz version`.
+    pub fn version(mut self, version: impl Into<String>) -> Self {
+        self.version = version.into();
+        self
+    }
+
+    /// Sets the total number of edges of the target.
+    pub fn total(mut self, target: impl Into<String>, edges: u32) -> Self {
+        self.totals.insert(target.into(), edges);
+        self
+    }
+
+    /// Adds an event reported by the target after previous ones.
+    pub fn event(mut self, target: impl Into<String>, event: Event) -> Self {
+        self.events.entry(target.into()).or_default().push(event);
+        self
+    }
+
+    /// Shell script of the fake `cargo`.
+    pub fn script(&self) -> String {
+        let mut script = String::from("#!/bin/sh\n[ \"$1\" = hfuzz ] || exit 0\ncase \"$2\" in\n");
+        script += &format!("version) echo {} ;;\n", quote(&format!("cargo-hfuzz {}", self.version)));
+        script += "run)\n  case \" $HFUZZ_RUN_ARGS \" in *\" -N 1 \"*) short=1 ;; *) short= ;; esac\n  case \"$3\" in\n";
+        let targets = self.totals.keys().chain(self.events.keys()).collect::<BTreeSet<_>>();
+        for target in targets {
+            let total = self.totals.get(target).copied().unwrap_or_default();
+            script += &format!("  {})\n", quote(target));
+            script += &format!(
+                "    if [ -n \"$short\" ]; then echo {} >&2; exit 0; fi\n",
+                quote(&format!(
+                    "Summary iterations:1 time:0 speed:0 crashes_count:0 timeout_count:0 new_units_added:0 slowest_unit_ms:0 guard_nb:{} branch_coverage_percent:0 peak_rss_mb:1",
+                    total
+                ))
+            );
+            script += "    echo 'Entering phase 2/3: Dynamic Main (Feedback Driven Mode)' >&2\n";
+            for event in self.events.get(target).into_iter().flatten() {
+                match event {
+                    Event::Coverage(edges) => {
+                        script += &format!(
+                            "    echo 'Sz:1 Tm:10us (i/b/h/e/p/c) New:0/0/0/{}/0/0, Cur:0/0/0/{}/0/0' >&2\n",
+                            edges, edges
+                        )
+                    }
+                    Event::Crash(file) => {
+                        // the crashing input is saved as honggfuzz does, relative to the project directory
+                        let quoted = quote(file);
+                        script += &format!("    mkdir -p \"$(dirname {})\" && echo crash > {}\n", quoted, quoted);
+                        script += &format!("    echo {} >&2\n", quote(&format!("Crash: saved as '{}'", file)));
+                    }
+                }
+            }
+            script += "    ;;\n";
+        }
+        script += "  *) echo \"unknown target $3\" >&2; exit 1 ;;\n  esac\n  ;;\nesac\n";
+        script
+    }
+
+    /// Writes the fake `cargo` executable into the directory, returning its path.
+    pub fn install(&self, dir: impl AsRef<Path>) -> io::Result<PathBuf> {
+        use std::os::unix::fs::PermissionsExt;
+        let file = dir.as_ref().join("cargo");
+        std::fs::write(&file, self.script())?;
+        std::fs::set_permissions(&file, std::fs::Permissions::from_mode(0o755))?;
+        Ok(file)
+    }
+
+    /// `PATH` with the directory prepended, so the fake `cargo` installed there is used.
+    pub fn path_env(dir: impl AsRef<Path>) -> OsString {
+        let path = std::env::var_os("PATH").unwrap_or_default();
+        std::env::join_paths(std::iter::once(dir.as_ref().to_path_buf()).chain(std::env::split_paths(&path)))
+            .unwrap_or(path)
+    }
+}
+
+/// Quotes the text for the shell.
+fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+/// Builder of GitHub push event payloads accepted by the webhook endpoint.
+pub struct PushPayload {
+    repo: String,
+    branch: String,
+    commit: Option<String>,
+    message: String,
+    forced: bool,
+}
+
+impl PushPayload {
+    pub fn new(repo: impl Into<String>, branch: impl Into<String>) -> Self {
+        Self {
+            repo: repo.into(),
+            branch: branch.into(),
+            commit: None,
+            message: "Test push".to_string(),
+            forced: false,
+        }
+    }
+
+    /// Sets the head commit id, a random one is used by default.
+    pub fn commit(mut self, commit: impl Into<String>) -> Self {
+        self.commit = Some(commit.into());
+        self
+    }
+
+    pub fn message(mut self, message: impl Into<String>) -> Self {
+        self.message = message.into();
+        self
+    }
+
+    /// Marks the push as forced, rewriting the branch history.
+    pub fn forced(mut self) -> Self {
+        self.forced = true;
+        self
+    }
+
+    /// Value of the `X-GitHub-Event` header the payload should be sent with.
+    pub fn event(&self) -> &'static str {
+        "push"
+    }
+
+    pub fn build(&self) -> serde_json::Value {
+        simulate::payload(&Push {
+            repo: &self.repo,
+            branch: &self.branch,
+            commit: self.commit.as_deref(),
+            message: &self.message,
+            forced: self.forced,
+        })
+    }
+}
+
+/// Value of the `X-Hub-Signature-256` header of the payload sent to a server with the
+/// webhook secret, with the payload serialized by `serde_json::to_vec`.
+pub fn signature(secret: &str, payload: &serde_json::Value) -> String {
+    github::webhook_signature(secret, &serde_json::to_vec(payload).unwrap_or_default())
+}
+
+/// GitHub ping event payload, sent with `X-GitHub-Event: ping` when a webhook is added.
+pub fn ping_payload() -> serde_json::Value {
+    json!({ "zen": "Keep it logically awesome." })
+}
+
+/// GitHub delete event payload for the branch, sent with `X-GitHub-Event: delete`.
+pub fn delete_payload(repo: &str, branch: &str) -> serde_json::Value {
+    json!({
+        "ref": branch,
+        "ref_type": "branch",
+        "repository": {
+            "ssh_url": repo,
+            "url": repo,
+        },
+    })
+}
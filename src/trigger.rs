@@ -0,0 +1,180 @@
+use std::time::Duration;
+
+use crate::error::Error;
+use reqwest::{Client, RequestBuilder, Response};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use url::Url;
+
+use crate::{
+    audit::Entry,
+    deliveries::Delivery,
+    report::Baseline,
+    runs::{DisabledTarget, Run, RunStatus},
+};
+
+#[derive(Serialize)]
+struct RunRequest<'a> {
+    branch: &'a str,
+    commit: Option<&'a str>,
+    repo: Option<&'a str>,
+    targets: &'a [String],
+    duration: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct BaselineRequest<'a> {
+    branch: &'a str,
+    repo: Option<&'a str>,
+    targets: &'a [String],
+    baseline: Baseline,
+}
+
+/// Report whose baseline is changed, relative to the reports directory, and its changed targets.
+#[derive(Deserialize)]
+pub struct BaselineChange {
+    pub report: String,
+    pub targets: Vec<String>,
+}
+
+/// Client of the fuzzing CI server API.
+pub struct Trigger {
+    client: Client,
+    server: Url,
+    token: String,
+}
+
+impl Trigger {
+    pub fn new(server: Url, token: String) -> Self {
+        Self {
+            client: Client::new(),
+            server,
+            token,
+        }
+    }
+
+    fn url(&self, path: &str) -> Result<Url, Error> {
+        Ok(self.server.join("api/")?.join(path)?)
+    }
+
+    async fn send<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, Error> {
+        let response = request.bearer_auth(&self.token).send().await?;
+        Self::parse(response).await
+    }
+
+    async fn parse<T: DeserializeOwned>(response: Response) -> Result<T, Error> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response.json().await?);
+        }
+        let body = response.text().await.unwrap_or_default();
+        let error = serde_json::from_str::<serde_json::Value>(&body)
+            .ok()
+            .and_then(|v| v["error"].as_str().map(String::from))
+            .unwrap_or(body);
+        Err(Error::other(format!("server responded with {}: {}", status, error)))
+    }
+
+    /// Starts a fuzzing run of the branch, of the `targets` or all targets if empty, fuzzing
+    /// each for `duration` seconds or the configured time.
+    pub async fn start(
+        &self,
+        branch: &str,
+        commit: Option<&str>,
+        repo: Option<&str>,
+        targets: &[String],
+        duration: Option<u64>,
+    ) -> Result<Run, Error> {
+        let request = RunRequest { branch, commit, repo, targets, duration };
+        self.send(self.client.post(self.url("runs")?).json(&request)).await
+    }
+
+    /// Runs started since the server start.
+    pub async fn runs(&self) -> Result<Vec<Run>, Error> {
+        self.send(self.client.get(self.url("runs")?)).await
+    }
+
+    pub async fn run(&self, id: &str) -> Result<Run, Error> {
+        self.send(self.client.get(self.url(&format!("runs/{}", id))?)).await
+    }
+
+    /// Cancels the run in progress, or only its fuzzing project or target at the `scope` path,
+    /// like `project/target`, or with the `scope` name if it is unique in the run.
+    pub async fn cancel(&self, id: &str, scope: Option<&str>) -> Result<Run, Error> {
+        let path = match scope {
+            Some(scope) => format!("runs/{}/cancel/{}", id, scope),
+            None => format!("runs/{}/cancel", id),
+        };
+        self.send(self.client.post(self.url(&path)?)).await
+    }
+
+    /// Pauses the run in progress, stopping its processes until it is resumed.
+    pub async fn pause(&self, id: &str) -> Result<Run, Error> {
+        self.send(self.client.post(self.url(&format!("runs/{}/pause", id))?)).await
+    }
+
+    /// Resumes the paused run.
+    pub async fn resume(&self, id: &str) -> Result<Run, Error> {
+        self.send(self.client.post(self.url(&format!("runs/{}/resume", id))?)).await
+    }
+
+    /// Changes the baseline of the `targets`, or all targets if empty, in the most recent report of the branch.
+    pub async fn baseline(
+        &self,
+        branch: &str,
+        repo: Option<&str>,
+        targets: &[String],
+        baseline: Baseline,
+    ) -> Result<BaselineChange, Error> {
+        let request = BaselineRequest { branch, repo, targets, baseline };
+        self.send(self.client.post(self.url("baseline")?).json(&request)).await
+    }
+
+    /// Targets excluded from runs.
+    pub async fn disabled_targets(&self) -> Result<Vec<DisabledTarget>, Error> {
+        self.send(self.client.get(self.url("targets/disabled")?)).await
+    }
+
+    /// Excludes the target from runs started later, returning all disabled targets.
+    pub async fn disable(&self, target: &str) -> Result<Vec<DisabledTarget>, Error> {
+        self.send(self.client.post(self.url(&format!("targets/{}/disable", target))?)).await
+    }
+
+    /// Includes the disabled target in runs started later again, returning all disabled targets.
+    pub async fn enable(&self, target: &str) -> Result<Vec<DisabledTarget>, Error> {
+        self.send(self.client.post(self.url(&format!("targets/{}/enable", target))?)).await
+    }
+
+    /// The last `limit` control actions recorded in the audit log, oldest first.
+    pub async fn audit(&self, limit: Option<usize>) -> Result<Vec<Entry>, Error> {
+        let mut request = self.client.get(self.url("audit")?);
+        if let Some(limit) = limit {
+            request = request.query(&[("limit", limit)]);
+        }
+        self.send(request).await
+    }
+
+    /// The last `limit` webhook deliveries stored by the server, oldest first.
+    pub async fn deliveries(&self, limit: Option<usize>) -> Result<Vec<Delivery>, Error> {
+        let mut request = self.client.get(self.url("deliveries")?);
+        if let Some(limit) = limit {
+            request = request.query(&[("limit", limit)]);
+        }
+        self.send(request).await
+    }
+
+    /// Processes the stored webhook delivery again, failing if its processing fails.
+    pub async fn replay_delivery(&self, id: &str) -> Result<Delivery, Error> {
+        self.send(self.client.post(self.url(&format!("deliveries/{}/replay", id))?)).await
+    }
+
+    /// Polls the run until it is completed.
+    pub async fn wait(&self, id: &str, interval: Duration) -> Result<Run, Error> {
+        loop {
+            let run = self.run(id).await?;
+            if run.status != RunStatus::Running {
+                return Ok(run);
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use slog::{debug, trace, FnValue, Logger};
+use tokio::process::Command;
+
+use crate::{
+    common::u8_slice_to_string,
+    error::{output_tail, Error, Result},
+};
+
+/// Subset of `cargo metadata` output describing a Cargo workspace.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Metadata {
+    pub packages: Vec<Package>,
+    pub workspace_members: Vec<String>,
+    pub target_directory: PathBuf,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Package {
+    pub id: String,
+    pub name: String,
+    pub manifest_path: PathBuf,
+    #[serde(default)]
+    pub dependencies: Vec<Dependency>,
+    #[serde(default)]
+    pub targets: Vec<Target>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Dependency {
+    pub name: String,
+}
+
+/// Build target of a package, like a library or a binary.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Target {
+    pub name: String,
+    pub kind: Vec<String>,
+    pub src_path: PathBuf,
+}
+
+impl Target {
+    fn is_bin(&self) -> bool {
+        self.kind.iter().any(|k| k == "bin")
+    }
+
+    fn in_fuzz_targets(&self) -> bool {
+        self.src_path.components().any(|c| c.as_os_str() == "fuzz_targets")
+    }
+}
+
+impl Metadata {
+    /// Runs `cargo metadata` for the package or workspace located in `dir`.
+    pub async fn read(dir: impl AsRef<Path>, log: &Logger) -> Result<Self> {
+        debug!(log, "Reading cargo metadata"; "dir" => dir.as_ref().to_str());
+        let output = Command::new("cargo")
+            .args(&["metadata", "--format-version", "1", "--no-deps"])
+            .current_dir(dir.as_ref())
+            .output()
+            .await?;
+        if !output.status.success() {
+            debug!(log, "cargo metadata returned error";
+                   "stderr" => FnValue(|_| u8_slice_to_string(&output.stderr)),
+                   "code" => output.status.code());
+            return Err(Error::Build {
+                command: "cargo metadata".to_string(),
+                dir: dir.as_ref().to_path_buf(),
+                status: output.status,
+                output: output_tail(&output.stderr),
+            });
+        }
+        let metadata: Self = serde_json::from_slice(&output.stdout)?;
+        trace!(log, "Workspace members: {:?}", metadata.members().map(|p| &p.name).collect::<Vec<_>>());
+        Ok(metadata)
+    }
+
+    /// Same as [Metadata::read], but returns `None` if `dir` is not a Cargo
+    /// package or workspace.
+    pub async fn read_opt(dir: impl AsRef<Path>, log: &Logger) -> Result<Option<Self>> {
+        if !dir.as_ref().join("Cargo.toml").is_file() {
+            return Ok(None);
+        }
+        Self::read(dir, log).await.map(Some)
+    }
+
+    /// Packages that are members of the workspace.
+    pub fn members(&self) -> impl Iterator<Item = &Package> {
+        self.packages
+            .iter()
+            .filter(move |p| self.workspace_members.contains(&p.id))
+    }
+
+    /// Finds a workspace member by its package name.
+    pub fn member(&self, name: &str) -> Option<&Package> {
+        self.members().find(|p| p.name == name)
+    }
+
+    /// Directory containing build artifacts for the profile specified.
+    pub fn profile_dir(&self, profile: &str) -> PathBuf {
+        self.target_directory.join(profile)
+    }
+}
+
+impl Package {
+    /// Names of fuzz targets of the package, if it looks like a fuzzing project:
+    /// all binaries of a package depending on `honggfuzz`, or otherwise ones with
+    /// sources in a `fuzz_targets` directory.
+    pub fn fuzz_targets(&self) -> Vec<String> {
+        let honggfuzz = self.dependencies.iter().any(|d| d.name == "honggfuzz");
+        self.targets
+            .iter()
+            .filter(|t| t.is_bin() && (honggfuzz || t.in_fuzz_targets()))
+            .map(|t| t.name.clone())
+            .collect()
+    }
+
+    /// Directory containing the package manifest.
+    pub fn dir(&self) -> &Path {
+        self.manifest_path
+            .parent()
+            .expect("manifest path should have parent")
+    }
+}
@@ -0,0 +1,83 @@
+//! Fuzzing of a project with the fake engine, reporting to a recording feedback client.
+
+use std::{collections::HashMap, path::Path, sync::Arc, time::Duration};
+
+use fuzzing_ci::{
+    cancel::Scope,
+    config::{self, HonggfuzzConfig, TargetConfig},
+    feedback::{Feedback, FeedbackLevel},
+    hfuzz, report,
+    testing::{Event, FakeEngine, RecordingClient},
+};
+
+#[tokio::test]
+async fn fuzzing_reports_coverage_and_crashes() {
+    let dir = tempfile::tempdir().unwrap();
+    let bin = dir.path().join("bin");
+    let project = dir.path().join("project");
+    std::fs::create_dir_all(&bin).unwrap();
+    std::fs::create_dir_all(&project).unwrap();
+    FakeEngine::new()
+        .total("a", 100)
+        .event("a", Event::Coverage(10))
+        .event("a", Event::Coverage(15))
+        .event("a", Event::Crash("hfuzz_workspace/a/SIGSEGV.PC.1.fuzz".into()))
+        .total("b", 50)
+        .event("b", Event::Coverage(5))
+        .install(&bin)
+        .unwrap();
+    std::env::set_var("PATH", FakeEngine::path_env(&bin));
+
+    let log = slog::Logger::root(slog::Discard, slog::o!());
+    let (_, output_format) = hfuzz::detect(None).await.unwrap();
+    let time = config::Time::default();
+    let report = report::Report::new(
+        &dir.path().join("reports"),
+        &None,
+        Path::new("run"),
+        Some(1),
+        &time,
+        log.clone(),
+    )
+    .await
+    .unwrap();
+    let client = RecordingClient::new();
+    let feedback = Arc::new(Feedback::new(
+        &config::Feedback::default(),
+        &time,
+        Box::new(client.clone()),
+        report,
+        log.clone(),
+    ));
+
+    feedback.started().await;
+    hfuzz::run(
+        &project,
+        HashMap::new(),
+        TargetConfig::new(None, vec!["a".to_string(), "b".to_string()]),
+        HonggfuzzConfig::new(String::new()),
+        output_format,
+        None,
+        feedback.clone(),
+        None,
+        Scope::new(),
+        log,
+    )
+    .await
+    .unwrap();
+    feedback.stopped();
+    // crashes are delivered in background
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let status = feedback.status();
+    assert_eq!(status["a"].total, 100);
+    assert_eq!(status["a"].covered, 25);
+    assert_eq!(status["a"].errors, 1);
+    assert_eq!(status["b"].covered, 5);
+    assert_eq!(status["b"].errors, 0);
+
+    assert_eq!(client.texts(FeedbackLevel::Info), ["Fuzzing is started", "Fuzzing is stopped"]);
+    let crashes = client.texts(FeedbackLevel::Error);
+    assert_eq!(crashes.len(), 1);
+    assert!(crashes[0].contains("SIGSEGV.PC.1.fuzz"), "{}", crashes[0]);
+}
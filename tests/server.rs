@@ -0,0 +1,139 @@
+//! Runs of the webhook server started by pushes, fuzzing with the fake engine.
+
+use std::{path::Path, process::Command, time::Duration};
+
+use fuzzing_ci::{
+    config::Config,
+    policy::Verdict,
+    runs::RunStatus,
+    server::{self, Overrides},
+    simulate,
+    testing::{self, Event, FakeEngine, PushPayload},
+    trigger::Trigger,
+};
+
+const SECRET: &str = "secret";
+const TOKEN: &str = "token";
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .args(&["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .current_dir(dir)
+        .status()
+        .unwrap();
+    assert!(status.success(), "git {:?} failed", args);
+}
+
+/// Repository with the fuzzing project `p` providing an input of its target `a`,
+/// returning the id of its head commit.
+fn repository(dir: &Path) -> String {
+    let input = dir.join("p").join("hfuzz_workspace").join("a").join("input");
+    std::fs::create_dir_all(&input).unwrap();
+    std::fs::write(input.join("seed"), "seed").unwrap();
+    std::fs::write(dir.join("p").join("Cargo.toml"), "[package]\nname = \"p\"\n").unwrap();
+    git(dir, &["init", "-q", "-b", "master"]);
+    git(dir, &["add", "."]);
+    git(dir, &["commit", "-q", "-m", "Fuzzing project"]);
+    let output = Command::new("git").args(&["rev-parse", "HEAD"]).current_dir(dir).output().unwrap();
+    String::from_utf8(output.stdout).unwrap().trim().to_string()
+}
+
+#[tokio::test]
+async fn push_starts_fuzzing() {
+    let dir = tempfile::tempdir().unwrap();
+    let bin = dir.path().join("bin");
+    let repo = dir.path().join("repo");
+    std::fs::create_dir_all(&bin).unwrap();
+    std::fs::create_dir_all(&repo).unwrap();
+    std::fs::create_dir_all(dir.path().join("corpus")).unwrap();
+    FakeEngine::new()
+        .total("a", 100)
+        .event("a", Event::Coverage(10))
+        .event("a", Event::Crash("hfuzz_workspace/a/SIGSEGV.PC.1.fuzz".into()))
+        .install(&bin)
+        .unwrap();
+    std::env::set_var("PATH", FakeEngine::path_env(&bin));
+    let commit = repository(&repo);
+
+    let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let config_file = dir.path().join("fuzz-ci.toml");
+    std::fs::write(
+        &config_file,
+        format!(
+            r#"
+address = "127.0.0.1:{port}"
+webhook_secret = "{secret}"
+reports_path = "{dir}/reports"
+corpus = "{dir}/corpus"
+branches = ["master"]
+
+[targets.p]
+targets = ["a"]
+
+[workdir]
+path = "{dir}/work"
+kill_orphans = false
+
+[api]
+token = "{token}"
+"#,
+            port = port,
+            secret = SECRET,
+            token = TOKEN,
+            dir = dir.path().display(),
+        ),
+    )
+    .unwrap();
+    let config = Config::read(&config_file).unwrap();
+    let webhook = simulate::webhook_url(&config).unwrap();
+    let log = slog::Logger::root(slog::Discard, slog::o!());
+    // the server runs on its own runtime, as it does when started by `fuzz-ci server`
+    std::thread::spawn(move || {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(server::start(config_file, config, Overrides::default(), log)).unwrap();
+    });
+
+    let push = PushPayload::new(repo.to_string_lossy(), "master").commit(&commit);
+    let payload = push.build();
+    let client = reqwest::Client::new();
+    let mut delivered = false;
+    for _ in 0..50 {
+        let response = client
+            .post(webhook.clone())
+            .header("X-GitHub-Event", push.event())
+            .header("X-Hub-Signature-256", testing::signature(SECRET, &payload))
+            .json(&payload)
+            .send()
+            .await;
+        match response {
+            Ok(response) => {
+                assert!(response.status().is_success(), "push rejected with {}", response.status());
+                delivered = true;
+                break;
+            }
+            // the server is not listening yet
+            Err(_) => tokio::time::sleep(Duration::from_millis(100)).await,
+        }
+    }
+    assert!(delivered, "the server doesn't accept pushes");
+
+    let trigger = Trigger::new(format!("http://127.0.0.1:{}/", port).parse().unwrap(), TOKEN.to_string());
+    let runs = trigger.runs().await.unwrap();
+    assert_eq!(runs.len(), 1);
+    assert_eq!(runs[0].branch, "master");
+    let run = tokio::time::timeout(Duration::from_secs(60), trigger.wait(&runs[0].id, Duration::from_millis(100)))
+        .await
+        .expect("the run isn't finished")
+        .unwrap();
+    assert!(run.description.contains(&commit[..5]), "{}", run.description);
+    assert_eq!(run.status, RunStatus::Success);
+    // runs with crashes fail without other policy rules
+    assert_eq!(run.verdict, Some(Verdict::Fail));
+
+    // the crashing input is kept in the run report
+    let reports = std::fs::read_dir(dir.path().join("reports").join("master")).unwrap();
+    assert!(reports
+        .map(|report| report.unwrap().path().join("failures").join("a").join("SIGSEGV.PC.1.fuzz"))
+        .any(|input| input.is_file()));
+}